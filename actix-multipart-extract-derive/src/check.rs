@@ -0,0 +1,178 @@
+use quote::ToTokens;
+use syn::Path;
+
+/// Options `#[multipart(...)]` accepts on a field.
+const FIELD_OPTIONS: [&str; 26] = [
+    "max_size",
+    "deadline",
+    "hash",
+    "validate",
+    "with",
+    "pattern",
+    "one_of",
+    "max_scale",
+    "max_filename_length",
+    "filename_pattern",
+    "rename",
+    "alias",
+    "default",
+    "default_fn",
+    "content_id",
+    "format",
+    "verify_content_type",
+    "strict_content_type",
+    "uuid",
+    "image",
+    "trim",
+    "collapse_whitespace",
+    "lowercase",
+    "as_file",
+    "as_text",
+    "base64_file",
+];
+
+/// Options `#[multipart(...)]` accepts on a struct or an enum.
+const CONTAINER_OPTIONS: [&str; 2] = ["rename_all", "tag"];
+
+/// Options which can be set more than once on a field.
+const REPEATABLE: [&str; 1] = ["alias"];
+
+/// Options which can't be set together on a field, with the reason.
+const CONFLICTS: [(&str, &[&str], &str); 5] = [
+    (
+        "as_file",
+        &["as_text"],
+        "a part can't be read as a file and as text",
+    ),
+    (
+        "base64_file",
+        &["as_file", "as_text", "with"],
+        "the text of the part is decoded into a file",
+    ),
+    (
+        "default",
+        &["default_fn"],
+        "a field can only have one default",
+    ),
+    (
+        "with",
+        &[
+            "format",
+            "pattern",
+            "one_of",
+            "uuid",
+            "max_scale",
+            "trim",
+            "collapse_whitespace",
+            "lowercase",
+        ],
+        "the function of `with` converts the whole part, text options aren't applied",
+    ),
+    (
+        "as_text",
+        &[
+            "verify_content_type",
+            "strict_content_type",
+            "hash",
+            "image",
+            "max_filename_length",
+            "filename_pattern",
+        ],
+        "parts read as text aren't files, file options aren't applied",
+    ),
+];
+
+/// Options set on a single field, checked for duplicates and conflicts.
+#[derive(Default)]
+pub struct SeenOptions(Vec<Path>);
+
+impl SeenOptions {
+    /// Add an option, failing if it was already set.
+    pub fn add(&mut self, path: &Path) -> Result<(), syn::Error> {
+        let name = name(path);
+
+        if !REPEATABLE.contains(&name.as_str()) && self.0.contains(path) {
+            return Err(syn::Error::new_spanned(
+                path,
+                format!("`{name}` is set more than once"),
+            ));
+        }
+
+        self.0.push(path.clone());
+        Ok(())
+    }
+
+    /// Check that no options conflicting with each other were set.
+    pub fn check_conflicts(&self) -> Result<(), syn::Error> {
+        for (option, conflicts, reason) in CONFLICTS {
+            if !self.0.iter().any(|seen| seen.is_ident(option)) {
+                continue;
+            }
+
+            if let Some(conflict) = self
+                .0
+                .iter()
+                .find(|seen| conflicts.iter().any(|conflict| seen.is_ident(conflict)))
+            {
+                return Err(syn::Error::new_spanned(
+                    conflict,
+                    format!(
+                        "`{}` can't be combined with `{option}`, {reason}",
+                        name(conflict)
+                    ),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn name(path: &Path) -> String {
+    path.to_token_stream().to_string().replace(' ', "")
+}
+
+/// Error for an unknown option of a field, suggesting a known option with a similar name.
+pub fn unknown_option(path: &Path) -> syn::Error {
+    let name = name(path);
+
+    let message = if FIELD_OPTIONS.contains(&name.as_str()) {
+        format!("`{name}` doesn't take this kind of value")
+    } else if CONTAINER_OPTIONS.contains(&name.as_str()) {
+        format!("`{name}` is set on the struct, not on a field")
+    } else {
+        let suggestion = FIELD_OPTIONS
+            .iter()
+            .map(|known| (distance(&name, known), known))
+            .filter(|(distance, _)| *distance <= 2)
+            .min_by_key(|(distance, _)| *distance);
+
+        match suggestion {
+            Some((_, known)) => {
+                format!("unknown multipart option `{name}`, did you mean `{known}`?")
+            }
+            None => format!("unknown multipart option `{name}`"),
+        }
+    };
+
+    syn::Error::new_spanned(path, message)
+}
+
+/// Edit distance between two option names.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous + usize::from(a != *b);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(previous + 1).min(row[j] + 1);
+        }
+    }
+
+    row[b.len()]
+}
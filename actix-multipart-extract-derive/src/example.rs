@@ -0,0 +1,81 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{punctuated::Punctuated, token::Comma, Field};
+
+use crate::{tagged::TaggedEnum, types, FieldDefault, FieldOptions};
+
+/// Generate the `example` method of `MultipartForm`, with a part for every field. Enums are
+/// described by their first variant.
+pub(crate) fn example(
+    fields: &Punctuated<Field, Comma>,
+    options: &[FieldOptions],
+    tagged: Option<&TaggedEnum>,
+) -> TokenStream {
+    let (tag, indexes): (Option<TokenStream>, Vec<usize>) = match tagged {
+        Some(tagged) => match tagged.variants.first() {
+            Some(variant) => {
+                let (name, value) = (&tagged.tag, &variant.tag);
+                let tag = quote! {
+                    actix_multipart_extract::form::ExamplePart {
+                        name: #name,
+                        value: actix_multipart_extract::form::ExampleValue::Text(#value),
+                    },
+                };
+
+                (Some(tag), variant.fields.clone())
+            }
+            None => (None, Vec::new()),
+        },
+        None => (None, (0..fields.len()).collect()),
+    };
+
+    let parts = indexes.into_iter().map(|i| {
+        let name = options[i].part_name(i);
+        let value = value(&fields[i], &options[i]);
+
+        quote! {
+            actix_multipart_extract::form::ExamplePart {
+                name: #name,
+                value: #value,
+            },
+        }
+    });
+
+    quote! {
+        fn example() -> actix_multipart_extract::form::FormExample {
+            // Serde renamed field names ordered by field.
+            #[allow(unused_variables)]
+            let introspected = <Self as actix_multipart_extract::form::MultipartForm>::fields();
+
+            actix_multipart_extract::form::FormExample {
+                parts: vec![#tag #(#parts)*],
+            }
+        }
+    }
+}
+
+/// Example value of a field, a placeholder for files.
+fn value(field: &Field, options: &FieldOptions) -> TokenStream {
+    if options.base64_file {
+        return quote! {
+            actix_multipart_extract::form::ExampleValue::Text("data:text/plain;base64,SGVsbG8=")
+        };
+    }
+
+    if (types::is_any_file(&field.ty) || options.as_file) && !options.as_text {
+        return quote! {
+            actix_multipart_extract::form::ExampleValue::File {
+                filename: "file.bin",
+                content_type: "application/octet-stream",
+            }
+        };
+    }
+
+    let text = match (&options.default, &options.one_of) {
+        (Some(FieldDefault::Text(text)), _) => text.clone(),
+        (_, Some(one_of)) if !one_of.is_empty() => one_of[0].clone(),
+        _ => types::example_text(&field.ty).to_owned(),
+    };
+
+    quote! { actix_multipart_extract::form::ExampleValue::Text(#text) }
+}
@@ -1,96 +1,1706 @@
 use parse_size::parse_size;
 use proc_macro::TokenStream;
-use proc_macro2::Span;
 use quote::quote;
 use syn::{
-    parse_macro_input, Data, DeriveInput, Field, Fields, FieldsNamed, Ident, Lit, Meta, MetaList,
-    MetaNameValue, NestedMeta, Path,
+    parse::{Parse, ParseStream},
+    parse_macro_input, parse_quote,
+    punctuated::Punctuated,
+    token::Comma,
+    Attribute, Data, DeriveInput, Expr, Field, Fields, FieldsNamed, FieldsUnnamed, Generics, Ident,
+    Lit, LitStr, Meta, MetaList, MetaNameValue, NestedMeta, Path, Token, Type,
 };
 
+mod check;
+mod example;
+mod rename;
+#[cfg(feature = "schemars")]
+mod schema;
+mod tagged;
+mod types;
+
+/// Options set with `#[multipart(...)]` on a single field.
+#[derive(Default)]
+struct FieldOptions {
+    /// Max size in bytes, a `usize` expression.
+    max_size: Option<proc_macro2::TokenStream>,
+    verify_content_type: bool,
+    strict_content_type: bool,
+    uuid: bool,
+    as_file: bool,
+    as_text: bool,
+    base64_file: bool,
+    trim: bool,
+    collapse_whitespace: bool,
+    lowercase: bool,
+    hash: Option<Ident>,
+    /// Deadline in milliseconds.
+    deadline: Option<u64>,
+    image: Option<ImageOptions>,
+    validate: Option<Path>,
+    pattern: Option<LitStr>,
+    one_of: Option<Vec<String>>,
+    format: Option<LitStr>,
+    max_scale: Option<u32>,
+    max_filename_length: Option<usize>,
+    filename_pattern: Option<LitStr>,
+    content_id: Option<String>,
+    default: Option<FieldDefault>,
+    rename: Option<LitStr>,
+    with: Option<Path>,
+    aliases: Vec<LitStr>,
+}
+
+impl FieldOptions {
+    /// Name of the parts of the `i`th field, its rename or its serde renamed name.
+    fn part_name(&self, i: usize) -> proc_macro2::TokenStream {
+        match &self.rename {
+            Some(rename) => quote! { #rename },
+            None => quote! { introspected[#i] },
+        }
+    }
+}
+
+/// Rules of an image field set with `#[multipart(image(...))]`.
+#[derive(Default)]
+struct ImageOptions {
+    max_width: Option<u32>,
+    max_height: Option<u32>,
+    formats: Vec<String>,
+}
+
+/// Formats `#[multipart(image(formats = "..."))]` accepts.
+#[cfg(feature = "image")]
+const IMAGE_FORMATS: [&str; 4] = ["png", "jpeg", "gif", "webp"];
+
+/// Parse the options of `#[multipart(image(...))]`.
+#[cfg(feature = "image")]
+fn parse_image(list: MetaList) -> Result<ImageOptions, syn::Error> {
+    let mut image = ImageOptions::default();
+
+    for meta in list.nested {
+        let (path, lit) = match meta {
+            NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, lit, .. })) => (path, lit),
+            meta => return Err(syn::Error::new_spanned(meta, "expected `name = value`")),
+        };
+
+        if path.is_ident("max_width") || path.is_ident("max_height") {
+            let size = match &lit {
+                Lit::Int(int) => int.base10_parse()?,
+                _ => return Err(syn::Error::new(lit.span(), "must be a number")),
+            };
+
+            if path.is_ident("max_width") {
+                image.max_width = Some(size);
+            } else {
+                image.max_height = Some(size);
+            }
+        } else if path.is_ident("formats") {
+            let formats = match &lit {
+                Lit::Str(s) => s.value(),
+                _ => return Err(syn::Error::new(lit.span(), "must be a string")),
+            };
+
+            for format in formats.split(',').map(|f| f.trim().to_ascii_lowercase()) {
+                let format = if format == "jpg" {
+                    "jpeg".to_string()
+                } else {
+                    format
+                };
+
+                if !IMAGE_FORMATS.contains(&format.as_str()) {
+                    return Err(syn::Error::new(
+                        lit.span(),
+                        format!(
+                            "unsupported format {:?}, expected {}",
+                            format,
+                            IMAGE_FORMATS.join(", ")
+                        ),
+                    ));
+                }
+
+                image.formats.push(format);
+            }
+        } else {
+            return Err(syn::Error::new_spanned(
+                path,
+                "expected max_width, max_height or formats",
+            ));
+        }
+    }
+
+    Ok(image)
+}
+
+#[cfg(not(feature = "image"))]
+fn parse_image(list: MetaList) -> Result<ImageOptions, syn::Error> {
+    Err(syn::Error::new_spanned(
+        list.path,
+        "image requires the `image` feature",
+    ))
+}
+
+/// Default of a field set with `#[multipart(default = ...)]` or `#[multipart(default_fn = "...")]`.
+enum FieldDefault {
+    /// Text of a literal, parsed like text sent for the field.
+    Text(String),
+    /// Function returning the value of the field.
+    Function(Path),
+}
+
+impl FieldOptions {
+    fn from_field(field: &Field) -> Result<Self, syn::Error> {
+        let mut options = Self::default();
+        let mut seen = check::SeenOptions::default();
+
+        for option in parse_multipart_options(&field.attrs)? {
+            match &option {
+                MultipartOption::Expr(path, _) => seen.add(path)?,
+                MultipartOption::Meta(NestedMeta::Meta(meta)) => seen.add(meta.path())?,
+                MultipartOption::Meta(NestedMeta::Lit(lit)) => {
+                    return Err(syn::Error::new_spanned(lit, "expected an option"))
+                }
+            }
+
+            let meta = match option {
+                // Max sizes can be constants or other constant expressions
+                MultipartOption::Expr(path, expr) if path.is_ident("max_size") => {
+                    options.max_size = Some(quote! { #expr });
+                    continue;
+                }
+                MultipartOption::Expr(_, expr) => {
+                    return Err(syn::Error::new_spanned(expr, "must be a literal"))
+                }
+                MultipartOption::Meta(meta) => meta,
+            };
+
+            match meta {
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, lit, .. }))
+                    if path.is_ident("max_size") =>
+                {
+                    let max_size = parse_max_size(&lit)?;
+                    options.max_size = Some(quote! { #max_size });
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, lit, .. }))
+                    if path.is_ident("hash") =>
+                {
+                    options.hash = Some(parse_hash_algorithm(&lit)?);
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, lit, .. }))
+                    if path.is_ident("deadline") =>
+                {
+                    options.deadline = Some(parse_deadline(&lit)?);
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, lit, .. }))
+                    if path.is_ident("validate") =>
+                {
+                    options.validate = Some(parse_path(&lit)?);
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, lit, .. }))
+                    if path.is_ident("with") =>
+                {
+                    options.with = Some(parse_path(&lit)?);
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, lit, .. }))
+                    if path.is_ident("pattern") =>
+                {
+                    options.pattern = Some(parse_pattern(&lit)?);
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, lit, .. }))
+                    if path.is_ident("one_of") =>
+                {
+                    options.one_of = Some(parse_one_of(&lit)?);
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, lit, .. }))
+                    if path.is_ident("max_scale") =>
+                {
+                    options.max_scale = Some(match &lit {
+                        Lit::Int(int) => int.base10_parse()?,
+                        _ => return Err(syn::Error::new(lit.span(), "must be a number")),
+                    });
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, lit, .. }))
+                    if path.is_ident("max_filename_length") =>
+                {
+                    options.max_filename_length = Some(match &lit {
+                        Lit::Int(int) => int.base10_parse()?,
+                        _ => return Err(syn::Error::new(lit.span(), "must be a number")),
+                    });
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, lit, .. }))
+                    if path.is_ident("filename_pattern") =>
+                {
+                    options.filename_pattern = Some(parse_pattern(&lit)?);
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, lit, .. }))
+                    if path.is_ident("rename") =>
+                {
+                    options.rename = Some(parse_str(&lit)?);
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, lit, .. }))
+                    if path.is_ident("alias") =>
+                {
+                    options.aliases.push(parse_str(&lit)?);
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, lit, .. }))
+                    if path.is_ident("default") =>
+                {
+                    options.default = Some(FieldDefault::Text(parse_default(&lit)?));
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, lit, .. }))
+                    if path.is_ident("default_fn") =>
+                {
+                    options.default = Some(FieldDefault::Function(parse_path(&lit)?));
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, lit, .. }))
+                    if path.is_ident("content_id") =>
+                {
+                    options.content_id = Some(parse_content_id(&lit)?);
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, lit, .. }))
+                    if path.is_ident("format") =>
+                {
+                    options.format = Some(parse_str(&lit)?);
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("verify_content_type") => {
+                    options.verify_content_type = parse_sniff(&path)?;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("strict_content_type") => {
+                    options.strict_content_type = parse_sniff(&path)?;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("uuid") => {
+                    options.uuid = true;
+                }
+                NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("image") => {
+                    options.image = Some(parse_image(list)?);
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("trim") => {
+                    options.trim = true;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("collapse_whitespace") => {
+                    options.collapse_whitespace = true;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("lowercase") => {
+                    options.lowercase = true;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("as_file") => {
+                    options.as_file = true;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("as_text") => {
+                    options.as_text = true;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("base64_file") => {
+                    options.base64_file = true;
+                }
+                NestedMeta::Meta(meta) => return Err(check::unknown_option(meta.path())),
+                NestedMeta::Lit(lit) => {
+                    return Err(syn::Error::new_spanned(lit, "expected an option"))
+                }
+            }
+        }
+
+        seen.check_conflicts()?;
+
+        Ok(options)
+    }
+
+    /// Options of every field, renaming fields without a rename with the `rename_all` of the
+    /// struct.
+    fn from_fields(
+        attrs: &[Attribute],
+        fields: &Punctuated<Field, Comma>,
+    ) -> Result<Vec<Self>, syn::Error> {
+        let rename_all = rename::RenameRule::from_attrs(attrs)?;
+
+        fields
+            .iter()
+            .map(|field| {
+                let mut options = Self::from_field(field)?;
+
+                if let (Some(rule), None, Some(ident)) = (rename_all, &options.rename, &field.ident)
+                {
+                    let renamed = rule.apply(ident.to_string().trim_start_matches("r#"));
+                    options.rename = Some(LitStr::new(&renamed, ident.span()));
+                }
+
+                Ok(options)
+            })
+            .collect()
+    }
+}
+
+/// An option of a `#[multipart(...)]` attribute.
+enum MultipartOption {
+    Meta(NestedMeta),
+    /// A `name = value` option with a value which isn't a literal, like a constant.
+    Expr(Path, Box<Expr>),
+}
+
+impl Parse for MultipartOption {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let fork = input.fork();
+
+        if fork.parse::<Path>().is_ok() && fork.parse::<Token![=]>().is_ok() {
+            // Literals followed by more tokens start an expression, like `2 * MAX_SIZE`
+            let literal = fork.parse::<Lit>().is_ok() && (fork.is_empty() || fork.peek(Token![,]));
+
+            if !literal {
+                let path = input.parse()?;
+                input.parse::<Token![=]>()?;
+                return Ok(Self::Expr(path, input.parse()?));
+            }
+        }
+
+        input.parse().map(Self::Meta)
+    }
+}
+
+/// Options of the `#[multipart(...)]` attributes, including options set to an expression.
+fn parse_multipart_options(attrs: &[Attribute]) -> Result<Vec<MultipartOption>, syn::Error> {
+    let mut options = Vec::new();
+
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("multipart")) {
+        options.extend(attr.parse_args_with(Punctuated::<_, Comma>::parse_terminated)?);
+    }
+
+    Ok(options)
+}
+
+/// Options of the `#[multipart(...)]` attributes, which must be set to literals.
+fn multipart_options(attrs: &[Attribute]) -> Result<Vec<NestedMeta>, syn::Error> {
+    parse_multipart_options(attrs)?
+        .into_iter()
+        .map(|option| match option {
+            MultipartOption::Meta(meta) => Ok(meta),
+            MultipartOption::Expr(_, expr) => {
+                Err(syn::Error::new_spanned(expr, "must be a literal"))
+            }
+        })
+        .collect()
+}
+
+/// Fields of a struct with named fields, the derives don't support other items.
+fn named_fields<'a>(
+    ast: &'a DeriveInput,
+    derive: &str,
+) -> Result<&'a Punctuated<Field, Comma>, syn::Error> {
+    let message = format!("{derive} can only be derived on structs with named fields");
+
+    match &ast.data {
+        Data::Struct(syn::DataStruct {
+            fields: Fields::Named(FieldsNamed { named, .. }),
+            ..
+        }) => match tagged::multipart_tag(&ast.attrs)? {
+            Some(tag) => Err(syn::Error::new_spanned(tag, "tag can only be set on enums")),
+            None => Ok(named),
+        },
+        Data::Struct(syn::DataStruct {
+            fields: Fields::Unnamed(fields),
+            ..
+        }) => Err(syn::Error::new_spanned(fields, message)),
+        Data::Struct(syn::DataStruct {
+            fields: Fields::Unit,
+            ..
+        }) => Err(syn::Error::new_spanned(&ast.ident, message)),
+        Data::Enum(data) => Err(syn::Error::new_spanned(data.enum_token, message)),
+        Data::Union(data) => Err(syn::Error::new_spanned(data.union_token, message)),
+    }
+}
+
+/// Generics of the struct, requiring it to be deserializable to introspect its field names.
+fn form_generics(ast: &DeriveInput) -> Generics {
+    let mut generics = ast.generics.clone();
+    let name = &ast.ident;
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+
+    generics.make_where_clause().predicates.push(parse_quote! {
+        #name #ty_generics: actix_multipart_extract::serde::de::DeserializeOwned
+    });
+
+    generics
+}
+
+fn parse_str(lit: &Lit) -> Result<LitStr, syn::Error> {
+    match lit {
+        Lit::Str(s) => Ok(s.clone()),
+        _ => Err(syn::Error::new(lit.span(), "must be a string")),
+    }
+}
+
+fn parse_max_size(lit: &Lit) -> Result<usize, syn::Error> {
+    let lit_string = match lit {
+        Lit::Int(l) => l.to_string(),
+        Lit::Float(f) => f.to_string(),
+        _ => {
+            return Err(syn::Error::new(
+                lit.span(),
+                "must be a number with size suffix",
+            ))
+        }
+    };
+
+    match parse_size(lit_string) {
+        Ok(v) => Ok(v as usize),
+        Err(_) => Err(syn::Error::new(lit.span(), "invalid size")),
+    }
+}
+
+/// Parse a duration like `30s` into milliseconds.
+fn parse_deadline(lit: &Lit) -> Result<u64, syn::Error> {
+    let value = parse_str(lit)?.value();
+    let invalid = || {
+        syn::Error::new(
+            lit.span(),
+            "invalid duration, expected a number with a unit of ms, s, m or h",
+        )
+    };
+
+    let unit_start = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (amount, unit) = value.split_at(unit_start);
+    let amount: u64 = amount.parse().map_err(|_| invalid())?;
+
+    let millis = match unit.trim() {
+        "ms" => 1,
+        "s" => 1000,
+        "m" => 60 * 1000,
+        "h" => 60 * 60 * 1000,
+        _ => return Err(invalid()),
+    };
+
+    amount.checked_mul(millis).ok_or_else(invalid)
+}
+
+/// Parse a digest algorithm name into its `HashAlgorithm` variant.
+#[cfg(feature = "hash")]
+fn parse_hash_algorithm(lit: &Lit) -> Result<Ident, syn::Error> {
+    let variant = match lit {
+        Lit::Str(s) => match s.value().to_ascii_lowercase().as_str() {
+            "sha256" => "Sha256",
+            "sha384" => "Sha384",
+            "sha512" => "Sha512",
+            _ => {
+                return Err(syn::Error::new(
+                    lit.span(),
+                    "unsupported hash algorithm, expected sha256, sha384 or sha512",
+                ))
+            }
+        },
+        _ => return Err(syn::Error::new(lit.span(), "must be a string")),
+    };
+
+    Ok(Ident::new(variant, lit.span()))
+}
+
+#[cfg(not(feature = "hash"))]
+fn parse_hash_algorithm(lit: &Lit) -> Result<Ident, syn::Error> {
+    Err(syn::Error::new(
+        lit.span(),
+        "hash requires the `hash` feature",
+    ))
+}
+
+/// Parse a literal default into the text it is parsed from.
+fn parse_default(lit: &Lit) -> Result<String, syn::Error> {
+    match lit {
+        Lit::Str(s) => Ok(s.value()),
+        Lit::Int(int) => Ok(int.base10_digits().to_string()),
+        Lit::Float(float) => Ok(float.base10_digits().to_string()),
+        Lit::Bool(bool) => Ok(bool.value.to_string()),
+        _ => Err(syn::Error::new(
+            lit.span(),
+            "must be a string, number or boolean",
+        )),
+    }
+}
+
+/// Parse a string literal containing a path, such as a function.
+fn parse_path(lit: &Lit) -> Result<Path, syn::Error> {
+    match lit {
+        Lit::Str(s) => s.parse(),
+        _ => Err(syn::Error::new(lit.span(), "must be a string")),
+    }
+}
+
+/// Parse a regex, making sure it compiles.
+#[cfg(feature = "pattern")]
+fn parse_pattern(lit: &Lit) -> Result<LitStr, syn::Error> {
+    let pattern = match lit {
+        Lit::Str(s) => s,
+        _ => return Err(syn::Error::new(lit.span(), "must be a string")),
+    };
+
+    match regex::Regex::new(&pattern.value()) {
+        Ok(_) => Ok(pattern.clone()),
+        Err(err) => Err(syn::Error::new(lit.span(), err)),
+    }
+}
+
+#[cfg(not(feature = "pattern"))]
+fn parse_pattern(lit: &Lit) -> Result<LitStr, syn::Error> {
+    Err(syn::Error::new(
+        lit.span(),
+        "pattern requires the `pattern` feature",
+    ))
+}
+
+/// Enable an option checking the content type of files.
+#[cfg(feature = "sniff")]
+fn parse_sniff(_path: &Path) -> Result<bool, syn::Error> {
+    Ok(true)
+}
+
+#[cfg(not(feature = "sniff"))]
+fn parse_sniff(path: &Path) -> Result<bool, syn::Error> {
+    Err(syn::Error::new_spanned(
+        path,
+        format!("{} requires the `sniff` feature", quote!(#path)),
+    ))
+}
+
+/// Parse a `Content-ID`, the angle brackets around it are optional.
+fn parse_content_id(lit: &Lit) -> Result<String, syn::Error> {
+    let value = match lit {
+        Lit::Str(s) => s.value(),
+        _ => return Err(syn::Error::new(lit.span(), "must be a string")),
+    };
+
+    let content_id = value.trim();
+    let content_id = content_id
+        .strip_prefix('<')
+        .and_then(|id| id.strip_suffix('>'))
+        .unwrap_or(content_id);
+
+    if content_id.is_empty() {
+        return Err(syn::Error::new(lit.span(), "must not be empty"));
+    }
+
+    Ok(content_id.to_string())
+}
+
+/// Parse a comma separated list of allowed values.
+fn parse_one_of(lit: &Lit) -> Result<Vec<String>, syn::Error> {
+    let values = match lit {
+        Lit::Str(s) => s.value(),
+        _ => return Err(syn::Error::new(lit.span(), "must be a string")),
+    };
+
+    let values: Vec<_> = values
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect();
+
+    if values.is_empty() {
+        return Err(syn::Error::new(
+            lit.span(),
+            "must contain at least one value",
+        ));
+    }
+
+    Ok(values)
+}
+
+/// Generate checks for a text value of a field, returning `Err(message)` on failure.
+fn text_checks(options: &FieldOptions) -> Vec<proc_macro2::TokenStream> {
+    let mut checks = Vec::new();
+
+    if let Some(pattern) = &options.pattern {
+        // Compiled once on first use.
+        checks.push(quote! {{
+            static PATTERN: std::sync::OnceLock<actix_multipart_extract::regex::Regex> =
+                std::sync::OnceLock::new();
+
+            let regex = PATTERN.get_or_init(|| {
+                actix_multipart_extract::regex::Regex::new(#pattern).unwrap()
+            });
+
+            if !regex.is_match(value) {
+                return Err(format!("must match the pattern {}", #pattern));
+            }
+        }});
+    }
+
+    if let Some(one_of) = &options.one_of {
+        let allowed = one_of.join(", ");
+
+        checks.push(quote! {
+            if ![#(#one_of),*].contains(&value) {
+                return Err(format!("must be one of {}", #allowed));
+            }
+        });
+    }
+
+    if options.uuid {
+        checks.push(quote! {
+            if !actix_multipart_extract::form::is_uuid(value) {
+                return Err("must be a UUID".to_string());
+            }
+        });
+    }
+
+    if let Some(max_scale) = options.max_scale {
+        checks.push(quote! {
+            actix_multipart_extract::form::check_scale(value, #max_scale)?;
+        });
+    }
+
+    checks
+}
+
+/// Checks of a file name, `value` is the file name after applying the filename policy.
+fn filename_checks(options: &FieldOptions) -> Vec<proc_macro2::TokenStream> {
+    let mut checks = Vec::new();
+
+    if let Some(max_length) = options.max_filename_length {
+        checks.push(quote! {
+            if value.chars().count() > #max_length {
+                return Err(format!("file name must be at most {} characters", #max_length));
+            }
+        });
+    }
+
+    if let Some(pattern) = &options.filename_pattern {
+        // Compiled once on first use.
+        checks.push(quote! {{
+            static PATTERN: std::sync::OnceLock<actix_multipart_extract::regex::Regex> =
+                std::sync::OnceLock::new();
+
+            let regex = PATTERN.get_or_init(|| {
+                actix_multipart_extract::regex::Regex::new(#pattern).unwrap()
+            });
+
+            if !regex.is_match(value) {
+                return Err(format!("file name must match the pattern {}", #pattern));
+            }
+        }});
+    }
+
+    checks
+}
+
+/// Tokens of an optional number, `Some(value)` or `None`.
+fn option_tokens(value: Option<u32>) -> proc_macro2::TokenStream {
+    match value {
+        Some(value) => quote! { Some(#value) },
+        None => quote! { None },
+    }
+}
+
+/// Generate a lookup of a per field value by its serde renamed name.
+///
+/// `values` must be ordered by field.
+fn field_lookup(
+    values: &[proc_macro2::TokenStream],
+    ty: proc_macro2::TokenStream,
+    fallback: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let field_len = values.len();
+
+    quote! {
+        // Array of values ordered by field.
+        static VALUES: [#ty; #field_len] = [#(#values,)*];
+
+        // Serde renamed field names ordered by field.
+        let introspected = <Self as actix_multipart_extract::form::MultipartForm>::fields();
+
+        match introspected.iter().position(|f| f == &field) {
+            Some(i) => VALUES[i],
+            None => #fallback
+        }
+    }
+}
+
 #[proc_macro_derive(MultipartForm, attributes(multipart))]
 pub fn multipart_form(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
-    let name = ast.ident;
+    let name = &ast.ident;
 
-    let fields = if let Data::Struct(syn::DataStruct {
-        fields: Fields::Named(FieldsNamed { ref named, .. }),
+    if let Data::Struct(syn::DataStruct {
+        fields: Fields::Unnamed(fields),
         ..
-    }) = ast.data
+    }) = &ast.data
     {
-        named
+        return match newtype_form(&ast, fields) {
+            Ok(v) => v.into(),
+            Err(err) => err.to_compile_error().into(),
+        };
+    }
+
+    // Enums are read like a struct with the fields of every variant
+    let tagged = match &ast.data {
+        Data::Enum(data) => match tagged::TaggedEnum::parse(name, &ast.attrs, data) {
+            Ok(v) => Some(v),
+            Err(err) => return err.to_compile_error().into(),
+        },
+        _ => None,
+    };
+
+    let fields = match &tagged {
+        Some(tagged) => &tagged.fields,
+        None => match named_fields(&ast, "MultipartForm") {
+            Ok(v) => v,
+            Err(err) => return err.to_compile_error().into(),
+        },
+    };
+
+    let options = match FieldOptions::from_fields(&ast.attrs, fields) {
+        Ok(v) => v,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    let generics = form_generics(&ast);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let field_max_sizes: Vec<_> = options
+        .iter()
+        .map(|options| match &options.max_size {
+            Some(max_size) => quote! { Some(#max_size) },
+            None => quote! { None },
+        })
+        .collect();
+
+    let max_size_lookup = field_lookup(&field_max_sizes, quote! { Option<usize> }, quote! { None });
+
+    // Only generated when used, this requires the `sniff` feature.
+    let verify_content_type = if options.iter().any(|o| o.verify_content_type) {
+        let values: Vec<_> = options
+            .iter()
+            .map(|options| {
+                let verify = options.verify_content_type;
+                quote! { #verify }
+            })
+            .collect();
+
+        let lookup = field_lookup(&values, quote! { bool }, quote! { false });
+
+        quote! {
+            fn verify_content_type(field: &str) -> bool {
+                #lookup
+            }
+        }
     } else {
-        panic!("can only derive on a struct")
+        quote! {}
     };
 
-    let field_max_sizes = fields.iter().map(|field| {
-        let Field { attrs, .. } = field;
+    // Only generated when used, this requires the `sniff` feature.
+    let strict_content_type = if options.iter().any(|o| o.strict_content_type) {
+        let values: Vec<_> = options
+            .iter()
+            .map(|options| {
+                let strict = options.strict_content_type;
+                quote! { #strict }
+            })
+            .collect();
+
+        let lookup = field_lookup(&values, quote! { bool }, quote! { false });
 
-        for attr in attrs {
-            if let Ok(meta) = attr.parse_meta() {
-                if let Meta::List(MetaList { path, nested, .. }) = meta {
-                    // Check for multipart attribute.
-                    if path.get_ident().unwrap()
-                        != &Ident::new("multipart", proc_macro2::Span::call_site())
-                    {
-                        continue;
+        quote! {
+            fn strict_content_type(field: &str) -> bool {
+                #lookup
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Only generated when used, this requires the `image` feature.
+    let image_rules = if options.iter().any(|o| o.image.is_some()) {
+        let values: Vec<_> = options
+            .iter()
+            .map(|options| match &options.image {
+                Some(image) => {
+                    let max_width = option_tokens(image.max_width);
+                    let max_height = option_tokens(image.max_height);
+                    let formats = &image.formats;
+
+                    quote! {
+                        Some(actix_multipart_extract::ImageRules {
+                            max_width: #max_width,
+                            max_height: #max_height,
+                            formats: &[#(#formats),*],
+                        })
                     }
+                }
+                None => quote! { None },
+            })
+            .collect();
+
+        let lookup = field_lookup(
+            &values,
+            quote! { Option<actix_multipart_extract::ImageRules> },
+            quote! { None },
+        );
+
+        quote! {
+            fn image_rules(field: &str) -> Option<actix_multipart_extract::ImageRules> {
+                #lookup
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Only generated when used.
+    let deadline = if options.iter().any(|o| o.deadline.is_some()) {
+        let values: Vec<_> = options
+            .iter()
+            .map(|options| match options.deadline {
+                Some(millis) => quote! { Some(std::time::Duration::from_millis(#millis)) },
+                None => quote! { None },
+            })
+            .collect();
+
+        let lookup = field_lookup(
+            &values,
+            quote! { Option<std::time::Duration> },
+            quote! { None },
+        );
+
+        quote! {
+            fn deadline(field: &str) -> Option<std::time::Duration> {
+                #lookup
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Only generated when used, this requires the `hash` feature.
+    let hash_algorithm = if options.iter().any(|o| o.hash.is_some()) {
+        let values: Vec<_> = options
+            .iter()
+            .map(|options| match &options.hash {
+                Some(variant) => quote! { Some(actix_multipart_extract::HashAlgorithm::#variant) },
+                None => quote! { None },
+            })
+            .collect();
+
+        let lookup = field_lookup(
+            &values,
+            quote! { Option<actix_multipart_extract::HashAlgorithm> },
+            quote! { None },
+        );
+
+        quote! {
+            fn hash_algorithm(field: &str) -> Option<actix_multipart_extract::HashAlgorithm> {
+                #lookup
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Only generated when used.
+    let as_file = if options.iter().any(|o| o.as_file) {
+        let values: Vec<_> = options
+            .iter()
+            .map(|options| {
+                let as_file = options.as_file;
+                quote! { #as_file }
+            })
+            .collect();
+
+        let lookup = field_lookup(&values, quote! { bool }, quote! { false });
+
+        quote! {
+            fn as_file(field: &str) -> bool {
+                #lookup
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Only generated when used.
+    let base64_file = if options.iter().any(|o| o.base64_file) {
+        let values: Vec<_> = options
+            .iter()
+            .map(|options| {
+                let base64_file = options.base64_file;
+                quote! { #base64_file }
+            })
+            .collect();
+
+        let lookup = field_lookup(&values, quote! { bool }, quote! { false });
+
+        quote! {
+            fn base64_file(field: &str) -> bool {
+                #lookup
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Only generated when used.
+    let as_text = if options.iter().any(|o| o.as_text) {
+        let values: Vec<_> = options
+            .iter()
+            .map(|options| {
+                let as_text = options.as_text;
+                quote! { #as_text }
+            })
+            .collect();
+
+        let lookup = field_lookup(&values, quote! { bool }, quote! { false });
+
+        quote! {
+            fn as_text(field: &str) -> bool {
+                #lookup
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Only generated when used.
+    let parser = if options.iter().any(|o| o.with.is_some()) {
+        let values: Vec<_> = options
+            .iter()
+            .map(|options| match &options.with {
+                Some(with) => quote! { Some(#with) },
+                None => quote! { None },
+            })
+            .collect();
+
+        let lookup = field_lookup(
+            &values,
+            quote! { Option<actix_multipart_extract::form::PartParser> },
+            quote! { None },
+        );
+
+        quote! {
+            fn parser(field: &str) -> Option<actix_multipart_extract::form::PartParser> {
+                #lookup
+            }
+        }
+    } else {
+        quote! {}
+    };
 
-                    if let Some(NestedMeta::Meta(Meta::NameValue(MetaNameValue {
-                        path: Path { segments, .. },
-                        lit,
-                        ..
-                    }))) = nested.first()
-                    {
-                        for segment in segments {
-                            if &segment.ident == &Ident::new("max_size", Span::call_site()) {
-                                let lit_string = match lit {
-                                    Lit::Int(l) => l.to_string(),
-                                    Lit::Float(f) => f.to_string(),
-                                    _ => {
-                                        return syn::Error::new(
-                                            lit.span(),
-                                            "must be a number with size suffix",
-                                        )
-                                        .to_compile_error()
-                                    }
-                                };
-
-                                let max_size = match parse_size(lit_string) {
-                                    Ok(v) => v as usize,
-                                    Err(_) => {
-                                        return syn::Error::new(lit.span(), "invalid size")
-                                            .to_compile_error();
-                                    }
-                                };
-
-                                return quote! { Some(#max_size) };
-                            }
-                        }
+    // Only generated when used.
+    let normalize = if options
+        .iter()
+        .any(|o| o.trim || o.collapse_whitespace || o.lowercase)
+    {
+        let values: Vec<_> = options
+            .iter()
+            .map(|options| {
+                let (trim, collapse_whitespace, lowercase) =
+                    (options.trim, options.collapse_whitespace, options.lowercase);
+
+                quote! {
+                    actix_multipart_extract::form::Normalize {
+                        trim: #trim,
+                        collapse_whitespace: #collapse_whitespace,
+                        lowercase: #lowercase,
                     }
                 }
+            })
+            .collect();
+
+        let lookup = field_lookup(
+            &values,
+            quote! { actix_multipart_extract::form::Normalize },
+            quote! { actix_multipart_extract::form::Normalize::NONE },
+        );
+
+        quote! {
+            fn normalize(field: &str) -> actix_multipart_extract::form::Normalize {
+                #lookup
             }
         }
+    } else {
+        quote! {}
+    };
 
-        quote! { None }
-    });
+    // Only generated when used.
+    let text_arms: Vec<_> = options
+        .iter()
+        .map(text_checks)
+        .enumerate()
+        .filter(|(_, checks)| !checks.is_empty())
+        .map(|(i, checks)| {
+            quote! {
+                Some(#i) => {
+                    #(#checks)*
+                    Ok(())
+                }
+            }
+        })
+        .collect();
+
+    let validate_text = if text_arms.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            fn validate_text(field: &str, value: &str) -> Result<(), String> {
+                let introspected = <Self as actix_multipart_extract::form::MultipartForm>::fields();
+
+                match introspected.iter().position(|f| f == &field) {
+                    #(#text_arms,)*
+                    _ => Ok(()),
+                }
+            }
+        }
+    };
+
+    // Only generated when used.
+    let filename_arms: Vec<_> = options
+        .iter()
+        .map(filename_checks)
+        .enumerate()
+        .filter(|(_, checks)| !checks.is_empty())
+        .map(|(i, checks)| {
+            quote! {
+                Some(#i) => {
+                    #(#checks)*
+                    Ok(())
+                }
+            }
+        })
+        .collect();
+
+    let validate_filename = if filename_arms.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            fn validate_filename(field: &str, value: &str) -> Result<(), String> {
+                let introspected = <Self as actix_multipart_extract::form::MultipartForm>::fields();
+
+                match introspected.iter().position(|f| f == &field) {
+                    #(#filename_arms,)*
+                    _ => Ok(()),
+                }
+            }
+        }
+    };
+
+    // Only generated when used.
+    let field_kind = if fields
+        .iter()
+        .any(|field| types::is_text(&field.ty) || types::is_bytes(&field.ty))
+    {
+        let values: Vec<_> = fields
+            .iter()
+            .map(|field| {
+                if types::is_text(&field.ty) {
+                    quote! { actix_multipart_extract::form::FieldKind::Text }
+                } else if types::is_bytes(&field.ty) {
+                    quote! { actix_multipart_extract::form::FieldKind::Bytes }
+                } else {
+                    quote! { actix_multipart_extract::form::FieldKind::Guess }
+                }
+            })
+            .collect();
+
+        let lookup = field_lookup(
+            &values,
+            quote! { actix_multipart_extract::form::FieldKind },
+            quote! { actix_multipart_extract::form::FieldKind::Guess },
+        );
+
+        quote! {
+            fn field_kind(field: &str) -> actix_multipart_extract::form::FieldKind {
+                #lookup
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Only generated when used.
+    let spooled = if fields.iter().any(|field| types::is_spooled_file(&field.ty)) {
+        let values: Vec<_> = fields
+            .iter()
+            .map(|field| {
+                let spooled = types::is_spooled_file(&field.ty);
+                quote! { #spooled }
+            })
+            .collect();
+
+        let lookup = field_lookup(&values, quote! { bool }, quote! { false });
+
+        quote! {
+            fn spooled(field: &str) -> bool {
+                #lookup
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Only generated when used, this requires the `chrono` feature.
+    let convert_arms: Vec<_> = fields
+        .iter()
+        .zip(&options)
+        .enumerate()
+        .filter_map(|(i, (field, options))| {
+            let format = options.format.as_ref()?;
+            let ty = types::inner_type(&field.ty);
+
+            Some(quote! {
+                Some(#i) => actix_multipart_extract::form::parse_format::<#ty>(value, #format)
+                    .map(Some)
+            })
+        })
+        .collect();
+
+    let convert_text = if convert_arms.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            fn convert_text(
+                field: &str,
+                value: &str,
+            ) -> Result<Option<actix_multipart_extract::serde_json::Value>, String> {
+                let introspected = <Self as actix_multipart_extract::form::MultipartForm>::fields();
+
+                match introspected.iter().position(|f| f == &field) {
+                    #(#convert_arms,)*
+                    _ => Ok(None),
+                }
+            }
+        }
+    };
+
+    // Only generated when used, parts of `multipart/related` bodies are named by their Content-ID.
+    let content_id_arms: Vec<_> = options
+        .iter()
+        .enumerate()
+        .filter_map(|(i, options)| {
+            let content_id = options.content_id.as_ref()?;
+            Some(quote! { #content_id => Some(introspected[#i]) })
+        })
+        .collect();
+
+    let content_id_field = if content_id_arms.is_empty() {
+        quote! {}
+    } else {
+        let values: Vec<_> = fields
+            .iter()
+            .map(|field| {
+                let is_file = types::is_file(&field.ty);
+                quote! { #is_file }
+            })
+            .collect();
+
+        let lookup = field_lookup(&values, quote! { bool }, quote! { false });
+
+        quote! {
+            fn content_id_field(content_id: &str) -> Option<&'static str> {
+                let introspected = <Self as actix_multipart_extract::form::MultipartForm>::fields();
+
+                match content_id {
+                    #(#content_id_arms,)*
+                    _ => None,
+                }
+            }
+
+            fn is_file(field: &str) -> bool {
+                #lookup
+            }
+        }
+    };
+
+    // Only generated when used, parts converted with `with` are converted into the whole list.
+    let is_list = |(field, options): (&Field, &FieldOptions)| {
+        types::is_list(&field.ty) && options.with.is_none()
+    };
+
+    let is_list = if fields.iter().zip(&options).any(is_list) {
+        let values: Vec<_> = fields
+            .iter()
+            .zip(&options)
+            .map(|field| {
+                let is_list = is_list(field);
+                quote! { #is_list }
+            })
+            .collect();
+
+        let lookup = field_lookup(&values, quote! { bool }, quote! { false });
+
+        quote! {
+            fn is_list(field: &str) -> bool {
+                #lookup
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Parts are matched by the renames and aliases of fields before their serde renamed names.
+    let mut name_arms = Vec::new();
+    let mut names = Vec::new();
+    let mut name_lits = Vec::new();
+
+    for (i, options) in options.iter().enumerate() {
+        for name in options.rename.iter().chain(&options.aliases) {
+            if names.contains(&name.value()) {
+                return syn::Error::new(name.span(), "name is already used by another field")
+                    .to_compile_error()
+                    .into();
+            }
+
+            names.push(name.value());
+            name_lits.push(name);
+            name_arms.push(quote! { #name => Some(introspected[#i]) });
+        }
+    }
+
+    let renamed: Vec<_> = options.iter().map(|o| o.rename.is_some()).collect();
+    let field_len = renamed.len();
+
+    let field_for_part = quote! {
+        fn field_for_part(name: &str) -> Option<&'static str> {
+            // Whether fields have a rename, ordered by field.
+            static RENAMED: [bool; #field_len] = [#(#renamed,)*];
+
+            let introspected = <Self as actix_multipart_extract::form::MultipartForm>::fields();
+
+            match name {
+                #(#name_arms,)*
+                _ => introspected
+                    .iter()
+                    .position(|f| f == &name)
+                    .filter(|i| !RENAMED[*i])
+                    .map(|i| introspected[i]),
+            }
+        }
+
+        fn part_names() -> Vec<&'static str> {
+            // Whether fields have a rename, ordered by field.
+            static RENAMED: [bool; #field_len] = [#(#renamed,)*];
+
+            let introspected = <Self as actix_multipart_extract::form::MultipartForm>::fields();
+
+            let mut names = vec![#(#name_lits,)*];
+            names.extend(
+                introspected
+                    .iter()
+                    .zip(RENAMED)
+                    .filter(|(_, renamed)| !renamed)
+                    .map(|(field, _)| *field),
+            );
+            names
+        }
+    };
+
+    // Only generated when used.
+    let default_arms: Vec<_> = fields
+        .iter()
+        .zip(&options)
+        .enumerate()
+        .filter_map(|(i, (field, options))| {
+            let ty = &field.ty;
+
+            Some(match options.default.as_ref()? {
+                FieldDefault::Text(text) => quote! {
+                    Some(#i) => Ok(Some(actix_multipart_extract::form::FieldDefault::Text(#text)))
+                },
+                FieldDefault::Function(path) => quote! {
+                    Some(#i) => actix_multipart_extract::serde_json::to_value::<#ty>(#path())
+                        .map(|value| Some(actix_multipart_extract::form::FieldDefault::Value(value)))
+                        .map_err(|err| err.to_string())
+                },
+            })
+        })
+        .collect();
+
+    let field_default = if default_arms.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            fn field_default(
+                field: &str,
+            ) -> Result<Option<actix_multipart_extract::form::FieldDefault>, String> {
+                let introspected = <Self as actix_multipart_extract::form::MultipartForm>::fields();
+
+                match introspected.iter().position(|f| f == &field) {
+                    #(#default_arms,)*
+                    _ => Ok(None),
+                }
+            }
+        }
+    };
+
+    #[cfg(feature = "schemars")]
+    let json_schema = match &tagged {
+        Some(tagged) => schema::enum_json_schema(name, &generics, tagged, &options),
+        None => schema::json_schema(name, &generics, fields, &options),
+    };
+    #[cfg(not(feature = "schemars"))]
+    let json_schema = quote! {};
+
+    // Per field validation functions, called with a reference to the field.
+    let validate_field = |i: usize, ident: &proc_macro2::TokenStream| {
+        let validate = options[i].validate.as_ref()?;
+
+        Some(quote! {
+            if let Err(message) = #validate(#ident) {
+                return Err(actix_multipart_extract::MultipartError::FieldValidationError {
+                    field: <Self as actix_multipart_extract::form::MultipartForm>::fields()[#i]
+                        .to_string(),
+                    message,
+                });
+            }
+        })
+    };
+
+    #[allow(unused_mut)]
+    let mut validators: Vec<proc_macro2::TokenStream> = match &tagged {
+        Some(tagged) => tagged
+            .match_fields(|i, ident| validate_field(i, &quote! { #ident }))
+            .into_iter()
+            .collect(),
+        None => fields
+            .iter()
+            .enumerate()
+            .filter_map(|(i, field)| {
+                let ident = &field.ident;
+                validate_field(i, &quote! { &self.#ident })
+            })
+            .collect(),
+    };
+
+    // Forms implementing `validator::Validate` or `garde::Validate` are validated, this
+    // requires the `validate` or `garde` feature.
+
+    #[cfg(feature = "validate")]
+    validators.push(quote! {{
+        use actix_multipart_extract::form::{ValidatorProbe, ValidatorSkip, ValidatorValidate};
+
+        (&&ValidatorProbe(self)).validate_form()?;
+    }});
+
+    #[cfg(feature = "garde")]
+    validators.push(quote! {{
+        use actix_multipart_extract::form::{GardeProbe, GardeSkip, GardeValidate};
+
+        (&&GardeProbe(self)).validate_form()?;
+    }});
+
+    let validate = if validators.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            fn validate(&self) -> Result<(), actix_multipart_extract::MultipartError> {
+                #(#validators)*
+                Ok(())
+            }
+        }
+    };
 
-    let field_len = field_max_sizes.len();
+    let example = example::example(fields, &options, tagged.as_ref());
+
+    let fields_method = match &tagged {
+        Some(tagged) => tagged.methods(),
+        None => quote! {
+            fn fields() -> &'static [&'static str] {
+                actix_multipart_extract::serde_introspect::<Self>()
+            }
+        },
+    };
 
     let expanded = quote! {
-        impl actix_multipart_extract::form::MultipartForm for #name {
+        #json_schema
+
+        impl #impl_generics actix_multipart_extract::form::MultipartForm for #name #ty_generics
+        #where_clause
+        {
+            #fields_method
+
+            #example
+
             fn max_size(field: &str) -> Option<usize> {
-                // Array of max sizes ordered by field.
-                static max_sizes: [Option<usize>; #field_len] = [#(#field_max_sizes,)*];
+                #max_size_lookup
+            }
 
-                // Serde renamed field names ordered by field.
-                let introspected = actix_multipart_extract::serde_introspect::<Self>();
+            #deadline
 
-                match introspected.iter().position(|f| f == &field) {
-                    Some(i) => max_sizes[i],
-                    None => None
+            #verify_content_type
+
+            #strict_content_type
+
+            #hash_algorithm
+
+            #image_rules
+
+            #normalize
+
+            #validate_text
+
+            #validate_filename
+
+            #field_kind
+
+            #spooled
+
+            #convert_text
+
+            #content_id_field
+
+            #is_list
+
+            #as_file
+
+            #as_text
+
+            #base64_file
+
+            #parser
+
+            #field_default
+
+            #field_for_part
+
+            #validate
+        }
+    };
+
+    expanded.into()
+}
+
+/// Implement `MultipartForm` for a newtype by delegating to the form it wraps.
+fn newtype_form(
+    ast: &DeriveInput,
+    fields: &FieldsUnnamed,
+) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let inner = match fields.unnamed.first() {
+        Some(field) if fields.unnamed.len() == 1 => field,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                fields,
+                "tuple structs must have a single field wrapping a form",
+            ))
+        }
+    };
+
+    if let Some(meta) = multipart_options(&ast.attrs)?
+        .into_iter()
+        .chain(multipart_options(&inner.attrs)?)
+        .next()
+    {
+        return Err(syn::Error::new_spanned(
+            meta,
+            "options of newtype forms are set on the form they wrap",
+        ));
+    }
+
+    let name = &ast.ident;
+    let ty = &inner.ty;
+
+    let mut generics = form_generics(ast);
+    generics
+        .make_where_clause()
+        .predicates
+        .push(parse_quote! { #ty: actix_multipart_extract::form::MultipartForm });
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    #[cfg(feature = "schemars")]
+    let json_schema = schema::newtype_json_schema(name, &generics, ty);
+    #[cfg(not(feature = "schemars"))]
+    let json_schema = quote! {};
+
+    Ok(quote! {
+        #json_schema
+
+        impl #impl_generics actix_multipart_extract::form::MultipartForm for #name #ty_generics
+        #where_clause
+        {
+            fn fields() -> &'static [&'static str] {
+                <#ty as actix_multipart_extract::form::MultipartForm>::fields()
+            }
+
+            fn tag() -> Option<&'static str> {
+                <#ty as actix_multipart_extract::form::MultipartForm>::tag()
+            }
+
+            fn variant_fields(tag: &str) -> Option<&'static [&'static str]> {
+                <#ty as actix_multipart_extract::form::MultipartForm>::variant_fields(tag)
+            }
+
+            fn example() -> actix_multipart_extract::form::FormExample {
+                <#ty as actix_multipart_extract::form::MultipartForm>::example()
+            }
+
+            fn max_size(field: &str) -> Option<usize> {
+                <#ty as actix_multipart_extract::form::MultipartForm>::max_size(field)
+            }
+
+            fn deadline(field: &str) -> Option<std::time::Duration> {
+                <#ty as actix_multipart_extract::form::MultipartForm>::deadline(field)
+            }
+
+            fn normalize(field: &str) -> actix_multipart_extract::form::Normalize {
+                <#ty as actix_multipart_extract::form::MultipartForm>::normalize(field)
+            }
+
+            fn validate_text(field: &str, value: &str) -> Result<(), String> {
+                <#ty as actix_multipart_extract::form::MultipartForm>::validate_text(field, value)
+            }
+
+            fn validate_filename(field: &str, value: &str) -> Result<(), String> {
+                <#ty as actix_multipart_extract::form::MultipartForm>::validate_filename(
+                    field, value,
+                )
+            }
+
+            fn field_kind(field: &str) -> actix_multipart_extract::form::FieldKind {
+                <#ty as actix_multipart_extract::form::MultipartForm>::field_kind(field)
+            }
+
+            fn spooled(field: &str) -> bool {
+                <#ty as actix_multipart_extract::form::MultipartForm>::spooled(field)
+            }
+
+            fn convert_text(
+                field: &str,
+                value: &str,
+            ) -> Result<Option<actix_multipart_extract::serde_json::Value>, String> {
+                <#ty as actix_multipart_extract::form::MultipartForm>::convert_text(field, value)
+            }
+
+            fn content_id_field(content_id: &str) -> Option<&'static str> {
+                <#ty as actix_multipart_extract::form::MultipartForm>::content_id_field(content_id)
+            }
+
+            fn field_for_part(name: &str) -> Option<&'static str> {
+                <#ty as actix_multipart_extract::form::MultipartForm>::field_for_part(name)
+            }
+
+            fn part_names() -> Vec<&'static str> {
+                <#ty as actix_multipart_extract::form::MultipartForm>::part_names()
+            }
+
+            fn is_file(field: &str) -> bool {
+                <#ty as actix_multipart_extract::form::MultipartForm>::is_file(field)
+            }
+
+            fn is_list(field: &str) -> bool {
+                <#ty as actix_multipart_extract::form::MultipartForm>::is_list(field)
+            }
+
+            fn as_file(field: &str) -> bool {
+                <#ty as actix_multipart_extract::form::MultipartForm>::as_file(field)
+            }
+
+            fn as_text(field: &str) -> bool {
+                <#ty as actix_multipart_extract::form::MultipartForm>::as_text(field)
+            }
+
+            fn base64_file(field: &str) -> bool {
+                <#ty as actix_multipart_extract::form::MultipartForm>::base64_file(field)
+            }
+
+            fn parser(field: &str) -> Option<actix_multipart_extract::form::PartParser> {
+                <#ty as actix_multipart_extract::form::MultipartForm>::parser(field)
+            }
+
+            fn field_default(
+                field: &str,
+            ) -> Result<Option<actix_multipart_extract::form::FieldDefault>, String> {
+                <#ty as actix_multipart_extract::form::MultipartForm>::field_default(field)
+            }
+
+            fn validate(&self) -> Result<(), actix_multipart_extract::MultipartError> {
+                actix_multipart_extract::form::MultipartForm::validate(&self.0)
+            }
+
+            actix_multipart_extract::__delegate_feature_methods!(#ty);
+        }
+    })
+}
+
+/// Write the values of a bytes field as raw parts, a part for every element of lists named
+/// `name[]`.
+fn write_bytes(
+    ty: &Type,
+    value: proc_macro2::TokenStream,
+    name: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    match types::wrapped_type(ty) {
+        Some(("Option", inner)) => {
+            let write = write_bytes(inner, quote! { value }, name);
+            quote! {
+                if let Some(value) = #value {
+                    #write
                 }
             }
         }
+        Some((_, inner)) => {
+            let write = write_bytes(inner, quote! { value }, quote! { name.as_str() });
+            quote! {
+                {
+                    let name = format!("{}[]", #name);
+                    for value in #value {
+                        #write
+                    }
+                }
+            }
+        }
+        None => quote! {
+            body.bytes(#name, AsRef::<[u8]>::as_ref(#value).to_vec());
+        },
+    }
+}
+
+#[proc_macro_derive(MultipartSerialize, attributes(multipart))]
+pub fn multipart_serialize(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    let name = &ast.ident;
+
+    let fields = match named_fields(&ast, "MultipartSerialize") {
+        Ok(v) => v,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    let options = match FieldOptions::from_fields(&ast.attrs, fields) {
+        Ok(v) => v,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    let writes = fields
+        .iter()
+        .zip(&options)
+        .enumerate()
+        .map(|(i, (field, options))| {
+            let ident = &field.ident;
+            let name = options.part_name(i);
+
+            if types::is_any_file(&field.ty) {
+                quote! {
+                    actix_multipart_extract::FileField::write_file(&self.#ident, #name, body)?;
+                }
+            } else if types::is_bytes(&field.ty) {
+                let write = write_bytes(&field.ty, quote! { &self.#ident }, name);
+                quote! { #write }
+            } else {
+                quote! {
+                    body.serialize(#name, &self.#ident)?;
+                }
+            }
+        });
+
+    let generics = form_generics(&ast);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let expanded = quote! {
+        impl #impl_generics actix_multipart_extract::MultipartSerialize for #name #ty_generics
+        #where_clause
+        {
+            fn write_multipart(
+                &self,
+                body: &mut actix_multipart_extract::MultipartBody,
+            ) -> Result<(), actix_multipart_extract::serde_json::Error> {
+                // Serde renamed field names ordered by field.
+                let introspected = <Self as actix_multipart_extract::form::MultipartForm>::fields();
+
+                #(#writes)*
+                Ok(())
+            }
+        }
     };
 
     expanded.into()
@@ -0,0 +1,128 @@
+use syn::{Attribute, Lit, Meta, MetaNameValue, NestedMeta};
+
+/// Naming convention set with `#[multipart(rename_all = "...")]` on a struct, applied to the
+/// snake_case names of its fields.
+#[derive(Clone, Copy)]
+pub enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    /// Parse the `rename_all` option of the container attributes, if set.
+    pub fn from_attrs(attrs: &[Attribute]) -> Result<Option<Self>, syn::Error> {
+        let mut rule = None;
+        let mut seen = crate::check::SeenOptions::default();
+
+        for meta in crate::multipart_options(attrs)? {
+            if let NestedMeta::Meta(meta) = &meta {
+                seen.add(meta.path())?;
+            }
+
+            match meta {
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, lit, .. }))
+                    if path.is_ident("rename_all") =>
+                {
+                    rule = Some(Self::parse(&lit)?);
+                }
+                // Parsed with the variants of enums
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, .. }))
+                    if path.is_ident("tag") => {}
+                meta => {
+                    return Err(syn::Error::new_spanned(
+                        meta,
+                        "expected `rename_all` or `tag`",
+                    ))
+                }
+            }
+        }
+
+        Ok(rule)
+    }
+
+    pub fn parse(lit: &Lit) -> Result<Self, syn::Error> {
+        let rule = match lit {
+            Lit::Str(s) => match s.value().as_str() {
+                "lowercase" => Some(Self::Lower),
+                "UPPERCASE" => Some(Self::Upper),
+                "PascalCase" => Some(Self::Pascal),
+                "camelCase" => Some(Self::Camel),
+                "snake_case" => Some(Self::Snake),
+                "SCREAMING_SNAKE_CASE" => Some(Self::ScreamingSnake),
+                "kebab-case" => Some(Self::Kebab),
+                "SCREAMING-KEBAB-CASE" => Some(Self::ScreamingKebab),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        rule.ok_or_else(|| {
+            syn::Error::new(
+                lit.span(),
+                "must be one of lowercase, UPPERCASE, PascalCase, camelCase, snake_case, \
+                 SCREAMING_SNAKE_CASE, kebab-case or SCREAMING-KEBAB-CASE",
+            )
+        })
+    }
+
+    /// Rename a snake_case field name.
+    pub fn apply(self, field: &str) -> String {
+        match self {
+            Self::Lower | Self::Snake => field.to_owned(),
+            Self::Upper | Self::ScreamingSnake => field.to_ascii_uppercase(),
+            Self::Pascal | Self::Camel => {
+                let mut renamed = String::new();
+                let mut capitalize = matches!(self, Self::Pascal);
+
+                for c in field.chars() {
+                    if c == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        renamed.push(c.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        renamed.push(c);
+                    }
+                }
+
+                renamed
+            }
+            Self::Kebab => field.replace('_', "-"),
+            Self::ScreamingKebab => field.replace('_', "-").to_ascii_uppercase(),
+        }
+    }
+
+    /// Rename a PascalCase variant name.
+    pub fn apply_to_variant(self, variant: &str) -> String {
+        match self {
+            Self::Pascal => variant.to_owned(),
+            Self::Lower => variant.to_ascii_lowercase(),
+            Self::Upper => variant.to_ascii_uppercase(),
+            Self::Camel => {
+                let mut chars = variant.chars();
+                match chars.next() {
+                    Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+                    None => String::new(),
+                }
+            }
+            Self::Snake | Self::ScreamingSnake | Self::Kebab | Self::ScreamingKebab => {
+                let mut snake = String::new();
+
+                for (i, c) in variant.char_indices() {
+                    if i > 0 && c.is_uppercase() {
+                        snake.push('_');
+                    }
+                    snake.push(c.to_ascii_lowercase());
+                }
+
+                self.apply(&snake)
+            }
+        }
+    }
+}
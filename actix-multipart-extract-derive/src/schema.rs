@@ -0,0 +1,211 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parse_quote, punctuated::Punctuated, token::Comma, Field, GenericArgument, Generics, Ident,
+    PathArguments, Type,
+};
+
+use crate::{tagged::TaggedEnum, FieldOptions};
+
+/// Whether the type is an `Option`, these fields are not required.
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path.path.segments.last().is_some_and(|segment| {
+            segment.ident == "Option"
+                && matches!(
+                    &segment.arguments,
+                    PathArguments::AngleBracketed(args)
+                        if matches!(args.args.first(), Some(GenericArgument::Type(_)))
+                )
+        }),
+        _ => false,
+    }
+}
+
+/// Generate a `schemars::JsonSchema` implementation describing the multipart fields.
+///
+/// File fields are described by the `File` schema as binary strings, max sizes are added as
+/// an `x-max-size` annotation on the field.
+pub(crate) fn json_schema(
+    name: &Ident,
+    generics: &Generics,
+    fields: &Punctuated<Field, Comma>,
+    options: &[FieldOptions],
+) -> TokenStream {
+    let properties = fields
+        .iter()
+        .zip(options)
+        .enumerate()
+        .map(|(i, (field, options))| property(i, field, options));
+
+    impl_json_schema(
+        name,
+        generics,
+        quote! {
+            // Serde renamed field names ordered by field.
+            let introspected = <Self as actix_multipart_extract::form::MultipartForm>::fields();
+            let mut object = schemars::schema::ObjectValidation::default();
+
+            #(#properties)*
+
+            schemars::schema::SchemaObject {
+                instance_type: Some(schemars::schema::InstanceType::Object.into()),
+                object: Some(Box::new(object)),
+                ..Default::default()
+            }
+            .into()
+        },
+    )
+}
+
+/// Generate a `schemars::JsonSchema` implementation of an enum form, one of the objects of its
+/// variants with their tag.
+pub(crate) fn enum_json_schema(
+    name: &Ident,
+    generics: &Generics,
+    tagged: &TaggedEnum,
+    options: &[FieldOptions],
+) -> TokenStream {
+    let tag = &tagged.tag;
+
+    let variants = tagged.variants.iter().map(|variant| {
+        let value = &variant.tag;
+        let properties = variant
+            .fields
+            .iter()
+            .map(|i| property(*i, &tagged.fields[*i], &options[*i]));
+
+        quote! {{
+            let mut object = schemars::schema::ObjectValidation::default();
+
+            object.properties.insert(
+                #tag.to_owned(),
+                schemars::schema::SchemaObject {
+                    instance_type: Some(schemars::schema::InstanceType::String.into()),
+                    enum_values: Some(vec![#value.into()]),
+                    ..Default::default()
+                }
+                .into(),
+            );
+            object.required.insert(#tag.to_owned());
+
+            #(#properties)*
+
+            variants.push(
+                schemars::schema::SchemaObject {
+                    instance_type: Some(schemars::schema::InstanceType::Object.into()),
+                    object: Some(Box::new(object)),
+                    ..Default::default()
+                }
+                .into(),
+            );
+        }}
+    });
+
+    impl_json_schema(
+        name,
+        generics,
+        quote! {
+            // Serde renamed field names ordered by field.
+            let introspected = <Self as actix_multipart_extract::form::MultipartForm>::fields();
+            let mut variants = Vec::new();
+
+            #(#variants)*
+
+            schemars::schema::SchemaObject {
+                subschemas: Some(Box::new(schemars::schema::SubschemaValidation {
+                    one_of: Some(variants),
+                    ..Default::default()
+                })),
+                ..Default::default()
+            }
+            .into()
+        },
+    )
+}
+
+/// Generate a `schemars::JsonSchema` implementation of a newtype form, described by the
+/// schema of the form it wraps.
+pub(crate) fn newtype_json_schema(name: &Ident, generics: &Generics, inner: &Type) -> TokenStream {
+    impl_json_schema(
+        name,
+        generics,
+        quote! {
+            <#inner as schemars::JsonSchema>::json_schema(gen)
+        },
+    )
+}
+
+/// Insert the schema of the `i`th field into `object`.
+fn property(i: usize, field: &Field, options: &FieldOptions) -> TokenStream {
+    let ty = &field.ty;
+    let required = !is_option(ty);
+    let part_name = options.part_name(i);
+
+    let max_size = match &options.max_size {
+        Some(max_size) => quote! {
+            if let schemars::schema::Schema::Object(object) = &mut schema {
+                object.extensions.insert(
+                    "x-max-size".to_owned(),
+                    actix_multipart_extract::serde_json::Value::from(#max_size),
+                );
+            }
+        },
+        None => quote! {},
+    };
+
+    quote! {
+        {
+            #[allow(unused_mut)]
+            let mut schema = gen.subschema_for::<#ty>();
+            #max_size
+
+            object.properties.insert(#part_name.to_owned(), schema);
+            if #required {
+                object.required.insert(#part_name.to_owned());
+            }
+        }
+    }
+}
+
+/// Implement `schemars::JsonSchema` building the schema with `body`.
+fn impl_json_schema(name: &Ident, generics: &Generics, body: TokenStream) -> TokenStream {
+    // Type parameters are described by their own schema.
+    let mut generics = generics.clone();
+    let params: Vec<_> = generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect();
+    for param in params {
+        generics.make_where_clause().predicates.push(parse_quote! {
+            #param: actix_multipart_extract::schemars::JsonSchema
+        });
+    }
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    // Instances of generic forms share the name, they are inlined instead of referenced.
+    let is_referenceable = generics.params.is_empty();
+
+    quote! {
+        impl #impl_generics actix_multipart_extract::schemars::JsonSchema for #name #ty_generics
+        #where_clause
+        {
+            fn schema_name() -> String {
+                stringify!(#name).to_owned()
+            }
+
+            fn is_referenceable() -> bool {
+                #is_referenceable
+            }
+
+            fn json_schema(
+                gen: &mut actix_multipart_extract::schemars::gen::SchemaGenerator,
+            ) -> actix_multipart_extract::schemars::schema::Schema {
+                use actix_multipart_extract::schemars;
+
+                #body
+            }
+        }
+    }
+}
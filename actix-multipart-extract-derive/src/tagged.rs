@@ -0,0 +1,256 @@
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::{
+    punctuated::Punctuated, token::Comma, Attribute, DataEnum, Field, Fields, Ident, Lit, LitStr,
+    Meta, MetaNameValue, NestedMeta,
+};
+
+use crate::rename::RenameRule;
+
+/// Enum form selecting its variant with the part named by `#[multipart(tag = "...")]`.
+pub struct TaggedEnum {
+    /// Name of the tag part.
+    pub tag: LitStr,
+    pub variants: Vec<Variant>,
+    /// Fields of every variant, fields with the same name in several variants are only listed
+    /// once.
+    pub fields: Punctuated<Field, Comma>,
+    /// Serde names of `fields`.
+    pub names: Vec<String>,
+}
+
+/// A variant of a [`TaggedEnum`].
+pub struct Variant {
+    pub ident: Ident,
+    /// Text of the tag part selecting the variant.
+    pub tag: String,
+    /// Indexes of the fields of the variant in [`TaggedEnum::fields`].
+    pub fields: Vec<usize>,
+}
+
+/// Get the `#[multipart(tag = "...")]` option of the container attributes, if set.
+pub fn multipart_tag(attrs: &[Attribute]) -> Result<Option<LitStr>, syn::Error> {
+    let mut tag = None;
+
+    for meta in crate::multipart_options(attrs)? {
+        if let NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, lit, .. })) = meta {
+            if path.is_ident("tag") {
+                tag = Some(crate::parse_str(&lit)?);
+            }
+        }
+    }
+
+    Ok(tag)
+}
+
+/// Get a string option of the `#[serde(...)]` attributes, malformed attributes are reported by
+/// serde.
+fn serde_option(attrs: &[Attribute], name: &str) -> Option<LitStr> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("serde"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::List(list)) => Some(list.nested),
+            _ => None,
+        })
+        .flatten()
+        .find_map(|meta| match meta {
+            NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                path,
+                lit: Lit::Str(value),
+                ..
+            })) if path.is_ident(name) => Some(value),
+            _ => None,
+        })
+}
+
+impl TaggedEnum {
+    pub fn parse(ident: &Ident, attrs: &[Attribute], data: &DataEnum) -> Result<Self, syn::Error> {
+        let tag = multipart_tag(attrs)?.ok_or_else(|| {
+            syn::Error::new_spanned(
+                data.enum_token,
+                "enums must set the part selecting their variant with `#[multipart(tag = \"...\")]`",
+            )
+        })?;
+
+        // Serde reads the tag from the same map as the fields
+        if serde_option(attrs, "tag").map(|serde_tag| serde_tag.value()) != Some(tag.value()) {
+            return Err(syn::Error::new_spanned(
+                &tag,
+                format!(
+                    "{ident} must also be tagged with `#[serde(tag = {:?})]`",
+                    tag.value()
+                ),
+            ));
+        }
+
+        let rename_all = match serde_option(attrs, "rename_all") {
+            Some(rule) => Some(RenameRule::parse(&Lit::Str(rule))?),
+            None => None,
+        };
+
+        let mut tagged = Self {
+            tag,
+            variants: Vec::new(),
+            fields: Punctuated::new(),
+            names: Vec::new(),
+        };
+
+        for variant in &data.variants {
+            let fields = match &variant.fields {
+                Fields::Named(fields) => fields.named.iter().collect(),
+                Fields::Unit => Vec::new(),
+                Fields::Unnamed(fields) => {
+                    return Err(syn::Error::new_spanned(
+                        fields,
+                        "variants of enum forms must have named fields",
+                    ))
+                }
+            };
+
+            let tag = match serde_option(&variant.attrs, "rename") {
+                Some(rename) => rename.value(),
+                None => match rename_all {
+                    Some(rule) => rule.apply_to_variant(&variant.ident.to_string()),
+                    None => variant.ident.to_string(),
+                },
+            };
+
+            let fields = fields
+                .into_iter()
+                .map(|field| tagged.add_field(field))
+                .collect::<Result<_, _>>()?;
+
+            tagged.variants.push(Variant {
+                ident: variant.ident.clone(),
+                tag,
+                fields,
+            });
+        }
+
+        Ok(tagged)
+    }
+
+    /// Add a field of a variant, returning its index in [`TaggedEnum::fields`].
+    fn add_field(&mut self, field: &Field) -> Result<usize, syn::Error> {
+        let ident = field.ident.as_ref().expect("named field");
+        let name = match serde_option(&field.attrs, "rename") {
+            Some(rename) => rename.value(),
+            None => ident.to_string().trim_start_matches("r#").to_owned(),
+        };
+
+        if name == self.tag.value() {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "field has the name of the tag of the enum",
+            ));
+        }
+
+        let i = match self.names.iter().position(|existing| *existing == name) {
+            Some(i) => i,
+            None => {
+                self.fields.push(field.clone());
+                self.names.push(name);
+                return Ok(self.fields.len() - 1);
+            }
+        };
+
+        // Options are looked up by name, fields sharing a name must be read the same way
+        let existing = &self.fields[i];
+        if existing.ty.to_token_stream().to_string() != field.ty.to_token_stream().to_string()
+            || multipart_attrs(existing) != multipart_attrs(field)
+        {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "fields named like a field of another variant must have the same type and \
+                 multipart options",
+            ));
+        }
+
+        Ok(i)
+    }
+
+    /// Generate the methods of `MultipartForm` describing the tag and the variants.
+    pub fn methods(&self) -> TokenStream {
+        let tag = &self.tag;
+        let names = &self.names;
+
+        let variant_arms = self.variants.iter().map(|variant| {
+            let value = &variant.tag;
+            let fields = variant.fields.iter().map(|i| &self.names[*i]);
+
+            quote! { #value => Some(&[#(#fields),*]) }
+        });
+
+        quote! {
+            fn fields() -> &'static [&'static str] {
+                &[#(#names),*]
+            }
+
+            fn tag() -> Option<&'static str> {
+                Some(#tag)
+            }
+
+            fn variant_fields(tag: &str) -> Option<&'static [&'static str]> {
+                match tag {
+                    #(#variant_arms,)*
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Generate a match running `check` with the identifier of every field for which it
+    /// returns checks, `check` is called with the index of the field.
+    pub fn match_fields(
+        &self,
+        mut check: impl FnMut(usize, &Ident) -> Option<TokenStream>,
+    ) -> Option<TokenStream> {
+        let arms: Vec<_> = self
+            .variants
+            .iter()
+            .filter_map(|variant| {
+                let (idents, checks): (Vec<_>, Vec<_>) = variant
+                    .fields
+                    .iter()
+                    .filter_map(|i| {
+                        let ident = self.fields[*i].ident.as_ref()?;
+                        Some((ident, check(*i, ident)?))
+                    })
+                    .unzip();
+
+                if checks.is_empty() {
+                    return None;
+                }
+
+                let variant = &variant.ident;
+                Some(quote! {
+                    Self::#variant { #(#idents,)* .. } => {
+                        #(#checks)*
+                    }
+                })
+            })
+            .collect();
+
+        if arms.is_empty() {
+            return None;
+        }
+
+        Some(quote! {
+            #[allow(unreachable_patterns)]
+            match self {
+                #(#arms)*
+                _ => {}
+            }
+        })
+    }
+}
+
+fn multipart_attrs(field: &Field) -> Vec<String> {
+    field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("multipart"))
+        .map(|attr| attr.to_token_stream().to_string())
+        .collect()
+}
@@ -0,0 +1,212 @@
+use syn::{GenericArgument, PathArguments, Type};
+
+/// Types deserialized from strings, text sent for these fields is never guessed as a number or
+/// boolean.
+const TEXT_TYPES: &[&str] = &[
+    "String",
+    // chrono
+    "DateTime",
+    "NaiveDate",
+    "NaiveDateTime",
+    "NaiveTime",
+    // time
+    "Date",
+    "OffsetDateTime",
+    "PrimitiveDateTime",
+    "Time",
+    // uuid
+    "Uuid",
+    // rust_decimal, bigdecimal
+    "Decimal",
+    "BigDecimal",
+    // smol_str, compact_str, arcstr
+    "SmolStr",
+    "CompactString",
+    "ArcStr",
+];
+
+/// Pointers which are deserialized from strings when they hold a `str`, like `Cow<'static, str>`.
+/// `Rc<str>` and `Arc<str>` require the `rc` feature of serde.
+const STR_POINTERS: &[&str] = &["Cow", "Box", "Rc", "Arc"];
+
+/// Types holding the raw bytes of a part, `Vec<u8>` is also one.
+const BYTES_TYPES: &[&str] = &["Bytes", "BytesMut", "ByteBuf"];
+
+/// Get the type of the values of a field, unwrapping `Option` and `Vec`. `Vec<u8>` is a value,
+/// not a list.
+pub(crate) fn inner_type(ty: &Type) -> &Type {
+    if let Type::Path(path) = ty {
+        if let Some(segment) = path.path.segments.last() {
+            if segment.ident == "Option" || segment.ident == "Vec" && !is_byte_vec(ty) {
+                if let PathArguments::AngleBracketed(args) = &segment.arguments {
+                    if let Some(GenericArgument::Type(ty)) = args.args.first() {
+                        return inner_type(ty);
+                    }
+                }
+            }
+        }
+    }
+
+    ty
+}
+
+/// Get the wrapper and wrapped type of an `Option` or `Vec` which isn't `Vec<u8>`.
+pub(crate) fn wrapped_type(ty: &Type) -> Option<(&'static str, &Type)> {
+    if let Type::Path(path) = ty {
+        if let Some(segment) = path.path.segments.last() {
+            let wrapper = if segment.ident == "Option" {
+                "Option"
+            } else if segment.ident == "Vec" && !is_byte_vec(ty) {
+                "Vec"
+            } else {
+                return None;
+            };
+
+            if let PathArguments::AngleBracketed(args) = &segment.arguments {
+                if let Some(GenericArgument::Type(ty)) = args.args.first() {
+                    return Some((wrapper, ty));
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Whether a field is a `Vec`, or an `Option` of one, collecting every part sent for it.
+pub(crate) fn is_list(ty: &Type) -> bool {
+    if let Type::Path(path) = ty {
+        if let Some(segment) = path.path.segments.last() {
+            if segment.ident == "Vec" {
+                return !is_byte_vec(ty);
+            }
+
+            if segment.ident == "Option" {
+                if let PathArguments::AngleBracketed(args) = &segment.arguments {
+                    if let Some(GenericArgument::Type(ty)) = args.args.first() {
+                        return is_list(ty);
+                    }
+                }
+            }
+        }
+    }
+
+    false
+}
+
+/// Whether a type is `Vec<u8>`.
+fn is_byte_vec(ty: &Type) -> bool {
+    if let Type::Path(path) = ty {
+        if let Some(segment) = path.path.segments.last() {
+            if segment.ident == "Vec" {
+                if let PathArguments::AngleBracketed(args) = &segment.arguments {
+                    if let Some(GenericArgument::Type(Type::Path(arg))) = args.args.first() {
+                        return arg.path.is_ident("u8");
+                    }
+                }
+            }
+        }
+    }
+
+    false
+}
+
+/// Whether the values of a field are the raw bytes of their parts.
+pub(crate) fn is_bytes(ty: &Type) -> bool {
+    let ty = inner_type(ty);
+    is_byte_vec(ty) || type_name(ty).is_some_and(|name| BYTES_TYPES.contains(&name.as_str()))
+}
+
+/// Whether the values of a field are files.
+pub(crate) fn is_file(ty: &Type) -> bool {
+    match inner_type(ty) {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "File"),
+        _ => false,
+    }
+}
+
+/// Whether the values of a field are `SpooledFile`s.
+pub(crate) fn is_spooled_file(ty: &Type) -> bool {
+    match inner_type(ty) {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "SpooledFile"),
+        _ => false,
+    }
+}
+
+/// Whether the values of a field are deserialized from strings.
+pub(crate) fn is_text(ty: &Type) -> bool {
+    match inner_type(ty) {
+        Type::Path(path) => path.path.segments.last().is_some_and(|segment| {
+            TEXT_TYPES.iter().any(|name| segment.ident == name)
+                || STR_POINTERS.iter().any(|name| segment.ident == name)
+                    && holds_str(&segment.arguments)
+        }),
+        _ => false,
+    }
+}
+
+/// Whether the generic arguments of a pointer are a `str`.
+fn holds_str(arguments: &PathArguments) -> bool {
+    match arguments {
+        PathArguments::AngleBracketed(args) => args.args.iter().any(|arg| {
+            matches!(arg, GenericArgument::Type(Type::Path(path)) if path.path.is_ident("str"))
+        }),
+        _ => false,
+    }
+}
+
+/// Types of files, sent as file parts.
+const FILE_TYPES: &[&str] = &[
+    "File",
+    "SpooledFile",
+    "StreamedFile",
+    "TempFile",
+    "UploadedObject",
+];
+
+/// Name of the type of the values of a field, without its path and generics.
+fn type_name(ty: &Type) -> Option<String> {
+    match inner_type(ty) {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string()),
+        _ => None,
+    }
+}
+
+/// Whether the values of a field are any type of file.
+pub(crate) fn is_any_file(ty: &Type) -> bool {
+    type_name(ty).is_some_and(|name| FILE_TYPES.contains(&name.as_str()))
+}
+
+/// Sample text of the values of a field for examples, text for types which aren't known.
+pub(crate) fn example_text(ty: &Type) -> &'static str {
+    let name = match type_name(ty) {
+        Some(name) => name,
+        None => return "text",
+    };
+
+    match name.as_str() {
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128"
+        | "usize" => "1",
+        "f32" | "f64" | "Decimal" | "BigDecimal" => "1.5",
+        "bool" => "true",
+        "char" => "a",
+        "Uuid" => "00000000-0000-0000-0000-000000000000",
+        "DateTime" | "OffsetDateTime" => "2024-01-01T00:00:00Z",
+        "NaiveDateTime" | "PrimitiveDateTime" => "2024-01-01T00:00:00",
+        "NaiveDate" | "Date" => "2024-01-01",
+        "NaiveTime" | "Time" => "00:00:00",
+        _ => "text",
+    }
+}
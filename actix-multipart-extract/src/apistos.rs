@@ -0,0 +1,84 @@
+//! Apistos OpenAPI support for [`Multipart`], [`File`] and [`StreamedFile`].
+
+use apistos::{reference_or::ReferenceOr, ApiComponent, Schema};
+use apistos_schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, SchemaObject},
+    JsonSchema,
+};
+use serde::de::DeserializeOwned;
+
+use crate::{form::MultipartForm, File, Multipart, StreamedFile};
+
+/// Files are documented as binary strings.
+macro_rules! impl_json_schema {
+    ($($ty:ty),*) => {$(
+        impl JsonSchema for $ty {
+            fn schema_name() -> String {
+                "File".to_owned()
+            }
+
+            fn is_referenceable() -> bool {
+                false
+            }
+
+            fn json_schema(_: &mut SchemaGenerator) -> Schema {
+                SchemaObject {
+                    instance_type: Some(InstanceType::String.into()),
+                    format: Some("binary".to_owned()),
+                    ..Default::default()
+                }
+                .into()
+            }
+        }
+    )*};
+}
+
+impl_json_schema!(File, StreamedFile);
+#[cfg(feature = "object-store")]
+impl_json_schema!(crate::UploadedObject);
+#[cfg(feature = "tempfile")]
+impl_json_schema!(crate::TempFile, crate::SpooledFile);
+
+/// The request body is documented as `multipart/form-data` using the schema of the form, with
+/// max sizes of fields added as an `x-max-size` annotation and the example of the form.
+impl<T> ApiComponent for Multipart<T>
+where
+    T: ApiComponent + MultipartForm + DeserializeOwned,
+{
+    fn content_type() -> String {
+        mime::MULTIPART_FORM_DATA.to_string()
+    }
+
+    fn child_schemas() -> Vec<(String, ReferenceOr<Schema>)> {
+        T::child_schemas()
+    }
+
+    fn schema() -> Option<(String, ReferenceOr<Schema>)> {
+        T::schema().map(|(name, mut schema)| {
+            if let Some(Schema::Object(object)) = schema.get_object_mut() {
+                add_max_sizes::<T>(object);
+                object.metadata().examples.push(T::example().to_json());
+            }
+
+            (name, schema)
+        })
+    }
+}
+
+fn add_max_sizes<T: MultipartForm + DeserializeOwned>(object: &mut SchemaObject) {
+    let properties = match &mut object.object {
+        Some(validation) => &mut validation.properties,
+        None => return,
+    };
+
+    for field in T::fields() {
+        if let (Some(max_size), Some(Schema::Object(property))) =
+            (T::max_size(field), properties.get_mut(*field))
+        {
+            property
+                .extensions
+                .insert("x-max-size".to_owned(), max_size.into());
+        }
+    }
+}
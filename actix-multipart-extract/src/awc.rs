@@ -0,0 +1,31 @@
+//! awc client support for sending forms.
+
+use awc::{error::SendRequestError, ClientRequest, SendClientRequest};
+
+use crate::{MultipartBody, MultipartSerialize};
+
+/// Extension trait sending multipart forms with [`ClientRequest`].
+pub trait ClientRequestExt {
+    /// Set the content type and stream the body.
+    fn send_multipart(self, body: MultipartBody) -> SendClientRequest;
+
+    /// Encode the form, set the content type and stream the body.
+    fn send_multipart_form<T: MultipartSerialize>(self, form: &T) -> SendClientRequest;
+}
+
+impl ClientRequestExt for ClientRequest {
+    fn send_multipart(self, body: MultipartBody) -> SendClientRequest {
+        self.content_type(body.content_type())
+            .send_stream(body.into_stream())
+    }
+
+    fn send_multipart_form<T: MultipartSerialize>(self, form: &T) -> SendClientRequest {
+        match form.to_multipart() {
+            Ok(body) => self.send_multipart(body),
+            Err(err) => {
+                SendRequestError::Custom(Box::new(err), Box::new("multipart serialization error"))
+                    .into()
+            }
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+
+use crate::form::ParseFormat;
+
+impl ParseFormat for NaiveDate {
+    fn parse_format(value: &str, format: &str) -> Result<Self, String> {
+        NaiveDate::parse_from_str(value, format).map_err(|err| err.to_string())
+    }
+}
+
+impl ParseFormat for NaiveDateTime {
+    fn parse_format(value: &str, format: &str) -> Result<Self, String> {
+        NaiveDateTime::parse_from_str(value, format).map_err(|err| err.to_string())
+    }
+}
+
+impl ParseFormat for NaiveTime {
+    fn parse_format(value: &str, format: &str) -> Result<Self, String> {
+        NaiveTime::parse_from_str(value, format).map_err(|err| err.to_string())
+    }
+}
+
+impl ParseFormat for DateTime<FixedOffset> {
+    fn parse_format(value: &str, format: &str) -> Result<Self, String> {
+        DateTime::parse_from_str(value, format).map_err(|err| err.to_string())
+    }
+}
+
+impl ParseFormat for DateTime<Utc> {
+    fn parse_format(value: &str, format: &str) -> Result<Self, String> {
+        DateTime::<FixedOffset>::parse_format(value, format).map(|date| date.with_timezone(&Utc))
+    }
+}
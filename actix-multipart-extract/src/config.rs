@@ -1,15 +1,243 @@
-use actix_web::HttpResponse;
+use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse};
+use std::{borrow::Cow, sync::OnceLock, time::Duration};
 
-use crate::MultipartError;
+use crate::{
+    ContentScanner, FileStorage, FilenamePolicy, MemoryBudget, MultipartError, MultipartMetrics,
+};
+
+/// Environment variable used when [`MultipartConfig::temp_dir`] isn't set.
+#[cfg(feature = "tempfile")]
+pub const TEMP_DIR_ENV: &str = "ACTIX_MULTIPART_TEMP_DIR";
+
+/// Default for [`MultipartConfig::max_decompressed_size`] of fields without a max size.
+#[cfg(feature = "compression")]
+pub(crate) const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = 10 * 1024 * 1024;
 
 type MultipartErrorHandler = Box<dyn Fn(MultipartError) -> HttpResponse + Send + Sync + 'static>;
+type PartialErrorHandler =
+    Box<dyn Fn(MultipartError) -> Result<HttpResponse, MultipartError> + Send + Sync + 'static>;
+type ErrorMessage =
+    Box<dyn Fn(&MultipartError, &HttpRequest) -> Option<String> + Send + Sync + 'static>;
+type StatusFor = Box<dyn Fn(&MultipartError) -> StatusCode + Send + Sync + 'static>;
+type ProgressHandler = Box<dyn Fn(&str, usize, Option<usize>) + Send + Sync + 'static>;
+type LimitsFor = Box<dyn Fn(&HttpRequest) -> Limits + Send + Sync + 'static>;
+
+/// Limits of a single request, returned by [`MultipartConfig::set_limits_for`].
+///
+/// Every limit is unlimited by default, build the limits with `..Default::default()` to only
+/// set some of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Limits {
+    /// Max bytes of the whole request, larger requests respond with `413 Payload Too Large`.
+    pub total: Option<usize>,
+    /// Max size of every file, fields with a smaller max size keep their own.
+    pub per_file: Option<usize>,
+    /// Max amount of files in the request.
+    pub max_files: Option<usize>,
+    /// Max amount of parts in the request, files included.
+    pub max_fields: Option<usize>,
+}
+
+impl Limits {
+    /// Max size of a file of a field with the given max size.
+    pub(crate) fn file_limit(&self, max_size: Option<usize>) -> Option<usize> {
+        match (max_size, self.per_file) {
+            (Some(max_size), Some(per_file)) => Some(max_size.min(per_file)),
+            (max_size, per_file) => max_size.or(per_file),
+        }
+    }
+
+    /// Check that another file can be read after `files` files.
+    pub(crate) fn check_files(&self, files: usize) -> Result<(), MultipartError> {
+        match self.max_files {
+            Some(limit) if files >= limit => Err(MultipartError::TooManyFiles {
+                limit,
+                count: files + 1,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that another part can be read after `fields` parts.
+    pub(crate) fn check_fields(&self, fields: usize) -> Result<(), MultipartError> {
+        match self.max_fields {
+            Some(limit) if fields >= limit => Err(MultipartError::TooManyFields {
+                limit,
+                count: fields + 1,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// What happens to the rest of the request once every field of a form was received, set with
+/// [`MultipartConfig::set_early_exit`].
+///
+/// A form is complete once every field it declares was received and none of them is a list,
+/// since more parts of a list may follow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EarlyExit {
+    /// Read and parse the whole request.
+    #[default]
+    Disabled,
+    /// Discard the remaining parts without parsing them, keeping the connection reusable.
+    Drain,
+    /// Stop reading the request, actix closes the connection once the response was sent.
+    Close,
+}
+
+/// What happens to parts without a name, set with [`MultipartConfig::set_unnamed_parts`].
+///
+/// actix-multipart rejects parts without a name as malformed, only multer with the `multer`
+/// feature reads them. Parts mapped to a field by their `Content-ID` have a field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnnamedParts {
+    /// Skip the parts.
+    #[default]
+    Ignore,
+    /// Reject the request with [`MultipartError::UnnamedPart`].
+    Reject,
+    /// Keep the parts in the rest of a [`MultipartWithRest`](crate::MultipartWithRest), other
+    /// extractors skip them.
+    Collect,
+}
+
+/// Body of the default responses of errors, set with [`MultipartConfig::set_error_format`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorFormat {
+    /// The message of the error as plain text.
+    #[default]
+    Text,
+    /// A JSON object with the [`code`](MultipartError::code) of the error, its `message` and its
+    /// [`field`](MultipartError::field), `null` for errors without a field.
+    Json,
+}
+
+/// Whitespace removed around the text of text parts before it is parsed, set with
+/// [`MultipartConfig::set_text_whitespace`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextWhitespace {
+    /// Keep the text as sent.
+    #[default]
+    Keep,
+    /// Remove leading and trailing whitespace.
+    Trim,
+    /// Remove leading whitespace.
+    TrimStart,
+    /// Remove trailing whitespace.
+    TrimEnd,
+}
+
+impl TextWhitespace {
+    fn apply(self, text: &str) -> &str {
+        match self {
+            Self::Keep => text,
+            Self::Trim => text.trim(),
+            Self::TrimStart => text.trim_start(),
+            Self::TrimEnd => text.trim_end(),
+        }
+    }
+}
 
 /// Config for Multipart data, insert with [`actix_web::App::app_data`] to actix
+///
+/// Like the configs of actix's own extractors the config can be set on a scope or resource with
+/// `app_data`, the nearest one is used. It can also be wrapped in [`web::Data`].
+#[derive(Default)]
 pub struct MultipartConfig {
     pub error_handler: Option<MultipartErrorHandler>,
+    /// Builds the responses of some errors before the error handler, errors it returns back go
+    /// to the error handler or get the default response.
+    pub partial_error_handler: Option<PartialErrorHandler>,
+    /// Message of the default responses of errors, returning `None` keeps the default
+    /// message. Used to localize errors with the headers of the
+    /// request.
+    pub error_message: Option<ErrorMessage>,
+    /// Status of the default responses of errors, defaults to the status of
+    /// [`ResponseError::status_code`](actix_web::ResponseError::status_code).
+    pub status_for: Option<StatusFor>,
+    /// Body of the default responses of errors, plain text by default.
+    pub error_format: ErrorFormat,
+    /// How file names sent by the client are treated, sanitized by default.
+    pub filename_policy: FilenamePolicy,
+    /// Whether to stop reading the request once the form is complete, disabled by default.
+    pub early_exit: EarlyExit,
+    /// Whether parts which aren't fields of the form are rejected with
+    /// [`MultipartError::UnknownField`], they are skipped by default.
+    pub deny_unknown_fields: bool,
+    /// Whether part names match the fields of the form ignoring ASCII case, when no field
+    /// matches exactly. Disabled by default.
+    pub case_insensitive_fields: bool,
+    /// What happens to parts without a name, skipped by default.
+    pub unnamed_parts: UnnamedParts,
+    /// Whether text parts which are exactly `null` are read as null, so `Option` fields are
+    /// `None`. Disabled by default, the text is then parsed like any other.
+    pub null_literal: bool,
+    /// Whether a UTF-8 byte order mark at the start of text parts is removed before they are
+    /// parsed, kept by default.
+    pub strip_bom: bool,
+    /// Whitespace removed around text parts before they are parsed, before the normalization
+    /// of their field. Kept by default.
+    pub text_whitespace: TextWhitespace,
+    /// Whether hashed files identical to an earlier file of the request are marked with
+    /// [`File::duplicate_of`](crate::File::duplicate_of), disabled by default.
+    #[cfg(feature = "hash")]
+    pub detect_duplicate_files: bool,
+    /// Max size of a single part after decompressing it, the max size of its field also applies.
+    /// Defaults to 10MB for fields without a max size.
+    #[cfg(feature = "compression")]
+    pub max_decompressed_size: Option<usize>,
+    /// Max bytes of the headers of a single part, checked before its body is read. Unlimited
+    /// by default, the parser still limits the amount of headers.
+    pub max_part_header_size: Option<usize>,
+    /// Max time to wait for the next chunk of the request, waits forever by default.
+    pub read_timeout: Option<Duration>,
+    /// Max average bandwidth used to read the request, unlimited by default.
+    pub max_bytes_per_second: Option<usize>,
+    /// Max size of the chunks handed to the parser, larger chunks received from the client are
+    /// split. Chunks are passed as received by default.
+    pub read_chunk_size: Option<usize>,
+    /// Max bytes read from the request before the parser processes what it buffered, it reads
+    /// everything the client already sent by default.
+    pub max_read_ahead: Option<usize>,
+    /// Called while chunks arrive with the name of the field being read, the amount of bytes
+    /// read from the request and the length of the request if known.
+    pub progress_handler: Option<ProgressHandler>,
+    /// Where the bytes of file fields go, buffered in memory with
+    /// [`MemoryStorage`](crate::MemoryStorage) by default.
+    pub file_storage: Option<Box<dyn FileStorage>>,
+    /// Checks the bytes of every file part before the handler runs, files aren't scanned by
+    /// default.
+    pub content_scanner: Option<Box<dyn ContentScanner>>,
+    /// Hooks called once requests were extracted or rejected, none by default.
+    pub metrics: Option<Box<dyn MultipartMetrics>>,
+    /// Called once per request before it is read to get its [`Limits`], unlimited by default.
+    pub limits_for: Option<LimitsFor>,
+    /// Budget of bytes buffered in memory shared by all requests, unlimited by default.
+    pub shared_memory_limit: Option<MemoryBudget>,
+    /// Directory temporary files are written to, defaults to the directory in the
+    /// [`TEMP_DIR_ENV`] environment variable or the system temporary directory.
+    #[cfg(feature = "tempfile")]
+    pub temp_dir: Option<std::path::PathBuf>,
+    /// Max size of files of [`SpooledFile`](crate::SpooledFile) fields kept in memory, larger
+    /// files spill to a temporary file. Files of other fields are stored like without it.
+    #[cfg(feature = "tempfile")]
+    pub memory_threshold: Option<usize>,
+    /// Uploads sent in chunks with a `Content-Range` header, the header is ignored by default.
+    #[cfg(feature = "resumable")]
+    pub resumable_uploads: Option<crate::ResumableUploads>,
 }
 
 impl MultipartConfig {
+    /// Get the config of the resource handling a request, or the default config if none is set.
+    pub(crate) fn from_req(req: &HttpRequest) -> &Self {
+        static DEFAULT: OnceLock<MultipartConfig> = OnceLock::new();
+
+        req.app_data::<Self>()
+            .or_else(|| req.app_data::<web::Data<Self>>().map(|data| data.get_ref()))
+            .unwrap_or_else(|| DEFAULT.get_or_init(Self::default))
+    }
+
     pub fn set_error_handler<F>(mut self, error_handler: F) -> Self
     where
         F: Fn(MultipartError) -> HttpResponse + Send + Sync + 'static,
@@ -17,12 +245,203 @@ impl MultipartConfig {
         self.error_handler = Some(Box::new(error_handler));
         self
     }
-}
 
-impl Default for MultipartConfig {
-    fn default() -> Self {
-        Self {
-            error_handler: None,
+    /// Set an error handler building the responses of some errors, errors it returns back go to
+    /// the error handler or get the default response.
+    pub fn set_partial_error_handler<F>(mut self, error_handler: F) -> Self
+    where
+        F: Fn(MultipartError) -> Result<HttpResponse, MultipartError> + Send + Sync + 'static,
+    {
+        self.partial_error_handler = Some(Box::new(error_handler));
+        self
+    }
+
+    pub fn set_error_message<F>(mut self, error_message: F) -> Self
+    where
+        F: Fn(&MultipartError, &HttpRequest) -> Option<String> + Send + Sync + 'static,
+    {
+        self.error_message = Some(Box::new(error_message));
+        self
+    }
+
+    pub fn set_status_for<F>(mut self, status_for: F) -> Self
+    where
+        F: Fn(&MultipartError) -> StatusCode + Send + Sync + 'static,
+    {
+        self.status_for = Some(Box::new(status_for));
+        self
+    }
+
+    pub fn set_error_format(mut self, error_format: ErrorFormat) -> Self {
+        self.error_format = error_format;
+        self
+    }
+
+    pub fn set_filename_policy(mut self, filename_policy: FilenamePolicy) -> Self {
+        self.filename_policy = filename_policy;
+        self
+    }
+
+    pub fn set_early_exit(mut self, early_exit: EarlyExit) -> Self {
+        self.early_exit = early_exit;
+        self
+    }
+
+    pub fn set_deny_unknown_fields(mut self, deny_unknown_fields: bool) -> Self {
+        self.deny_unknown_fields = deny_unknown_fields;
+        self
+    }
+
+    pub fn set_case_insensitive_fields(mut self, case_insensitive_fields: bool) -> Self {
+        self.case_insensitive_fields = case_insensitive_fields;
+        self
+    }
+
+    pub fn set_unnamed_parts(mut self, unnamed_parts: UnnamedParts) -> Self {
+        self.unnamed_parts = unnamed_parts;
+        self
+    }
+
+    pub fn set_null_literal(mut self, null_literal: bool) -> Self {
+        self.null_literal = null_literal;
+        self
+    }
+
+    pub fn set_strip_bom(mut self, strip_bom: bool) -> Self {
+        self.strip_bom = strip_bom;
+        self
+    }
+
+    pub fn set_text_whitespace(mut self, text_whitespace: TextWhitespace) -> Self {
+        self.text_whitespace = text_whitespace;
+        self
+    }
+
+    #[cfg(feature = "hash")]
+    pub fn set_detect_duplicate_files(mut self, detect_duplicate_files: bool) -> Self {
+        self.detect_duplicate_files = detect_duplicate_files;
+        self
+    }
+
+    #[cfg(feature = "compression")]
+    pub fn set_max_decompressed_size(mut self, max_decompressed_size: usize) -> Self {
+        self.max_decompressed_size = Some(max_decompressed_size);
+        self
+    }
+
+    pub fn set_max_part_header_size(mut self, max_part_header_size: usize) -> Self {
+        self.max_part_header_size = Some(max_part_header_size);
+        self
+    }
+
+    pub fn set_read_timeout(mut self, read_timeout: Duration) -> Self {
+        self.read_timeout = Some(read_timeout);
+        self
+    }
+
+    pub fn set_max_bytes_per_second(mut self, max_bytes_per_second: usize) -> Self {
+        self.max_bytes_per_second = Some(max_bytes_per_second);
+        self
+    }
+
+    pub fn set_read_chunk_size(mut self, read_chunk_size: usize) -> Self {
+        self.read_chunk_size = Some(read_chunk_size);
+        self
+    }
+
+    pub fn set_max_read_ahead(mut self, max_read_ahead: usize) -> Self {
+        self.max_read_ahead = Some(max_read_ahead);
+        self
+    }
+
+    pub fn set_progress_handler<F>(mut self, progress_handler: F) -> Self
+    where
+        F: Fn(&str, usize, Option<usize>) + Send + Sync + 'static,
+    {
+        self.progress_handler = Some(Box::new(progress_handler));
+        self
+    }
+
+    pub fn set_file_storage(mut self, file_storage: impl FileStorage) -> Self {
+        self.file_storage = Some(Box::new(file_storage));
+        self
+    }
+
+    pub fn set_content_scanner(mut self, content_scanner: impl ContentScanner) -> Self {
+        self.content_scanner = Some(Box::new(content_scanner));
+        self
+    }
+
+    pub fn set_metrics(mut self, metrics: impl MultipartMetrics) -> Self {
+        self.metrics = Some(Box::new(metrics));
+        self
+    }
+
+    pub fn set_limits_for<F>(mut self, limits_for: F) -> Self
+    where
+        F: Fn(&HttpRequest) -> Limits + Send + Sync + 'static,
+    {
+        self.limits_for = Some(Box::new(limits_for));
+        self
+    }
+
+    pub fn set_shared_memory_limit(mut self, shared_memory_limit: MemoryBudget) -> Self {
+        self.shared_memory_limit = Some(shared_memory_limit);
+        self
+    }
+
+    #[cfg(feature = "tempfile")]
+    pub fn set_temp_dir(mut self, temp_dir: impl Into<std::path::PathBuf>) -> Self {
+        self.temp_dir = Some(temp_dir.into());
+        self
+    }
+
+    #[cfg(feature = "tempfile")]
+    pub fn set_memory_threshold(mut self, memory_threshold: usize) -> Self {
+        self.memory_threshold = Some(memory_threshold);
+        self
+    }
+
+    #[cfg(feature = "resumable")]
+    pub fn set_resumable_uploads(mut self, resumable_uploads: crate::ResumableUploads) -> Self {
+        self.resumable_uploads = Some(resumable_uploads);
+        self
+    }
+
+    /// Remove the byte order mark and whitespace of text, it is only copied if it changes.
+    pub(crate) fn clean_text<'a>(&self, text: Cow<'a, str>) -> Cow<'a, str> {
+        match text {
+            Cow::Borrowed(text) => Cow::Borrowed(self.clean_str(text)),
+            Cow::Owned(text) => match self.clean_str(&text) {
+                cleaned if cleaned.len() == text.len() => Cow::Owned(text),
+                cleaned => Cow::Owned(cleaned.to_string()),
+            },
         }
     }
+
+    fn clean_str<'a>(&self, text: &'a str) -> &'a str {
+        let text = match self.strip_bom {
+            true => text.strip_prefix('\u{feff}').unwrap_or(text),
+            false => text,
+        };
+
+        self.text_whitespace.apply(text)
+    }
+
+    /// Get the limits of a request.
+    pub(crate) fn limits(&self, req: &HttpRequest) -> Limits {
+        self.limits_for
+            .as_ref()
+            .map(|limits_for| limits_for(req))
+            .unwrap_or_default()
+    }
+
+    /// Directory temporary files are written to.
+    #[cfg(feature = "tempfile")]
+    pub(crate) fn resolve_temp_dir(&self) -> std::path::PathBuf {
+        self.temp_dir
+            .clone()
+            .or_else(|| std::env::var_os(TEMP_DIR_ENV).map(Into::into))
+            .unwrap_or_else(std::env::temp_dir)
+    }
 }
@@ -0,0 +1,412 @@
+use actix_web::http::header::HeaderMap;
+use mime::Mime;
+use std::borrow::Cow;
+
+use crate::{pool, MultipartConfig, MultipartError};
+
+/// Name of the per-part transfer encoding header.
+const CONTENT_TRANSFER_ENCODING: &str = "content-transfer-encoding";
+
+/// Transfer encoding of a single part as declared by its `Content-Transfer-Encoding` header.
+///
+/// `7bit`, `8bit`, `binary` and unknown encodings are passed through untouched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TransferEncoding {
+    Identity,
+    Base64,
+    QuotedPrintable,
+}
+
+impl TransferEncoding {
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Self {
+        let value = match headers
+            .get(CONTENT_TRANSFER_ENCODING)
+            .and_then(|v| v.to_str().ok())
+        {
+            Some(v) => v.trim(),
+            None => return Self::Identity,
+        };
+
+        if value.eq_ignore_ascii_case("base64") {
+            Self::Base64
+        } else if value.eq_ignore_ascii_case("quoted-printable") {
+            Self::QuotedPrintable
+        } else {
+            Self::Identity
+        }
+    }
+
+    /// Upper bound of encoded bytes which can decode into at most `limit` bytes.
+    ///
+    /// Used to bound buffering of the encoded body before the real size check happens on
+    /// the decoded bytes. Line breaks are accounted for generously.
+    pub(crate) fn encoded_limit(&self, limit: usize) -> usize {
+        match self {
+            Self::Identity => limit,
+            Self::Base64 => limit.saturating_mul(2).saturating_add(4),
+            Self::QuotedPrintable => limit.saturating_mul(4),
+        }
+    }
+
+    /// Decode the full body of a part.
+    pub(crate) fn decode(&self, bytes: Vec<u8>) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Identity => Ok(bytes),
+            Self::Base64 => {
+                // Encoded bodies are usually wrapped at 76 characters.
+                let stripped: Vec<u8> = bytes
+                    .into_iter()
+                    .filter(|b| !b.is_ascii_whitespace())
+                    .collect();
+
+                base64::decode(stripped).map_err(|e| e.to_string())
+            }
+            Self::QuotedPrintable => {
+                quoted_printable::decode(bytes, quoted_printable::ParseMode::Robust)
+                    .map_err(|e| e.to_string())
+            }
+        }
+    }
+}
+
+/// Decodes the body of a part while it streams in.
+///
+/// Transfer encoded bodies are buffered and decoded once complete, content encoded bodies are
+/// decompressed as chunks arrive when the `compression` feature is enabled.
+pub(crate) struct BodyDecoder<'a> {
+    field: &'a str,
+    transfer_encoding: TransferEncoding,
+    buffer: Vec<u8>,
+    #[cfg(feature = "compression")]
+    content_decoder: Option<compression::ContentDecoder>,
+}
+
+impl<'a> BodyDecoder<'a> {
+    /// Create the decoder of a part, `max_size` is the max size of its field.
+    #[cfg_attr(not(feature = "compression"), allow(unused_variables))]
+    pub(crate) fn new(
+        field: &'a str,
+        headers: &HeaderMap,
+        max_size: Option<usize>,
+        config: &MultipartConfig,
+    ) -> Result<Self, MultipartError> {
+        Ok(Self {
+            field,
+            transfer_encoding: TransferEncoding::from_headers(headers),
+            buffer: Vec::new(),
+            #[cfg(feature = "compression")]
+            content_decoder: compression::ContentDecoder::from_headers(
+                headers,
+                match (config.max_decompressed_size, max_size) {
+                    (Some(limit), Some(max_size)) => limit.min(max_size),
+                    (limit, max_size) => limit
+                        .or(max_size)
+                        .unwrap_or(crate::config::DEFAULT_MAX_DECOMPRESSED_SIZE),
+                },
+            )
+            .map_err(|message| decode_error(field, message))?,
+        })
+    }
+
+    pub(crate) fn transfer_encoding(&self) -> TransferEncoding {
+        self.transfer_encoding
+    }
+
+    /// Reserve room for `capacity` bytes before the body is written.
+    pub(crate) fn preallocate(&mut self, capacity: usize) {
+        if self.buffer.capacity() == 0 {
+            self.buffer = pool::take(capacity);
+        } else {
+            self.buffer.reserve(capacity);
+        }
+    }
+
+    /// Bytes held by the decoder, the encoded body or the decompressed bytes.
+    pub(crate) fn buffered(&self) -> usize {
+        #[cfg(feature = "compression")]
+        if let Some(decoder) = &self.content_decoder {
+            return self.buffer.capacity() + decoder.output_capacity();
+        }
+
+        self.buffer.capacity()
+    }
+
+    /// Whether written chunks go straight to the content decoder, leaving the buffer unused.
+    pub(crate) fn decompresses_chunks(&self) -> bool {
+        #[cfg(feature = "compression")]
+        if self.content_decoder.is_some() {
+            return self.transfer_encoding == TransferEncoding::Identity;
+        }
+
+        false
+    }
+
+    /// Whether written chunks are already decoded.
+    pub(crate) fn is_identity(&self) -> bool {
+        #[cfg(feature = "compression")]
+        if self.content_decoder.is_some() {
+            return false;
+        }
+
+        self.transfer_encoding == TransferEncoding::Identity
+    }
+
+    /// Feed a raw chunk of the part body.
+    pub(crate) fn write(&mut self, chunk: &[u8]) -> Result<(), MultipartError> {
+        #[cfg(feature = "compression")]
+        if self.transfer_encoding == TransferEncoding::Identity {
+            if let Some(decoder) = &mut self.content_decoder {
+                return decoder
+                    .write(chunk)
+                    .map_err(|err| compression::map_error(self.field, err));
+            }
+        }
+
+        self.buffer.extend_from_slice(chunk);
+        Ok(())
+    }
+
+    /// Finish decoding, returning the decoded body.
+    pub(crate) fn finish(self) -> Result<Vec<u8>, MultipartError> {
+        let field = self.field;
+        let bytes = self
+            .transfer_encoding
+            .decode(self.buffer)
+            .map_err(|message| decode_error(field, message))?;
+
+        #[cfg(feature = "compression")]
+        if let Some(mut decoder) = self.content_decoder {
+            return decoder
+                .write(&bytes)
+                .and_then(|_| decoder.finish())
+                .map_err(|err| compression::map_error(field, err));
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// Bytes allowed for the header of a `data:` URL, before its data.
+const MAX_DATA_URL_HEADER: usize = 256;
+
+/// Upper bound of the text of a base64 file which can decode into at most `limit` bytes.
+pub(crate) fn base64_text_limit(limit: usize) -> usize {
+    TransferEncoding::Base64
+        .encoded_limit(limit)
+        .saturating_add(MAX_DATA_URL_HEADER)
+}
+
+/// Decode a file sent as a base64 string or a `data:` URL in a text part, returning the media
+/// type of the URL with the decoded bytes.
+pub(crate) fn decode_base64_file(text: &[u8]) -> Result<(Option<Mime>, Vec<u8>), String> {
+    let text = std::str::from_utf8(text)
+        .map_err(|_| "isn't base64 text".to_string())?
+        .trim();
+
+    let (media_type, data) = match text.strip_prefix("data:") {
+        Some(url) => {
+            let (header, data) = url
+                .split_once(',')
+                .ok_or_else(|| "data URL has no data".to_string())?;
+            let media_type = header
+                .strip_suffix(";base64")
+                .ok_or_else(|| "data URL isn't base64 encoded".to_string())?;
+
+            let media_type = match media_type {
+                "" => None,
+                media_type => Some(
+                    media_type
+                        .parse()
+                        .map_err(|_| format!("invalid media type ({media_type})"))?,
+                ),
+            };
+
+            (media_type, data)
+        }
+        None => (None, text),
+    };
+
+    let bytes = TransferEncoding::Base64.decode(data.as_bytes().to_vec())?;
+    Ok((media_type, bytes))
+}
+
+pub(crate) fn decode_error(field: &str, message: String) -> MultipartError {
+    MultipartError::DecodeError {
+        field: field.to_string(),
+        message,
+    }
+}
+
+/// Decode the bytes of a text part into a string.
+///
+/// Text is expected to be UTF-8, with the `encoding` feature the `charset` parameter of the
+/// content type is used to transcode other encodings. `None` is returned for text which could
+/// not be decoded without a charset.
+#[cfg_attr(not(feature = "encoding"), allow(unused_variables))]
+pub(crate) fn decode_text<'a>(
+    bytes: &'a [u8],
+    content_type: &Mime,
+) -> Result<Option<Cow<'a, str>>, String> {
+    #[cfg(feature = "encoding")]
+    if let Some(charset) = content_type.get_param(mime::CHARSET) {
+        let encoding = encoding_rs::Encoding::for_label(charset.as_str().as_bytes())
+            .ok_or_else(|| format!("unknown charset ({})", charset))?;
+
+        let (text, had_errors) = encoding.decode_without_bom_handling(bytes);
+        if had_errors {
+            return Err(format!("invalid {} text", encoding.name()));
+        }
+
+        return Ok(Some(text));
+    }
+
+    Ok(std::str::from_utf8(bytes).ok().map(Cow::Borrowed))
+}
+
+/// Decode the bytes of a text part like [`decode_text`], UTF-8 text keeps the buffer of the bytes
+/// instead of being copied.
+#[cfg_attr(not(feature = "encoding"), allow(unused_variables))]
+pub(crate) fn decode_owned_text(
+    bytes: Vec<u8>,
+    content_type: &Mime,
+) -> Result<Option<String>, String> {
+    #[cfg(feature = "encoding")]
+    if content_type.get_param(mime::CHARSET).is_some() {
+        return decode_text(&bytes, content_type).map(|text| text.map(Cow::into_owned));
+    }
+
+    Ok(String::from_utf8(bytes).ok())
+}
+
+#[cfg(feature = "compression")]
+mod compression {
+    use actix_web::http::header::{HeaderMap, CONTENT_ENCODING};
+    use std::io::{self, Write};
+
+    use crate::MultipartError;
+
+    /// Decompresses a part body according to its `Content-Encoding` header.
+    pub(crate) enum ContentDecoder {
+        Gzip(flate2::write::GzDecoder<LimitedWriter>),
+        Deflate(flate2::write::ZlibDecoder<LimitedWriter>),
+        Brotli(Box<brotli::DecompressorWriter<LimitedWriter>>),
+        Zstd(zstd::stream::write::Decoder<'static, LimitedWriter>),
+    }
+
+    impl ContentDecoder {
+        /// Create a decoder for the part, `None` if the part is not compressed.
+        pub(crate) fn from_headers(
+            headers: &HeaderMap,
+            limit: usize,
+        ) -> Result<Option<Self>, String> {
+            let encoding = match headers.get(CONTENT_ENCODING).and_then(|v| v.to_str().ok()) {
+                Some(v) => v.trim().to_ascii_lowercase(),
+                None => return Ok(None),
+            };
+
+            let writer = LimitedWriter {
+                bytes: Vec::new(),
+                limit,
+            };
+
+            Ok(Some(match encoding.as_str() {
+                "identity" => return Ok(None),
+                "gzip" | "x-gzip" => Self::Gzip(flate2::write::GzDecoder::new(writer)),
+                "deflate" => Self::Deflate(flate2::write::ZlibDecoder::new(writer)),
+                "br" => Self::Brotli(Box::new(brotli::DecompressorWriter::new(writer, 4096))),
+                "zstd" => Self::Zstd(
+                    zstd::stream::write::Decoder::new(writer).map_err(|e| e.to_string())?,
+                ),
+                _ => return Err(format!("unsupported content encoding ({})", encoding)),
+            }))
+        }
+
+        pub(crate) fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+            match self {
+                Self::Gzip(decoder) => decoder.write_all(bytes),
+                Self::Deflate(decoder) => decoder.write_all(bytes),
+                Self::Brotli(decoder) => decoder.write_all(bytes),
+                Self::Zstd(decoder) => decoder.write_all(bytes),
+            }
+        }
+
+        /// Bytes allocated for the decompressed body so far.
+        pub(crate) fn output_capacity(&self) -> usize {
+            let writer = match self {
+                Self::Gzip(decoder) => decoder.get_ref(),
+                Self::Deflate(decoder) => decoder.get_ref(),
+                Self::Brotli(decoder) => decoder.get_ref(),
+                Self::Zstd(decoder) => decoder.get_ref(),
+            };
+
+            writer.bytes.capacity()
+        }
+
+        pub(crate) fn finish(self) -> io::Result<Vec<u8>> {
+            let writer = match self {
+                Self::Gzip(decoder) => decoder.finish()?,
+                Self::Deflate(decoder) => decoder.finish()?,
+                Self::Brotli(decoder) => decoder.into_inner().map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidData, "truncated brotli stream")
+                })?,
+                Self::Zstd(mut decoder) => {
+                    decoder.flush()?;
+                    decoder.into_inner()
+                }
+            };
+
+            Ok(writer.bytes)
+        }
+    }
+
+    /// Marker error for a decompressed body growing past its limit.
+    #[derive(Debug)]
+    struct LimitExceeded(usize);
+
+    impl std::fmt::Display for LimitExceeded {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "decompressed size limit of {} bytes exceeded", self.0)
+        }
+    }
+
+    impl std::error::Error for LimitExceeded {}
+
+    /// Collects decompressed bytes, failing once more than `limit` bytes are written.
+    pub(crate) struct LimitedWriter {
+        bytes: Vec<u8>,
+        limit: usize,
+    }
+
+    impl Write for LimitedWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.bytes.len() + buf.len() > self.limit {
+                return Err(io::Error::other(LimitExceeded(self.limit)));
+            }
+
+            self.bytes.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    pub(crate) fn map_error(field: &str, err: io::Error) -> MultipartError {
+        let limit_exceeded = err
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<LimitExceeded>());
+
+        if let Some(LimitExceeded(limit)) = limit_exceeded {
+            MultipartError::DecompressedSizeError {
+                field: field.to_string(),
+                limit: *limit,
+            }
+        } else {
+            MultipartError::DecodeError {
+                field: field.to_string(),
+                message: err.to_string(),
+            }
+        }
+    }
+}
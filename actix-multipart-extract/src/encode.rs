@@ -0,0 +1,324 @@
+use actix_web::{http::header::HeaderValue, web::Bytes};
+use futures::Stream;
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    io,
+};
+use thiserror::Error;
+
+use crate::{form::MultipartForm, File};
+
+/// Types which can be encoded as a `multipart/form-data` body.
+///
+/// This shouldn't be implemented manually.
+/// Use [`actix_multipart_extract_derive::MultipartSerialize`], forms must also derive
+/// [`MultipartForm`](crate::MultipartForm) which names their parts.
+pub trait MultipartSerialize: MultipartForm {
+    /// Write every field of the form as parts of the body.
+    fn write_multipart(&self, body: &mut MultipartBody) -> Result<(), serde_json::Error>;
+
+    /// Encode the form into a new body.
+    fn to_multipart(&self) -> Result<MultipartBody, serde_json::Error> {
+        let mut body = MultipartBody::new();
+        self.write_multipart(&mut body)?;
+        Ok(body)
+    }
+}
+
+/// Error for values which can't be written in a [`MultipartBody`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EncodeError {
+    #[error("Boundary ({0:?}) must have 1 to 70 letters, digits, spaces or '()+_,-./:=? characters and not end with a space")]
+    InvalidBoundary(String),
+    #[error("Content type ({0:?}) of a file is not a valid media type")]
+    InvalidContentType(String),
+}
+
+/// A `multipart/form-data` body made of text, bytes and file parts.
+#[derive(Debug, Clone)]
+pub struct MultipartBody {
+    boundary: String,
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone)]
+struct Part {
+    name: String,
+    filename: Option<String>,
+    content_type: Option<String>,
+    bytes: Bytes,
+}
+
+impl Default for MultipartBody {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MultipartBody {
+    /// Create an empty body with a random boundary.
+    pub fn new() -> Self {
+        let random = || RandomState::new().build_hasher().finish();
+
+        Self {
+            boundary: format!("actix-multipart-extract-{:016x}{:016x}", random(), random()),
+            parts: Vec::new(),
+        }
+    }
+
+    /// Boundary delimiting parts.
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// Set the boundary delimiting parts, this must not occur in any part.
+    ///
+    /// Boundaries have 1 to 70 of the characters allowed by RFC 2046, letters, digits, spaces
+    /// and `'()+_,-./:=?`, and don't end with a space.
+    pub fn set_boundary(&mut self, boundary: impl Into<String>) -> Result<(), EncodeError> {
+        let boundary = boundary.into();
+        if !is_boundary(&boundary) {
+            return Err(EncodeError::InvalidBoundary(boundary));
+        }
+
+        self.boundary = boundary;
+        Ok(())
+    }
+
+    /// Add a text part.
+    pub fn text(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.parts.push(Part {
+            name: name.into(),
+            filename: None,
+            content_type: None,
+            bytes: Bytes::from(value.into()),
+        });
+    }
+
+    /// Add a file part, its content type must be a valid media type.
+    pub fn file(
+        &mut self,
+        name: impl Into<String>,
+        filename: impl Into<String>,
+        content_type: impl Into<String>,
+        bytes: impl Into<Bytes>,
+    ) -> Result<(), EncodeError> {
+        let content_type = content_type.into();
+        if !is_content_type(&content_type) {
+            return Err(EncodeError::InvalidContentType(content_type));
+        }
+
+        self.parts.push(Part {
+            name: name.into(),
+            filename: Some(filename.into()),
+            content_type: Some(content_type),
+            bytes: bytes.into(),
+        });
+        Ok(())
+    }
+
+    /// Add a part holding raw bytes, sent as `application/octet-stream` without a filename.
+    pub fn bytes(&mut self, name: impl Into<String>, bytes: impl Into<Bytes>) {
+        self.parts.push(Part {
+            name: name.into(),
+            filename: None,
+            content_type: Some("application/octet-stream".to_string()),
+            bytes: bytes.into(),
+        });
+    }
+
+    /// Add a value as text parts.
+    ///
+    /// Strings are written as is, numbers and booleans as their text, lists as a part for every
+    /// element named `name[]` and other values as JSON. `None` values are skipped.
+    pub fn serialize<T: Serialize + ?Sized>(
+        &mut self,
+        name: &str,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        match serde_json::to_value(value)? {
+            Value::Array(values) => {
+                let name = format!("{}[]", name);
+                for value in values {
+                    self.value(&name, value)?;
+                }
+            }
+            value => self.value(name, value)?,
+        }
+
+        Ok(())
+    }
+
+    fn value(&mut self, name: &str, value: Value) -> Result<(), serde_json::Error> {
+        match value {
+            Value::Null => {}
+            Value::String(str) => self.text(name, str),
+            Value::Bool(bool) => self.text(name, bool.to_string()),
+            Value::Number(number) => self.text(name, number.to_string()),
+            value => self.text(name, serde_json::to_string(&value)?),
+        }
+
+        Ok(())
+    }
+
+    /// Get the `Content-Type` header value including the boundary, quoted if it has characters
+    /// which can't be sent unquoted, like spaces.
+    pub fn content_type(&self) -> HeaderValue {
+        let is_token = self
+            .boundary
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b));
+        let content_type = match is_token {
+            true => format!("multipart/form-data; boundary={}", self.boundary),
+            false => format!("multipart/form-data; boundary=\"{}\"", self.boundary),
+        };
+
+        HeaderValue::from_str(&content_type).expect("boundaries are valid header values")
+    }
+
+    /// Encode the body.
+    pub fn to_bytes(&self) -> Bytes {
+        let mut bytes = Vec::new();
+
+        for chunk in self.chunks() {
+            bytes.extend_from_slice(&chunk);
+        }
+
+        Bytes::from(bytes)
+    }
+
+    /// Consume the body into a stream of encoded chunks.
+    ///
+    /// The content of parts is not copied.
+    pub fn into_stream(self) -> impl Stream<Item = Result<Bytes, io::Error>> + Send + Unpin {
+        futures::stream::iter(self.chunks().into_iter().map(Ok))
+    }
+
+    fn chunks(&self) -> Vec<Bytes> {
+        let mut chunks = Vec::new();
+
+        for part in &self.parts {
+            let mut head = format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"",
+                self.boundary,
+                escape(&part.name)
+            );
+
+            if let Some(filename) = &part.filename {
+                head.push_str(&format!("; filename=\"{}\"", escape(filename)));
+            }
+
+            head.push_str("\r\n");
+
+            if let Some(content_type) = &part.content_type {
+                head.push_str(&format!("Content-Type: {}\r\n", content_type));
+            }
+
+            head.push_str("\r\n");
+
+            chunks.push(Bytes::from(head));
+            chunks.push(part.bytes.clone());
+            chunks.push(Bytes::from_static(b"\r\n"));
+        }
+
+        chunks.push(Bytes::from(format!("--{}--\r\n", self.boundary)));
+        chunks
+    }
+}
+
+/// Whether a boundary follows RFC 2046.
+fn is_boundary(boundary: &str) -> bool {
+    (1..=70).contains(&boundary.len())
+        && !boundary.ends_with(' ')
+        && boundary
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"'()+_,-./:=? ".contains(&b))
+}
+
+/// Whether a content type is a media type which can be written in a header.
+fn is_content_type(content_type: &str) -> bool {
+    content_type.parse::<mime::Mime>().is_ok()
+        && content_type
+            .bytes()
+            .all(|b| b == b' ' || b.is_ascii_graphic())
+}
+
+/// Escape quotes and line breaks in parameters like browsers do.
+fn escape(value: &str) -> String {
+    value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Fields written as file parts.
+///
+/// This shouldn't be used or implemented manually.
+/// Use [`actix_multipart_extract_derive::MultipartSerialize`].
+pub trait FileField {
+    fn write_file(&self, name: &str, body: &mut MultipartBody) -> Result<(), serde_json::Error>;
+}
+
+impl FileField for File {
+    fn write_file(&self, name: &str, body: &mut MultipartBody) -> Result<(), serde_json::Error> {
+        body.file(
+            name,
+            self.name.clone(),
+            self.content_type.clone(),
+            self.bytes.clone(),
+        )
+        .map_err(serde::ser::Error::custom)
+    }
+}
+
+#[cfg(feature = "tempfile")]
+impl FileField for crate::SpooledFile {
+    fn write_file(&self, name: &str, body: &mut MultipartBody) -> Result<(), serde_json::Error> {
+        let bytes = self.bytes().map_err(serde::ser::Error::custom)?;
+        body.file(
+            name,
+            self.meta.name.clone(),
+            self.meta.content_type.clone(),
+            bytes,
+        )
+        .map_err(serde::ser::Error::custom)
+    }
+}
+
+#[cfg(feature = "tempfile")]
+impl FileField for crate::TempFile {
+    fn write_file(&self, name: &str, body: &mut MultipartBody) -> Result<(), serde_json::Error> {
+        let bytes = std::fs::read(self.file.path()).map_err(serde::ser::Error::custom)?;
+        body.file(
+            name,
+            self.meta.name.clone(),
+            self.meta.content_type.clone(),
+            bytes,
+        )
+        .map_err(serde::ser::Error::custom)
+    }
+}
+
+impl<T: FileField> FileField for Option<T> {
+    fn write_file(&self, name: &str, body: &mut MultipartBody) -> Result<(), serde_json::Error> {
+        match self {
+            Some(file) => file.write_file(name, body),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: FileField> FileField for Vec<T> {
+    fn write_file(&self, name: &str, body: &mut MultipartBody) -> Result<(), serde_json::Error> {
+        let name = format!("{}[]", name);
+        for file in self {
+            file.write_file(&name, body)?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,48 +1,350 @@
-use actix_web::{dev::Payload, http::ConnectionType, FromRequest, HttpRequest, HttpResponse};
-use futures::{Future, StreamExt, TryStreamExt};
-use serde::Deserialize;
-use serde_aux::prelude::serde_introspect;
+use actix_web::web::Bytes;
+use actix_web::{
+    dev::Payload,
+    error::{JsonPayloadError, UrlencodedError},
+    http::{
+        header::{HeaderMap, CONTENT_TYPE},
+        ConnectionType, StatusCode,
+    },
+    FromRequest, HttpMessage, HttpRequest, ResponseError,
+};
+use futures::{Future, SinkExt};
+use mime::Mime;
+use serde::Serialize;
 use serde_json::{Map, Number, Value};
 use std::{
+    borrow::Cow,
     ops::{Deref, DerefMut},
     pin::Pin,
+    time::{Duration, Instant},
 };
 use thiserror::Error;
 
-use crate::{form::MultipartForm, MultipartConfig};
+#[cfg(feature = "tempfile")]
+use crate::storage::SpoolSink;
+use crate::{
+    decode::{
+        base64_text_limit, decode_base64_file, decode_error, decode_owned_text, decode_text,
+        BodyDecoder,
+    },
+    form::{FieldDefault, FieldKind, MultipartForm},
+    memory::Reservation,
+    parser::{Disposition, Parser, Part, PartError},
+    payload::{PayloadStatus, TrackedPayload},
+    pool,
+    raw::read_raw_part,
+    scan::{scan_bytes, scan_streamed},
+    slot::{FormDeserializer, Slots},
+    EarlyExit, ErrorFormat, FileMeta, FileSink, MemoryStorage, MultipartConfig, MultipartStats,
+    PartMeta, RawPart, StreamedFile, UnnamedParts,
+};
+#[cfg(feature = "hash")]
+use crate::{hash::Hasher, HashAlgorithm};
 
 /// Error type for multipart forms.
+///
+/// Variants are added as the crate grows, matches must have a wildcard arm.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum MultipartError {
+    #[cfg_attr(
+        feature = "multer",
+        error(
+            "Expected content type multipart/form-data or multipart/related, got {content_type:?}"
+        )
+    )]
+    #[cfg_attr(
+        not(feature = "multer"),
+        error("Expected content type multipart/form-data, got {content_type:?}")
+    )]
+    NotMultipart { content_type: String },
+    #[error("Multipart stream ended before the final boundary")]
+    Incomplete,
+    #[error("Timed out waiting for the multipart stream")]
+    Timeout,
+    #[error("Field ({field}) wasn't received within its deadline ({deadline:?})")]
+    DeadlineExceeded { field: String, deadline: Duration },
+    #[error("Multipart stream is malformed")]
+    Malformed,
     #[error("Error while parsing field: {0}")]
-    ParseError(serde_json::Error),
+    ParseError(#[source] serde_json::Error),
     #[error("File for field ({field}) was too large (max size: {limit} bytes)")]
     FileSizeError { field: String, limit: usize },
+    #[error("Text of field ({field}) was too large (max size: {limit} bytes)")]
+    TextFieldTooLarge { field: String, limit: usize },
+    #[error("Field ({field}) is invalid: {message}")]
+    FieldValidationError { field: String, message: String },
+    #[error("Field ({field}) is missing")]
+    MissingField { field: String },
+    #[error("Field ({field}) is not a field of the form")]
+    UnknownField { field: String },
+    #[error("A part has no name")]
+    UnnamedPart,
+    #[error("Nested multipart parts require the multer feature")]
+    NestedUnsupported,
+    #[cfg(feature = "validate")]
+    #[error("Validation failed: {0}")]
+    Validation(#[source] validator::ValidationErrors),
+    #[cfg(feature = "garde")]
+    #[error("Validation failed: {0}")]
+    GardeValidation(#[source] garde::Report),
+    #[error("Could not store file for field ({field}): {error}")]
+    StorageError {
+        field: String,
+        #[source]
+        error: std::io::Error,
+    },
+    #[error("File for field ({field}) has an invalid name ({filename:?})")]
+    InvalidFilename { field: String, filename: String },
+    #[error("Could not decode field ({field}): {message}")]
+    DecodeError { field: String, message: String },
+    #[cfg(feature = "sniff")]
+    #[error("File for field ({field}) was declared as {declared} but detected as {detected}")]
+    ContentTypeMismatch {
+        field: String,
+        declared: String,
+        detected: String,
+    },
+    #[cfg(feature = "image")]
+    #[error("File for field ({field}) is not a valid image: {message}")]
+    InvalidImage { field: String, message: String },
+    #[cfg(feature = "compression")]
+    #[error("Decompressed field ({field}) was too large (max size: {limit} bytes)")]
+    DecompressedSizeError { field: String, limit: usize },
+    #[error("Request was too large (max size: {limit} bytes)")]
+    RequestSizeError { limit: usize },
+    #[error("Headers of a part were too large (max size: {limit} bytes)")]
+    PartHeaderSizeError { limit: usize },
+    #[error("Request has too many files (max files: {limit})")]
+    TooManyFiles { limit: usize, count: usize },
+    #[error("Request has too many fields (max fields: {limit})")]
+    TooManyFields { limit: usize, count: usize },
+    #[error("File for field ({field}) was rejected: {reason}")]
+    Rejected { field: String, reason: String },
+    #[error("Shared memory limit of {limit} bytes was reached")]
+    MemoryLimitError { limit: usize },
+    #[cfg(feature = "resumable")]
+    #[error("Upload for field ({field}) is invalid: {message}")]
+    InvalidUpload { field: String, message: String },
+    #[cfg(feature = "resumable")]
+    #[error("Chunk for field ({field}) doesn't start at the received bytes ({expected} bytes)")]
+    UploadOffsetMismatch { field: String, expected: usize },
+    #[cfg(feature = "resumable")]
+    #[error("Too many uploads are incomplete (max uploads: {limit})")]
+    TooManyUploads { limit: usize },
+    #[cfg(feature = "resumable")]
+    #[error("Upload for field ({field}) is incomplete ({received} of {total} bytes received)")]
+    UploadIncomplete {
+        field: String,
+        received: usize,
+        total: usize,
+    },
+}
+
+impl ResponseError for MultipartError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::NotMultipart { .. } => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::Timeout | Self::DeadlineExceeded { .. } => StatusCode::REQUEST_TIMEOUT,
+            Self::RequestSizeError { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::MemoryLimitError { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            #[cfg(feature = "resumable")]
+            Self::TooManyUploads { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            #[cfg(feature = "resumable")]
+            Self::UploadOffsetMismatch { .. } => StatusCode::CONFLICT,
+            #[cfg(feature = "resumable")]
+            Self::UploadIncomplete { .. } => StatusCode::ACCEPTED,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self) -> actix_web::HttpResponse {
+        self.response(self.status_code(), self.to_string(), ErrorFormat::Text)
+    }
 }
 
-/// Representing a file in a multipart form.
-#[derive(Debug, Deserialize)]
-pub struct File {
-    pub content_type: String,
-    pub name: String,
-    pub bytes: Vec<u8>,
+impl MultipartError {
+    /// Stable code of the error for clients to branch on, unlike the message it doesn't change
+    /// between versions.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotMultipart { .. } => "not_multipart",
+            Self::Incomplete => "incomplete",
+            Self::Malformed => "malformed",
+            Self::Timeout => "timeout",
+            Self::DeadlineExceeded { .. } => "deadline_exceeded",
+            Self::ParseError(_) => "parse_error",
+            Self::FileSizeError { .. } => "file_too_large",
+            Self::TextFieldTooLarge { .. } => "text_too_large",
+            Self::FieldValidationError { .. } => "invalid_field",
+            Self::MissingField { .. } => "missing_field",
+            Self::UnknownField { .. } => "unknown_field",
+            Self::UnnamedPart => "unnamed_part",
+            Self::NestedUnsupported => "nested_unsupported",
+            #[cfg(feature = "validate")]
+            Self::Validation(_) => "validation_failed",
+            #[cfg(feature = "garde")]
+            Self::GardeValidation(_) => "validation_failed",
+            Self::StorageError { .. } => "storage_error",
+            Self::InvalidFilename { .. } => "invalid_filename",
+            Self::DecodeError { .. } => "decode_error",
+            #[cfg(feature = "sniff")]
+            Self::ContentTypeMismatch { .. } => "content_type_mismatch",
+            #[cfg(feature = "image")]
+            Self::InvalidImage { .. } => "invalid_image",
+            #[cfg(feature = "compression")]
+            Self::DecompressedSizeError { .. } => "decompressed_too_large",
+            Self::RequestSizeError { .. } => "request_too_large",
+            Self::PartHeaderSizeError { .. } => "part_headers_too_large",
+            Self::TooManyFiles { .. } => "too_many_files",
+            Self::TooManyFields { .. } => "too_many_fields",
+            Self::Rejected { .. } => "rejected",
+            Self::MemoryLimitError { .. } => "memory_limit_reached",
+            #[cfg(feature = "resumable")]
+            Self::InvalidUpload { .. } => "invalid_upload",
+            #[cfg(feature = "resumable")]
+            Self::UploadOffsetMismatch { .. } => "upload_offset_mismatch",
+            #[cfg(feature = "resumable")]
+            Self::TooManyUploads { .. } => "too_many_uploads",
+            #[cfg(feature = "resumable")]
+            Self::UploadIncomplete { .. } => "upload_incomplete",
+        }
+    }
+
+    /// Field the error is about, `None` for errors about the whole request.
+    pub fn field(&self) -> Option<&str> {
+        match self {
+            Self::DeadlineExceeded { field, .. }
+            | Self::FileSizeError { field, .. }
+            | Self::TextFieldTooLarge { field, .. }
+            | Self::FieldValidationError { field, .. }
+            | Self::MissingField { field }
+            | Self::UnknownField { field }
+            | Self::StorageError { field, .. }
+            | Self::InvalidFilename { field, .. }
+            | Self::DecodeError { field, .. }
+            | Self::Rejected { field, .. } => Some(field),
+            #[cfg(feature = "sniff")]
+            Self::ContentTypeMismatch { field, .. } => Some(field),
+            #[cfg(feature = "image")]
+            Self::InvalidImage { field, .. } => Some(field),
+            #[cfg(feature = "compression")]
+            Self::DecompressedSizeError { field, .. } => Some(field),
+            #[cfg(feature = "resumable")]
+            Self::InvalidUpload { field, .. }
+            | Self::UploadOffsetMismatch { field, .. }
+            | Self::UploadIncomplete { field, .. } => Some(field),
+            _ => None,
+        }
+    }
+
+    /// Map an error deserializing the form `T`, fields of the form serde found missing are
+    /// [`MissingField`](Self::MissingField) errors.
+    fn from_parse<T: MultipartForm>(err: serde_json::Error) -> Self {
+        let message = err.to_string();
+        let field = message
+            .strip_prefix("missing field `")
+            .and_then(|rest| rest.split_once('`'))
+            .map(|(field, _)| field)
+            .filter(|field| T::fields().contains(field));
+
+        match field {
+            Some(field) => Self::MissingField {
+                field: field.to_string(),
+            },
+            None => Self::ParseError(err),
+        }
+    }
+
+    /// Response of the error with `status` and `message` as its body in the given format, and
+    /// its [`code`](Self::code) in an `X-Error-Code` header.
+    ///
+    /// Incomplete uploads tell the client how many bytes were received with an `Upload-Offset`
+    /// header and which bytes with a `Range` header, chunks not starting at the received bytes
+    /// with an `Upload-Offset` header.
+    fn response(
+        &self,
+        status: StatusCode,
+        message: String,
+        format: ErrorFormat,
+    ) -> actix_web::HttpResponse {
+        let mut res = actix_web::HttpResponse::build(status);
+        res.insert_header(("X-Error-Code", self.code()));
+
+        #[cfg(feature = "resumable")]
+        match self {
+            Self::UploadIncomplete { received, .. } => {
+                res.insert_header((crate::UPLOAD_OFFSET_HEADER, received.to_string()));
+                if *received > 0 {
+                    res.insert_header((
+                        actix_web::http::header::RANGE,
+                        format!("bytes=0-{}", received - 1),
+                    ));
+                }
+            }
+            Self::UploadOffsetMismatch { expected, .. } => {
+                res.insert_header((crate::UPLOAD_OFFSET_HEADER, expected.to_string()));
+            }
+            _ => {}
+        }
+
+        match format {
+            ErrorFormat::Text => res.content_type(mime::TEXT_PLAIN_UTF_8).body(message),
+            ErrorFormat::Json => res.json(serde_json::json!({
+                "code": self.code(),
+                "message": message,
+                "field": self.field(),
+            })),
+        }
+    }
 }
 
 /// Extractor to extract multipart forms from the request
-#[derive(Debug)]
-pub struct Multipart<T>(T);
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct Multipart<T> {
+    form: T,
+    #[serde(skip)]
+    order: Vec<String>,
+}
+
+impl<T> Multipart<T> {
+    /// Take ownership of the parsed form.
+    pub fn into_inner(self) -> T {
+        self.form
+    }
+
+    /// Fields of the parts of the form in the order they were received, named after serde
+    /// renaming. Fields sent in several parts are listed once per part, the position of a part
+    /// is its index. Parts which weren't fields of the form aren't listed.
+    pub fn ordered_fields(&self) -> &[String] {
+        &self.order
+    }
+}
+
+impl<T> AsRef<T> for Multipart<T> {
+    fn as_ref(&self) -> &T {
+        &self.form
+    }
+}
+
+impl<T> AsMut<T> for Multipart<T> {
+    fn as_mut(&mut self) -> &mut T {
+        &mut self.form
+    }
+}
 
 impl<T> Deref for Multipart<T> {
     type Target = T;
 
     fn deref(&self) -> &T {
-        &self.0
+        &self.form
     }
 }
 
 impl<T> DerefMut for Multipart<T> {
     fn deref_mut(&mut self) -> &mut T {
-        &mut self.0
+        &mut self.form
     }
 }
 
@@ -51,30 +353,545 @@ impl<T: serde::de::DeserializeOwned + MultipartForm> FromRequest for Multipart<T
     type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
 
     fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
-        let mut multipart = actix_multipart::Multipart::new(req.headers(), payload.take());
-        let req_owned = req.to_owned();
+        let (req, payload) = (req.to_owned(), payload.take());
+
+        Box::pin(async move {
+            let (form, order, _) = extract_request::<T>(req, payload, false).await?;
+            Ok(Multipart { form, order })
+        })
+    }
+}
+
+/// Extractor like [`Multipart`] which also keeps the parts that aren't fields of the form.
+///
+/// Parts `T` doesn't declare, or which belong to another variant of a tagged enum, are read
+/// into [`RawPart`]s in the order they were sent instead of being skipped, also with
+/// [`MultipartConfig::deny_unknown_fields`]. Parts with a file name count as files and are
+/// limited by [`Limits::per_file`](crate::Limits::per_file).
+#[derive(Debug)]
+pub struct MultipartWithRest<T> {
+    form: Multipart<T>,
+    rest: Vec<RawPart>,
+}
+
+impl<T> MultipartWithRest<T> {
+    /// Take ownership of the parsed form and the parts which weren't fields of it.
+    pub fn into_parts(self) -> (T, Vec<RawPart>) {
+        (self.form.form, self.rest)
+    }
+
+    /// Parts which weren't fields of the form, in the order they were sent.
+    pub fn rest(&self) -> &[RawPart] {
+        &self.rest
+    }
+
+    /// Fields of the parts of the form in the order they were received, see
+    /// [`Multipart::ordered_fields`].
+    pub fn ordered_fields(&self) -> &[String] {
+        self.form.ordered_fields()
+    }
+}
+
+impl<T> Deref for MultipartWithRest<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.form
+    }
+}
+
+impl<T> DerefMut for MultipartWithRest<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.form
+    }
+}
+
+impl<T: serde::de::DeserializeOwned + MultipartForm> FromRequest for MultipartWithRest<T> {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let (req, payload) = (req.to_owned(), payload.take());
+
+        Box::pin(async move {
+            let (form, order, rest) = extract_request::<T>(req, payload, true).await?;
+            Ok(MultipartWithRest {
+                form: Multipart { form, order },
+                rest,
+            })
+        })
+    }
+}
+
+/// Extractor accepting a form either as multipart or as JSON, depending on the content type.
+///
+/// Requests with a JSON content type, `application/json` or a `+json` suffix, are deserialized
+/// into `T` and checked by [`MultipartForm::validate`], everything else is extracted like
+/// [`Multipart`]. Both share the [`MultipartConfig`] of the route: JSON bodies are limited by
+/// [`Limits::total`](crate::Limits::total), 2MB when it isn't set, and their errors go through the
+/// same error handler, as [`MultipartError::ParseError`] and
+/// [`MultipartError::RequestSizeError`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum MultipartOrJson<T> {
+    Multipart(Multipart<T>),
+    Json(T),
+}
+
+impl<T> MultipartOrJson<T> {
+    /// Take ownership of the parsed form.
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::Multipart(form) => form.into_inner(),
+            Self::Json(form) => form,
+        }
+    }
+}
+
+impl<T> Deref for MultipartOrJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match self {
+            Self::Multipart(form) => form,
+            Self::Json(form) => form,
+        }
+    }
+}
+
+impl<T> DerefMut for MultipartOrJson<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        match self {
+            Self::Multipart(form) => form,
+            Self::Json(form) => form,
+        }
+    }
+}
+
+impl<T: serde::de::DeserializeOwned + MultipartForm> FromRequest for MultipartOrJson<T> {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let (req, mut payload) = (req.to_owned(), payload.take());
 
         Box::pin(async move {
-            let config = req_owned.app_data::<MultipartConfig>();
+            if !is_json(&req) {
+                let (form, order, _) = extract_request::<T>(req, payload, false).await?;
+                return Ok(MultipartOrJson::Multipart(Multipart { form, order }));
+            }
 
-            match multipart_to_json::<T>(serde_introspect::<T>(), &mut multipart).await {
-                Ok(v) => match serde_json::from_value::<T>(v) {
-                    Ok(parsed) => Ok(Multipart(parsed)),
-                    Err(err) => Err(handle_error(MultipartError::ParseError(err), config)),
-                },
-                Err(err) => Err(handle_error(err, config)),
+            let config = MultipartConfig::from_req(&req);
+            match extract_json::<T>(&req, &mut payload, config).await {
+                Ok(form) => Ok(MultipartOrJson::Json(form)),
+                Err(err) => Err(reject(err, &req, config)),
             }
         })
     }
 }
 
-fn handle_error(error: MultipartError, config: Option<&MultipartConfig>) -> actix_web::Error {
-    let mut res = match config {
-        Some(config) => match &config.error_handler {
-            Some(error_handler) => error_handler(error),
-            None => HttpResponse::BadRequest().body(error.to_string()),
-        },
-        None => HttpResponse::BadRequest().body(error.to_string()),
+/// Whether the request has a JSON content type.
+fn is_json(req: &HttpRequest) -> bool {
+    req.headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<Mime>().ok())
+        .is_some_and(|mime| mime.subtype() == mime::JSON || mime.suffix() == Some(mime::JSON))
+}
+
+/// Deserialize and validate the JSON body of a request.
+async fn extract_json<T: serde::de::DeserializeOwned + MultipartForm>(
+    req: &HttpRequest,
+    payload: &mut Payload,
+    config: &MultipartConfig,
+) -> Result<T, MultipartError> {
+    let mut body = actix_web::dev::JsonBody::<T>::new(req, payload, None, true);
+    if let Some(total) = config.limits(req).total {
+        body = body.limit(total);
+    }
+
+    let form = body.await.map_err(|err| match err {
+        JsonPayloadError::OverflowKnownLength { limit, .. }
+        | JsonPayloadError::Overflow { limit } => MultipartError::RequestSizeError { limit },
+        JsonPayloadError::Deserialize(err) => MultipartError::from_parse::<T>(err),
+        _ => MultipartError::Incomplete,
+    })?;
+
+    form.validate()?;
+    Ok(form)
+}
+
+/// Extractor accepting a form either as multipart or as `application/x-www-form-urlencoded`,
+/// like HTML forms without file inputs send it.
+///
+/// Urlencoded fields are read like the text parts of [`Multipart`], they are renamed, normalized,
+/// checked and converted the same way and fields which weren't sent get their default. Both
+/// share the [`MultipartConfig`] of the route: urlencoded bodies are limited by
+/// [`Limits::total`](crate::Limits::total), 32kB when it isn't set, and
+/// [`Limits::max_fields`](crate::Limits::max_fields), and their errors go through the same error
+/// handler.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum FormOrMultipart<T> {
+    Multipart(Multipart<T>),
+    Form(T),
+}
+
+impl<T> FormOrMultipart<T> {
+    /// Take ownership of the parsed form.
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::Multipart(form) => form.into_inner(),
+            Self::Form(form) => form,
+        }
+    }
+}
+
+impl<T> Deref for FormOrMultipart<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match self {
+            Self::Multipart(form) => form,
+            Self::Form(form) => form,
+        }
+    }
+}
+
+impl<T> DerefMut for FormOrMultipart<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        match self {
+            Self::Multipart(form) => form,
+            Self::Form(form) => form,
+        }
+    }
+}
+
+impl<T: serde::de::DeserializeOwned + MultipartForm> FromRequest for FormOrMultipart<T> {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let (req, mut payload) = (req.to_owned(), payload.take());
+
+        Box::pin(async move {
+            if req.content_type() != mime::APPLICATION_WWW_FORM_URLENCODED.essence_str() {
+                let (form, order, _) = extract_request::<T>(req, payload, false).await?;
+                return Ok(FormOrMultipart::Multipart(Multipart { form, order }));
+            }
+
+            let config = MultipartConfig::from_req(&req);
+            match extract_urlencoded::<T>(&req, &mut payload, config).await {
+                Ok(form) => Ok(FormOrMultipart::Form(form)),
+                Err(err) => Err(reject(err, &req, config)),
+            }
+        })
+    }
+}
+
+/// Parse and validate the urlencoded body of a request.
+async fn extract_urlencoded<T: serde::de::DeserializeOwned + MultipartForm>(
+    req: &HttpRequest,
+    payload: &mut Payload,
+    config: &MultipartConfig,
+) -> Result<T, MultipartError> {
+    let limits = config.limits(req);
+
+    let mut body = actix_web::dev::UrlEncoded::<Vec<(String, String)>>::new(req, payload);
+    if let Some(total) = limits.total {
+        body = body.limit(total);
+    }
+
+    let pairs = body.await.map_err(|err| match err {
+        UrlencodedError::Overflow { limit, .. } => MultipartError::RequestSizeError { limit },
+        UrlencodedError::Parse(_) | UrlencodedError::Encoding => MultipartError::Malformed,
+        _ => MultipartError::Incomplete,
+    })?;
+    if let Some(fields) = pairs.len().checked_sub(1) {
+        limits.check_fields(fields)?;
+    }
+
+    let value = urlencoded_to_json::<T>(pairs, config)?;
+    let form = serde_json::from_value::<T>(value).map_err(MultipartError::from_parse::<T>)?;
+
+    form.validate()?;
+    Ok(form)
+}
+
+/// Convert the fields of an urlencoded body to a [`Value::Object`], like the text parts of a
+/// multipart form.
+fn urlencoded_to_json<T: MultipartForm>(
+    pairs: Vec<(String, String)>,
+    config: &MultipartConfig,
+) -> Result<Value, MultipartError> {
+    let mut map = Map::new();
+    let mut valid_fields = T::fields();
+
+    // Enums take the fields of the variant named by their tag, wherever it was sent
+    if let Some(tag) = T::tag() {
+        let mut tags = pairs.iter().filter(|(name, _)| is_named(name, tag, config));
+        if let Some((_, text)) = tags.next() {
+            if tags.next().is_some() {
+                return Err(tag_error(tag, "must only be sent once"));
+            }
+
+            valid_fields = T::variant_fields(text)
+                .ok_or_else(|| tag_error(tag, &format!("has no variant named {text:?}")))?;
+            map.insert(tag.to_string(), Value::String(text.clone()));
+        }
+    }
+
+    for (name, text) in pairs {
+        if T::tag().is_some_and(|tag| is_named(&name, tag, config)) {
+            continue;
+        }
+
+        let field_name = match field_for_part::<T>(&name, config) {
+            Some(field_name) if valid_fields.contains(&field_name) => field_name,
+            _ if config.deny_unknown_fields => {
+                return Err(MultipartError::UnknownField { field: name })
+            }
+            _ => continue,
+        };
+
+        let value = match T::parser(field_name) {
+            Some(parser) => {
+                let meta = PartMeta {
+                    field: field_name.to_string(),
+                    filename: None,
+                    content_type: mime::TEXT_PLAIN.to_string(),
+                    headers: HeaderMap::new(),
+                };
+
+                parser(text.as_bytes(), &meta).map_err(|message| {
+                    MultipartError::FieldValidationError {
+                        field: field_name.to_string(),
+                        message,
+                    }
+                })?
+            }
+            None if text.is_empty() => Value::Null,
+            None => checked_text_value::<T>(field_name, Cow::Owned(text), config)?,
+        };
+
+        params_insert::<T>(&mut map, field_name, &field_name.to_string(), value);
+    }
+
+    // Fields which weren't sent get their default
+    for field_name in valid_fields {
+        if map.contains_key(*field_name) {
+            continue;
+        }
+
+        if let Some(value) = default_value::<T>(field_name)? {
+            map.insert(field_name.to_string(), value);
+        }
+    }
+
+    Ok(Value::Object(map))
+}
+
+/// Extract the form of a request, passing errors to the error handler. Parts which aren't fields
+/// of the form are returned when `keep_rest` is set.
+async fn extract_request<T: serde::de::DeserializeOwned + MultipartForm>(
+    req: HttpRequest,
+    payload: Payload,
+    keep_rest: bool,
+) -> Result<(T, Vec<String>, Vec<RawPart>), actix_web::Error> {
+    let extract = async move {
+        let config = MultipartConfig::from_req(&req);
+        let mut rest = Vec::new();
+        let rest_ref = keep_rest.then_some(&mut rest);
+
+        match extract::<T>(&req, payload, rest_ref, config).await {
+            Ok((form, order)) => Ok((form, order, rest)),
+            Err(err) => Err(reject(err, &req, config)),
+        }
+    };
+
+    #[cfg(feature = "tracing")]
+    let extract = tracing::Instrument::instrument(extract, extract_span::<T>());
+
+    extract.await
+}
+
+/// Report an error which failed extraction and pass it to the error handler.
+fn reject(err: MultipartError, req: &HttpRequest, config: &MultipartConfig) -> actix_web::Error {
+    // Incomplete uploads didn't fail, they aren't reported
+    #[cfg(feature = "resumable")]
+    if matches!(err, MultipartError::UploadIncomplete { .. }) {
+        return handle_error(err, req, config);
+    }
+
+    #[cfg(feature = "tracing")]
+    tracing::debug!(error = %err, "multipart extraction failed");
+    #[cfg(feature = "logging")]
+    log_error(&err);
+    if let Some(metrics) = &config.metrics {
+        metrics.on_rejected(&err);
+    }
+    handle_error(err, req, config)
+}
+
+/// Span the extraction of a form is recorded in, `bytes_read` is recorded once the form was
+/// read.
+#[cfg(feature = "tracing")]
+pub(crate) fn extract_span<T>() -> tracing::Span {
+    tracing::info_span!(
+        "multipart.extract",
+        form = std::any::type_name::<T>(),
+        bytes_read = tracing::field::Empty,
+    )
+}
+
+/// Parse and validate the form.
+async fn extract<T: serde::de::DeserializeOwned + MultipartForm>(
+    req: &HttpRequest,
+    payload: Payload,
+    rest: Option<&mut Vec<RawPart>>,
+    config: &MultipartConfig,
+) -> Result<(T, Vec<String>), MultipartError> {
+    check_content_type(req)?;
+
+    let started = Instant::now();
+    let (payload, status) = TrackedPayload::new(req, payload, config);
+    let mut multipart = Parser::new(req.headers(), payload);
+    let mut slots = Slots::default();
+    let mut memory = Reservation::new(config.shared_memory_limit.as_ref());
+    let mut order = Vec::new();
+    let value = multipart_to_json::<T>(
+        T::fields(),
+        &mut multipart,
+        &status,
+        &mut slots,
+        &mut memory,
+        &mut order,
+        rest,
+        config,
+    )
+    .await?;
+
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("bytes_read", &status.bytes_read());
+
+    let (parsed, cleanups) = slots.scope(|| T::deserialize(FormDeserializer(value)));
+    let parsed = parsed.map_err(MultipartError::from_parse::<T>)?;
+
+    parsed.validate()?;
+    cleanups.accept();
+
+    record_stats(req, &status, started, config);
+
+    Ok((parsed, order))
+}
+
+/// Report an extracted request to the metrics and insert its [`MultipartStats`] into the
+/// extensions of the request.
+pub(crate) fn record_stats(
+    req: &HttpRequest,
+    status: &PayloadStatus,
+    started: Instant,
+    config: &MultipartConfig,
+) {
+    let stats = MultipartStats {
+        total_bytes: status.bytes_read(),
+        file_count: status.files(),
+        field_count: status.fields(),
+        parse_duration: started.elapsed(),
+    };
+
+    if let Some(metrics) = &config.metrics {
+        metrics.on_request_complete(stats.total_bytes, stats.file_count, stats.parse_duration);
+    }
+
+    req.extensions_mut().insert(stats);
+}
+
+/// Make sure the request is a `multipart/form-data` request, or a `multipart/related` one with
+/// the `multer` feature. actix-multipart can't read past the parts of related bodies, which have
+/// no name.
+pub(crate) fn check_content_type(req: &HttpRequest) -> Result<(), MultipartError> {
+    let content_type = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+
+    match content_type.parse::<mime::Mime>() {
+        Ok(mime)
+            if mime.type_() == mime::MULTIPART
+                && (mime.subtype() == mime::FORM_DATA
+                    || cfg!(feature = "multer") && mime.subtype() == "related") =>
+        {
+            Ok(())
+        }
+        _ => Err(MultipartError::NotMultipart {
+            content_type: content_type.to_string(),
+        }),
+    }
+}
+
+/// Log an error which failed extraction, limits are logged as warnings.
+#[cfg(feature = "logging")]
+pub(crate) fn log_error(err: &MultipartError) {
+    match err {
+        MultipartError::Malformed => log::warn!("malformed multipart stream: {err}"),
+        MultipartError::FileSizeError { .. }
+        | MultipartError::TextFieldTooLarge { .. }
+        | MultipartError::RequestSizeError { .. }
+        | MultipartError::TooManyFiles { .. }
+        | MultipartError::TooManyFields { .. }
+        | MultipartError::PartHeaderSizeError { .. }
+        | MultipartError::MemoryLimitError { .. }
+        | MultipartError::DeadlineExceeded { .. } => {
+            log::warn!("multipart request rejected: {err}")
+        }
+        #[cfg(feature = "compression")]
+        MultipartError::DecompressedSizeError { .. } => {
+            log::warn!("multipart request rejected: {err}")
+        }
+        #[cfg(feature = "resumable")]
+        MultipartError::TooManyUploads { .. } => log::warn!("multipart request rejected: {err}"),
+        _ => log::debug!("multipart extraction failed: {err}"),
+    }
+}
+
+pub(crate) fn handle_error(
+    error: MultipartError,
+    req: &HttpRequest,
+    config: &MultipartConfig,
+) -> actix_web::Error {
+    // Incomplete uploads always get the default response, clients need its status and headers
+    #[cfg(feature = "resumable")]
+    let customized = !matches!(error, MultipartError::UploadIncomplete { .. });
+    #[cfg(not(feature = "resumable"))]
+    let customized = true;
+
+    let handled = match &config.partial_error_handler {
+        Some(partial_error_handler) if customized => partial_error_handler(error),
+        _ => Err(error),
+    };
+
+    let handled = match (handled, &config.error_handler) {
+        (Err(error), Some(error_handler)) if customized => Ok(error_handler(error)),
+        (handled, _) => handled,
+    };
+
+    let mut res = match handled {
+        Ok(res) => res,
+        Err(error) => {
+            let status = match &config.status_for {
+                Some(status_for) if customized => status_for(&error),
+                _ => error.status_code(),
+            };
+            let message = config
+                .error_message
+                .as_ref()
+                .and_then(|error_message| error_message(&error, req))
+                .unwrap_or_else(|| error.to_string());
+
+            error.response(status, message, config.error_format)
+        }
     };
 
     // We must do this manually because of a bug in actix_http
@@ -85,137 +902,1223 @@ fn handle_error(error: MultipartError, config: Option<&MultipartConfig>) -> acti
     actix_web::error::InternalError::from_response("invalid multipart", res).into()
 }
 
-/// Convert a [`actix_multipart::Multipart`] form to a [`Value::Object`].
+/// Convert the parts of a multipart form to a [`Value::Object`].
 ///
-/// This checks for valid fields and file size limits on the [`MultipartForm`].
+/// This checks for valid fields and file size limits on the [`MultipartForm`]. Parts which
+/// aren't fields of the form are pushed to `rest` if set.
+#[allow(clippy::too_many_arguments)]
 async fn multipart_to_json<T: MultipartForm>(
     valid_fields: &[&str],
-    multipart: &mut actix_multipart::Multipart,
+    multipart: &mut Parser,
+    payload: &PayloadStatus,
+    slots: &mut Slots,
+    memory: &mut Reservation,
+    order: &mut Vec<String>,
+    mut rest: Option<&mut Vec<RawPart>>,
+    config: &MultipartConfig,
 ) -> Result<Value, MultipartError> {
     let mut map = Map::new();
+    #[cfg(feature = "hash")]
+    let mut duplicates = Duplicates::new(config);
 
-    while let Ok(Some(mut field)) = multipart.try_next().await {
-        let disposition = field.content_disposition().clone();
+    // Enums only accept the fields of their variant once its tag was received
+    let mut valid_fields = valid_fields;
+    let mut variant_selected = T::tag().is_none();
 
-        let field_name = match disposition.get_name() {
-            Some(v) => v,
-            None => continue,
+    loop {
+        if config.early_exit != EarlyExit::Disabled
+            && variant_selected
+            && is_complete(valid_fields, &map)
+        {
+            if config.early_exit == EarlyExit::Drain {
+                // Parts are skipped by the parser when they are dropped unread
+                while let Some(Ok(_)) = payload.next(multipart).await? {}
+            }
+
+            break;
+        }
+
+        let mut field = match payload.next_part(multipart).await? {
+            Some(Ok(field)) => field,
+            None => break,
+            Some(Err(err)) => return Err(err.into_error()),
         };
 
-        let field_name_formatted = field_name.replace("[]", "");
+        let disposition = field.disposition().clone();
 
-        // Make sure the field actually exists on the form
-        if !valid_fields.contains(&field_name) {
+        if let Some(tag) = T::tag().filter(|tag| {
+            disposition
+                .get_name()
+                .is_some_and(|name| is_named(name, tag, config))
+        }) {
+            if variant_selected {
+                return Err(tag_error(tag, "must only be sent once"));
+            }
+
+            let bytes = read_field(
+                &mut field,
+                tag,
+                &ReadOptions::default(),
+                None,
+                payload,
+                memory,
+                config,
+            )
+            .await?
+            .bytes;
+
+            let text = String::from_utf8_lossy(&bytes);
+            valid_fields = T::variant_fields(&text)
+                .ok_or_else(|| tag_error(tag, &format!("has no variant named {text:?}")))?;
+            variant_selected = true;
+
+            map.insert(tag.to_string(), Value::String(text.into_owned()));
+            order.push(tag.to_string());
+            pool::give(bytes);
             continue;
         }
 
-        if field.content_disposition().get_filename().is_some() {
-            // Is a file
-            let mut data: Vec<Value> = Vec::new();
-
-            let max_size = T::max_size(field_name);
-            let mut size = 0;
-
-            while let Some(chunk) = field.next().await {
-                match chunk {
-                    Ok(bytes) => {
-                        size += bytes.len();
-                        if let Some(max_size) = max_size {
-                            if size > max_size {
-                                return Err(MultipartError::FileSizeError {
-                                    field: field_name.to_string(),
-                                    limit: max_size,
-                                });
-                            }
-                        }
+        let content_id_field = content_id(field.headers()).and_then(T::content_id_field);
 
-                        data.reserve_exact(bytes.len());
-                        for byte in bytes {
-                            data.push(Value::Number(Number::from(byte)));
-                        }
-                    }
-                    Err(_) => {
-                        map.insert(field_name_formatted.to_owned(), Value::Null);
-                        continue;
+        if content_id_field.is_none() && disposition.get_name().is_none() {
+            match config.unnamed_parts {
+                UnnamedParts::Ignore => {}
+                UnnamedParts::Reject => return Err(MultipartError::UnnamedPart),
+                UnnamedParts::Collect => {
+                    if let Some(rest) = rest.as_deref_mut() {
+                        rest.push(read_raw_part(&mut field, payload, memory, config).await?);
                     }
                 }
             }
 
-            let mut field_map = Map::new();
-            field_map.insert(
-                "content_type".to_owned(),
-                Value::String(field.content_type().to_string()),
-            );
+            continue;
+        }
 
-            field_map.insert(
-                "name".to_owned(),
-                Value::String(
-                    field
-                        .content_disposition()
-                        .get_filename()
-                        .unwrap()
-                        .to_string(),
-                ),
-            );
+        // Make sure the field actually exists on the form
+        let field_name = match content_id_field.or_else(|| {
+            disposition
+                .get_name()
+                .and_then(|name| field_for_part::<T>(name, config))
+        }) {
+            Some(v) => v,
+            None => match rest.as_deref_mut() {
+                Some(rest) => {
+                    rest.push(read_raw_part(&mut field, payload, memory, config).await?);
+                    continue;
+                }
+                None if config.deny_unknown_fields => {
+                    return Err(MultipartError::UnknownField {
+                        field: disposition.get_name().unwrap_or_default().to_string(),
+                    })
+                }
+                None => {
+                    #[cfg(feature = "logging")]
+                    log::debug!(
+                        "skipping part {:?}, {} has no such field",
+                        disposition.get_name(),
+                        std::any::type_name::<T>()
+                    );
+                    continue;
+                }
+            },
+        };
 
-            field_map.insert("bytes".to_owned(), Value::Array(data));
+        if !variant_selected {
+            return Err(tag_error(
+                T::tag().unwrap_or_default(),
+                "must be sent before the fields of its variant",
+            ));
+        }
 
-            params_insert(
-                &mut map,
-                field_name,
-                &field_name_formatted,
-                Value::Object(field_map),
-            );
-        } else if let Some(Ok(value)) = field.next().await {
-            // Not a file, parse as other JSON types
-            if let Ok(str) = std::str::from_utf8(&value) {
-                // Attempt to convert into a number
-                match str.parse::<isize>() {
-                    Ok(number) => params_insert(
-                        &mut map,
-                        field_name,
-                        &field_name_formatted,
-                        Value::Number(Number::from(number)),
-                    ),
-                    Err(_) => match str {
-                        "true" => params_insert(
-                            &mut map,
-                            field_name,
-                            &field_name_formatted,
-                            Value::Bool(true),
-                        ),
-                        "false" => params_insert(
-                            &mut map,
-                            field_name,
-                            &field_name_formatted,
-                            Value::Bool(false),
-                        ),
-                        _ => params_insert(
-                            &mut map,
-                            field_name,
-                            &field_name_formatted,
-                            Value::String(str.to_owned()),
-                        ),
-                    },
-                }
+        // Fields of other variants are skipped
+        if !valid_fields.contains(&field_name) {
+            if let Some(rest) = rest.as_deref_mut() {
+                rest.push(read_raw_part(&mut field, payload, memory, config).await?);
+                continue;
             }
-        } else {
-            // Nothing
-            params_insert(&mut map, field_name, &field_name_formatted, Value::Null)
-        }
-    }
 
-    Ok(Value::Object(map))
+            if config.deny_unknown_fields {
+                return Err(MultipartError::UnknownField {
+                    field: field_name.to_string(),
+                });
+            }
+
+            #[cfg(feature = "logging")]
+            log::debug!("skipping part {field_name:?}, the variant has no such field");
+            continue;
+        }
+
+        let field_name_formatted = field_name.to_string();
+        order.push(field_name_formatted.clone());
+
+        let filename = part_filename::<T>(field_name, &disposition, content_id_field);
+
+        if let Some(parser) = T::parser(field_name) {
+            // Converted by the function of the field, parts with a file name still count as files
+            let mut max_size = T::max_size(field_name);
+            if filename.is_some() {
+                payload.add_file()?;
+                max_size = payload.limits().file_limit(max_size);
+            }
+
+            let options = ReadOptions {
+                max_size,
+                deadline: T::deadline(field_name),
+                #[cfg(feature = "hash")]
+                hash_algorithm: None,
+                check_start: None,
+            };
+
+            let bytes = read_field(
+                &mut field, field_name, &options, None, payload, memory, config,
+            )
+            .await
+            .map_err(|err| match filename {
+                Some(_) => err,
+                None => text_size_error(err),
+            })?
+            .bytes;
+
+            let meta = PartMeta {
+                field: field_name.to_string(),
+                filename: filename.map(str::to_string),
+                content_type: field.content_type().to_string(),
+                headers: field.headers().clone(),
+            };
+
+            let value =
+                parser(&bytes, &meta).map_err(|message| MultipartError::FieldValidationError {
+                    field: field_name.to_string(),
+                    message,
+                })?;
+
+            pool::give(bytes);
+            params_insert::<T>(&mut map, field_name, &field_name_formatted, value);
+        } else if field.content_type().essence_str() == "multipart/mixed" {
+            // Several files sent for one field, each becomes an element of the field
+            let mut nested = field.into_nested()?;
+
+            let mut files = Vec::new();
+
+            loop {
+                let mut part = match payload.next_part(&mut nested).await? {
+                    Some(Ok(part)) => part,
+                    None => break,
+                    Some(Err(err)) => return Err(err.into_error()),
+                };
+
+                // Parts which aren't files are skipped
+                let filename = match part.disposition().get_filename() {
+                    Some(filename) => filename.to_string(),
+                    None => continue,
+                };
+
+                payload.add_file()?;
+
+                let file = read_file::<T>(
+                    &mut part, field_name, &filename, payload, slots, memory, config,
+                )
+                .await?;
+                #[cfg(feature = "hash")]
+                let file =
+                    duplicates.check(file, || next_slot(&map, field_name, true, files.len()));
+
+                files.push(file);
+            }
+
+            match map
+                .entry(field_name_formatted)
+                .or_insert_with(|| Value::Array(Vec::new()))
+            {
+                Value::Array(list) => list.extend(files),
+                value => *value = Value::Array(files),
+            }
+        } else if let Some(filename) = filename {
+            payload.add_file()?;
+
+            let value = read_file::<T>(
+                &mut field, field_name, filename, payload, slots, memory, config,
+            )
+            .await?;
+            #[cfg(feature = "hash")]
+            let value = duplicates.check(value, || {
+                next_slot(&map, field_name, T::is_list(field_name), 0)
+            });
+
+            params_insert::<T>(&mut map, field_name, &field_name_formatted, value);
+        } else if T::base64_file(field_name) {
+            payload.add_file()?;
+
+            let value =
+                read_base64_file::<T>(&mut field, field_name, payload, slots, memory, config)
+                    .await?;
+            #[cfg(feature = "hash")]
+            let value = duplicates.check(value, || {
+                next_slot(&map, field_name, T::is_list(field_name), 0)
+            });
+
+            params_insert::<T>(&mut map, field_name, &field_name_formatted, value);
+        } else {
+            let options = ReadOptions {
+                max_size: T::max_size(field_name),
+                deadline: T::deadline(field_name),
+                #[cfg(feature = "hash")]
+                hash_algorithm: None,
+                check_start: None,
+            };
+
+            let bytes = read_field(
+                &mut field, field_name, &options, None, payload, memory, config,
+            )
+            .await
+            .map_err(text_size_error)?
+            .bytes;
+
+            // Raw bytes are handed to their field from a slot, not as an array of numbers
+            if T::field_kind(field_name) == FieldKind::Bytes {
+                let value = slots.insert_bytes(bytes);
+                params_insert::<T>(&mut map, field_name, &field_name_formatted, value);
+            } else if let Some(value) =
+                text_part_value::<T>(field_name, bytes, field.content_type(), config)?
+            {
+                params_insert::<T>(&mut map, field_name, &field_name_formatted, value)
+            }
+        }
+    }
+
+    // Fields which weren't sent get their default
+    for field_name in valid_fields {
+        if map.contains_key(*field_name) {
+            continue;
+        }
+
+        if let Some(value) = default_value::<T>(field_name)? {
+            map.insert(field_name.to_string(), value);
+        }
+    }
+
+    Ok(Value::Object(map))
+}
+
+/// Hashes of the files of a request with where they went in the form, to mark files sent again
+/// with [`MultipartConfig::detect_duplicate_files`].
+#[cfg(feature = "hash")]
+struct Duplicates(Option<std::collections::HashMap<String, String>>);
+
+#[cfg(feature = "hash")]
+impl Duplicates {
+    fn new(config: &MultipartConfig) -> Self {
+        Self(config.detect_duplicate_files.then(Default::default))
+    }
+
+    /// Mark the value of a file with where the first file with the same hash went, `slot` is
+    /// where this file goes. Files without a hash aren't checked.
+    fn check(&mut self, mut value: Value, slot: impl FnOnce() -> String) -> Value {
+        let (hashes, file) = match (&mut self.0, &mut value) {
+            (Some(hashes), Value::Object(file)) => (hashes, file),
+            _ => return value,
+        };
+
+        if let Some(Value::String(hash)) = file.get("hash") {
+            match hashes.get(hash) {
+                Some(first) => {
+                    let first = Value::String(first.clone());
+                    file.insert("duplicate_of".to_owned(), first);
+                }
+                None => {
+                    hashes.insert(hash.clone(), slot());
+                }
+            }
+        }
+
+        value
+    }
+}
+
+/// Where the next value of a field goes in the form, with its index for lists. `offset` counts
+/// values which weren't inserted yet.
+#[cfg(feature = "hash")]
+fn next_slot(map: &Map<String, Value>, field_name: &str, is_list: bool, offset: usize) -> String {
+    match map.get(field_name) {
+        _ if !is_list => field_name.to_string(),
+        Some(Value::Array(list)) => format!("{field_name}[{}]", list.len() + offset),
+        _ => format!("{field_name}[{offset}]"),
+    }
+}
+
+fn tag_error(tag: &str, message: &str) -> MultipartError {
+    MultipartError::FieldValidationError {
+        field: tag.to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// Get the name of the field a part is parsed into. Parts of list fields may also be named with
+/// a `[]` suffix, and with [`MultipartConfig::case_insensitive_fields`] in any ASCII case.
+pub(crate) fn field_for_part<T: MultipartForm>(
+    name: &str,
+    config: &MultipartConfig,
+) -> Option<&'static str> {
+    let lookup = |name: &str| {
+        T::field_for_part(name).or_else(|| {
+            if !config.case_insensitive_fields {
+                return None;
+            }
+
+            let name = T::part_names()
+                .into_iter()
+                .find(|part_name| part_name.eq_ignore_ascii_case(name))?;
+            T::field_for_part(name)
+        })
+    };
+
+    lookup(name).or_else(|| {
+        let field = lookup(name.strip_suffix("[]")?)?;
+        T::is_list(field).then_some(field)
+    })
+}
+
+/// Whether a part is named `expected`, ignoring ASCII case with
+/// [`MultipartConfig::case_insensitive_fields`].
+fn is_named(name: &str, expected: &str, config: &MultipartConfig) -> bool {
+    match config.case_insensitive_fields {
+        true => name.eq_ignore_ascii_case(expected),
+        false => name == expected,
+    }
+}
+
+/// File name of a part parsed into a field, `None` if the part is read as text.
+pub(crate) fn part_filename<'a, T: MultipartForm>(
+    field_name: &str,
+    disposition: &'a Disposition,
+    content_id_field: Option<&str>,
+) -> Option<&'a str> {
+    if T::as_text(field_name) || T::field_kind(field_name) == FieldKind::Bytes {
+        return None;
+    }
+
+    // Parts mapped by their Content-ID or forced to be files have no file name
+    disposition.get_filename().or_else(|| {
+        let is_file = content_id_field.is_some_and(T::is_file) || T::as_file(field_name);
+        is_file.then_some("")
+    })
+}
+
+/// Apply the filename policy to the file name of a part and check it against its field.
+pub(crate) fn check_filename<'a, T: MultipartForm>(
+    field_name: &str,
+    filename: &'a str,
+    config: &MultipartConfig,
+) -> Result<Cow<'a, str>, MultipartError> {
+    let applied =
+        config
+            .filename_policy
+            .apply(filename)
+            .ok_or_else(|| MultipartError::InvalidFilename {
+                field: field_name.to_string(),
+                filename: filename.to_string(),
+            })?;
+
+    T::validate_filename(field_name, &applied).map_err(|message| {
+        MultipartError::FieldValidationError {
+            field: field_name.to_string(),
+            message,
+        }
+    })?;
+
+    Ok(applied)
+}
+
+/// Convert the text of a field into the value it is parsed from.
+fn text_value<T: MultipartForm>(field_name: &str, text: Cow<str>) -> Result<Value, MultipartError> {
+    let value = T::convert_text(field_name, &text).map_err(|message| {
+        MultipartError::FieldValidationError {
+            field: field_name.to_string(),
+            message,
+        }
+    })?;
+
+    Ok(value.unwrap_or_else(|| match T::field_kind(field_name) {
+        FieldKind::Guess => text_to_value(text),
+        FieldKind::Text => Value::String(text.into_owned()),
+        FieldKind::Bytes => bytes_value(text.as_bytes()),
+    }))
+}
+
+/// Get the value a field is parsed from when none of its parts were sent.
+pub(crate) fn default_value<T: MultipartForm>(
+    field_name: &str,
+) -> Result<Option<Value>, MultipartError> {
+    let default =
+        T::field_default(field_name).map_err(|message| MultipartError::FieldValidationError {
+            field: field_name.to_string(),
+            message,
+        })?;
+
+    match default {
+        Some(FieldDefault::Text(text)) => {
+            text_value::<T>(field_name, Cow::Borrowed(text)).map(Some)
+        }
+        Some(FieldDefault::Value(value)) => Ok(Some(value)),
+        None => Ok(None),
+    }
+}
+
+/// Read a file part, returning the value it is parsed from.
+async fn read_file<T: MultipartForm>(
+    field: &mut Part,
+    field_name: &str,
+    filename: &str,
+    payload: &PayloadStatus,
+    slots: &mut Slots,
+    memory: &mut Reservation,
+    config: &MultipartConfig,
+) -> Result<Value, MultipartError> {
+    let filename = check_filename::<T>(field_name, filename, config)?;
+
+    let options = ReadOptions {
+        max_size: payload.limits().file_limit(T::max_size(field_name)),
+        deadline: T::deadline(field_name),
+        #[cfg(feature = "hash")]
+        hash_algorithm: T::hash_algorithm(field_name),
+        check_start: None,
+    };
+
+    #[allow(unused_mut)]
+    let mut content_type = field.content_type().clone();
+
+    #[allow(unused_mut)]
+    let mut field_map = Map::new();
+
+    #[cfg(feature = "mime-guess")]
+    if let Some(guessed_type) = mime_guess::from_path(filename.as_ref()).first() {
+        // Clients omitting the content type get a generic default
+        if !field.headers().contains_key(CONTENT_TYPE) {
+            content_type = guessed_type.clone();
+        }
+
+        field_map.insert(
+            "guessed_type".to_owned(),
+            Value::String(guessed_type.to_string()),
+        );
+    }
+
+    let meta = FileMeta {
+        name: filename.to_string(),
+        content_type: content_type.to_string(),
+    };
+
+    #[cfg(feature = "resumable")]
+    let assembled =
+        crate::resumable::read_chunk(field, field_name, &options, payload, memory, config).await?;
+
+    // `SpooledFile` fields spill to a temporary file past the threshold
+    #[cfg(feature = "tempfile")]
+    let spool = config
+        .memory_threshold
+        .filter(|_| T::spooled(field_name))
+        .map(|threshold| -> Box<dyn FileSink> {
+            Box::new(SpoolSink::new(threshold, config.resolve_temp_dir()))
+        });
+    #[cfg(not(feature = "tempfile"))]
+    let spool = None;
+
+    // Stream into the sink instead of buffering
+    let sink = match spool {
+        Some(sink) => Some(sink),
+        None => config
+            .file_storage
+            .as_deref()
+            .unwrap_or(&MemoryStorage)
+            .sink(field_name, &meta, config)
+            .map_err(|error| MultipartError::StorageError {
+                field: field_name.to_string(),
+                error,
+            })?,
+    };
+
+    if let Some(sink) = sink {
+        let check = |bytes: &[u8]| {
+            #[cfg(feature = "sniff")]
+            detect_content_type::<T>(field_name, &content_type, bytes)?;
+
+            check_content::<T>(field_name, bytes)
+        };
+
+        #[cfg(feature = "resumable")]
+        if let Some(body) = assembled {
+            let mut sink = sink;
+            check(&body.bytes)?;
+            let bytes = match &config.content_scanner {
+                Some(scanner) => scan_bytes(&**scanner, field_name, &meta, body.bytes).await?,
+                None => body.bytes,
+            };
+
+            crate::resumable::write_sink(&mut sink, field_name, bytes).await?;
+            return Ok(insert_streamed(
+                slots,
+                StreamedFile::new(meta, body.size, sink),
+            ));
+        }
+
+        // Streamed files are held back until their start is checked
+        let options = &ReadOptions {
+            check_start: checked_start_len::<T>(field_name)
+                .map(|len| StartCheck { len, check: &check }),
+            ..options
+        };
+        let read = move |mut sink| async move {
+            let body = read_field(
+                field,
+                field_name,
+                options,
+                Some(&mut sink),
+                payload,
+                memory,
+                config,
+            )
+            .await;
+
+            (body, sink)
+        };
+
+        // Scanned while it streams into the sink
+        let (body, sink) = match &config.content_scanner {
+            Some(scanner) => scan_streamed(&**scanner, field_name, &meta, sink, read).await?,
+            None => {
+                let (body, sink) = read(sink).await;
+                (body?, sink)
+            }
+        };
+
+        return Ok(insert_streamed(
+            slots,
+            StreamedFile::new(meta, body.size, sink),
+        ));
+    }
+
+    #[cfg(feature = "resumable")]
+    let body = match assembled {
+        Some(body) => body,
+        None => read_field(field, field_name, &options, None, payload, memory, config).await?,
+    };
+    #[cfg(not(feature = "resumable"))]
+    let body = read_field(field, field_name, &options, None, payload, memory, config).await?;
+
+    let FieldBody {
+        mut bytes,
+        #[cfg(feature = "hash")]
+        hash,
+        ..
+    } = body;
+
+    if let Some(scanner) = &config.content_scanner {
+        bytes = scan_bytes(&**scanner, field_name, &meta, bytes).await?;
+    }
+
+    file_value::<T>(
+        field_name,
+        &filename,
+        &content_type,
+        field_map,
+        bytes,
+        #[cfg(feature = "hash")]
+        hash,
+        slots,
+    )
+}
+
+/// Read a text part holding a file as a base64 string or a `data:` URL, returning the value of
+/// the decoded file. The size limits of the field apply to the decoded bytes.
+async fn read_base64_file<T: MultipartForm>(
+    field: &mut Part,
+    field_name: &str,
+    payload: &PayloadStatus,
+    slots: &mut Slots,
+    memory: &mut Reservation,
+    config: &MultipartConfig,
+) -> Result<Value, MultipartError> {
+    let limit = payload.limits().file_limit(T::max_size(field_name));
+
+    let options = ReadOptions {
+        max_size: limit.map(base64_text_limit),
+        deadline: T::deadline(field_name),
+        ..Default::default()
+    };
+
+    let text = read_field(field, field_name, &options, None, payload, memory, config)
+        .await
+        .map_err(|err| match (err, limit) {
+            (MultipartError::FileSizeError { field, .. }, Some(limit)) => {
+                MultipartError::FileSizeError { field, limit }
+            }
+            (err, _) => err,
+        })?
+        .bytes;
+
+    let (media_type, bytes) =
+        decode_base64_file(&text).map_err(|message| decode_error(field_name, message))?;
+    pool::give(text);
+
+    if let Some(limit) = limit.filter(|limit| bytes.len() > *limit) {
+        return Err(MultipartError::FileSizeError {
+            field: field_name.to_string(),
+            limit,
+        });
+    }
+
+    let content_type = media_type.unwrap_or(mime::APPLICATION_OCTET_STREAM);
+
+    let bytes = match &config.content_scanner {
+        Some(scanner) => {
+            let meta = FileMeta {
+                name: String::new(),
+                content_type: content_type.to_string(),
+            };
+            scan_bytes(&**scanner, field_name, &meta, bytes).await?
+        }
+        None => bytes,
+    };
+
+    #[cfg(feature = "hash")]
+    let hash = T::hash_algorithm(field_name).map(|algorithm| {
+        let mut hasher = Hasher::new(algorithm);
+        hasher.update(&bytes);
+        hasher.finish()
+    });
+
+    file_value::<T>(
+        field_name,
+        "",
+        &content_type,
+        Map::new(),
+        bytes,
+        #[cfg(feature = "hash")]
+        hash,
+        slots,
+    )
+}
+
+/// Get the value of a file read into memory, after checking its content.
+fn file_value<T: MultipartForm>(
+    field_name: &str,
+    filename: &str,
+    content_type: &Mime,
+    mut field_map: Map<String, Value>,
+    bytes: Vec<u8>,
+    #[cfg(feature = "hash")] hash: Option<String>,
+    slots: &mut Slots,
+) -> Result<Value, MultipartError> {
+    #[cfg(feature = "sniff")]
+    if let Some(detected_type) = detect_content_type::<T>(field_name, content_type, &bytes)? {
+        field_map.insert(
+            "detected_type".to_owned(),
+            Value::String(detected_type.to_string()),
+        );
+    }
+
+    check_content::<T>(field_name, &bytes)?;
+
+    field_map.insert(
+        "content_type".to_owned(),
+        Value::String(content_type.to_string()),
+    );
+
+    field_map.insert("name".to_owned(), Value::String(filename.to_string()));
+
+    #[cfg(feature = "hash")]
+    if let Some(hash) = hash {
+        field_map.insert("hash".to_owned(), Value::String(hash));
+    }
+
+    field_map.insert("size".to_owned(), Value::Number(Number::from(bytes.len())));
+
+    // Kept out of the JSON, a value per byte would take far more memory than the bytes
+    field_map.insert("bytes".to_owned(), slots.insert(bytes));
+
+    Ok(Value::Object(field_map))
+}
+
+/// Store a file streamed into a sink, objects uploaded for it are deleted again if the form
+/// doesn't take it.
+fn insert_streamed(slots: &mut Slots, file: StreamedFile) -> Value {
+    #[cfg(feature = "object-store")]
+    if let Some(cleanup) = crate::object_store::orphan_cleanup(&file) {
+        return slots.insert_guarded(file, cleanup);
+    }
+
+    slots.insert(file)
+}
+
+/// Bytes at the start of an image streamed into a sink held back to decode its header, enough
+/// for the metadata segments of JPEGs before their dimensions.
+#[cfg(feature = "image")]
+const CHECKED_IMAGE_START_LEN: usize = 256 * 1024;
+
+/// Bytes at the start of a file streamed into a sink held back to detect its content type.
+#[cfg(feature = "sniff")]
+const SNIFFED_START_LEN: usize = 8 * 1024;
+
+/// Bytes held back from the sink to check the content of files of a field, `None` if their
+/// content isn't checked.
+#[cfg_attr(
+    not(any(feature = "image", feature = "sniff")),
+    allow(unused_variables, clippy::extra_unused_type_parameters)
+)]
+fn checked_start_len<T: MultipartForm>(field_name: &str) -> Option<usize> {
+    #[cfg(feature = "image")]
+    if T::image_rules(field_name).is_some() {
+        return Some(CHECKED_IMAGE_START_LEN);
+    }
+
+    #[cfg(feature = "sniff")]
+    if T::verify_content_type(field_name) || T::strict_content_type(field_name) {
+        return Some(SNIFFED_START_LEN);
+    }
+
+    None
+}
+
+/// Detect the content type of a file from its start, checking it against the declared type when
+/// the field verifies it.
+#[cfg(feature = "sniff")]
+fn detect_content_type<T: MultipartForm>(
+    field_name: &str,
+    declared: &Mime,
+    bytes: &[u8],
+) -> Result<Option<&'static str>, MultipartError> {
+    let mismatch = |detected: &str| MultipartError::ContentTypeMismatch {
+        field: field_name.to_string(),
+        declared: declared.to_string(),
+        detected: detected.to_string(),
+    };
+
+    let strict = T::strict_content_type(field_name);
+    let verify = strict || T::verify_content_type(field_name);
+
+    match infer::get(bytes).map(|t| t.mime_type()) {
+        Some(detected) if verify && declared.essence_str() != detected => Err(mismatch(detected)),
+        // Content which isn't what its declared type would have been detected as
+        None if strict && infer::is_mime_supported(declared.essence_str()) => {
+            Err(mismatch("unknown content"))
+        }
+        detected => Ok(detected),
+    }
+}
+
+/// Check the content of a file, or the start of a file streamed into a sink.
+#[cfg_attr(
+    not(feature = "image"),
+    allow(unused_variables, clippy::extra_unused_type_parameters)
+)]
+fn check_content<T: MultipartForm>(field_name: &str, bytes: &[u8]) -> Result<(), MultipartError> {
+    #[cfg(feature = "image")]
+    if let Some(rules) = T::image_rules(field_name) {
+        crate::image::check_image(bytes, &rules).map_err(|message| {
+            MultipartError::InvalidImage {
+                field: field_name.to_string(),
+                message,
+            }
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Get the next chunk of the body of a field, failing once its deadline passed.
+async fn next_chunk(
+    field: &mut Part,
+    field_name: &str,
+    deadline: Option<(Duration, Instant)>,
+    payload: &PayloadStatus,
+) -> Result<Option<Result<Bytes, PartError>>, MultipartError> {
+    let (deadline, at) = match deadline {
+        Some(deadline) => deadline,
+        None => return payload.next(field).await,
+    };
+
+    let remaining = at.saturating_duration_since(Instant::now());
+
+    actix_web::rt::time::timeout(remaining, payload.next(field))
+        .await
+        .map_err(|_| MultipartError::DeadlineExceeded {
+            field: field_name.to_string(),
+            deadline,
+        })?
+}
+
+/// Report a field read as text over its max size with [`MultipartError::TextFieldTooLarge`].
+pub(crate) fn text_size_error(err: MultipartError) -> MultipartError {
+    match err {
+        MultipartError::FileSizeError { field, limit } => {
+            MultipartError::TextFieldTooLarge { field, limit }
+        }
+        err => err,
+    }
+}
+
+/// Options for reading the body of a field.
+#[derive(Default)]
+pub(crate) struct ReadOptions<'a> {
+    pub(crate) max_size: Option<usize>,
+    /// How long the body may take to be received.
+    pub(crate) deadline: Option<Duration>,
+    #[cfg(feature = "hash")]
+    pub(crate) hash_algorithm: Option<HashAlgorithm>,
+    /// Check of the start of a body written to a sink, run before its first byte is written.
+    pub(crate) check_start: Option<StartCheck<'a>>,
+}
+
+/// Check of the first `len` bytes of a body, or all of them when it is shorter.
+pub(crate) struct StartCheck<'a> {
+    pub(crate) len: usize,
+    pub(crate) check: &'a dyn Fn(&[u8]) -> Result<(), MultipartError>,
+}
+
+/// Decoded body of a field.
+pub(crate) struct FieldBody {
+    /// Empty when the body was written to a sink.
+    pub(crate) bytes: Vec<u8>,
+    pub(crate) size: usize,
+    #[cfg(feature = "hash")]
+    pub(crate) hash: Option<String>,
+}
+
+/// Read the full body of a field, undoing any transfer or content encoding.
+///
+/// `max_size` is enforced on the decoded bytes. With a sink the decoded bytes are written to the
+/// sink instead of being returned, streaming them when they don't need decoding. Buffered bytes
+/// are reserved from the shared memory budget.
+pub(crate) async fn read_field(
+    field: &mut Part,
+    field_name: &str,
+    options: &ReadOptions<'_>,
+    mut sink: Option<&mut Box<dyn FileSink>>,
+    payload: &PayloadStatus,
+    memory: &mut Reservation,
+    config: &MultipartConfig,
+) -> Result<FieldBody, MultipartError> {
+    let max_size = options.max_size;
+    let size_error = |limit| MultipartError::FileSizeError {
+        field: field_name.to_string(),
+        limit,
+    };
+
+    let storage_error = |error| MultipartError::StorageError {
+        field: field_name.to_string(),
+        error,
+    };
+
+    #[cfg(feature = "tracing")]
+    let started = Instant::now();
+
+    let mut decoder = BodyDecoder::new(field_name, field.headers(), max_size, config)?;
+
+    // Encoded bodies are larger than their decoded form, bound them separately.
+    let encoded_limit =
+        max_size.map(|max_size| decoder.transfer_encoding().encoded_limit(max_size));
+    let mut size = 0;
+    let mut streamed = 0;
+    // Bytes of the decoder reserved from the memory budget
+    let mut reserved = 0;
+
+    // Held back from the sink until the start of the body was checked
+    let mut head = options
+        .check_start
+        .as_ref()
+        .filter(|_| sink.is_some())
+        .map(|check| (Vec::new(), check));
+
+    // Preallocate for the rest of the request, bounded by the limit of the field. The length of
+    // the request is declared by the client, larger bodies grow as their bytes arrive.
+    if sink.is_none() && !decoder.decompresses_chunks() {
+        let remaining = payload
+            .content_length()
+            .map_or(0, |len| len.saturating_sub(payload.bytes_read()));
+
+        decoder.preallocate(
+            remaining
+                .min(encoded_limit.unwrap_or(usize::MAX))
+                .min(pool::MAX_CAPACITY_HINT),
+        );
+        reserve_buffered(memory, &mut reserved, decoder.buffered())?;
+    }
+
+    #[cfg(feature = "hash")]
+    let mut hasher = options.hash_algorithm.map(Hasher::new);
+
+    let deadline = options
+        .deadline
+        .map(|deadline| (deadline, Instant::now() + deadline));
+
+    while let Some(chunk) = next_chunk(field, field_name, deadline, payload).await? {
+        // The parser can't read past a chunk which failed, the part and the rest of the stream
+        // are lost
+        let chunk = chunk.map_err(PartError::into_error)?;
+
+        size += chunk.len();
+        if let (Some(max_size), Some(encoded_limit)) = (max_size, encoded_limit) {
+            if size > encoded_limit {
+                return Err(size_error(max_size));
+            }
+        }
+
+        // Hash while streaming when chunks don't need decoding first
+        #[cfg(feature = "hash")]
+        if let Some(hasher) = hasher.as_mut().filter(|_| decoder.is_identity()) {
+            hasher.update(&chunk);
+        }
+
+        match &mut sink {
+            Some(sink) if decoder.is_identity() => {
+                streamed += chunk.len();
+
+                match &mut head {
+                    Some((buffer, check)) => {
+                        memory
+                            .grow(chunk.len())
+                            .map_err(|limit| MultipartError::MemoryLimitError { limit })?;
+                        buffer.extend_from_slice(&chunk);
+
+                        if buffer.len() >= check.len {
+                            (check.check)(buffer)?;
+                            let buffer = std::mem::take(buffer);
+                            head = None;
+                            sink.send(Bytes::from(buffer))
+                                .await
+                                .map_err(storage_error)?;
+                        }
+                    }
+                    None => sink.send(chunk).await.map_err(storage_error)?,
+                }
+            }
+            _ => {
+                decoder.write(&chunk)?;
+                reserve_buffered(memory, &mut reserved, decoder.buffered())?;
+            }
+        }
+
+        if let Some(progress_handler) = &config.progress_handler {
+            progress_handler(field_name, payload.bytes_read(), payload.content_length());
+        }
+    }
+
+    #[cfg(feature = "hash")]
+    let is_identity = decoder.is_identity();
+
+    let bytes = decoder.finish()?;
+    reserve_buffered(memory, &mut reserved, bytes.capacity())?;
+
+    if let Some(max_size) = max_size {
+        if bytes.len() > max_size {
+            return Err(size_error(max_size));
+        }
+    }
+
+    #[cfg(feature = "hash")]
+    let hash = hasher.map(|mut hasher| {
+        if !is_identity {
+            hasher.update(&bytes);
+        }
+
+        hasher.finish()
+    });
+
+    if let Some(sink) = sink {
+        // Shorter bodies and decoded bodies are checked once complete
+        if let Some((buffer, check)) = head {
+            match buffer.is_empty() {
+                true => (check.check)(&bytes)?,
+                false => {
+                    (check.check)(&buffer)?;
+                    sink.send(Bytes::from(buffer))
+                        .await
+                        .map_err(storage_error)?;
+                }
+            }
+        }
+
+        if !bytes.is_empty() {
+            streamed += bytes.len();
+            sink.send(Bytes::from(bytes)).await.map_err(storage_error)?;
+        }
+
+        sink.close().await.map_err(storage_error)?;
+
+        #[cfg(feature = "tracing")]
+        trace_field(field_name, field, streamed, started);
+
+        return Ok(FieldBody {
+            bytes: Vec::new(),
+            size: streamed,
+            #[cfg(feature = "hash")]
+            hash,
+        });
+    }
+
+    #[cfg(feature = "tracing")]
+    trace_field(field_name, field, bytes.len(), started);
+
+    Ok(FieldBody {
+        size: bytes.len(),
+        bytes,
+        #[cfg(feature = "hash")]
+        hash,
+    })
+}
+
+/// Reserve the bytes buffered by a decoder which weren't reserved yet, the output of decoders
+/// can be far larger than the chunks written to them.
+fn reserve_buffered(
+    memory: &mut Reservation,
+    reserved: &mut usize,
+    buffered: usize,
+) -> Result<(), MultipartError> {
+    if buffered > *reserved {
+        memory
+            .grow(buffered - *reserved)
+            .map_err(|limit| MultipartError::MemoryLimitError { limit })?;
+        *reserved = buffered;
+    }
+
+    Ok(())
+}
+
+/// Get the value the body of a text part is parsed from, `None` if its text isn't valid UTF-8.
+///
+/// Buffers which can be pooled are given back once the text was read.
+pub(crate) fn text_part_value<T: MultipartForm>(
+    field_name: &str,
+    bytes: Vec<u8>,
+    content_type: &mime::Mime,
+    config: &MultipartConfig,
+) -> Result<Option<Value>, MultipartError> {
+    // Raw bytes aren't decoded, they don't need to be UTF-8
+    if T::field_kind(field_name) == FieldKind::Bytes {
+        let value = bytes_value(&bytes);
+        pool::give(bytes);
+        return Ok(Some(value));
+    }
+
+    if bytes.is_empty() {
+        return Ok(Some(Value::Null));
+    }
+
+    // Buffers too large to be pooled become the text instead of being copied into it
+    if !pool::keeps(bytes.capacity()) {
+        let text = decode_owned_text(bytes, content_type)
+            .map_err(|message| decode_error(field_name, message))?;
+        let text = text.map(|mut text| {
+            text.shrink_to_fit();
+            Cow::Owned(text)
+        });
+        return text_value_or_skip::<T>(field_name, text, config);
+    }
+
+    let text =
+        decode_text(&bytes, content_type).map_err(|message| decode_error(field_name, message))?;
+    let value = text_value_or_skip::<T>(field_name, text, config);
+    pool::give(bytes);
+    value
+}
+
+/// Get the value of decoded text, `None` if it couldn't be decoded.
+fn text_value_or_skip<T: MultipartForm>(
+    field_name: &str,
+    text: Option<Cow<str>>,
+    config: &MultipartConfig,
+) -> Result<Option<Value>, MultipartError> {
+    // Not a file, parse as other JSON types
+    match text {
+        Some(text) => checked_text_value::<T>(field_name, text, config).map(Some),
+        None => {
+            #[cfg(feature = "logging")]
+            log::debug!("skipping field {field_name:?}, its text isn't valid UTF-8");
+            Ok(None)
+        }
+    }
+}
+
+/// Normalize and check the text of a field, returning the value it is parsed from.
+pub(crate) fn checked_text_value<T: MultipartForm>(
+    field_name: &str,
+    text: Cow<str>,
+    config: &MultipartConfig,
+) -> Result<Value, MultipartError> {
+    let text = T::normalize(field_name).apply(config.clean_text(text));
+
+    // A `null` literal is a missing value, the text isn't checked
+    if config.null_literal && text == "null" {
+        return Ok(Value::Null);
+    }
+
+    T::validate_text(field_name, &text).map_err(|message| {
+        MultipartError::FieldValidationError {
+            field: field_name.to_string(),
+            message,
+        }
+    })?;
+
+    text_value::<T>(field_name, text)
+}
+
+/// Record an event for a part which was read.
+#[cfg(feature = "tracing")]
+fn trace_field(field_name: &str, field: &Part, size: usize, started: Instant) {
+    tracing::debug!(
+        field = field_name,
+        size,
+        content_type = %field.content_type(),
+        duration_ms = started.elapsed().as_secs_f64() * 1000.0,
+        "multipart field read"
+    );
+}
+
+/// Get the `Content-ID` of a part without its angle brackets.
+pub(crate) fn content_id(headers: &HeaderMap) -> Option<&str> {
+    let content_id = headers.get("content-id")?.to_str().ok()?.trim();
+
+    Some(
+        content_id
+            .strip_prefix('<')
+            .and_then(|id| id.strip_suffix('>'))
+            .unwrap_or(content_id),
+    )
+}
+
+/// Whether every field of the form was received and no list can receive more parts.
+fn is_complete(valid_fields: &[&str], map: &Map<String, Value>) -> bool {
+    valid_fields
+        .iter()
+        .all(|field| matches!(map.get(*field), Some(value) if !value.is_array()))
+}
+
+/// Value raw bytes are deserialized from, an array of the bytes.
+fn bytes_value(bytes: &[u8]) -> Value {
+    Value::Array(bytes.iter().map(|byte| Value::from(*byte)).collect())
+}
+
+/// Guess the JSON type of a text value, owned text is moved into the value.
+fn text_to_value(text: Cow<str>) -> Value {
+    // Attempt to convert into a number
+    match text.parse::<isize>() {
+        Ok(number) => Value::Number(Number::from(number)),
+        Err(_) => match text.as_ref() {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => Value::String(text.into_owned()),
+        },
+    }
 }
 
 /// Insert params to the map. This works with individual fields and arrays.
-fn params_insert(
+fn params_insert<T: MultipartForm>(
     params: &mut Map<String, Value>,
     field_name: &str,
     field_name_formatted: &String,
     element: Value,
 ) {
-    if field_name.ends_with("[]") {
+    if field_name.ends_with("[]") || T::is_list(field_name) {
         if params.contains_key(field_name_formatted) {
             if let Value::Array(val) = params.get_mut(field_name_formatted).unwrap() {
                 val.push(element);
@@ -224,6 +2127,6 @@ fn params_insert(
             params.insert(field_name_formatted.to_owned(), Value::Array(vec![element]));
         }
     } else {
-        params.insert(field_name.to_owned(), element);
+        params.insert(field_name_formatted.to_owned(), element);
     }
 }
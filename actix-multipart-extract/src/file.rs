@@ -0,0 +1,148 @@
+use actix_web::web::Bytes;
+use futures::{io::AsyncRead, Stream};
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+use std::{fmt, io, path::Path};
+
+/// Size of the chunks yielded by [`File::into_stream`].
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Representing a file in a multipart form.
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+pub struct File {
+    pub content_type: String,
+    /// Content type detected from the magic bytes of the file, requires the `sniff` feature.
+    ///
+    /// Fields marked `#[multipart(verify_content_type)]` reject files not matching their
+    /// declared type, `#[multipart(strict_content_type)]` also rejects content which can't be
+    /// identified when its declared type could be. Both check files streamed into storage too.
+    #[serde(default)]
+    pub detected_type: Option<String>,
+    /// Content type guessed from the extension of the file name, requires the `mime-guess`
+    /// feature. This is used as the content type when the client did not send one.
+    #[serde(default)]
+    pub guessed_type: Option<String>,
+    pub name: String,
+    #[serde(deserialize_with = "crate::slot::deserialize_bytes")]
+    pub bytes: Vec<u8>,
+    /// Amount of bytes received for the file.
+    #[serde(default)]
+    pub size: usize,
+    /// Lowercase hex digest of the file when hashed with `#[multipart(hash = "...")]`,
+    /// requires the `hash` feature.
+    #[serde(default)]
+    pub hash: Option<String>,
+    /// Where the first file of the request with the same hash went, like `photos[0]`, when
+    /// duplicates are detected with
+    /// [`MultipartConfig::detect_duplicate_files`](crate::MultipartConfig). Requires the `hash`
+    /// feature.
+    #[serde(default)]
+    pub duplicate_of: Option<String>,
+}
+
+impl File {
+    /// Amount of bytes received for the file.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Whether the file was received without any content.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Parsed content type of the file.
+    ///
+    /// Falls back to `application/octet-stream` if the content type is invalid.
+    pub fn mime(&self) -> mime::Mime {
+        self.content_type
+            .parse()
+            .unwrap_or(mime::APPLICATION_OCTET_STREAM)
+    }
+
+    /// Extension of the file name, without the leading dot.
+    pub fn extension(&self) -> Option<&str> {
+        Path::new(&self.name)
+            .extension()
+            .and_then(|ext| ext.to_str())
+    }
+
+    /// Consume the file into a stream of its bytes, in chunks of 64 KiB sharing the buffer of
+    /// the file.
+    ///
+    /// Useful for forwarding the file to APIs taking a byte stream, such as object storage
+    /// clients.
+    pub fn into_stream(self) -> impl Stream<Item = Result<Bytes, io::Error>> + Send + Unpin {
+        let bytes = Bytes::from(self.bytes);
+        let len = bytes.len();
+
+        futures::stream::iter(
+            (0..len)
+                .step_by(STREAM_CHUNK_SIZE)
+                .map(move |start| Ok(bytes.slice(start..len.min(start + STREAM_CHUNK_SIZE)))),
+        )
+    }
+
+    /// Consume the file into an async reader of its bytes.
+    pub fn into_reader(self) -> impl AsyncRead + Send + Unpin {
+        futures::io::Cursor::new(self.bytes)
+    }
+}
+
+/// The bytes of files aren't printed, they would flood logs and leak their content.
+impl fmt::Debug for File {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("File")
+            .field("name", &self.name)
+            .field("content_type", &self.content_type)
+            .field("detected_type", &self.detected_type)
+            .field("guessed_type", &self.guessed_type)
+            .field("size", &self.size)
+            .field("hash", &self.hash)
+            .field("duplicate_of", &self.duplicate_of)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Files serialize to their metadata, `{"name", "content_type", "size"}`, without their bytes.
+/// Useful to log forms or respond with what was uploaded.
+impl Serialize for File {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut file = serializer.serialize_struct("File", 3)?;
+        file.serialize_field("name", &self.name)?;
+        file.serialize_field("content_type", &self.content_type)?;
+        file.serialize_field("size", &self.size)?;
+        file.end()
+    }
+}
+
+/// Files are documented as binary strings.
+#[cfg(feature = "schemars")]
+macro_rules! impl_json_schema {
+    ($($ty:ty),*) => {$(
+        impl schemars::JsonSchema for $ty {
+            fn schema_name() -> String {
+                "File".to_owned()
+            }
+
+            fn is_referenceable() -> bool {
+                false
+            }
+
+            fn json_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+                schemars::schema::SchemaObject {
+                    instance_type: Some(schemars::schema::InstanceType::String.into()),
+                    format: Some("binary".to_owned()),
+                    ..Default::default()
+                }
+                .into()
+            }
+        }
+    )*};
+}
+
+#[cfg(feature = "schemars")]
+impl_json_schema!(File, crate::StreamedFile);
+#[cfg(all(feature = "schemars", feature = "object-store"))]
+impl_json_schema!(crate::UploadedObject);
+#[cfg(all(feature = "schemars", feature = "tempfile"))]
+impl_json_schema!(crate::TempFile, crate::SpooledFile);
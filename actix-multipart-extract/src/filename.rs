@@ -0,0 +1,47 @@
+use std::borrow::Cow;
+
+/// How file names sent by the client are treated, set with
+/// [`crate::MultipartConfig::set_filename_policy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FilenamePolicy {
+    /// Keep file names exactly as sent.
+    Keep,
+    /// Strip directory components, null bytes and control characters.
+    #[default]
+    Sanitize,
+    /// Reject file names which would be changed by [`FilenamePolicy::Sanitize`],
+    /// such as `../../etc/passwd`.
+    Strict,
+}
+
+impl FilenamePolicy {
+    /// Apply the policy to a file name, `None` if the name is rejected.
+    pub(crate) fn apply<'a>(&self, name: &'a str) -> Option<Cow<'a, str>> {
+        match self {
+            Self::Keep => Some(Cow::Borrowed(name)),
+            Self::Sanitize => Some(sanitize(name)),
+            Self::Strict => match sanitize(name) {
+                Cow::Borrowed(sanitized) if sanitized == name => Some(Cow::Borrowed(name)),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Strip directory components, null bytes and control characters from a file name.
+fn sanitize(name: &str) -> Cow<'_, str> {
+    // Both separators are stripped since clients may run on any platform
+    let base = name.rsplit(['/', '\\']).next().unwrap_or_default();
+
+    let base: Cow<str> = if base.chars().any(char::is_control) {
+        Cow::Owned(base.chars().filter(|c| !c.is_control()).collect())
+    } else {
+        Cow::Borrowed(base)
+    };
+
+    match base.as_ref() {
+        "." | ".." => Cow::Borrowed(""),
+        _ if base.len() == name.len() => Cow::Borrowed(name),
+        _ => base,
+    }
+}
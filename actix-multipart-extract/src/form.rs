@@ -1,7 +1,504 @@
+use std::{borrow::Cow, time::Duration};
+
+use crate::{MultipartError, PartMeta};
+
+/// Function converting the bytes of a part into the value its field is deserialized from, set
+/// with `#[multipart(with = "...")]`.
+pub type PartParser = fn(&[u8], &PartMeta) -> Result<serde_json::Value, String>;
+
+/// How the text of a field is converted before it is parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    /// Guess whether the text is a number, boolean or string.
+    Guess,
+    /// Keep the text as a string, used for types deserialized from strings.
+    Text,
+    /// Keep the raw bytes of the part, also when they aren't UTF-8, used for `Vec<u8>` and
+    /// `Bytes` fields.
+    Bytes,
+}
+
+/// Default of a field which wasn't sent.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldDefault {
+    /// Text parsed as if it was sent for the field, set with `#[multipart(default = "...")]`.
+    Text(&'static str),
+    /// Value the field is deserialized from, set with `#[multipart(default_fn = "...")]`.
+    Value(serde_json::Value),
+}
+
+/// Normalization of the text of a field, applied before it is checked and parsed.
+///
+/// Set with `#[multipart(trim)]`, `#[multipart(collapse_whitespace)]` and
+/// `#[multipart(lowercase)]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Normalize {
+    /// Remove whitespace around the text.
+    pub trim: bool,
+    /// Replace every run of whitespace with a single space.
+    pub collapse_whitespace: bool,
+    /// Lowercase the text.
+    pub lowercase: bool,
+}
+
+impl Normalize {
+    pub const NONE: Self = Self {
+        trim: false,
+        collapse_whitespace: false,
+        lowercase: false,
+    };
+
+    /// Normalize the text, it is only copied if it changes.
+    pub fn apply<'a>(&self, value: Cow<'a, str>) -> Cow<'a, str> {
+        let mut value = value;
+
+        if self.trim && value.trim().len() != value.len() {
+            value = match value {
+                Cow::Borrowed(value) => Cow::Borrowed(value.trim()),
+                Cow::Owned(value) => Cow::Owned(value.trim().to_string()),
+            };
+        }
+
+        if self.collapse_whitespace && needs_collapse(&value) {
+            let mut collapsed = String::with_capacity(value.len());
+            let mut whitespace = false;
+
+            for c in value.chars() {
+                if c.is_whitespace() {
+                    if !whitespace {
+                        collapsed.push(' ');
+                    }
+                    whitespace = true;
+                } else {
+                    collapsed.push(c);
+                    whitespace = false;
+                }
+            }
+
+            value = Cow::Owned(collapsed);
+        }
+
+        if self.lowercase && value.chars().any(char::is_uppercase) {
+            value = Cow::Owned(value.to_lowercase());
+        }
+
+        value
+    }
+}
+
+/// Example body of a form for request samples in API docs, generated by the derive.
+///
+/// Text parts hold a sample value of their type, their default or the first value allowed by
+/// `one_of`, files are placeholders.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FormExample {
+    pub parts: Vec<ExamplePart>,
+}
+
+/// A part of a [`FormExample`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExamplePart {
+    /// Name of the part.
+    pub name: &'static str,
+    pub value: ExampleValue,
+}
+
+/// Content of an [`ExamplePart`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExampleValue {
+    Text(&'static str),
+    File {
+        filename: &'static str,
+        content_type: &'static str,
+    },
+}
+
+impl FormExample {
+    /// The example as a JSON object of the parts, the shape of examples of `multipart/form-data`
+    /// bodies in OpenAPI. Files are their file name.
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut object = serde_json::Map::new();
+
+        for part in &self.parts {
+            let value = match part.value {
+                ExampleValue::Text(text) => text,
+                ExampleValue::File { filename, .. } => filename,
+            };
+
+            object.insert(part.name.to_owned(), value.into());
+        }
+
+        object.into()
+    }
+}
+
+/// Whether the text has whitespace other than single spaces.
+fn needs_collapse(value: &str) -> bool {
+    let mut whitespace = false;
+
+    value.chars().any(|c| {
+        let collapse = c.is_whitespace() && (whitespace || c != ' ');
+        whitespace = c.is_whitespace();
+        collapse
+    })
+}
+
 /// This shouldn't be used or implemented manually.
 /// Use [`actix_multipart_extract_derive::MultipartForm`].
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a multipart form",
+    label = "`{Self}` doesn't derive `MultipartForm`",
+    note = "derive `MultipartForm` for `{Self}`, which is also required by `MultipartSerialize`"
+)]
 pub trait MultipartForm {
+    /// Names of the fields of the form, the fields of every variant of an enum.
+    /// The fields are named after serde renaming.
+    fn fields() -> &'static [&'static str];
+
+    /// Name of the part selecting the variant of an enum, set with
+    /// `#[multipart(tag = "...")]`. `None` for structs.
+    fn tag() -> Option<&'static str> {
+        None
+    }
+
+    /// Get the fields of the variant selected by the text of the tag part, `None` if no variant
+    /// has this tag.
+    /// The fields are named after serde renaming.
+    fn variant_fields(_tag: &str) -> Option<&'static [&'static str]> {
+        None
+    }
+
+    /// Example body of the form, an enum is described by its first variant.
+    fn example() -> FormExample {
+        FormExample::default()
+    }
+
     /// Get the max size of a named multipart field.
     /// The fields are named after serde renaming.
     fn max_size(field: &str) -> Option<usize>;
+
+    /// Get how long the body of a part of a named multipart field may take to be received, set
+    /// with `#[multipart(deadline = "30s")]`.
+    /// The fields are named after serde renaming.
+    fn deadline(_field: &str) -> Option<Duration> {
+        None
+    }
+
+    /// Get how the text of a named multipart field is normalized before it is checked.
+    /// The fields are named after serde renaming.
+    fn normalize(_field: &str) -> Normalize {
+        Normalize::NONE
+    }
+
+    /// Check the text value of a named multipart field before it is parsed.
+    /// The fields are named after serde renaming.
+    fn validate_text(_field: &str, _value: &str) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Check the file name of a part of a named multipart field, after applying the
+    /// [`FilenamePolicy`](crate::FilenamePolicy).
+    /// The fields are named after serde renaming.
+    fn validate_filename(_field: &str, _value: &str) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Get how the text of a named multipart field is converted.
+    /// The fields are named after serde renaming.
+    fn field_kind(_field: &str) -> FieldKind {
+        FieldKind::Guess
+    }
+
+    /// Whether the files of a named field are [`SpooledFile`](crate::SpooledFile)s, spilling to a
+    /// temporary file past [`MultipartConfig::memory_threshold`](crate::MultipartConfig).
+    /// The fields are named after serde renaming.
+    fn spooled(_field: &str) -> bool {
+        false
+    }
+
+    /// Convert the text value of a named multipart field into the value its type is
+    /// deserialized from, `None` if the text should be converted according to its [`FieldKind`].
+    /// The fields are named after serde renaming.
+    fn convert_text(_field: &str, _value: &str) -> Result<Option<serde_json::Value>, String> {
+        Ok(None)
+    }
+
+    /// Get the name of the field a part of a `multipart/related` body with a `Content-ID` is
+    /// parsed into, set with `#[multipart(content_id = "...")]`.
+    /// The fields are named after serde renaming.
+    fn content_id_field(_content_id: &str) -> Option<&'static str> {
+        None
+    }
+
+    /// Get the name of the field a part is parsed into, `None` if the form has no such field.
+    /// Parts are named after serde renaming unless the field has a
+    /// `#[multipart(rename = "...")]`, or after one of its `#[multipart(alias = "...")]`.
+    /// The fields are named after serde renaming.
+    fn field_for_part(name: &str) -> Option<&'static str>;
+
+    /// Names of the parts matched by [`field_for_part`](Self::field_for_part), the renames and
+    /// aliases of the fields and the names of the fields without a rename.
+    fn part_names() -> Vec<&'static str> {
+        Self::fields().to_vec()
+    }
+
+    /// Whether the values of a named field are files, parts mapped to them by their
+    /// `Content-ID` are read as files without a file name.
+    fn is_file(_field: &str) -> bool {
+        false
+    }
+
+    /// Whether a named field is a list collecting every part sent for it, even when the name
+    /// of the parts doesn't end with `[]`.
+    /// The fields are named after serde renaming.
+    fn is_list(_field: &str) -> bool {
+        false
+    }
+
+    /// Whether the parts of a named field are read as files even without a file name, set with
+    /// `#[multipart(as_file)]`.
+    /// The fields are named after serde renaming.
+    fn as_file(_field: &str) -> bool {
+        false
+    }
+
+    /// Whether the parts of a named field are read as text even with a file name, set with
+    /// `#[multipart(as_text)]`.
+    /// The fields are named after serde renaming.
+    fn as_text(_field: &str) -> bool {
+        false
+    }
+
+    /// Whether the text parts of a named field are base64 strings or `data:` URLs decoded into
+    /// files, set with `#[multipart(base64_file)]`.
+    /// The fields are named after serde renaming.
+    fn base64_file(_field: &str) -> bool {
+        false
+    }
+
+    /// Get the function converting the parts of a named multipart field, instead of reading
+    /// them as text or files.
+    /// The fields are named after serde renaming.
+    fn parser(_field: &str) -> Option<PartParser> {
+        None
+    }
+
+    /// Get the default of a named multipart field used when no part was sent for it.
+    /// The fields are named after serde renaming.
+    fn field_default(_field: &str) -> Result<Option<FieldDefault>, String> {
+        Ok(None)
+    }
+
+    /// Validate the form after it was parsed.
+    fn validate(&self) -> Result<(), MultipartError> {
+        Ok(())
+    }
+
+    /// Whether a file field must have magic bytes matching its declared content type.
+    #[cfg(feature = "sniff")]
+    fn verify_content_type(_field: &str) -> bool {
+        false
+    }
+
+    /// Whether a file field must have magic bytes matching its declared content type, rejecting
+    /// content which can't be identified when its declared type could be.
+    #[cfg(feature = "sniff")]
+    fn strict_content_type(_field: &str) -> bool {
+        false
+    }
+
+    /// Get the rules images of a file field are checked against.
+    #[cfg(feature = "image")]
+    fn image_rules(_field: &str) -> Option<crate::ImageRules> {
+        None
+    }
+
+    /// Get the digest algorithm to hash a file field with.
+    #[cfg(feature = "hash")]
+    fn hash_algorithm(_field: &str) -> Option<crate::HashAlgorithm> {
+        None
+    }
+}
+
+/// Delegate the methods of [`MultipartForm`] which depend on the features of this crate to the
+/// form wrapped by a newtype, the derive doesn't know which features are enabled.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __delegate_feature_methods {
+    ($inner:ty) => {
+        $crate::__delegate_sniff!($inner);
+        $crate::__delegate_image!($inner);
+        $crate::__delegate_hash!($inner);
+    };
+}
+
+#[cfg(feature = "sniff")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __delegate_sniff {
+    ($inner:ty) => {
+        fn verify_content_type(field: &str) -> bool {
+            <$inner as $crate::form::MultipartForm>::verify_content_type(field)
+        }
+
+        fn strict_content_type(field: &str) -> bool {
+            <$inner as $crate::form::MultipartForm>::strict_content_type(field)
+        }
+    };
+}
+
+#[cfg(not(feature = "sniff"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __delegate_sniff {
+    ($inner:ty) => {};
+}
+
+#[cfg(feature = "image")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __delegate_image {
+    ($inner:ty) => {
+        fn image_rules(field: &str) -> Option<$crate::ImageRules> {
+            <$inner as $crate::form::MultipartForm>::image_rules(field)
+        }
+    };
+}
+
+#[cfg(not(feature = "image"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __delegate_image {
+    ($inner:ty) => {};
+}
+
+#[cfg(feature = "hash")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __delegate_hash {
+    ($inner:ty) => {
+        fn hash_algorithm(field: &str) -> Option<$crate::HashAlgorithm> {
+            <$inner as $crate::form::MultipartForm>::hash_algorithm(field)
+        }
+    };
+}
+
+#[cfg(not(feature = "hash"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __delegate_hash {
+    ($inner:ty) => {};
+}
+
+/// Whether the text is a UUID in the simple, hyphenated, braced or URN format.
+pub fn is_uuid(value: &str) -> bool {
+    let hyphenated = value
+        .strip_prefix("urn:uuid:")
+        .or_else(|| value.strip_prefix('{').and_then(|v| v.strip_suffix('}')))
+        .unwrap_or(value);
+
+    let groups: Vec<_> = hyphenated.split('-').collect();
+    let lengths: &[usize] = match groups.len() {
+        1 if hyphenated.len() == value.len() => &[32],
+        5 => &[8, 4, 4, 4, 12],
+        _ => return false,
+    };
+
+    groups
+        .iter()
+        .zip(lengths)
+        .all(|(group, len)| group.len() == *len && group.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+/// Check that the text is a decimal with at most `max_scale` decimal places.
+#[cfg(feature = "decimal")]
+pub fn check_scale(value: &str, max_scale: u32) -> Result<(), String> {
+    let decimal = rust_decimal::Decimal::from_str_exact(value.trim())
+        .map_err(|_| "must be a decimal".to_string())?;
+
+    if decimal.scale() > max_scale {
+        return Err(format!("must have at most {} decimal places", max_scale));
+    }
+
+    Ok(())
+}
+
+/// Probe running [`validator::Validate`] for forms implementing it.
+///
+/// Calling `(&&ValidatorProbe(form)).validate_form()` resolves to [`ValidatorValidate`] when
+/// the form implements [`validator::Validate`] and to the no-op [`ValidatorSkip`] otherwise.
+#[cfg(feature = "validate")]
+pub struct ValidatorProbe<'a, T>(pub &'a T);
+
+#[cfg(feature = "validate")]
+pub trait ValidatorValidate {
+    fn validate_form(&self) -> Result<(), MultipartError>;
+}
+
+#[cfg(feature = "validate")]
+impl<T: validator::Validate> ValidatorValidate for &ValidatorProbe<'_, T> {
+    fn validate_form(&self) -> Result<(), MultipartError> {
+        self.0.validate().map_err(MultipartError::Validation)
+    }
+}
+
+#[cfg(feature = "validate")]
+pub trait ValidatorSkip {
+    fn validate_form(&self) -> Result<(), MultipartError>;
+}
+
+#[cfg(feature = "validate")]
+impl<T> ValidatorSkip for ValidatorProbe<'_, T> {
+    fn validate_form(&self) -> Result<(), MultipartError> {
+        Ok(())
+    }
+}
+
+/// Probe running [`garde::Validate`] for forms implementing it.
+///
+/// Works like [`ValidatorProbe`], the form's validation context is created with [`Default`].
+#[cfg(feature = "garde")]
+pub struct GardeProbe<'a, T>(pub &'a T);
+
+#[cfg(feature = "garde")]
+pub trait GardeValidate {
+    fn validate_form(&self) -> Result<(), MultipartError>;
+}
+
+#[cfg(feature = "garde")]
+impl<T: garde::Validate> GardeValidate for &GardeProbe<'_, T>
+where
+    T::Context: Default,
+{
+    fn validate_form(&self) -> Result<(), MultipartError> {
+        self.0.validate().map_err(MultipartError::GardeValidation)
+    }
+}
+
+#[cfg(feature = "garde")]
+pub trait GardeSkip {
+    fn validate_form(&self) -> Result<(), MultipartError>;
+}
+
+#[cfg(feature = "garde")]
+impl<T> GardeSkip for GardeProbe<'_, T> {
+    fn validate_form(&self) -> Result<(), MultipartError> {
+        Ok(())
+    }
+}
+
+/// Types which can be parsed from text using a format, set with `#[multipart(format = "...")]`.
+#[cfg(feature = "chrono")]
+pub trait ParseFormat: serde::Serialize + Sized {
+    fn parse_format(value: &str, format: &str) -> Result<Self, String>;
+}
+
+/// Parse text using a format into the value `T` is deserialized from.
+#[cfg(feature = "chrono")]
+pub fn parse_format<T: ParseFormat>(
+    value: &str,
+    format: &str,
+) -> Result<serde_json::Value, String> {
+    let parsed = T::parse_format(value, format)?;
+    serde_json::to_value(parsed).map_err(|err| err.to_string())
 }
@@ -0,0 +1,35 @@
+use actix_web::{
+    guard::{fn_guard, Guard},
+    http::header::CONTENT_TYPE,
+};
+use mime::Mime;
+
+/// Guard matching `multipart/form-data` requests with a boundary, to route requests to
+/// different handlers by the type of their body instead of failing in the extractor.
+///
+/// ```
+/// use actix_multipart_extract::multipart_guard;
+/// use actix_web::{web, App, HttpResponse};
+///
+/// App::new().service(
+///     web::resource("/posts")
+///         .route(web::post().guard(multipart_guard()).to(HttpResponse::Created))
+///         .route(web::post().to(HttpResponse::Ok)),
+/// );
+/// ```
+pub fn multipart_guard() -> impl Guard {
+    fn_guard(|ctx| {
+        ctx.head()
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<Mime>().ok())
+            .is_some_and(|mime| {
+                mime.type_() == mime::MULTIPART
+                    && mime.subtype() == mime::FORM_DATA
+                    && mime
+                        .get_param(mime::BOUNDARY)
+                        .is_some_and(|boundary| !boundary.as_str().is_empty())
+            })
+    })
+}
@@ -0,0 +1,62 @@
+//! Images checked with `#[multipart(image(...))]`, only their header is decoded.
+
+use image::{ImageFormat, ImageReader};
+use std::io::Cursor;
+
+/// Rules of an image field set with
+/// `#[multipart(image(max_width = 4096, max_height = 4096, formats = "png,jpeg"))]`.
+///
+/// Every option is optional, the formats are `png`, `jpeg`, `gif` and `webp`. Corrupt images,
+/// other formats and larger dimensions are rejected with
+/// [`MultipartError::InvalidImage`](crate::MultipartError::InvalidImage). Images streamed into a
+/// sink are held back until their first 256 KiB were checked, images whose header is further in
+/// are rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageRules {
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    /// Names of the accepted formats, any supported format if empty.
+    pub formats: &'static [&'static str],
+}
+
+fn format_name(format: ImageFormat) -> Option<&'static str> {
+    match format {
+        ImageFormat::Png => Some("png"),
+        ImageFormat::Jpeg => Some("jpeg"),
+        ImageFormat::Gif => Some("gif"),
+        ImageFormat::WebP => Some("webp"),
+        _ => None,
+    }
+}
+
+/// Check the format and dimensions of an image, the message is the reason it was rejected.
+pub(crate) fn check_image(bytes: &[u8], rules: &ImageRules) -> Result<(), String> {
+    let reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|err| err.to_string())?;
+
+    let format = reader
+        .format()
+        .and_then(format_name)
+        .ok_or_else(|| "unknown image format".to_string())?;
+
+    if !rules.formats.is_empty() && !rules.formats.contains(&format) {
+        return Err(format!(
+            "format {} isn't one of {}",
+            format,
+            rules.formats.join(", ")
+        ));
+    }
+
+    let (width, height) = reader.into_dimensions().map_err(|err| err.to_string())?;
+
+    if let Some(max_width) = rules.max_width.filter(|max_width| width > *max_width) {
+        return Err(format!("width of {width}px is over {max_width}px"));
+    }
+
+    if let Some(max_height) = rules.max_height.filter(|max_height| height > *max_height) {
+        return Err(format!("height of {height}px is over {max_height}px"));
+    }
+
+    Ok(())
+}
@@ -1,13 +1,84 @@
+#[cfg(feature = "apistos")]
+mod apistos;
+#[cfg(feature = "awc")]
+mod awc;
+#[cfg(feature = "chrono")]
+mod chrono;
 mod config;
+mod decode;
+mod encode;
 mod extractor;
+mod file;
+mod filename;
+mod guard;
+#[cfg(feature = "hash")]
+mod hash;
+#[cfg(feature = "image")]
+mod image;
+mod memory;
+mod metrics;
+mod middleware;
+#[cfg(feature = "object-store")]
+mod object_store;
+#[cfg(feature = "paperclip")]
+mod paperclip;
+mod parser;
+mod payload;
+mod pool;
+mod raw;
+#[cfg(feature = "resumable")]
+mod resumable;
+mod scan;
+mod sink;
+mod slot;
+mod storage;
+mod stream;
+
+#[cfg(feature = "test-util")]
+pub mod test;
 
 /// Don't use this module directly, use [`actix_multipart_extract_derive::MultipartForm`].
 pub mod form;
 
+#[cfg(feature = "awc")]
+pub use crate::awc::ClientRequestExt;
+#[cfg(feature = "image")]
+pub use crate::image::ImageRules;
+#[cfg(feature = "object-store")]
+pub use crate::object_store::{ObjectStoreSink, UploadedObject};
 pub use config::*;
+pub use encode::{EncodeError, FileField, MultipartBody, MultipartSerialize};
 pub use extractor::*;
+pub use file::*;
+pub use filename::FilenamePolicy;
+pub use guard::multipart_guard;
+#[cfg(feature = "hash")]
+pub use hash::HashAlgorithm;
+pub use memory::MemoryBudget;
+pub use metrics::{MultipartMetrics, MultipartStats};
+pub use middleware::{MultipartLimit, MultipartLimitMiddleware};
+pub use raw::{RawMultipart, RawPart};
+#[cfg(feature = "resumable")]
+pub use resumable::{ResumableUploads, UPLOAD_ID_HEADER, UPLOAD_OFFSET_HEADER};
+pub use scan::ContentScanner;
+pub use sink::{FileMeta, FileSink, PartMeta, StreamedFile};
+pub use storage::{FileStorage, MemoryStorage};
+#[cfg(feature = "tempfile")]
+pub use storage::{SpooledFile, TempDirStorage, TempFile, TempFileSink};
+pub use stream::{Field, FileStream, MultipartStream};
 
-pub use actix_multipart_extract_derive::MultipartForm;
+pub use actix_multipart_extract_derive::{MultipartForm, MultipartSerialize};
+pub use mime;
 
+#[doc(hidden)]
+pub use serde;
 /// Required for proc-macro usage at runtime.
 pub use serde_aux::serde_introspection::serde_introspect;
+#[doc(hidden)]
+pub use serde_json;
+
+#[cfg(feature = "pattern")]
+#[doc(hidden)]
+pub use regex;
+#[cfg(feature = "schemars")]
+pub use schemars;
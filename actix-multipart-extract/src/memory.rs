@@ -0,0 +1,88 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+#[derive(Debug)]
+struct Budget {
+    limit: usize,
+    used: AtomicUsize,
+}
+
+/// Bytes which can be buffered in memory by all requests together, set with
+/// [`MultipartConfig::set_shared_memory_limit`](crate::MultipartConfig::set_shared_memory_limit).
+///
+/// Clones share the same budget, create it once outside of `HttpServer::new` so every worker
+/// uses the same one. Bytes are counted while a request is being extracted.
+#[derive(Debug, Clone)]
+pub struct MemoryBudget(Arc<Budget>);
+
+impl MemoryBudget {
+    pub fn new(limit: usize) -> Self {
+        Self(Arc::new(Budget {
+            limit,
+            used: AtomicUsize::new(0),
+        }))
+    }
+
+    pub fn limit(&self) -> usize {
+        self.0.limit
+    }
+
+    /// Bytes currently buffered by requests being extracted.
+    pub fn used(&self) -> usize {
+        self.0.used.load(Ordering::Relaxed)
+    }
+}
+
+/// Bytes reserved from a [`MemoryBudget`] by a single request, released once dropped.
+pub(crate) struct Reservation {
+    budget: Option<MemoryBudget>,
+    bytes: usize,
+}
+
+impl Reservation {
+    pub(crate) fn new(budget: Option<&MemoryBudget>) -> Self {
+        Self {
+            budget: budget.cloned(),
+            bytes: 0,
+        }
+    }
+
+    /// Reserve `bytes` more, failing with the limit of the budget if it would be exceeded.
+    pub(crate) fn grow(&mut self, bytes: usize) -> Result<(), usize> {
+        let budget = match &self.budget {
+            Some(budget) => &budget.0,
+            None => return Ok(()),
+        };
+
+        budget
+            .used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                used.checked_add(bytes).filter(|used| *used <= budget.limit)
+            })
+            .map_err(|_| budget.limit)?;
+
+        self.bytes += bytes;
+        Ok(())
+    }
+
+    /// Take over the bytes `other` reserved from the same budget, they are released with this
+    /// reservation instead. Bytes of another budget are released right away.
+    #[cfg(feature = "resumable")]
+    pub(crate) fn absorb(&mut self, mut other: Reservation) {
+        if let (Some(budget), Some(other_budget)) = (&self.budget, &other.budget) {
+            if Arc::ptr_eq(&budget.0, &other_budget.0) {
+                self.bytes += std::mem::take(&mut other.bytes);
+            }
+        }
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if let Some(budget) = &self.budget {
+            budget.0.used.fetch_sub(self.bytes, Ordering::AcqRel);
+        }
+    }
+}
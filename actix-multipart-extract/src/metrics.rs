@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+use crate::MultipartError;
+
+/// Hooks called as requests are extracted, set with
+/// [`MultipartConfig::set_metrics`](crate::MultipartConfig::set_metrics) to maintain counters
+/// without wrapping every handler.
+///
+/// Called for [`Multipart`](crate::Multipart) and [`RawMultipart`](crate::RawMultipart)
+/// extractors, every hook does nothing by default.
+pub trait MultipartMetrics: Send + Sync + 'static {
+    /// Called once a request was extracted with the amount of bytes read, the amount of file
+    /// parts and the time taken to read it.
+    fn on_request_complete(&self, _bytes: usize, _files: usize, _duration: Duration) {}
+
+    /// Called when a request failed to extract, before the error handler.
+    fn on_rejected(&self, _error: &MultipartError) {}
+}
+
+/// Statistics of an extracted request, inserted into the extensions of the request once
+/// [`Multipart`](crate::Multipart) or [`RawMultipart`](crate::RawMultipart) extracted it.
+///
+/// Middleware can read them with `req.extensions().get::<MultipartStats>()` after the handler
+/// ran, requests which failed to extract have none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MultipartStats {
+    /// Bytes read from the request.
+    pub total_bytes: usize,
+    /// Amount of file parts.
+    pub file_count: usize,
+    /// Amount of parts, files included.
+    pub field_count: usize,
+    /// Time taken to read and parse the request.
+    pub parse_duration: Duration,
+}
@@ -0,0 +1,93 @@
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::CONTENT_LENGTH,
+    Error,
+};
+use futures::future::{ready, LocalBoxFuture, Ready};
+
+use crate::{
+    extractor::{check_content_type, handle_error},
+    MultipartConfig, MultipartError,
+};
+
+/// Middleware rejecting multipart requests whose `Content-Length` is over a limit before their
+/// body is read.
+///
+/// Rejected requests get the response of [`MultipartError::RequestSizeError`] from the
+/// [`MultipartConfig`] error handler and the connection is closed. Requests without a
+/// `Content-Length` pass through, use [`Limits::total`](crate::Limits::total) to also limit
+/// them while they are read.
+#[derive(Debug, Clone, Copy)]
+pub struct MultipartLimit {
+    max: usize,
+}
+
+impl MultipartLimit {
+    pub fn new(max: usize) -> Self {
+        Self { max }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for MultipartLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = MultipartLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(MultipartLimitMiddleware {
+            service,
+            max: self.max,
+        }))
+    }
+}
+
+/// Service created by [`MultipartLimit`].
+pub struct MultipartLimitMiddleware<S> {
+    service: S,
+    max: usize,
+}
+
+impl<S, B> Service<ServiceRequest> for MultipartLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let content_length = req
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<usize>().ok());
+
+        let too_large = content_length.is_some_and(|len| len > self.max);
+
+        if too_large && check_content_type(req.request()).is_ok() {
+            let config = MultipartConfig::from_req(req.request());
+            let error = handle_error(
+                MultipartError::RequestSizeError { limit: self.max },
+                req.request(),
+                config,
+            );
+
+            return Box::pin(ready(Ok(req.error_response(error).map_into_right_body())));
+        }
+
+        let res = self.service.call(req);
+        Box::pin(async move { res.await.map(ServiceResponse::map_into_left_body) })
+    }
+}
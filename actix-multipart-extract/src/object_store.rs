@@ -0,0 +1,245 @@
+use actix_web::web::Bytes;
+use futures::{future::BoxFuture, Future, FutureExt, Sink};
+use object_store::{path::Path, MultipartUpload, ObjectStore, PutResult, WriteMultipart};
+use serde::{de, Deserialize, Deserializer};
+use std::{
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use crate::{slot, FileMeta, MultipartConfig, StreamedFile};
+
+/// Amount of parts uploaded concurrently before reading more of the request.
+const MAX_CONCURRENCY: usize = 8;
+
+enum State {
+    Starting(BoxFuture<'static, object_store::Result<Box<dyn MultipartUpload>>>),
+    Writing(WriteMultipart),
+    Finishing(BoxFuture<'static, object_store::Result<PutResult>>),
+    Done(PutResult),
+    Failed,
+}
+
+/// [`FileSink`](crate::FileSink) uploading a file part to an [`ObjectStore`] as it arrives.
+///
+/// Fields streamed into it can use the [`UploadedObject`] type, see
+/// [`MultipartConfig::set_object_store`].
+///
+/// The uploaded object is deleted if the form is rejected, such as when another field fails to
+/// validate, or if the client disconnects before the form was read. Uploads which weren't
+/// finished are aborted once the sink is dropped.
+pub struct ObjectStoreSink {
+    store: Arc<dyn ObjectStore>,
+    location: Path,
+    size: usize,
+    state: State,
+}
+
+impl ObjectStoreSink {
+    /// Create a sink starting a multipart upload to `location`.
+    pub fn new(store: Arc<dyn ObjectStore>, location: Path) -> Self {
+        let (path, upload_store) = (location.clone(), store.clone());
+
+        Self {
+            store,
+            location,
+            size: 0,
+            state: State::Starting(async move { upload_store.put_multipart(&path).await }.boxed()),
+        }
+    }
+
+    /// Location the file is uploaded to.
+    pub fn location(&self) -> &Path {
+        &self.location
+    }
+
+    /// Result of the upload, `None` until the sink was closed.
+    pub fn result(&self) -> Option<&PutResult> {
+        match &self.state {
+            State::Done(result) => Some(result),
+            _ => None,
+        }
+    }
+
+    fn poll_writing(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<&mut WriteMultipart>> {
+        if let State::Starting(start) = &mut self.state {
+            match start.poll_unpin(cx) {
+                Poll::Ready(Ok(upload)) => self.state = State::Writing(WriteMultipart::new(upload)),
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(self.fail(err))),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        match &mut self.state {
+            State::Writing(upload) => Poll::Ready(Ok(upload)),
+            _ => Poll::Ready(Err(closed())),
+        }
+    }
+
+    fn fail(&mut self, err: object_store::Error) -> io::Error {
+        self.state = State::Failed;
+        io::Error::other(err)
+    }
+}
+
+impl Sink<Bytes> for ObjectStoreSink {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let upload = match this.poll_writing(cx) {
+            Poll::Ready(Ok(upload)) => upload,
+            Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+            Poll::Pending => return Poll::Pending,
+        };
+
+        match upload.poll_for_capacity(cx, MAX_CONCURRENCY) {
+            Poll::Ready(Err(err)) => Poll::Ready(Err(this.fail(err))),
+            poll => poll.map(|_| Ok(())),
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Bytes) -> io::Result<()> {
+        let this = self.get_mut();
+        match &mut this.state {
+            State::Writing(upload) => {
+                this.size += item.len();
+                upload.put(item);
+                Ok(())
+            }
+            _ => Err(closed()),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        // Empty files are closed before anything was written.
+        if matches!(this.state, State::Starting(_)) {
+            match this.poll_writing(cx) {
+                Poll::Ready(Ok(_)) => {}
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        if let State::Writing(_) = this.state {
+            if let State::Writing(upload) = std::mem::replace(&mut this.state, State::Failed) {
+                this.state = State::Finishing(upload.finish().boxed());
+            }
+        }
+
+        match &mut this.state {
+            State::Finishing(finish) => match finish.poll_unpin(cx) {
+                Poll::Ready(Ok(result)) => {
+                    this.state = State::Done(result);
+                    Poll::Ready(Ok(()))
+                }
+                Poll::Ready(Err(err)) => Poll::Ready(Err(this.fail(err))),
+                Poll::Pending => Poll::Pending,
+            },
+            State::Done(_) => Poll::Ready(Ok(())),
+            _ => Poll::Ready(Err(closed())),
+        }
+    }
+}
+
+impl Drop for ObjectStoreSink {
+    fn drop(&mut self) {
+        match std::mem::replace(&mut self.state, State::Failed) {
+            State::Writing(upload) => spawn_cleanup(async move {
+                let _ = upload.abort().await;
+            }),
+            // The upload may have completed before the sink was dropped
+            State::Finishing(_) => delete_later(self.store.clone(), self.location.clone()),
+            _ => {}
+        }
+    }
+}
+
+/// Cleanup deleting the object a file was uploaded to, run if the file isn't taken by the form.
+pub(crate) fn orphan_cleanup(file: &StreamedFile) -> Option<impl FnOnce() + 'static> {
+    let sink = file.downcast_ref::<ObjectStoreSink>()?;
+    let (store, location) = (sink.store.clone(), sink.location.clone());
+    Some(move || delete_later(store, location))
+}
+
+/// Delete an object in the background.
+fn delete_later(store: Arc<dyn ObjectStore>, location: Path) {
+    spawn_cleanup(async move {
+        let _ = store.delete(&location).await;
+    })
+}
+
+/// Run a cleanup on the system arbiter, nothing is cleaned up outside of an actix system.
+fn spawn_cleanup(cleanup: impl Future<Output = ()> + Send + 'static) {
+    if let Some(system) = actix_web::rt::System::try_current() {
+        system.arbiter().spawn(cleanup);
+    }
+}
+
+fn closed() -> io::Error {
+    io::Error::other("object store upload failed")
+}
+
+/// A file uploaded to an [`ObjectStore`] while the request was read.
+///
+/// Use this as the type of fields streamed with [`MultipartConfig::set_object_store`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadedObject {
+    pub meta: FileMeta,
+    /// Location of the object in the store.
+    pub location: Path,
+    /// Amount of bytes uploaded.
+    pub size: usize,
+    /// Entity tag of the object, if the store returned one.
+    pub etag: Option<String>,
+}
+
+impl<'de> Deserialize<'de> for UploadedObject {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let file: StreamedFile = slot::deserialize(deserializer)?;
+        let meta = file.meta.clone();
+        let sink = file.downcast::<ObjectStoreSink>().map_err(|_| {
+            de::Error::custom("expected a file uploaded with MultipartConfig::set_object_store")
+        })?;
+
+        let result = sink
+            .result()
+            .ok_or_else(|| de::Error::custom("object store upload was not finished"))?;
+
+        // Deleted if another field of the form is rejected
+        let (store, location) = (sink.store.clone(), sink.location.clone());
+        slot::on_rejected(move || delete_later(store, location));
+
+        Ok(Self {
+            meta,
+            etag: result.e_tag.clone(),
+            size: sink.size,
+            location: sink.location.clone(),
+        })
+    }
+}
+
+impl MultipartConfig {
+    /// Upload file fields to `store` as they arrive.
+    ///
+    /// `location` is called with the name and metadata of every file field, returning a path
+    /// uploads the file there. Uploaded fields must use the [`UploadedObject`] type.
+    pub fn set_object_store<F>(self, store: Arc<dyn ObjectStore>, location: F) -> Self
+    where
+        F: Fn(&str, &FileMeta) -> Option<Path> + Send + Sync + 'static,
+    {
+        self.set_file_storage(move |field: &str, meta: &FileMeta| {
+            location(field, meta).map(|path| {
+                Box::new(ObjectStoreSink::new(store.clone(), path)) as Box<dyn crate::FileSink>
+            })
+        })
+    }
+}
@@ -0,0 +1,80 @@
+//! Paperclip OpenAPI v2 support for [`Multipart`], [`File`] and [`StreamedFile`].
+
+use paperclip::{
+    actix::OperationModifier,
+    v2::{
+        models::{
+            DataType, DataTypeFormat, DefaultOperationRaw, DefaultSchemaRaw, Either, Items,
+            MediaRange, Parameter, ParameterIn,
+        },
+        schema::{Apiv2Schema, TypedData},
+    },
+};
+use std::collections::BTreeMap;
+
+use crate::{File, Multipart, StreamedFile};
+
+/// Files are documented as binary files.
+macro_rules! impl_typed_data {
+    ($($ty:ty),*) => {$(
+        impl TypedData for $ty {
+            fn data_type() -> DataType {
+                DataType::File
+            }
+
+            fn format() -> Option<DataTypeFormat> {
+                Some(DataTypeFormat::Binary)
+            }
+        }
+    )*};
+}
+
+impl_typed_data!(File, StreamedFile);
+#[cfg(feature = "object-store")]
+impl_typed_data!(crate::UploadedObject);
+#[cfg(feature = "tempfile")]
+impl_typed_data!(crate::TempFile, crate::SpooledFile);
+
+impl<T: Apiv2Schema> Apiv2Schema for Multipart<T> {}
+
+/// Each field of the form is documented as a `formData` parameter.
+impl<T: Apiv2Schema> OperationModifier for Multipart<T> {
+    fn update_parameter(op: &mut DefaultOperationRaw) {
+        let def = T::raw_schema();
+
+        for (name, schema) in def.properties {
+            op.parameters.push(Either::Right(Parameter {
+                in_: ParameterIn::FormData,
+                required: def.required.contains(&name),
+                data_type: schema.data_type,
+                format: schema.format.clone(),
+                enum_: schema.enum_.clone(),
+                description: schema.description.clone(),
+                items: schema.items.as_deref().map(schema_to_items),
+                name,
+                ..Default::default()
+            }));
+        }
+
+        op.consumes
+            .get_or_insert_with(Default::default)
+            .insert(MediaRange(mime::MULTIPART_FORM_DATA));
+    }
+
+    // Fields are only used as parameters, there are no definitions to add.
+    fn update_definitions(_map: &mut BTreeMap<String, DefaultSchemaRaw>) {}
+}
+
+/// Convert the schema of a list field to its items.
+fn schema_to_items(schema: &DefaultSchemaRaw) -> Items {
+    Items {
+        data_type: schema.data_type,
+        format: schema.format.clone(),
+        enum_: schema.enum_.clone(),
+        items: schema
+            .items
+            .as_deref()
+            .map(|schema| Box::new(schema_to_items(schema))),
+        ..Default::default()
+    }
+}
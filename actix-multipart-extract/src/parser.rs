@@ -0,0 +1,334 @@
+//! Parsing of the multipart payload into parts, with `actix-multipart` or with `multer` when the
+//! `multer` feature is enabled.
+
+use actix_web::{
+    http::header::{HeaderMap, CONTENT_DISPOSITION},
+    web::Bytes,
+};
+use futures::Stream;
+use mime::Mime;
+use percent_encoding::percent_decode_str;
+use std::{
+    borrow::Cow,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{payload::TrackedPayload, MultipartError};
+
+/// Error reading a part.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PartError {
+    /// The stream ended early, usually because the client aborted the upload.
+    Incomplete,
+    /// The stream isn't valid multipart data.
+    Malformed,
+    /// A part is a nested multipart stream, actix-multipart can't parse it.
+    #[cfg_attr(feature = "multer", allow(dead_code))]
+    Nested,
+}
+
+impl PartError {
+    /// Error of a stream which failed between parts, the parser can't read the parts after it.
+    pub(crate) fn into_error(self) -> MultipartError {
+        match self {
+            Self::Incomplete => MultipartError::Incomplete,
+            Self::Malformed => MultipartError::Malformed,
+            Self::Nested => MultipartError::NestedUnsupported,
+        }
+    }
+}
+
+/// Parts of a multipart payload.
+pub(crate) struct Parser {
+    #[cfg(not(feature = "multer"))]
+    inner: actix_multipart::Multipart,
+    #[cfg(feature = "multer")]
+    inner: Result<multer::Multipart<'static>, Option<PartError>>,
+}
+
+/// Names of a part from its `Content-Disposition`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Disposition {
+    name: Option<String>,
+    filename: Option<String>,
+}
+
+impl Disposition {
+    /// Names of a part from the names parsed by the backend. Extended `name*` parameters and
+    /// percent-encoded names are decoded, so fields with non-ASCII names are matched.
+    fn new(name: Option<&str>, filename: Option<&str>, headers: &HeaderMap) -> Self {
+        Self {
+            name: extended_name(headers).or_else(|| name.map(decode_name)),
+            filename: filename.map(str::to_string),
+        }
+    }
+
+    pub(crate) fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// File name of the part as sent.
+    pub(crate) fn get_filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+}
+
+/// Decode a percent-encoded name, names which aren't UTF-8 once decoded are kept as sent.
+fn decode_name(name: &str) -> String {
+    percent_decode_str(name)
+        .decode_utf8()
+        .map_or_else(|_| name.to_string(), Cow::into_owned)
+}
+
+/// Get the RFC 5987 `name*` parameter of the `Content-Disposition` of a part, encoded in UTF-8
+/// or ISO-8859-1.
+fn extended_name(headers: &HeaderMap) -> Option<String> {
+    let disposition = headers.get(CONTENT_DISPOSITION)?.to_str().ok()?;
+    let value = disposition.split(';').find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("name*")
+            .then_some(value.trim())
+    })?;
+
+    let (charset, value) = value.split_once('\'')?;
+    let (_language, encoded) = value.split_once('\'')?;
+    let bytes = percent_decode_str(encoded).collect::<Vec<u8>>();
+
+    match charset.to_ascii_lowercase().as_str() {
+        "utf-8" => String::from_utf8(bytes).ok(),
+        "iso-8859-1" => Some(bytes.into_iter().map(char::from).collect()),
+        _ => None,
+    }
+}
+
+/// A single part of a multipart payload, streaming its body.
+pub(crate) struct Part {
+    disposition: Disposition,
+    content_type: Mime,
+    headers: HeaderMap,
+    #[cfg(not(feature = "multer"))]
+    inner: actix_multipart::Field,
+    #[cfg(feature = "multer")]
+    inner: multer::Field<'static>,
+}
+
+impl Part {
+    pub(crate) fn disposition(&self) -> &Disposition {
+        &self.disposition
+    }
+
+    /// Content type of the part, `application/octet-stream` if it has none.
+    pub(crate) fn content_type(&self) -> &Mime {
+        &self.content_type
+    }
+
+    pub(crate) fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Size of the headers of the part as sent, with a `: ` and a line break per header.
+    pub(crate) fn header_size(&self) -> usize {
+        self.headers
+            .iter()
+            .map(|(name, value)| name.as_str().len() + value.len() + 4)
+            .sum()
+    }
+}
+
+#[cfg(not(feature = "multer"))]
+mod backend {
+    use super::*;
+
+    impl Parser {
+        pub(crate) fn new(headers: &HeaderMap, payload: TrackedPayload) -> Self {
+            Self {
+                inner: actix_multipart::Multipart::new(headers, payload),
+            }
+        }
+    }
+
+    impl Part {
+        /// actix-multipart rejects nested parts before yielding them, there is nothing to parse.
+        pub(crate) fn into_nested(self) -> Result<Parser, MultipartError> {
+            Err(MultipartError::NestedUnsupported)
+        }
+    }
+
+    impl Stream for Parser {
+        type Item = Result<Part, PartError>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            Pin::new(&mut self.inner).poll_next(cx).map(|item| {
+                item.map(|field| {
+                    let field = field.map_err(map_error)?;
+                    let disposition = field.content_disposition();
+
+                    Ok(Part {
+                        disposition: Disposition::new(
+                            disposition.get_name(),
+                            disposition.get_filename(),
+                            field.headers(),
+                        ),
+                        content_type: field.content_type().clone(),
+                        headers: field.headers().clone(),
+                        inner: field,
+                    })
+                })
+            })
+        }
+    }
+
+    impl Stream for Part {
+        type Item = Result<Bytes, PartError>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            Pin::new(&mut self.inner)
+                .poll_next(cx)
+                .map(|item| item.map(|chunk| chunk.map_err(map_error)))
+        }
+    }
+
+    fn map_error(err: actix_multipart::MultipartError) -> PartError {
+        match err {
+            actix_multipart::MultipartError::Incomplete
+            | actix_multipart::MultipartError::Payload(
+                actix_web::error::PayloadError::Incomplete(_),
+            ) => PartError::Incomplete,
+            actix_multipart::MultipartError::Nested => PartError::Nested,
+            _ => PartError::Malformed,
+        }
+    }
+}
+
+#[cfg(feature = "multer")]
+mod backend {
+    use actix_web::{
+        error::PayloadError,
+        http::header::{HeaderName, HeaderValue, CONTENT_TYPE},
+    };
+    use futures::{channel::mpsc, SinkExt, StreamExt};
+
+    use super::*;
+
+    impl Parser {
+        pub(crate) fn new(headers: &HeaderMap, mut payload: TrackedPayload) -> Self {
+            let boundary = headers
+                .get(CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<Mime>().ok())
+                .and_then(|mime| Some(mime.get_param(mime::BOUNDARY)?.to_string()));
+
+            let boundary = match boundary {
+                Some(boundary) => boundary,
+                None => {
+                    return Self {
+                        inner: Err(Some(PartError::Malformed)),
+                    }
+                }
+            };
+
+            // multer requires a `Send` stream, the payload is forwarded to it by a local task
+            let (mut sender, receiver) = mpsc::channel::<Result<Bytes, PayloadError>>(1);
+            actix_web::rt::spawn(async move {
+                while let Some(chunk) = payload.next().await {
+                    if sender.send(chunk).await.is_err() {
+                        break;
+                    }
+                }
+            });
+
+            Self {
+                inner: Ok(multer::Multipart::new(receiver, boundary)),
+            }
+        }
+    }
+
+    impl Part {
+        /// Parse the body of a `multipart/mixed` part as its own multipart stream.
+        pub(crate) fn into_nested(self) -> Result<Parser, MultipartError> {
+            let boundary = match self.content_type.get_param(mime::BOUNDARY) {
+                Some(boundary) => boundary.to_string(),
+                None => return Err(MultipartError::Malformed),
+            };
+
+            Ok(Parser {
+                inner: Ok(multer::Multipart::new(self.inner, boundary)),
+            })
+        }
+    }
+
+    impl Stream for Parser {
+        type Item = Result<Part, PartError>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            // A payload without a boundary yields its error once
+            let multipart = match &mut self.inner {
+                Ok(multipart) => multipart,
+                Err(err) => return Poll::Ready(err.take().map(Err)),
+            };
+
+            multipart.poll_next_field(cx).map(|item| match item {
+                Ok(Some(field)) => {
+                    let headers = convert_headers(field.headers());
+
+                    Some(Ok(Part {
+                        disposition: Disposition::new(field.name(), field.file_name(), &headers),
+                        content_type: field
+                            .content_type()
+                            .cloned()
+                            .unwrap_or(mime::APPLICATION_OCTET_STREAM),
+                        headers,
+                        inner: field,
+                    }))
+                }
+                Ok(None) => None,
+                Err(err) => Some(Err(map_error(err))),
+            })
+        }
+    }
+
+    impl Stream for Part {
+        type Item = Result<Bytes, PartError>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            self.inner
+                .poll_next_unpin(cx)
+                .map(|item| item.map(|chunk| chunk.map_err(map_error)))
+        }
+    }
+
+    /// multer uses the `http` crate headers, which are a different type from actix headers.
+    fn convert_headers(headers: &http::HeaderMap) -> HeaderMap {
+        let mut converted = HeaderMap::new();
+
+        for (name, value) in headers {
+            let name = HeaderName::from_bytes(name.as_str().as_bytes());
+            let value = HeaderValue::from_bytes(value.as_bytes());
+
+            if let (Ok(name), Ok(value)) = (name, value) {
+                converted.append(name, value);
+            }
+        }
+
+        converted
+    }
+
+    fn map_error(err: multer::Error) -> PartError {
+        match err {
+            multer::Error::IncompleteStream
+            | multer::Error::IncompleteFieldData { .. }
+            | multer::Error::IncompleteHeaders => PartError::Incomplete,
+            multer::Error::StreamReadFailed(err)
+                if matches!(
+                    err.downcast_ref::<PayloadError>(),
+                    Some(PayloadError::Incomplete(_))
+                ) =>
+            {
+                PartError::Incomplete
+            }
+            _ => PartError::Malformed,
+        }
+    }
+}
@@ -0,0 +1,342 @@
+use actix_web::{
+    dev::Payload,
+    error::PayloadError,
+    http::header::CONTENT_LENGTH,
+    rt::time::{sleep, Instant, Sleep},
+    web::Bytes,
+    HttpRequest,
+};
+use futures::{Future, Stream, StreamExt};
+use std::{
+    cell::Cell,
+    io,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+#[cfg(feature = "resumable")]
+use actix_web::http::header::HeaderMap;
+
+use crate::{
+    parser::{Parser, Part, PartError},
+    Limits, MultipartConfig, MultipartError,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Reading,
+    Ended,
+    TimedOut,
+    TooLarge,
+}
+
+/// Request payload remembering when it ended, failing when no chunk arrives within the read
+/// timeout or when it is larger than its limit and throttling reads to the max bandwidth.
+///
+/// Chunks are split to the read chunk size, and reads yield back to the parser once the read
+/// ahead limit was read without the payload running dry.
+pub(crate) struct TrackedPayload {
+    payload: Payload,
+    shared: Rc<Shared>,
+    timeout: Option<(Duration, Pin<Box<Sleep>>)>,
+    throttle: Option<Throttle>,
+    /// Whether the last read of the payload was pending, the read timeout only runs while
+    /// waiting for the client.
+    waiting: bool,
+    chunk_size: Option<usize>,
+    /// Rest of a chunk larger than the chunk size.
+    pending: Bytes,
+    read_ahead: Option<usize>,
+    /// Bytes returned since the parser last had to wait.
+    read: usize,
+}
+
+/// Delays reads to keep the average rate under a max amount of bytes per second.
+struct Throttle {
+    bytes_per_second: usize,
+    started: Instant,
+    bytes: usize,
+    delay: Option<Pin<Box<Sleep>>>,
+}
+
+impl Throttle {
+    /// Count a chunk, delaying the next read if the rate went over the limit.
+    fn record(&mut self, len: usize) {
+        self.bytes += len;
+
+        let expected = Duration::from_secs_f64(self.bytes as f64 / self.bytes_per_second as f64);
+        let elapsed = self.started.elapsed();
+
+        if expected > elapsed {
+            self.delay = Some(Box::pin(sleep(expected - elapsed)));
+        }
+    }
+}
+
+/// State shared between a [`TrackedPayload`] and its [`PayloadStatus`].
+struct Shared {
+    state: Cell<State>,
+    bytes_read: Cell<usize>,
+    files: Cell<usize>,
+    fields: Cell<usize>,
+    content_length: Option<usize>,
+    limits: Limits,
+    max_part_header_size: Option<usize>,
+    #[cfg(feature = "resumable")]
+    headers: HeaderMap,
+}
+
+impl TrackedPayload {
+    pub(crate) fn new(
+        req: &HttpRequest,
+        payload: Payload,
+        config: &MultipartConfig,
+    ) -> (Self, PayloadStatus) {
+        let headers = req.headers();
+        let content_length = headers
+            .get(CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok());
+
+        let limits = config.limits(req);
+
+        // Requests known to be too large aren't read at all
+        let state = match (content_length, limits.total) {
+            (Some(len), Some(total)) if len > total => State::TooLarge,
+            _ => State::Reading,
+        };
+
+        let shared = Rc::new(Shared {
+            state: Cell::new(state),
+            bytes_read: Cell::new(0),
+            files: Cell::new(0),
+            fields: Cell::new(0),
+            content_length,
+            limits,
+            max_part_header_size: config.max_part_header_size,
+            #[cfg(feature = "resumable")]
+            headers: headers.clone(),
+        });
+
+        (
+            Self {
+                payload,
+                shared: shared.clone(),
+                timeout: config
+                    .read_timeout
+                    .map(|timeout| (timeout, Box::pin(sleep(timeout)))),
+                waiting: false,
+                chunk_size: config.read_chunk_size.filter(|size| *size > 0),
+                pending: Bytes::new(),
+                read_ahead: config.max_read_ahead.filter(|size| *size > 0),
+                read: 0,
+                throttle: config
+                    .max_bytes_per_second
+                    .filter(|bytes_per_second| *bytes_per_second > 0)
+                    .map(|bytes_per_second| Throttle {
+                        bytes_per_second,
+                        started: Instant::now(),
+                        bytes: 0,
+                        delay: None,
+                    }),
+            },
+            PayloadStatus(shared),
+        )
+    }
+}
+
+impl Stream for TrackedPayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.shared.state.get() != State::Reading {
+            return Poll::Ready(None);
+        }
+
+        // The parser buffers every chunk it can read, let it process what it has first
+        if matches!(self.read_ahead, Some(read_ahead) if self.read >= read_ahead) {
+            self.read = 0;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+
+        if !self.pending.is_empty() {
+            let pending = std::mem::take(&mut self.pending);
+            let chunk = self.split_chunk(pending);
+            return Poll::Ready(Some(Ok(chunk)));
+        }
+
+        if let Some(throttle) = &mut self.throttle {
+            if let Some(delay) = &mut throttle.delay {
+                if delay.as_mut().poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+
+                throttle.delay = None;
+            }
+        }
+
+        // Time spent throttling or by the consumer handling the last chunk doesn't count towards
+        // the read timeout, it starts once more bytes are asked for.
+        if !self.waiting {
+            if let Some((timeout, sleep)) = &mut self.timeout {
+                sleep.as_mut().reset(Instant::now() + *timeout);
+            }
+        }
+
+        let chunk = self.payload.poll_next_unpin(cx);
+        self.waiting = chunk.is_pending();
+
+        match chunk {
+            Poll::Ready(None) => {
+                self.shared.state.set(State::Ended);
+                Poll::Ready(None)
+            }
+            Poll::Ready(Some(chunk)) => {
+                if let Ok(chunk) = &chunk {
+                    let bytes_read = self.shared.bytes_read.get() + chunk.len();
+                    self.shared.bytes_read.set(bytes_read);
+
+                    if matches!(self.shared.limits.total, Some(total) if bytes_read > total) {
+                        self.shared.state.set(State::TooLarge);
+                        return Poll::Ready(Some(Err(PayloadError::Overflow)));
+                    }
+
+                    if let Some(throttle) = &mut self.throttle {
+                        throttle.record(chunk.len());
+                    }
+                }
+
+                Poll::Ready(Some(chunk.map(|chunk| self.split_chunk(chunk))))
+            }
+            Poll::Pending => {
+                self.read = 0;
+
+                let timed_out = match &mut self.timeout {
+                    Some((_, sleep)) => sleep.as_mut().poll(cx).is_ready(),
+                    None => false,
+                };
+
+                if timed_out {
+                    self.shared.state.set(State::TimedOut);
+                    Poll::Ready(Some(Err(PayloadError::Io(io::ErrorKind::TimedOut.into()))))
+                } else {
+                    Poll::Pending
+                }
+            }
+        }
+    }
+}
+
+impl TrackedPayload {
+    /// Take the part of a chunk returned next, keeping the rest for the next read.
+    fn split_chunk(&mut self, mut chunk: Bytes) -> Bytes {
+        if let Some(chunk_size) = self.chunk_size {
+            if chunk.len() > chunk_size {
+                self.pending = chunk.split_off(chunk_size);
+            }
+        }
+
+        self.read += chunk.len();
+        chunk
+    }
+}
+
+/// Status of the payload of a [`TrackedPayload`].
+#[derive(Clone)]
+pub(crate) struct PayloadStatus(Rc<Shared>);
+
+impl PayloadStatus {
+    /// Amount of bytes read from the payload.
+    pub(crate) fn bytes_read(&self) -> usize {
+        self.0.bytes_read.get()
+    }
+
+    /// Length of the payload sent by the client, if known.
+    pub(crate) fn content_length(&self) -> Option<usize> {
+        self.0.content_length
+    }
+
+    /// Headers of the request sending the payload.
+    #[cfg(feature = "resumable")]
+    pub(crate) fn headers(&self) -> &HeaderMap {
+        &self.0.headers
+    }
+
+    /// Limits of the request sending the payload.
+    pub(crate) fn limits(&self) -> &Limits {
+        &self.0.limits
+    }
+
+    /// Count a file part, failing if the request has too many files.
+    pub(crate) fn add_file(&self) -> Result<(), MultipartError> {
+        self.0.limits.check_files(self.0.files.get())?;
+        self.0.files.set(self.0.files.get() + 1);
+        Ok(())
+    }
+
+    /// Amount of file parts read so far.
+    pub(crate) fn files(&self) -> usize {
+        self.0.files.get()
+    }
+
+    /// Amount of parts read so far, files included.
+    pub(crate) fn fields(&self) -> usize {
+        self.0.fields.get()
+    }
+
+    /// Get the next item of a stream reading the payload.
+    ///
+    /// The multipart parser waits for more data forever when the payload ends in the middle of
+    /// a boundary, a stream which can't make progress after the payload ended is incomplete.
+    pub(crate) async fn next<S: Stream + Unpin>(
+        &self,
+        stream: &mut S,
+    ) -> Result<Option<S::Item>, MultipartError> {
+        futures::future::poll_fn(|cx| self.poll_next(stream, cx)).await
+    }
+
+    /// Get the next part of a parser, failing if its headers are larger than
+    /// [`MultipartConfig::max_part_header_size`] or if the request has too many parts before
+    /// its body is read.
+    pub(crate) async fn next_part(
+        &self,
+        parser: &mut Parser,
+    ) -> Result<Option<Result<Part, PartError>>, MultipartError> {
+        let item = self.next(parser).await?;
+
+        if let Some(Ok(part)) = &item {
+            if let Some(limit) = self.0.max_part_header_size {
+                if part.header_size() > limit {
+                    return Err(MultipartError::PartHeaderSizeError { limit });
+                }
+            }
+
+            self.0.limits.check_fields(self.0.fields.get())?;
+            self.0.fields.set(self.0.fields.get() + 1);
+        }
+
+        Ok(item)
+    }
+
+    /// Poll the next item of a stream reading the payload, see [`PayloadStatus::next`].
+    pub(crate) fn poll_next<S: Stream + Unpin>(
+        &self,
+        stream: &mut S,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<S::Item>, MultipartError>> {
+        let item = stream.poll_next_unpin(cx);
+
+        match (self.0.state.get(), item) {
+            (State::TimedOut, _) => Poll::Ready(Err(MultipartError::Timeout)),
+            (State::TooLarge, _) => Poll::Ready(Err(MultipartError::RequestSizeError {
+                limit: self.0.limits.total.unwrap_or_default(),
+            })),
+            (State::Ended, Poll::Pending) => Poll::Ready(Err(MultipartError::Incomplete)),
+            (_, Poll::Pending) => Poll::Pending,
+            (_, Poll::Ready(item)) => Poll::Ready(Ok(item)),
+        }
+    }
+}
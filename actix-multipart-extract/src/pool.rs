@@ -0,0 +1,49 @@
+//! Buffers of text parts are reused by later parts read on the same worker.
+
+use std::cell::RefCell;
+
+/// Max amount of buffers kept per thread.
+const MAX_POOLED: usize = 16;
+
+/// Buffers with a larger capacity are freed instead of being kept.
+const MAX_POOLED_CAPACITY: usize = 64 * 1024;
+
+/// Max capacity preallocated for a part, whatever its limit and the length of the request.
+pub(crate) const MAX_CAPACITY_HINT: usize = MAX_POOLED_CAPACITY;
+
+thread_local! {
+    static POOL: RefCell<Vec<Vec<u8>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Whether a buffer with `capacity` bytes of capacity is kept once given back.
+pub(crate) fn keeps(capacity: usize) -> bool {
+    capacity > 0 && capacity <= MAX_POOLED_CAPACITY
+}
+
+/// Take an empty buffer with at least `capacity` bytes of capacity.
+pub(crate) fn take(capacity: usize) -> Vec<u8> {
+    let buffer = POOL.with(|pool| {
+        let mut pool = pool.borrow_mut();
+        let index = pool
+            .iter()
+            .position(|buffer| buffer.capacity() >= capacity)?;
+        Some(pool.swap_remove(index))
+    });
+
+    buffer.unwrap_or_else(|| Vec::with_capacity(capacity))
+}
+
+/// Return a buffer which is no longer used.
+pub(crate) fn give(mut buffer: Vec<u8>) {
+    if !keeps(buffer.capacity()) {
+        return;
+    }
+
+    buffer.clear();
+    POOL.with(|pool| {
+        let mut pool = pool.borrow_mut();
+        if pool.len() < MAX_POOLED {
+            pool.push(buffer);
+        }
+    });
+}
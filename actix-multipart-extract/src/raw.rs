@@ -0,0 +1,163 @@
+use actix_web::{dev::Payload, http::header::HeaderMap, web::Bytes, FromRequest, HttpRequest};
+use futures::Future;
+use std::{
+    ops::{Deref, DerefMut},
+    pin::Pin,
+    time::Instant,
+};
+
+#[cfg(feature = "tracing")]
+use crate::extractor::extract_span;
+use crate::{
+    extractor::{check_content_type, handle_error, read_field, record_stats, ReadOptions},
+    memory::Reservation,
+    parser::{Parser, Part},
+    payload::{PayloadStatus, TrackedPayload},
+    MultipartConfig, MultipartError,
+};
+
+/// A part of a [`RawMultipart`] request.
+#[derive(Debug, Clone)]
+pub struct RawPart {
+    /// Name of the part from its `Content-Disposition`.
+    pub name: Option<String>,
+    /// File name of the part after applying the [`FilenamePolicy`](crate::FilenamePolicy).
+    pub filename: Option<String>,
+    pub headers: HeaderMap,
+    /// Body of the part with any transfer or content encoding undone.
+    pub bytes: Bytes,
+}
+
+/// Extractor for the parts of a multipart request in the order they were sent, without a form.
+///
+/// Uses the same [`MultipartConfig`] as [`Multipart`](crate::Multipart), errors are passed to
+/// its error handler.
+#[derive(Debug, Clone)]
+pub struct RawMultipart(Vec<RawPart>);
+
+impl RawMultipart {
+    pub fn into_inner(self) -> Vec<RawPart> {
+        self.0
+    }
+}
+
+impl Deref for RawMultipart {
+    type Target = Vec<RawPart>;
+
+    fn deref(&self) -> &Vec<RawPart> {
+        &self.0
+    }
+}
+
+impl DerefMut for RawMultipart {
+    fn deref_mut(&mut self) -> &mut Vec<RawPart> {
+        &mut self.0
+    }
+}
+
+impl FromRequest for RawMultipart {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let payload = payload.take();
+        let req_owned = req.to_owned();
+
+        let extract = async move {
+            let config = MultipartConfig::from_req(&req_owned);
+
+            match extract(&req_owned, payload, config).await {
+                Ok(parts) => Ok(RawMultipart(parts)),
+                Err(err) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(error = %err, "multipart extraction failed");
+                    #[cfg(feature = "logging")]
+                    crate::extractor::log_error(&err);
+                    if let Some(metrics) = &config.metrics {
+                        metrics.on_rejected(&err);
+                    }
+                    Err(handle_error(err, &req_owned, config))
+                }
+            }
+        };
+
+        #[cfg(feature = "tracing")]
+        let extract = tracing::Instrument::instrument(extract, extract_span::<Self>());
+
+        Box::pin(extract)
+    }
+}
+
+async fn extract(
+    req: &HttpRequest,
+    payload: Payload,
+    config: &MultipartConfig,
+) -> Result<Vec<RawPart>, MultipartError> {
+    check_content_type(req)?;
+
+    let started = Instant::now();
+    let (payload, status) = TrackedPayload::new(req, payload, config);
+    let mut multipart = Parser::new(req.headers(), payload);
+    let mut memory = Reservation::new(config.shared_memory_limit.as_ref());
+    let mut parts = Vec::new();
+
+    loop {
+        let mut field = match status.next_part(&mut multipart).await? {
+            Some(Ok(field)) => field,
+            None => break,
+            Some(Err(err)) => return Err(err.into_error()),
+        };
+
+        parts.push(read_raw_part(&mut field, &status, &mut memory, config).await?);
+    }
+
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("bytes_read", &status.bytes_read());
+
+    record_stats(req, &status, started, config);
+
+    Ok(parts)
+}
+
+/// Read a part as a [`RawPart`], parts with a file name are limited like files.
+pub(crate) async fn read_raw_part(
+    field: &mut Part,
+    status: &PayloadStatus,
+    memory: &mut Reservation,
+    config: &MultipartConfig,
+) -> Result<RawPart, MultipartError> {
+    let disposition = field.disposition().clone();
+    let name = disposition.get_name().unwrap_or_default();
+
+    let filename = match disposition.get_filename() {
+        Some(filename) => Some(
+            config
+                .filename_policy
+                .apply(filename)
+                .ok_or_else(|| MultipartError::InvalidFilename {
+                    field: name.to_string(),
+                    filename: filename.to_string(),
+                })?
+                .into_owned(),
+        ),
+        None => None,
+    };
+
+    // Limits of files apply to parts with a file name
+    let mut options = ReadOptions::default();
+    if filename.is_some() {
+        status.add_file()?;
+        options.max_size = status.limits().per_file;
+    }
+
+    let bytes = read_field(field, name, &options, None, status, memory, config)
+        .await?
+        .bytes;
+
+    Ok(RawPart {
+        name: disposition.get_name().map(str::to_string),
+        filename,
+        headers: field.headers().clone(),
+        bytes: Bytes::from(bytes),
+    })
+}
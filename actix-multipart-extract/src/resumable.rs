@@ -0,0 +1,367 @@
+//! Files uploaded in chunks over several requests, each request sending a `Content-Range` of
+//! the file.
+
+use actix_web::{
+    http::header::{HeaderMap, CONTENT_RANGE},
+    web::Bytes,
+};
+use futures::SinkExt;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "hash")]
+use crate::hash::Hasher;
+use crate::{
+    extractor::{read_field, FieldBody, ReadOptions},
+    memory::Reservation,
+    parser::Part,
+    payload::PayloadStatus,
+    FileSink, MemoryBudget, MultipartConfig, MultipartError,
+};
+
+/// Request header identifying the upload a chunk belongs to, chosen by the client.
+///
+/// Any request sending the ID can add chunks to the upload. IDs must be unguessable, like random
+/// UUIDs handed out by the server, or be checked against the caller before the form is
+/// extracted.
+pub const UPLOAD_ID_HEADER: &str = "upload-id";
+
+/// Response header with the amount of bytes of an upload which were received.
+pub const UPLOAD_OFFSET_HEADER: &str = "upload-offset";
+
+/// Default for [`ResumableUploads::set_max_uploads`].
+const DEFAULT_MAX_UPLOADS: usize = 1000;
+
+/// Default for [`ResumableUploads::set_max_bytes`].
+const DEFAULT_MAX_BYTES: usize = 100 * 1024 * 1024;
+
+/// A file being uploaded, its bytes are kept until every chunk was received.
+struct Upload {
+    total: usize,
+    bytes: Vec<u8>,
+    updated: Instant,
+    /// Bytes of the upload reserved from the memory budget of the request which started it.
+    memory: Reservation,
+}
+
+impl std::fmt::Debug for Upload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Upload")
+            .field("total", &self.total)
+            .field("received", &self.bytes.len())
+            .finish()
+    }
+}
+
+type Uploads = HashMap<(String, String), Upload>;
+
+/// Chunks of uploads which weren't completed yet, set with
+/// [`MultipartConfig::set_resumable_uploads`].
+///
+/// Clones share the same uploads, create it once outside of `HttpServer::new` so chunks can be
+/// received by any worker. Uploads which didn't receive a chunk within `max_age` are dropped.
+///
+/// Chunks are kept in memory and counted in the shared memory limit of the config. At most 1000
+/// uploads with 100MB of chunks together are kept by default.
+///
+/// Requests which don't complete their upload respond with
+/// [`MultipartError::UploadIncomplete`], which always gets the default response. It skips the
+/// error handlers, metrics and logs of the config.
+#[derive(Debug, Clone)]
+pub struct ResumableUploads {
+    max_age: Duration,
+    max_uploads: usize,
+    max_bytes: usize,
+    uploads: Arc<Mutex<Uploads>>,
+}
+
+impl ResumableUploads {
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            max_uploads: DEFAULT_MAX_UPLOADS,
+            max_bytes: DEFAULT_MAX_BYTES,
+            uploads: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Max amount of uploads which weren't completed yet, starting another one fails with
+    /// [`MultipartError::TooManyUploads`].
+    pub fn set_max_uploads(mut self, max_uploads: usize) -> Self {
+        self.max_uploads = max_uploads;
+        self
+    }
+
+    /// Max bytes of all uploads which weren't completed yet, chunks which don't fit fail with
+    /// [`MultipartError::MemoryLimitError`]. Larger files are rejected from their first chunk.
+    pub fn set_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Amount of uploads which weren't completed yet.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Uploads> {
+        let mut uploads = self.uploads.lock().unwrap_or_else(|err| err.into_inner());
+        uploads.retain(|_, upload| upload.updated.elapsed() <= self.max_age);
+        uploads
+    }
+
+    /// Add a chunk to an upload, returning the upload once it is complete.
+    ///
+    /// Uploads started by the chunk reserve their bytes from `budget`.
+    fn write(
+        &self,
+        key: (String, String),
+        range: Range,
+        chunk: Vec<u8>,
+        budget: Option<&MemoryBudget>,
+    ) -> Result<Upload, MultipartError> {
+        let field = key.1.clone();
+        let mut uploads = self.lock();
+
+        let (start, total) = match range {
+            Range::Status { total } => {
+                return Err(MultipartError::UploadIncomplete {
+                    field,
+                    received: uploads.get(&key).map_or(0, |upload| upload.bytes.len()),
+                    total,
+                })
+            }
+            Range::Bytes { start, end, total } => {
+                if chunk.len() != end - start + 1 {
+                    return Err(MultipartError::InvalidUpload {
+                        field,
+                        message: format!(
+                            "chunk has {} bytes but its Content-Range has {}",
+                            chunk.len(),
+                            end - start + 1
+                        ),
+                    });
+                }
+
+                (start, total)
+            }
+        };
+
+        let stored: usize = uploads.values().map(|upload| upload.bytes.len()).sum();
+        if stored + chunk.len() > self.max_bytes {
+            return Err(MultipartError::MemoryLimitError {
+                limit: self.max_bytes,
+            });
+        }
+
+        let count = uploads.len();
+        let memory_error = |limit| MultipartError::MemoryLimitError { limit };
+        let upload = match uploads.entry(key.clone()) {
+            Entry::Occupied(entry) => {
+                let upload = entry.into_mut();
+                if upload.total != total {
+                    return Err(MultipartError::InvalidUpload {
+                        field,
+                        message: format!("file has {} bytes, not {}", upload.total, total),
+                    });
+                }
+
+                if start != upload.bytes.len() {
+                    return Err(MultipartError::UploadOffsetMismatch {
+                        field,
+                        expected: upload.bytes.len(),
+                    });
+                }
+
+                upload.memory.grow(chunk.len()).map_err(memory_error)?;
+                upload
+            }
+            // Uploads are only kept once their first chunk was accepted
+            Entry::Vacant(entry) => {
+                if start != 0 {
+                    return Err(MultipartError::UploadOffsetMismatch { field, expected: 0 });
+                }
+
+                if count >= self.max_uploads {
+                    return Err(MultipartError::TooManyUploads {
+                        limit: self.max_uploads,
+                    });
+                }
+
+                let mut memory = Reservation::new(budget);
+                memory.grow(chunk.len()).map_err(memory_error)?;
+                entry.insert(Upload {
+                    total,
+                    bytes: Vec::new(),
+                    updated: Instant::now(),
+                    memory,
+                })
+            }
+        };
+
+        upload.bytes.extend_from_slice(&chunk);
+        upload.updated = Instant::now();
+
+        if upload.bytes.len() < total {
+            return Err(MultipartError::UploadIncomplete {
+                field,
+                received: upload.bytes.len(),
+                total,
+            });
+        }
+
+        Ok(uploads.remove(&key).expect("upload was just written"))
+    }
+}
+
+/// Range of a file sent by a request.
+#[derive(Debug, Clone, Copy)]
+enum Range {
+    /// `bytes */total`, asking how many bytes were received.
+    Status { total: usize },
+    /// `bytes start-end/total`, the end is inclusive.
+    Bytes {
+        start: usize,
+        end: usize,
+        total: usize,
+    },
+}
+
+impl Range {
+    fn parse(value: &str) -> Option<Self> {
+        let (range, total) = value.trim().strip_prefix("bytes ")?.split_once('/')?;
+        let total = total.trim().parse().ok().filter(|total| *total > 0)?;
+
+        if range.trim() == "*" {
+            return Some(Self::Status { total });
+        }
+
+        let (start, end) = range.split_once('-')?;
+        let (start, end) = (start.trim().parse().ok()?, end.trim().parse().ok()?);
+
+        (start <= end && end < total).then_some(Self::Bytes { start, end, total })
+    }
+}
+
+/// Read a file part of a request sending a chunk, returning its body once every chunk was
+/// received and `None` if the request doesn't send a chunk.
+///
+/// Requests which don't complete the file fail with [`MultipartError::UploadIncomplete`].
+pub(crate) async fn read_chunk(
+    field: &mut Part,
+    field_name: &str,
+    options: &ReadOptions<'_>,
+    payload: &PayloadStatus,
+    memory: &mut Reservation,
+    config: &MultipartConfig,
+) -> Result<Option<FieldBody>, MultipartError> {
+    let uploads = match &config.resumable_uploads {
+        Some(uploads) => uploads,
+        None => return Ok(None),
+    };
+
+    let headers: &HeaderMap = payload.headers();
+    let range = match headers.get(CONTENT_RANGE) {
+        Some(range) => range,
+        None => return Ok(None),
+    };
+
+    let invalid = |message: &str| MultipartError::InvalidUpload {
+        field: field_name.to_string(),
+        message: message.to_string(),
+    };
+
+    let range = range
+        .to_str()
+        .ok()
+        .and_then(Range::parse)
+        .ok_or_else(|| invalid("Content-Range is invalid"))?;
+
+    let id = headers
+        .get(UPLOAD_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .filter(|id| !id.is_empty())
+        .ok_or_else(|| invalid("the Upload-Id header is missing"))?;
+
+    let total = match range {
+        Range::Status { total } | Range::Bytes { total, .. } => total,
+    };
+
+    let limit = options.max_size.map_or(uploads.max_bytes, |max_size| {
+        max_size.min(uploads.max_bytes)
+    });
+    if total > limit {
+        return Err(MultipartError::FileSizeError {
+            field: field_name.to_string(),
+            limit,
+        });
+    }
+
+    // The chunk is hashed once the file is complete
+    let chunk = read_field(
+        field,
+        field_name,
+        &ReadOptions {
+            max_size: options.max_size,
+            deadline: options.deadline,
+            #[cfg(feature = "hash")]
+            hash_algorithm: None,
+            check_start: None,
+        },
+        None,
+        payload,
+        memory,
+        config,
+    )
+    .await?;
+
+    let upload = uploads.write(
+        (id.to_string(), field_name.to_string()),
+        range,
+        chunk.bytes,
+        config.shared_memory_limit.as_ref(),
+    )?;
+
+    // The file is buffered by this request now
+    memory.absorb(upload.memory);
+    let bytes = upload.bytes;
+
+    #[cfg(feature = "hash")]
+    let hash = options.hash_algorithm.map(|algorithm| {
+        let mut hasher = Hasher::new(algorithm);
+        hasher.update(&bytes);
+        hasher.finish()
+    });
+
+    Ok(Some(FieldBody {
+        size: bytes.len(),
+        bytes,
+        #[cfg(feature = "hash")]
+        hash,
+    }))
+}
+
+/// Write the bytes of a completed upload to a sink and close it.
+pub(crate) async fn write_sink(
+    sink: &mut Box<dyn FileSink>,
+    field_name: &str,
+    bytes: Vec<u8>,
+) -> Result<(), MultipartError> {
+    let storage_error = |error| MultipartError::StorageError {
+        field: field_name.to_string(),
+        error,
+    };
+
+    if !bytes.is_empty() {
+        sink.send(Bytes::from(bytes)).await.map_err(storage_error)?;
+    }
+
+    sink.close().await.map_err(storage_error)
+}
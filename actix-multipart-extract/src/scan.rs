@@ -0,0 +1,149 @@
+//! Files checked by a [`ContentScanner`] before the handler runs.
+
+use actix_web::web::Bytes;
+use futures::{
+    channel::mpsc,
+    future::LocalBoxFuture,
+    stream::{self, LocalBoxStream},
+    Sink, StreamExt,
+};
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{FileMeta, FileSink, MultipartError};
+
+/// Size of the chunks buffered files are given to the scanner in.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Checks the bytes of every file part, set with
+/// [`MultipartConfig::set_content_scanner`](crate::MultipartConfig::set_content_scanner).
+///
+/// Files written to a [`FileStorage`](crate::FileStorage) are scanned while they stream,
+/// buffered files once they were read. Rejected files fail the request with
+/// [`MultipartError::Rejected`].
+pub trait ContentScanner: Send + Sync + 'static {
+    /// Scan the bytes of a file, `Err` with the reason to reject it.
+    ///
+    /// The stream ends once the whole file was read, it can be dropped early.
+    fn scan<'a>(
+        &'a self,
+        field: &'a str,
+        meta: &'a FileMeta,
+        bytes: LocalBoxStream<'static, Bytes>,
+    ) -> LocalBoxFuture<'a, Result<(), String>>;
+}
+
+fn rejected(field_name: &str, reason: String) -> MultipartError {
+    MultipartError::Rejected {
+        field: field_name.to_string(),
+        reason,
+    }
+}
+
+/// Scan the bytes of a buffered file, returning them if it wasn't rejected.
+pub(crate) async fn scan_bytes(
+    scanner: &dyn ContentScanner,
+    field_name: &str,
+    meta: &FileMeta,
+    bytes: Vec<u8>,
+) -> Result<Vec<u8>, MultipartError> {
+    let bytes = Bytes::from(bytes);
+
+    let chunks: Vec<_> = (0..bytes.len())
+        .step_by(CHUNK_SIZE)
+        .map(|start| bytes.slice(start..bytes.len().min(start + CHUNK_SIZE)))
+        .collect();
+
+    scanner
+        .scan(field_name, meta, stream::iter(chunks).boxed_local())
+        .await
+        .map_err(|reason| rejected(field_name, reason))?;
+
+    // The chunks were dropped with the stream, the bytes aren't copied back
+    Ok(Vec::from(bytes))
+}
+
+/// Scan a file while `read` streams it into the sink it is given, returning the output of
+/// `read` and the sink.
+pub(crate) async fn scan_streamed<F, T>(
+    scanner: &dyn ContentScanner,
+    field_name: &str,
+    meta: &FileMeta,
+    sink: Box<dyn FileSink>,
+    read: impl FnOnce(Box<dyn FileSink>) -> F,
+) -> Result<(T, Box<dyn FileSink>), MultipartError>
+where
+    F: Future<Output = (Result<T, MultipartError>, Box<dyn FileSink>)>,
+{
+    let (sender, receiver) = mpsc::channel(1);
+    let mut closer = sender.clone();
+
+    let sink = Box::new(ScanSink {
+        inner: sink,
+        scan: Some(sender),
+    });
+
+    let read = async {
+        let output = read(sink).await;
+        // The scan can only finish once the stream ended, even if reading failed
+        closer.close_channel();
+        output
+    };
+
+    let ((output, sink), scan) =
+        futures::join!(read, scanner.scan(field_name, meta, receiver.boxed_local()));
+
+    let output = output?;
+    scan.map_err(|reason| rejected(field_name, reason))?;
+
+    match sink.into_any().downcast::<ScanSink>() {
+        Ok(sink) => Ok((output, sink.inner)),
+        Err(_) => unreachable!("the sink is a `ScanSink`"),
+    }
+}
+
+/// Forwards the bytes of a file to its sink and to a scanner.
+struct ScanSink {
+    inner: Box<dyn FileSink>,
+    /// `None` once the scanner dropped its stream.
+    scan: Option<mpsc::Sender<Bytes>>,
+}
+
+impl Sink<Bytes> for ScanSink {
+    type Error = io::Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        if let Some(scan) = &mut self.scan {
+            match scan.poll_ready(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(_)) => self.scan = None,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        Pin::new(&mut self.inner).poll_ready(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, chunk: Bytes) -> Result<(), io::Error> {
+        if let Some(scan) = &mut self.scan {
+            if scan.start_send(chunk.clone()).is_err() {
+                self.scan = None;
+            }
+        }
+
+        Pin::new(&mut self.inner).start_send(chunk)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        self.scan = None;
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
@@ -0,0 +1,95 @@
+use actix_web::{http::header::HeaderMap, web::Bytes};
+use futures::Sink;
+use serde::{Deserialize, Deserializer};
+use std::{any::Any, fmt, io};
+
+use crate::slot;
+
+/// Metadata of a file part, known before its bytes are read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileMeta {
+    /// Name of the file after applying the [`FilenamePolicy`](crate::FilenamePolicy).
+    pub name: String,
+    pub content_type: String,
+}
+
+/// Metadata of a part converted by a `#[multipart(with = "...")]` function.
+#[derive(Debug, Clone)]
+pub struct PartMeta {
+    /// Name of the field the part is parsed into, after serde renaming.
+    pub field: String,
+    /// File name of the part as sent, if it has one.
+    pub filename: Option<String>,
+    pub content_type: String,
+    pub headers: HeaderMap,
+}
+
+/// Destination streaming the bytes of a file part, created by a [`FileStorage`].
+///
+/// Implemented for every [`Sink`] of [`Bytes`], the sink is closed once the part was read.
+/// Sinks are dropped without being closed when the request is rejected or the client
+/// disconnects while the part is read, sinks should remove what they wrote when dropped early.
+///
+/// [`FileStorage`]: crate::FileStorage
+pub trait FileSink: Sink<Bytes, Error = io::Error> + Unpin {
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<T: Sink<Bytes, Error = io::Error> + Unpin + 'static> FileSink for T {
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+/// A file streamed into a [`FileSink`] instead of being buffered.
+///
+/// Use this as the type of fields which are streamed, the closed sink can be taken back with
+/// [`StreamedFile::downcast`].
+pub struct StreamedFile {
+    pub meta: FileMeta,
+    /// Amount of bytes written to the sink.
+    pub size: usize,
+    sink: Box<dyn Any>,
+}
+
+impl StreamedFile {
+    pub(crate) fn new(meta: FileMeta, size: usize, sink: Box<dyn FileSink>) -> Self {
+        Self {
+            meta,
+            size,
+            sink: sink.into_any(),
+        }
+    }
+
+    /// Get a reference to the sink if it is a `T`.
+    pub fn downcast_ref<T: 'static>(&self) -> Option<&T> {
+        self.sink.downcast_ref()
+    }
+
+    /// Take the sink if it is a `T`.
+    pub fn downcast<T: 'static>(self) -> Result<T, Self> {
+        match self.sink.downcast::<T>() {
+            Ok(sink) => Ok(*sink),
+            Err(sink) => Err(Self {
+                meta: self.meta,
+                size: self.size,
+                sink,
+            }),
+        }
+    }
+}
+
+impl fmt::Debug for StreamedFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamedFile")
+            .field("meta", &self.meta)
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'de> Deserialize<'de> for StreamedFile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        slot::deserialize(deserializer)
+    }
+}
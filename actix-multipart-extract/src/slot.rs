@@ -0,0 +1,282 @@
+//! Values which can't be represented as JSON, such as open file sinks, are kept in slots while
+//! the form is deserialized. The JSON only contains a reference to the slot.
+
+use serde::{
+    de::{
+        value::{MapDeserializer, SeqDeserializer},
+        Error, IntoDeserializer, Visitor,
+    },
+    forward_to_deserialize_any, Deserialize, Deserializer,
+};
+use serde_json::{Map, Number, Value};
+use std::{any::Any, cell::RefCell};
+
+/// Key of the JSON object referencing a slot.
+const SLOT_KEY: &str = "$multipart_slot";
+/// Key of the JSON object referencing the raw bytes of a part in a slot, see [`FormDeserializer`].
+const BYTES_KEY: &str = "$multipart_bytes";
+
+thread_local! {
+    /// Slots of the form currently being deserialized.
+    static SLOTS: RefCell<Vec<Option<Box<dyn Any>>>> = RefCell::new(Vec::new());
+    /// Cleanups of the values taken from the slots of the form currently being deserialized.
+    static CLEANUPS: RefCell<Vec<Box<dyn FnOnce()>>> = RefCell::new(Vec::new());
+}
+
+/// Values of a single form.
+///
+/// Values are dropped with the slots unless they were taken, also when the extraction is
+/// cancelled.
+#[derive(Default)]
+pub(crate) struct Slots {
+    values: Vec<Option<Box<dyn Any>>>,
+    /// Cleanups of values, run if the value is dropped without being taken.
+    guards: Vec<(usize, Box<dyn FnOnce()>)>,
+}
+
+impl Slots {
+    /// Store a value, returning the JSON referencing it.
+    pub(crate) fn insert<T: 'static>(&mut self, value: T) -> Value {
+        self.reference(SLOT_KEY, Box::new(value))
+    }
+
+    /// Store the raw bytes of a part, returning the JSON referencing them. Only
+    /// [`FormDeserializer`] hands them to the field.
+    pub(crate) fn insert_bytes(&mut self, bytes: Vec<u8>) -> Value {
+        self.reference(BYTES_KEY, Box::new(bytes))
+    }
+
+    fn reference(&mut self, key: &str, value: Box<dyn Any>) -> Value {
+        self.values.push(Some(value));
+
+        let mut map = Map::new();
+        map.insert(
+            key.to_owned(),
+            Value::Number(Number::from(self.values.len() - 1)),
+        );
+        Value::Object(map)
+    }
+
+    /// Store a value with a cleanup run if the value isn't taken by the form, such as when a
+    /// later part is rejected or the client disconnects.
+    #[cfg(feature = "object-store")]
+    pub(crate) fn insert_guarded<T: 'static>(
+        &mut self,
+        value: T,
+        cleanup: impl FnOnce() + 'static,
+    ) -> Value {
+        self.guards.push((self.values.len(), Box::new(cleanup)));
+        self.insert(value)
+    }
+
+    /// Make the values available to [`deserialize`] while `f` runs.
+    ///
+    /// Values which weren't taken are dropped afterwards, the cleanups of the values which were
+    /// run once the returned [`Cleanups`] are dropped unless the form was accepted.
+    pub(crate) fn scope<R>(mut self, f: impl FnOnce() -> R) -> (R, Cleanups) {
+        let previous = SLOTS.with(|slots| slots.replace(std::mem::take(&mut self.values)));
+        let previous_cleanups = CLEANUPS.with(|cleanups| cleanups.take());
+        let result = f();
+        self.values = SLOTS.with(|slots| slots.replace(previous));
+        let cleanups = CLEANUPS.with(|cleanups| cleanups.replace(previous_cleanups));
+        (result, Cleanups(cleanups))
+    }
+}
+
+impl Drop for Slots {
+    fn drop(&mut self) {
+        for (slot, guard) in self.guards.drain(..) {
+            if matches!(self.values.get(slot), Some(Some(_))) {
+                guard();
+            }
+        }
+    }
+}
+
+/// Cleanups of the values taken from the slots of a form, run once dropped unless the form was
+/// accepted.
+#[must_use]
+pub(crate) struct Cleanups(Vec<Box<dyn FnOnce()>>);
+
+impl Cleanups {
+    /// Keep the values taken from the slots, the form was handed to the handler.
+    pub(crate) fn accept(mut self) {
+        self.0.clear();
+    }
+}
+
+impl Drop for Cleanups {
+    fn drop(&mut self) {
+        for cleanup in self.0.drain(..) {
+            cleanup();
+        }
+    }
+}
+
+/// Run `cleanup` if the form a value was taken from is rejected after the value was
+/// deserialized, such as when a later field fails to deserialize or validate.
+#[cfg(feature = "object-store")]
+pub(crate) fn on_rejected(cleanup: impl FnOnce() + 'static) {
+    CLEANUPS.with(|cleanups| cleanups.borrow_mut().push(Box::new(cleanup)));
+}
+
+#[derive(Deserialize)]
+struct SlotRef {
+    #[serde(rename = "$multipart_slot")]
+    slot: usize,
+}
+
+/// Deserialize a value stored in a slot.
+pub(crate) fn deserialize<'de, D: Deserializer<'de>, T: 'static>(
+    deserializer: D,
+) -> Result<T, D::Error> {
+    let SlotRef { slot } = SlotRef::deserialize(deserializer)?;
+    take(slot)
+}
+
+/// Deserialize bytes stored in a slot, or a sequence of bytes when not deserialized by the
+/// multipart extractor.
+pub(crate) fn deserialize_bytes<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<u8>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Slot(SlotRef),
+        Bytes(Vec<u8>),
+    }
+
+    match Repr::deserialize(deserializer)? {
+        Repr::Slot(SlotRef { slot }) => take(slot),
+        Repr::Bytes(bytes) => Ok(bytes),
+    }
+}
+
+fn take<T: 'static, E: Error>(slot: usize) -> Result<T, E> {
+    let value = SLOTS.with(|slots| slots.borrow_mut().get_mut(slot).and_then(Option::take));
+
+    match value.map(|value| value.downcast::<T>()) {
+        Some(Ok(value)) => Ok(*value),
+        Some(Err(_)) => Err(E::custom(format!(
+            "expected {}",
+            std::any::type_name::<T>()
+        ))),
+        None => Err(E::custom(
+            "can only be deserialized by the multipart extractor",
+        )),
+    }
+}
+
+/// Deserializer of the JSON of a form, handing the bytes stored with [`Slots::insert_bytes`] to
+/// their fields instead of an array with a number per byte. Other values are deserialized like
+/// with [`serde_json::from_value`].
+pub(crate) struct FormDeserializer(pub(crate) Value);
+
+impl FormDeserializer {
+    /// Slot of the bytes the value references.
+    fn bytes_slot(&self) -> Option<usize> {
+        match &self.0 {
+            Value::Object(map) if map.len() == 1 => {
+                map.get(BYTES_KEY)?.as_u64().map(|slot| slot as usize)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, serde_json::Error> for FormDeserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! delegate_to_value {
+    ($($method:ident)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            self.0.$method(visitor)
+        }
+    )*};
+}
+
+impl<'de> Deserializer<'de> for FormDeserializer {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if let Some(slot) = self.bytes_slot() {
+            let bytes: Vec<u8> = take(slot)?;
+            return SeqDeserializer::new(bytes.into_iter()).deserialize_any(visitor);
+        }
+
+        match self.0 {
+            Value::Array(values) => SeqDeserializer::new(values.into_iter().map(FormDeserializer))
+                .deserialize_any(visitor),
+            Value::Object(map) => MapDeserializer::new(
+                map.into_iter()
+                    .map(|(key, value)| (key, FormDeserializer(value))),
+            )
+            .deserialize_any(visitor),
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.bytes_slot() {
+            Some(slot) => visitor.visit_byte_buf(take(slot)?),
+            None => self.0.deserialize_byte_buf(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        drop(self);
+        visitor.visit_unit()
+    }
+
+    delegate_to_value! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_unit deserialize_identifier
+    }
+
+    forward_to_deserialize_any! {
+        seq tuple tuple_struct map struct
+    }
+}
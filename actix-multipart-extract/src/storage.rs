@@ -0,0 +1,347 @@
+use std::io;
+
+use crate::{FileMeta, FileSink, MultipartConfig};
+
+/// Decides where the bytes of every file part go, set with
+/// [`MultipartConfig::set_file_storage`](crate::MultipartConfig::set_file_storage).
+///
+/// Implemented for closures taking the field name and file metadata.
+pub trait FileStorage: Send + Sync + 'static {
+    /// Create the sink a file part is streamed into, `None` to buffer it in memory as a
+    /// [`File`](crate::File). Fields streamed into a sink must use the
+    /// [`StreamedFile`](crate::StreamedFile) type or a type taking its sink.
+    fn sink(
+        &self,
+        field: &str,
+        meta: &FileMeta,
+        config: &MultipartConfig,
+    ) -> io::Result<Option<Box<dyn FileSink>>>;
+}
+
+impl<F> FileStorage for F
+where
+    F: Fn(&str, &FileMeta) -> Option<Box<dyn FileSink>> + Send + Sync + 'static,
+{
+    fn sink(
+        &self,
+        field: &str,
+        meta: &FileMeta,
+        _: &MultipartConfig,
+    ) -> io::Result<Option<Box<dyn FileSink>>> {
+        Ok(self(field, meta))
+    }
+}
+
+/// Buffers every file in memory as a [`File`](crate::File), the default storage.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemoryStorage;
+
+impl FileStorage for MemoryStorage {
+    fn sink(
+        &self,
+        _: &str,
+        _: &FileMeta,
+        _: &MultipartConfig,
+    ) -> io::Result<Option<Box<dyn FileSink>>> {
+        Ok(None)
+    }
+}
+
+#[cfg(feature = "tempfile")]
+pub(crate) use temp::SpoolSink;
+#[cfg(feature = "tempfile")]
+pub use temp::{SpooledFile, TempDirStorage, TempFile, TempFileSink};
+
+#[cfg(feature = "tempfile")]
+mod temp {
+    use actix_web::web::{Buf, Bytes, BytesMut};
+    use futures::{ready, Sink};
+    use serde::{de, Deserialize, Deserializer};
+    use std::{
+        io::{self, Read},
+        path::{Path, PathBuf},
+        pin::Pin,
+        task::{Context, Poll},
+    };
+    use tempfile::NamedTempFile;
+    use tokio::io::AsyncWrite;
+
+    use super::FileStorage;
+    use crate::{slot, FileMeta, FileSink, MultipartConfig, StreamedFile};
+
+    /// Writes every file to a temporary file, fields must use the [`TempFile`] type.
+    #[derive(Debug, Clone, Default)]
+    pub struct TempDirStorage {
+        dir: Option<PathBuf>,
+    }
+
+    impl TempDirStorage {
+        /// Store files in [`MultipartConfig::temp_dir`].
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Store files in `dir`.
+        pub fn in_dir(dir: impl Into<PathBuf>) -> Self {
+            Self {
+                dir: Some(dir.into()),
+            }
+        }
+    }
+
+    impl FileStorage for TempDirStorage {
+        fn sink(
+            &self,
+            _: &str,
+            _: &FileMeta,
+            config: &MultipartConfig,
+        ) -> io::Result<Option<Box<dyn FileSink>>> {
+            let file = match &self.dir {
+                Some(dir) => NamedTempFile::new_in(dir)?,
+                None => NamedTempFile::new_in(config.resolve_temp_dir())?,
+            };
+
+            Ok(Some(Box::new(TempFileSink::new(file)?)))
+        }
+    }
+
+    /// [`FileSink`] writing a file part into a temporary file.
+    pub struct TempFileSink {
+        file: NamedTempFile,
+        writer: tokio::fs::File,
+        pending: Bytes,
+        size: usize,
+    }
+
+    impl TempFileSink {
+        pub fn new(file: NamedTempFile) -> io::Result<Self> {
+            let writer = tokio::fs::File::from_std(file.as_file().try_clone()?);
+
+            Ok(Self {
+                file,
+                writer,
+                pending: Bytes::new(),
+                size: 0,
+            })
+        }
+
+        /// Take the temporary file, it is deleted once dropped unless it was persisted.
+        pub fn into_file(self) -> NamedTempFile {
+            self.file
+        }
+
+        fn poll_write_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            while !self.pending.is_empty() {
+                let written = ready!(Pin::new(&mut self.writer).poll_write(cx, &self.pending))?;
+                if written == 0 {
+                    return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+                }
+
+                self.pending.advance(written);
+            }
+
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl Sink<Bytes> for TempFileSink {
+        type Error = io::Error;
+
+        fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            self.get_mut().poll_write_pending(cx)
+        }
+
+        fn start_send(self: Pin<&mut Self>, item: Bytes) -> io::Result<()> {
+            let this = self.get_mut();
+            this.size += item.len();
+            this.pending = item;
+            Ok(())
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            let this = self.get_mut();
+            ready!(this.poll_write_pending(cx))?;
+            Pin::new(&mut this.writer).poll_flush(cx)
+        }
+
+        fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            let this = self.get_mut();
+            ready!(this.poll_write_pending(cx))?;
+            Pin::new(&mut this.writer).poll_shutdown(cx)
+        }
+    }
+
+    /// A file written to a temporary file while the request was read, see [`TempDirStorage`].
+    ///
+    /// The file is deleted once this is dropped unless it was persisted with
+    /// [`NamedTempFile::persist`].
+    #[derive(Debug)]
+    pub struct TempFile {
+        pub meta: FileMeta,
+        /// Amount of bytes written to the file.
+        pub size: usize,
+        pub file: NamedTempFile,
+    }
+
+    impl<'de> Deserialize<'de> for TempFile {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let file: StreamedFile = slot::deserialize(deserializer)?;
+            let meta = file.meta.clone();
+            let sink = file
+                .downcast::<TempFileSink>()
+                .map_err(|_| de::Error::custom("expected a file stored with TempDirStorage"))?;
+
+            Ok(Self {
+                meta,
+                size: sink.size,
+                file: sink.into_file(),
+            })
+        }
+    }
+
+    enum Spool {
+        Memory(BytesMut),
+        Disk(TempFileSink),
+    }
+
+    /// [`FileSink`] buffering a file part in memory until it grows past a threshold, then
+    /// spilling it to a temporary file.
+    pub(crate) struct SpoolSink {
+        threshold: usize,
+        dir: PathBuf,
+        spool: Spool,
+    }
+
+    impl SpoolSink {
+        pub(crate) fn new(threshold: usize, dir: PathBuf) -> Self {
+            Self {
+                threshold,
+                dir,
+                spool: Spool::Memory(BytesMut::new()),
+            }
+        }
+    }
+
+    impl Sink<Bytes> for SpoolSink {
+        type Error = io::Error;
+
+        fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            match &mut self.get_mut().spool {
+                Spool::Memory(_) => Poll::Ready(Ok(())),
+                Spool::Disk(sink) => Pin::new(sink).poll_ready(cx),
+            }
+        }
+
+        fn start_send(self: Pin<&mut Self>, item: Bytes) -> io::Result<()> {
+            let this = self.get_mut();
+            match &mut this.spool {
+                Spool::Memory(buffer) if buffer.len() + item.len() > this.threshold => {
+                    let mut sink = TempFileSink::new(NamedTempFile::new_in(&this.dir)?)?;
+
+                    buffer.extend_from_slice(&item);
+                    Pin::new(&mut sink).start_send(buffer.split().freeze())?;
+                    this.spool = Spool::Disk(sink);
+                    Ok(())
+                }
+                Spool::Memory(buffer) => {
+                    buffer.extend_from_slice(&item);
+                    Ok(())
+                }
+                Spool::Disk(sink) => Pin::new(sink).start_send(item),
+            }
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            match &mut self.get_mut().spool {
+                Spool::Memory(_) => Poll::Ready(Ok(())),
+                Spool::Disk(sink) => Pin::new(sink).poll_flush(cx),
+            }
+        }
+
+        fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            match &mut self.get_mut().spool {
+                Spool::Memory(_) => Poll::Ready(Ok(())),
+                Spool::Disk(sink) => Pin::new(sink).poll_close(cx),
+            }
+        }
+    }
+
+    enum SpooledData {
+        Memory(Bytes),
+        Disk(NamedTempFile),
+    }
+
+    /// A file kept in memory or spilled to a temporary file depending on its size, see
+    /// [`MultipartConfig::memory_threshold`].
+    pub struct SpooledFile {
+        pub meta: FileMeta,
+        pub size: usize,
+        data: SpooledData,
+    }
+
+    impl SpooledFile {
+        /// Whether the file was small enough to stay in memory.
+        pub fn is_in_memory(&self) -> bool {
+            matches!(self.data, SpooledData::Memory(_))
+        }
+
+        /// Path of the temporary file, `None` if the file is in memory.
+        pub fn path(&self) -> Option<&Path> {
+            match &self.data {
+                SpooledData::Memory(_) => None,
+                SpooledData::Disk(file) => Some(file.path()),
+            }
+        }
+
+        /// Read the contents of the file.
+        pub fn bytes(&self) -> io::Result<Bytes> {
+            match &self.data {
+                SpooledData::Memory(bytes) => Ok(bytes.clone()),
+                SpooledData::Disk(file) => std::fs::read(file.path()).map(Bytes::from),
+            }
+        }
+
+        /// Get a reader over the contents of the file.
+        pub fn reader(&self) -> io::Result<Box<dyn Read + Send>> {
+            match &self.data {
+                SpooledData::Memory(bytes) => Ok(Box::new(bytes.clone().reader())),
+                SpooledData::Disk(file) => Ok(Box::new(file.reopen()?)),
+            }
+        }
+
+        /// Write the file to `path`, moving the temporary file there if it was spilled.
+        pub fn persist(self, path: impl AsRef<Path>) -> io::Result<()> {
+            match self.data {
+                SpooledData::Memory(bytes) => std::fs::write(path, bytes),
+                SpooledData::Disk(file) => file.persist(path).map(|_| ()).map_err(|err| err.error),
+            }
+        }
+    }
+
+    impl std::fmt::Debug for SpooledFile {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("SpooledFile")
+                .field("meta", &self.meta)
+                .field("size", &self.size)
+                .field("path", &self.path())
+                .finish()
+        }
+    }
+
+    impl<'de> Deserialize<'de> for SpooledFile {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let file: StreamedFile = slot::deserialize(deserializer)?;
+            let (meta, size) = (file.meta.clone(), file.size);
+            let sink = file.downcast::<SpoolSink>().map_err(|_| {
+                de::Error::custom("expected a file stored with MultipartConfig::memory_threshold")
+            })?;
+
+            let data = match sink.spool {
+                Spool::Memory(buffer) => SpooledData::Memory(buffer.freeze()),
+                Spool::Disk(sink) => SpooledData::Disk(sink.into_file()),
+            };
+
+            Ok(Self { meta, size, data })
+        }
+    }
+}
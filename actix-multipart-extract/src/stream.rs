@@ -0,0 +1,332 @@
+use actix_web::{dev::Payload, web::Bytes, FromRequest, HttpRequest};
+use futures::{future::Ready, Stream};
+use serde::de::DeserializeOwned;
+use serde_aux::prelude::serde_introspect;
+use serde_json::Value;
+use std::{
+    fmt,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{
+    extractor::{
+        check_content_type, check_filename, content_id, default_value, field_for_part,
+        handle_error, part_filename, read_field, text_part_value, text_size_error, ReadOptions,
+    },
+    form::MultipartForm,
+    memory::Reservation,
+    parser::{Parser, Part},
+    payload::{PayloadStatus, TrackedPayload},
+    FileMeta, MultipartConfig, MultipartError, PartMeta, UnnamedParts,
+};
+
+/// A field streamed by [`MultipartStream`].
+#[derive(Debug)]
+pub enum Field {
+    /// A text field, or a field converted by its `with` function, with the value `Multipart`
+    /// would deserialize it from.
+    Text { name: String, value: Value },
+    File {
+        name: String,
+        meta: FileMeta,
+        stream: FileStream,
+    },
+}
+
+/// Extractor streaming the fields of a form to the handler as they arrive.
+///
+/// Fields are checked against the [`MultipartForm`] `T` without buffering files: fields `T`
+/// doesn't declare are skipped unless [`MultipartConfig::deny_unknown_fields`] is set, text
+/// fields are validated and files are limited to their max size while they stream. A file must
+/// be read or dropped before the next field is read. Once the form ended, fields which weren't
+/// sent and have a default are yielded with it.
+///
+/// Text fields count towards the [`MultipartConfig::shared_memory_limit`] until the stream is
+/// dropped.
+pub struct MultipartStream<T> {
+    req: HttpRequest,
+    multipart: Parser,
+    status: PayloadStatus,
+    memory: Reservation,
+    received: Vec<&'static str>,
+    defaults: Option<std::vec::IntoIter<(&'static str, Value)>>,
+    _form: PhantomData<T>,
+}
+
+impl<T: DeserializeOwned + MultipartForm> MultipartStream<T> {
+    /// Get the next field, `None` once the form ended.
+    pub async fn next(&mut self) -> Result<Option<Field>, MultipartError> {
+        let config = MultipartConfig::from_req(&self.req);
+
+        loop {
+            if let Some(defaults) = &mut self.defaults {
+                return Ok(defaults.next().map(|(name, value)| Field::Text {
+                    name: name.to_string(),
+                    value,
+                }));
+            }
+
+            let mut field = match self.status.next_part(&mut self.multipart).await? {
+                Some(Ok(field)) => field,
+                None => {
+                    self.defaults = Some(self.unsent_defaults()?.into_iter());
+                    continue;
+                }
+                Some(Err(err)) => return Err(err.into_error()),
+            };
+
+            let disposition = field.disposition().clone();
+            let content_id_field = content_id(field.headers()).and_then(T::content_id_field);
+
+            if content_id_field.is_none()
+                && disposition.get_name().is_none()
+                && config.unnamed_parts == UnnamedParts::Reject
+            {
+                return Err(MultipartError::UnnamedPart);
+            }
+
+            // Fields `T` doesn't declare are skipped or rejected
+            let name = match content_id_field.or_else(|| {
+                disposition
+                    .get_name()
+                    .and_then(|name| field_for_part::<T>(name, config))
+            }) {
+                Some(name) => name,
+                None if config.deny_unknown_fields => {
+                    return Err(MultipartError::UnknownField {
+                        field: disposition.get_name().unwrap_or_default().to_string(),
+                    })
+                }
+                None => {
+                    #[cfg(feature = "logging")]
+                    log::debug!(
+                        "skipping part {:?}, {} has no such field",
+                        disposition.get_name(),
+                        std::any::type_name::<T>()
+                    );
+                    continue;
+                }
+            };
+
+            if !self.received.contains(&name) {
+                self.received.push(name);
+            }
+
+            let filename = part_filename::<T>(name, &disposition, content_id_field);
+
+            if let Some(parser) = T::parser(name) {
+                // Converted by the function of the field, parts with a file name still count as files
+                let mut max_size = T::max_size(name);
+                if filename.is_some() {
+                    self.status.add_file()?;
+                    max_size = self.status.limits().file_limit(max_size);
+                }
+
+                let options = ReadOptions {
+                    max_size,
+                    deadline: T::deadline(name),
+                    #[cfg(feature = "hash")]
+                    hash_algorithm: None,
+                    check_start: None,
+                };
+
+                let mut memory = Reservation::new(config.shared_memory_limit.as_ref());
+                let bytes = read_field(
+                    &mut field,
+                    name,
+                    &options,
+                    None,
+                    &self.status,
+                    &mut memory,
+                    config,
+                )
+                .await?
+                .bytes;
+
+                let meta = PartMeta {
+                    field: name.to_string(),
+                    filename: filename.map(str::to_string),
+                    content_type: field.content_type().to_string(),
+                    headers: field.headers().clone(),
+                };
+
+                let value = parser(&bytes, &meta).map_err(|message| {
+                    MultipartError::FieldValidationError {
+                        field: name.to_string(),
+                        message,
+                    }
+                })?;
+
+                return Ok(Some(Field::Text {
+                    name: name.to_string(),
+                    value,
+                }));
+            }
+
+            if let Some(filename) = filename {
+                self.status.add_file()?;
+
+                let filename = check_filename::<T>(name, filename, config)?;
+
+                let meta = FileMeta {
+                    name: filename.to_string(),
+                    content_type: field.content_type().to_string(),
+                };
+
+                return Ok(Some(Field::File {
+                    name: name.to_string(),
+                    meta,
+                    stream: FileStream {
+                        field: name.to_string(),
+                        max_size: self.status.limits().file_limit(T::max_size(name)),
+                        size: 0,
+                        done: false,
+                        inner: Box::new(field),
+                        status: self.status.clone(),
+                    },
+                }));
+            }
+
+            let options = ReadOptions {
+                max_size: T::max_size(name),
+                deadline: T::deadline(name),
+                #[cfg(feature = "hash")]
+                hash_algorithm: None,
+                check_start: None,
+            };
+
+            let bytes = read_field(
+                &mut field,
+                name,
+                &options,
+                None,
+                &self.status,
+                &mut self.memory,
+                config,
+            )
+            .await
+            .map_err(text_size_error)?
+            .bytes;
+
+            // Text which isn't valid UTF-8 is skipped like in `Multipart`
+            if let Some(value) = text_part_value::<T>(name, bytes, field.content_type(), config)? {
+                return Ok(Some(Field::Text {
+                    name: name.to_string(),
+                    value,
+                }));
+            }
+        }
+    }
+
+    /// Defaults of the fields which weren't sent.
+    fn unsent_defaults(&self) -> Result<Vec<(&'static str, Value)>, MultipartError> {
+        let mut defaults = Vec::new();
+
+        for name in serde_introspect::<T>() {
+            if self.received.contains(name) {
+                continue;
+            }
+
+            if let Some(value) = default_value::<T>(name)? {
+                defaults.push((*name, value));
+            }
+        }
+
+        Ok(defaults)
+    }
+}
+
+impl<T: DeserializeOwned + MultipartForm> FromRequest for MultipartStream<T> {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let config = MultipartConfig::from_req(req);
+
+        if let Err(err) = check_content_type(req) {
+            return futures::future::err(handle_error(err, req, config));
+        }
+
+        let (payload, status) = TrackedPayload::new(req, payload.take(), config);
+
+        futures::future::ok(Self {
+            req: req.clone(),
+            multipart: Parser::new(req.headers(), payload),
+            status,
+            memory: Reservation::new(config.shared_memory_limit.as_ref()),
+            received: Vec::new(),
+            defaults: None,
+            _form: PhantomData,
+        })
+    }
+}
+
+/// Bytes of a file streamed by [`MultipartStream`].
+///
+/// The bytes are yielded as sent, a transfer or content encoding of the part isn't undone.
+pub struct FileStream {
+    field: String,
+    max_size: Option<usize>,
+    size: usize,
+    done: bool,
+    inner: Box<Part>,
+    status: PayloadStatus,
+}
+
+impl FileStream {
+    /// Amount of bytes streamed so far.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+impl Stream for FileStream {
+    type Item = Result<Bytes, MultipartError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if this.done {
+            return Poll::Ready(None);
+        }
+
+        let item = match this.status.poll_next(&mut *this.inner, cx) {
+            Poll::Ready(item) => item,
+            Poll::Pending => return Poll::Pending,
+        };
+
+        let result = match item {
+            Ok(Some(Ok(chunk))) => {
+                this.size += chunk.len();
+
+                match this.max_size {
+                    Some(limit) if this.size > limit => Err(MultipartError::FileSizeError {
+                        field: this.field.clone(),
+                        limit,
+                    }),
+                    _ => Ok(chunk),
+                }
+            }
+            Ok(Some(Err(err))) => Err(err.into_error()),
+            Ok(None) => {
+                this.done = true;
+                return Poll::Ready(None);
+            }
+            Err(err) => Err(err),
+        };
+
+        this.done = result.is_err();
+        Poll::Ready(Some(result))
+    }
+}
+
+impl fmt::Debug for FileStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileStream")
+            .field("field", &self.field)
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
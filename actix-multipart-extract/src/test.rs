@@ -0,0 +1,99 @@
+//! Utilities for testing handlers using [`Multipart`](crate::Multipart) extractors.
+
+use actix_web::{
+    dev::Payload,
+    http::header::{self, HeaderValue},
+    test::TestRequest,
+    web::Bytes,
+    HttpRequest,
+};
+
+use crate::MultipartBody;
+
+/// Builder for `multipart/form-data` bodies sent with [`TestRequest`].
+///
+/// # Example
+/// ```
+/// use actix_multipart_extract::test::TestMultipartBuilder;
+///
+/// let req = TestMultipartBuilder::new()
+///     .text("name", "avatar")
+///     .file("avatar", "a.png", "image/png", vec![0x89, 0x50, 0x4e, 0x47])
+///     .to_request();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TestMultipartBuilder {
+    body: MultipartBody,
+}
+
+impl TestMultipartBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the boundary delimiting parts, this must not occur in any part.
+    ///
+    /// # Panics
+    /// If the boundary isn't valid, see [`MultipartBody::set_boundary`].
+    pub fn boundary(mut self, boundary: impl Into<String>) -> Self {
+        self.body
+            .set_boundary(boundary)
+            .expect("boundary must be valid");
+        self
+    }
+
+    /// Add a text field.
+    pub fn text(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.body.text(name, value);
+        self
+    }
+
+    /// Add a file field.
+    ///
+    /// # Panics
+    /// If the content type isn't a valid media type, see [`MultipartBody::file`].
+    pub fn file(
+        mut self,
+        name: impl Into<String>,
+        filename: impl Into<String>,
+        content_type: impl Into<String>,
+        bytes: impl Into<Bytes>,
+    ) -> Self {
+        self.body
+            .file(name, filename, content_type, bytes)
+            .expect("content type must be valid");
+        self
+    }
+
+    /// Get the `Content-Type` header value including the boundary.
+    pub fn content_type(&self) -> HeaderValue {
+        self.body.content_type()
+    }
+
+    /// Encode the body.
+    pub fn body(&self) -> Bytes {
+        self.body.to_bytes()
+    }
+
+    /// Set the content type and body on a [`TestRequest`].
+    pub fn set_on(&self, req: TestRequest) -> TestRequest {
+        req.insert_header((header::CONTENT_TYPE, self.content_type()))
+            .set_payload(self.body())
+    }
+
+    /// Create a `POST` request with the form.
+    pub fn to_request(&self) -> actix_http::Request {
+        self.set_on(TestRequest::post()).to_request()
+    }
+
+    /// Create a `POST` request and payload with the form, for calling extractors directly.
+    pub fn to_http_parts(&self) -> (HttpRequest, Payload) {
+        self.set_on(TestRequest::post()).to_http_parts()
+    }
+}
+
+impl From<MultipartBody> for TestMultipartBuilder {
+    fn from(body: MultipartBody) -> Self {
+        Self { body }
+    }
+}
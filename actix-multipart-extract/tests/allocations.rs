@@ -0,0 +1,96 @@
+use actix_multipart_extract::{test::TestMultipartBuilder, Multipart, MultipartForm};
+use actix_web::FromRequest;
+use serde::Deserialize;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, PoisonError,
+    },
+};
+
+/// Counts the bytes allocated, tests hold [`LOCK`] so nothing else allocates meanwhile.
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static LOCK: Mutex<()> = Mutex::new(());
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATED.fetch_add(new_size.saturating_sub(layout.size()), Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// Extract a form from a body, returning the bytes allocated meanwhile.
+fn extract_counting<T: FromRequest>(body: TestMultipartBuilder) -> (T, usize) {
+    let _lock = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    let (req, mut payload) = body.to_http_parts();
+    let system = actix_web::rt::System::new();
+
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let extracted = system.block_on(T::from_request(&req, &mut payload));
+    let allocated = ALLOCATED.load(Ordering::Relaxed) - before;
+
+    (extracted.ok().unwrap(), allocated)
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Document {
+    #[multipart(max_size = 8MB)]
+    text: String,
+}
+
+#[test]
+fn moves_large_text_into_its_value() {
+    let text = "a".repeat(4 * 1024 * 1024);
+    let (form, allocated) = extract_counting::<Multipart<Document>>(
+        TestMultipartBuilder::new().text("text", text.as_str()),
+    );
+
+    // The parser buffers the part once, its buffer becomes the text without another copy
+    assert_eq!(form.text, text);
+    assert!(
+        allocated < 5 * text.len() / 2,
+        "{allocated} bytes allocated for {} bytes of text",
+        text.len()
+    );
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Upload {
+    #[multipart(max_size = 8MB)]
+    data: Vec<u8>,
+}
+
+#[test]
+fn moves_bytes_into_their_field() {
+    let data = vec![0xff; 4 * 1024 * 1024];
+    let (form, allocated) =
+        extract_counting::<Multipart<Upload>>(TestMultipartBuilder::new().file(
+            "data",
+            "data.bin",
+            "application/octet-stream",
+            data.clone(),
+        ));
+
+    // The bytes are copied once into the `Vec`, instead of being expanded into a number per byte
+    assert_eq!(form.data, data);
+    assert!(
+        allocated < 4 * data.len(),
+        "{allocated} bytes allocated for {} bytes",
+        data.len()
+    );
+}
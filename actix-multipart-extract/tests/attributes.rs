@@ -0,0 +1,481 @@
+mod common;
+
+use actix_multipart_extract::{
+    test::TestMultipartBuilder, File, Multipart, MultipartConfig, MultipartForm,
+    MultipartSerialize, PartMeta,
+};
+use actix_web::{test::TestRequest, web::Bytes};
+use common::{error_of, extract, extract_with};
+use serde::{Deserialize, Serialize};
+
+fn texts(fields: &[(&str, &str)]) -> TestRequest {
+    fields
+        .iter()
+        .fold(TestMultipartBuilder::new(), |form, (name, value)| {
+            form.text(*name, *value)
+        })
+        .set_on(TestRequest::post())
+}
+
+fn default_tags() -> Vec<String> {
+    vec!["new".to_string()]
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Defaults {
+    #[multipart(default = 10)]
+    limit: u32,
+    #[multipart(default = "public", one_of = "private,unlisted")]
+    visibility: String,
+    #[multipart(default = true)]
+    notify: bool,
+    #[multipart(default_fn = "default_tags")]
+    tags: Vec<String>,
+}
+
+#[actix_web::test]
+async fn uses_defaults_for_missing_fields() {
+    let req = texts(&[("unknown", "skipped")]);
+    let form = extract::<Multipart<Defaults>>(req).await.unwrap();
+
+    assert_eq!(form.limit, 10);
+    // Defaults aren't checked
+    assert_eq!(form.visibility, "public");
+    assert!(form.notify);
+    assert_eq!(form.tags, ["new"]);
+}
+
+#[actix_web::test]
+async fn prefers_sent_values_to_defaults() {
+    let req = texts(&[
+        ("limit", "3"),
+        ("visibility", "private"),
+        ("notify", "false"),
+        ("tags", "a"),
+    ]);
+    let form = extract::<Multipart<Defaults>>(req).await.unwrap();
+
+    assert_eq!(form.limit, 3);
+    assert_eq!(form.visibility, "private");
+    assert!(!form.notify);
+    assert_eq!(form.tags, ["a"]);
+
+    let err = extract::<Multipart<Defaults>>(texts(&[("visibility", "public")]))
+        .await
+        .unwrap_err();
+    assert_eq!(error_of(&err).1, "invalid_field");
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Aliased {
+    #[multipart(alias = "upload", alias = "attachment")]
+    file: File,
+    #[multipart(alias = "name")]
+    title: String,
+}
+
+#[actix_web::test]
+async fn accepts_parts_under_aliases() {
+    for (file, title) in [
+        ("file", "title"),
+        ("upload", "name"),
+        ("attachment", "title"),
+    ] {
+        let req = TestMultipartBuilder::new()
+            .file(file, "a.txt", "text/plain", "A")
+            .text(title, "Notes")
+            .set_on(TestRequest::post());
+        let form = extract::<Multipart<Aliased>>(req).await.unwrap();
+
+        assert_eq!(form.file.bytes, b"A", "{file}");
+        assert_eq!(form.title, "Notes", "{title}");
+    }
+}
+
+#[derive(Deserialize, Serialize, MultipartForm, MultipartSerialize, Debug)]
+struct Renamed {
+    #[multipart(rename = "user-name")]
+    #[serde(rename = "userName")]
+    name: String,
+    #[serde(rename = "userAge")]
+    age: Option<u32>,
+}
+
+#[actix_web::test]
+async fn matches_parts_by_their_rename() {
+    let req = texts(&[("user-name", "Ana"), ("userAge", "30")]);
+    let form = extract::<Multipart<Renamed>>(req).await.unwrap();
+    assert_eq!((form.name.as_str(), form.age), ("Ana", Some(30)));
+
+    // Serde names only match fields without a rename
+    for name in ["userName", "name"] {
+        let err = extract::<Multipart<Renamed>>(texts(&[(name, "Ana")]))
+            .await
+            .unwrap_err();
+        assert_eq!(error_of(&err).1, "missing_field", "{name}");
+    }
+
+    let mut body = form.to_multipart().unwrap();
+    body.set_boundary("b").unwrap();
+    let encoded = String::from_utf8(body.to_bytes().to_vec()).unwrap();
+    assert!(encoded.contains(r#"name="user-name""#), "{encoded}");
+    assert!(encoded.contains(r#"name="userAge""#), "{encoded}");
+}
+
+#[actix_web::test]
+async fn matches_parts_ignoring_case() {
+    let config = || MultipartConfig::default().set_case_insensitive_fields(true);
+
+    let req = TestMultipartBuilder::new()
+        .file("FILE", "a.txt", "text/plain", "A")
+        .text("Name", "Notes")
+        .set_on(TestRequest::post());
+    let form = extract_with::<Multipart<Aliased>>(req, config())
+        .await
+        .unwrap();
+    assert_eq!(
+        (form.file.bytes.as_slice(), form.title.as_str()),
+        (&b"A"[..], "Notes")
+    );
+
+    let req = texts(&[("USER-NAME", "Ana"), ("userage", "30")]);
+    let form = extract_with::<Multipart<Renamed>>(req, config())
+        .await
+        .unwrap();
+    assert_eq!((form.name.as_str(), form.age), ("Ana", Some(30)));
+
+    // Serde names of renamed fields still don't match
+    let err = extract_with::<Multipart<Renamed>>(texts(&[("USERNAME", "Ana")]), config())
+        .await
+        .unwrap_err();
+    assert_eq!(error_of(&err).1, "missing_field");
+
+    // Matched exactly by default
+    let err = extract::<Multipart<Renamed>>(texts(&[("USER-NAME", "Ana")]))
+        .await
+        .unwrap_err();
+    assert_eq!(error_of(&err).1, "missing_field");
+}
+
+#[derive(Deserialize, Serialize, MultipartForm, Debug)]
+#[multipart(rename_all = "camelCase")]
+struct Profile {
+    first_name: String,
+    #[multipart(rename = "mail")]
+    email_address: String,
+    r#type: String,
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+#[multipart(rename_all = "SCREAMING-KEBAB-CASE")]
+struct Shouted {
+    first_name: String,
+}
+
+#[actix_web::test]
+async fn renames_every_field() {
+    let req = texts(&[
+        ("firstName", "Ana"),
+        ("mail", "ana@example.com"),
+        ("type", "admin"),
+    ]);
+    let form = extract::<Multipart<Profile>>(req).await.unwrap();
+    assert_eq!(form.first_name, "Ana");
+    assert_eq!(form.email_address, "ana@example.com");
+    assert_eq!(form.r#type, "admin");
+
+    // Serde names are unchanged
+    let json = serde_json::to_value(&form).unwrap();
+    assert_eq!(json["first_name"], "Ana");
+
+    let err = extract::<Multipart<Profile>>(texts(&[("first_name", "Ana")]))
+        .await
+        .unwrap_err();
+    assert_eq!(error_of(&err).1, "missing_field");
+
+    let form = extract::<Multipart<Shouted>>(texts(&[("FIRST-NAME", "Ana")]))
+        .await
+        .unwrap();
+    assert_eq!(form.first_name, "Ana");
+}
+
+fn parse_csv(bytes: &[u8], meta: &PartMeta) -> Result<serde_json::Value, String> {
+    let text = std::str::from_utf8(bytes).map_err(|err| err.to_string())?;
+    let mut values: Vec<_> = text.split(',').map(str::trim).collect();
+    if let Some(filename) = &meta.filename {
+        values.push(filename);
+    }
+    Ok(values.into())
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Import {
+    #[multipart(with = "parse_csv", max_size = 16)]
+    tags: Vec<String>,
+}
+
+#[actix_web::test]
+async fn converts_parts_with_functions() {
+    let form = extract::<Multipart<Import>>(texts(&[("tags", "a, b,c")]))
+        .await
+        .unwrap();
+    assert_eq!(form.tags, ["a", "b", "c"]);
+
+    let req = TestMultipartBuilder::new()
+        .file("tags", "tags.csv", "text/csv", "a")
+        .set_on(TestRequest::post());
+    let form = extract::<Multipart<Import>>(req).await.unwrap();
+    assert_eq!(form.tags, ["a", "tags.csv"]);
+}
+
+#[actix_web::test]
+async fn rejects_parts_functions_fail_on() {
+    let req = TestMultipartBuilder::new()
+        .file("tags", "tags.csv", "text/csv", Bytes::from_static(&[0xff]))
+        .set_on(TestRequest::post());
+    let err = extract::<Multipart<Import>>(req).await.unwrap_err();
+    let (status, code, body) = common::error_body(&err).await;
+    assert_eq!((status.as_u16(), code.as_str()), (400, "invalid_field"));
+    assert!(body.contains("invalid utf-8"), "{body}");
+
+    let err = extract::<Multipart<Import>>(texts(&[("tags", "more,than,sixteen,bytes")]))
+        .await
+        .unwrap_err();
+    assert_eq!(error_of(&err).1, "text_too_large");
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Forced {
+    #[multipart(as_file)]
+    avatar: File,
+    #[multipart(as_text)]
+    notes: String,
+}
+
+#[actix_web::test]
+async fn forces_parts_to_be_files_or_text() {
+    let req = TestMultipartBuilder::new()
+        .text("avatar", "pixels")
+        .file("notes", "notes.txt", "text/plain", "Hello")
+        .set_on(TestRequest::post());
+    let form = extract::<Multipart<Forced>>(req).await.unwrap();
+
+    assert_eq!(form.avatar.name, "");
+    assert_eq!(form.avatar.bytes, b"pixels");
+    assert_eq!(form.notes, "Hello");
+
+    // Parts already sent the right way are read the same
+    let req = TestMultipartBuilder::new()
+        .file("avatar", "avatar.png", "image/png", "pixels")
+        .text("notes", "Hello")
+        .set_on(TestRequest::post());
+    let form = extract::<Multipart<Forced>>(req).await.unwrap();
+    assert_eq!(form.avatar.name, "avatar.png");
+    assert_eq!(form.notes, "Hello");
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Encoded {
+    #[multipart(base64_file, max_size = 6)]
+    avatar: File,
+    #[multipart(base64_file)]
+    attachment: File,
+}
+
+#[actix_web::test]
+async fn decodes_base64_files() {
+    let req = texts(&[
+        ("avatar", "data:image/png;base64,cGl4ZWxz"),
+        ("attachment", "SGVsbG8="),
+    ]);
+    let form = extract::<Multipart<Encoded>>(req).await.unwrap();
+
+    assert_eq!(form.avatar.bytes, b"pixels");
+    assert_eq!(form.avatar.content_type, "image/png");
+    assert_eq!(form.avatar.size, 6);
+    assert_eq!(form.attachment.bytes, b"Hello");
+    assert_eq!(form.attachment.content_type, "application/octet-stream");
+
+    // The limit applies to the decoded bytes
+    let req = texts(&[("avatar", "cGl4ZWxzIQ=="), ("attachment", "")]);
+    let err = extract::<Multipart<Encoded>>(req).await.unwrap_err();
+    let (_, code, body) = common::error_body(&err).await;
+    assert_eq!(code, "file_too_large");
+    assert!(body.contains("max size: 6 bytes"), "{body}");
+
+    let req = texts(&[("avatar", "data:image/png,pixels"), ("attachment", "")]);
+    let err = extract::<Multipart<Encoded>>(req).await.unwrap_err();
+    assert_eq!(error_of(&err).1, "decode_error");
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Normalized {
+    #[multipart(trim)]
+    title: String,
+    #[multipart(trim, collapse_whitespace)]
+    summary: String,
+    #[multipart(trim, collapse_whitespace, lowercase, one_of = "draft,published")]
+    status: String,
+    #[multipart(trim)]
+    count: u32,
+    body: String,
+}
+
+#[actix_web::test]
+async fn normalizes_text_before_parsing_it() {
+    let req = texts(&[
+        ("title", "  Hello world \n"),
+        ("summary", " A\tshort \r\n  summary "),
+        ("status", "  PUBLISHED "),
+        ("count", " 3 "),
+        ("body", "  kept as sent "),
+    ]);
+    let form = extract::<Multipart<Normalized>>(req).await.unwrap();
+
+    assert_eq!(form.title, "Hello world");
+    assert_eq!(form.summary, "A short summary");
+    assert_eq!(form.status, "published");
+    assert_eq!(form.count, 3);
+    assert_eq!(form.body, "  kept as sent ");
+}
+
+#[actix_web::test]
+async fn checks_normalized_text() {
+    let req = texts(&[
+        ("title", "Hello"),
+        ("summary", "Summary"),
+        ("status", " Archived "),
+        ("count", "1"),
+        ("body", "Body"),
+    ]);
+    let err = extract::<Multipart<Normalized>>(req).await.unwrap_err();
+    assert_eq!(error_of(&err).1, "invalid_field");
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Document {
+    #[multipart(max_filename_length = 12)]
+    file: File,
+}
+
+fn document(filename: &str) -> TestRequest {
+    TestMultipartBuilder::new()
+        .file("file", filename, "text/plain", "Hello")
+        .set_on(TestRequest::post())
+}
+
+#[actix_web::test]
+async fn limits_filename_lengths() {
+    // Checked after the directories were stripped, in characters
+    let form = extract::<Multipart<Document>>(document("../../notes.txt"))
+        .await
+        .unwrap();
+    assert_eq!(form.file.name, "notes.txt");
+
+    let form = extract::<Multipart<Document>>(document("éééééééé.txt"))
+        .await
+        .unwrap();
+    assert_eq!(form.file.name, "éééééééé.txt");
+
+    let err = extract::<Multipart<Document>>(document("meeting-notes.txt"))
+        .await
+        .unwrap_err();
+    let (status, code, body) = common::error_body(&err).await;
+    assert_eq!((status.as_u16(), code.as_str()), (400, "invalid_field"));
+    assert!(body.contains("at most 12 characters"), "{body}");
+}
+
+const MAX_AVATAR_BYTES: usize = 8;
+
+mod limits {
+    pub const MAX_BIO_LENGTH: usize = 4;
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Limited {
+    #[multipart(max_size = MAX_AVATAR_BYTES)]
+    avatar: File,
+    #[multipart(max_size = 2 * MAX_AVATAR_BYTES)]
+    banner: File,
+    #[multipart(max_size = limits::MAX_BIO_LENGTH)]
+    bio: String,
+}
+
+fn limited(avatar: &str, banner: &str, bio: &str) -> TestRequest {
+    TestMultipartBuilder::new()
+        .file("avatar", "avatar.png", "image/png", avatar.to_string())
+        .file("banner", "banner.png", "image/png", banner.to_string())
+        .text("bio", bio)
+        .set_on(TestRequest::post())
+}
+
+#[actix_web::test]
+async fn limits_fields_with_constant_sizes() {
+    let req = limited("8 bytes!", "sixteen bytes!!!", "Hey!");
+    let form = extract::<Multipart<Limited>>(req).await.unwrap();
+    assert_eq!((form.avatar.size, form.banner.size), (8, 16));
+    assert_eq!(form.bio, "Hey!");
+
+    for (req, code) in [
+        (limited("nine byte", "", ""), "file_too_large"),
+        (limited("", "seventeen bytes!!", ""), "file_too_large"),
+        (limited("", "", "Hello"), "text_too_large"),
+    ] {
+        let err = extract::<Multipart<Limited>>(req).await.unwrap_err();
+        assert_eq!(error_of(&err).1, code);
+    }
+}
+
+#[cfg(feature = "encoding")]
+mod encoding {
+    use super::*;
+
+    #[actix_web::test]
+    async fn decodes_text_read_from_files() {
+        let req = TestMultipartBuilder::new()
+            .text("avatar", "pixels")
+            .file(
+                "notes",
+                "notes.txt",
+                "text/plain; charset=iso-8859-1",
+                Bytes::from_static(b"caf\xe9"),
+            )
+            .set_on(TestRequest::post());
+        let form = extract::<Multipart<Forced>>(req).await.unwrap();
+
+        assert_eq!(form.avatar.bytes, b"pixels");
+        assert_eq!(form.notes, "café");
+    }
+}
+
+#[cfg(feature = "pattern")]
+mod pattern {
+    use super::*;
+
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Report {
+        #[multipart(filename_pattern = r"^[\w.-]+\.pdf$")]
+        file: File,
+    }
+
+    fn report(filename: &str) -> TestRequest {
+        TestMultipartBuilder::new()
+            .file("file", filename, "application/pdf", "%PDF")
+            .set_on(TestRequest::post())
+    }
+
+    #[actix_web::test]
+    async fn restricts_filename_characters() {
+        let form = extract::<Multipart<Report>>(report("q3_report-v2.pdf"))
+            .await
+            .unwrap();
+        assert_eq!(form.file.name, "q3_report-v2.pdf");
+
+        for filename in ["q3 report.pdf", "q3.exe"] {
+            let err = extract::<Multipart<Report>>(report(filename))
+                .await
+                .unwrap_err();
+            assert_eq!(error_of(&err).1, "invalid_field", "{filename}");
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! Helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use actix_multipart_extract::MultipartConfig;
+use actix_web::{
+    dev::Payload,
+    error::PayloadError,
+    http::{header, StatusCode},
+    test::TestRequest,
+    web::Bytes,
+    FromRequest,
+};
+use futures::Stream;
+use std::pin::Pin;
+
+/// Boundary of bodies built with [`RawBody`].
+pub const BOUNDARY: &str = "test-boundary";
+
+/// Body of a multipart request with parts written as sent, for headers `TestMultipartBuilder`
+/// doesn't write.
+#[derive(Default)]
+pub struct RawBody {
+    bytes: Vec<u8>,
+}
+
+impl RawBody {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a part with its headers, one per line without the line endings.
+    pub fn part(mut self, headers: &[&str], body: impl AsRef<[u8]>) -> Self {
+        self.bytes
+            .extend_from_slice(format!("--{BOUNDARY}\r\n").as_bytes());
+        for header in headers {
+            self.bytes.extend_from_slice(header.as_bytes());
+            self.bytes.extend_from_slice(b"\r\n");
+        }
+        self.bytes.extend_from_slice(b"\r\n");
+        self.bytes.extend_from_slice(body.as_ref());
+        self.bytes.extend_from_slice(b"\r\n");
+        self
+    }
+
+    /// Add a text part.
+    pub fn text(self, name: &str, value: impl AsRef<[u8]>) -> Self {
+        self.part(
+            &[&format!("Content-Disposition: form-data; name=\"{name}\"")],
+            value,
+        )
+    }
+
+    /// Add a file part.
+    pub fn file(
+        self,
+        name: &str,
+        filename: &str,
+        content_type: &str,
+        bytes: impl AsRef<[u8]>,
+    ) -> Self {
+        self.part(
+            &[
+                &format!(
+                    "Content-Disposition: form-data; name=\"{name}\"; filename=\"{filename}\""
+                ),
+                &format!("Content-Type: {content_type}"),
+            ],
+            bytes,
+        )
+    }
+
+    /// Encode the body without its closing boundary, like a request which was cut off.
+    pub fn truncated(&self) -> Vec<u8> {
+        self.bytes.clone()
+    }
+
+    /// Encode the body.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.bytes.clone();
+        bytes.extend_from_slice(format!("--{BOUNDARY}--\r\n").as_bytes());
+        bytes
+    }
+
+    /// Create a `POST` request with the body as `multipart/form-data`.
+    pub fn request(&self) -> TestRequest {
+        self.request_as("multipart/form-data")
+    }
+
+    /// Create a `POST` request with the body and another multipart content type.
+    pub fn request_as(&self, content_type: &str) -> TestRequest {
+        TestRequest::post()
+            .insert_header((
+                header::CONTENT_TYPE,
+                format!("{content_type}; boundary={BOUNDARY}"),
+            ))
+            .set_payload(self.to_bytes())
+    }
+}
+
+/// Run the extractor `E` on a request.
+pub async fn extract<E: FromRequest>(req: TestRequest) -> Result<E, E::Error> {
+    let (req, mut payload) = req.to_http_parts();
+    E::from_request(&req, &mut payload).await
+}
+
+/// Run the extractor `E` on a request with a config.
+pub async fn extract_with<E: FromRequest>(
+    req: TestRequest,
+    config: MultipartConfig,
+) -> Result<E, E::Error> {
+    extract(req.app_data(config)).await
+}
+
+/// Run the extractor `E` on a request with its body received from `chunks` instead of its
+/// payload, like the body of a slow client.
+pub async fn extract_streamed<E: FromRequest>(
+    req: TestRequest,
+    chunks: impl Stream<Item = Result<Bytes, PayloadError>> + 'static,
+) -> Result<E, E::Error> {
+    let (req, _) = req.to_http_parts();
+    let chunks: Pin<Box<dyn Stream<Item = _>>> = Box::pin(chunks);
+    let mut payload = Payload::Stream { payload: chunks };
+    E::from_request(&req, &mut payload).await
+}
+
+/// Status and `X-Error-Code` of the response to an error.
+///
+/// The response of an extractor error can only be taken once, use [`error_body`] to also read
+/// the body.
+pub fn error_of(err: &actix_web::Error) -> (StatusCode, String) {
+    let res = err.as_response_error().error_response();
+    (res.status(), error_code(res.headers()))
+}
+
+/// Status, `X-Error-Code` and body of the response to an error.
+pub async fn error_body(err: &actix_web::Error) -> (StatusCode, String, String) {
+    let res = err.as_response_error().error_response();
+    let (status, code) = (res.status(), error_code(res.headers()));
+    let body = actix_web::body::to_bytes(res.into_body())
+        .await
+        .ok()
+        .unwrap();
+
+    (status, code, String::from_utf8(body.to_vec()).unwrap())
+}
+
+fn error_code(headers: &header::HeaderMap) -> String {
+    headers
+        .get("X-Error-Code")
+        .and_then(|code| code.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use actix_multipart_extract::{
+    test::TestMultipartBuilder, File, Multipart, MultipartConfig, MultipartForm,
+};
+use actix_web::{
+    http::StatusCode,
+    test::{call_service, init_service, TestRequest},
+    web, App, HttpResponse,
+};
+use serde::Deserialize;
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Upload {
+    file: File,
+}
+
+async fn upload(form: Multipart<Upload>) -> String {
+    form.file.name.clone()
+}
+
+/// Config responding to every error with `status`.
+fn responding(status: StatusCode) -> MultipartConfig {
+    MultipartConfig::default().set_error_handler(move |_| HttpResponse::new(status))
+}
+
+#[actix_web::test]
+async fn uses_the_config_nearest_to_the_route() {
+    let app = init_service(
+        App::new()
+            .app_data(responding(StatusCode::IM_A_TEAPOT))
+            .route("/", web::post().to(upload))
+            .service(
+                web::scope("/scope")
+                    .app_data(responding(StatusCode::CONFLICT))
+                    .route("", web::post().to(upload))
+                    .service(
+                        web::resource("/resource")
+                            .app_data(responding(StatusCode::GONE))
+                            .route(web::post().to(upload)),
+                    ),
+            ),
+    )
+    .await;
+
+    for (uri, status) in [
+        ("/", StatusCode::IM_A_TEAPOT),
+        ("/scope", StatusCode::CONFLICT),
+        ("/scope/resource", StatusCode::GONE),
+    ] {
+        // The file is missing
+        let req = TestMultipartBuilder::new()
+            .text("title", "Notes")
+            .set_on(TestRequest::post().uri(uri));
+        assert_eq!(
+            call_service(&app, req.to_request()).await.status(),
+            status,
+            "{uri}"
+        );
+
+        let req = TestMultipartBuilder::new()
+            .file("file", "a.txt", "text/plain", "A")
+            .set_on(TestRequest::post().uri(uri));
+        assert!(call_service(&app, req.to_request())
+            .await
+            .status()
+            .is_success());
+    }
+}
+
+#[actix_web::test]
+async fn uses_configs_wrapped_in_data() {
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(responding(StatusCode::IM_A_TEAPOT)))
+            .route("/", web::post().to(upload)),
+    )
+    .await;
+
+    let req = TestMultipartBuilder::new().set_on(TestRequest::post());
+    assert_eq!(
+        call_service(&app, req.to_request()).await.status(),
+        StatusCode::IM_A_TEAPOT
+    );
+}
+
+#[actix_web::test]
+async fn uses_the_default_config_without_one() {
+    let app = init_service(App::new().route("/", web::post().to(upload))).await;
+
+    let req = TestMultipartBuilder::new().set_on(TestRequest::post());
+    assert_eq!(
+        call_service(&app, req.to_request()).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+}
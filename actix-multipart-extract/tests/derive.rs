@@ -0,0 +1,253 @@
+mod common;
+
+use actix_multipart_extract::{
+    form::{ExampleValue, FormExample, MultipartForm as _},
+    test::TestMultipartBuilder,
+    File, Multipart, MultipartForm,
+};
+use actix_web::test::TestRequest;
+use common::{error_of, extract};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::json;
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Upload<T> {
+    #[multipart(max_size = 8)]
+    file: File,
+    meta: T,
+}
+
+/// Form wrapping a list of other values, with a bound on its parameter.
+#[derive(Deserialize, MultipartForm, Debug)]
+#[serde(bound = "T: DeserializeOwned")]
+struct Batch<T>
+where
+    T: DeserializeOwned + Default,
+{
+    items: Vec<T>,
+    #[serde(default)]
+    fallback: T,
+}
+
+#[actix_web::test]
+async fn extracts_generic_forms() {
+    let req = TestMultipartBuilder::new()
+        .file("file", "a.png", "image/png", "pixels")
+        .text("meta", "7")
+        .set_on(TestRequest::post());
+    let form = extract::<Multipart<Upload<u32>>>(req).await.unwrap();
+    assert_eq!((form.file.bytes.as_slice(), form.meta), (&b"pixels"[..], 7));
+
+    let req = TestMultipartBuilder::new()
+        .file("file", "a.png", "image/png", "pixels")
+        .set_on(TestRequest::post());
+    let form = extract::<Multipart<Upload<Option<String>>>>(req)
+        .await
+        .unwrap();
+    assert_eq!(form.meta, None);
+
+    let req = TestMultipartBuilder::new()
+        .text("items[]", "a")
+        .text("items[]", "b")
+        .set_on(TestRequest::post());
+    let form = extract::<Multipart<Batch<String>>>(req).await.unwrap();
+    assert_eq!(form.items, ["a", "b"]);
+    assert_eq!(form.fallback, "");
+}
+
+#[actix_web::test]
+async fn applies_the_options_of_generic_forms() {
+    let req = TestMultipartBuilder::new()
+        .file("file", "a.png", "image/png", "far too many pixels")
+        .text("meta", "7")
+        .set_on(TestRequest::post());
+    let err = extract::<Multipart<Upload<u32>>>(req).await.unwrap_err();
+    assert_eq!(error_of(&err).1, "file_too_large");
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[multipart(tag = "kind")]
+enum Post {
+    Image {
+        #[multipart(max_size = 8)]
+        file: File,
+        caption: Option<String>,
+    },
+    Link {
+        url: String,
+        #[multipart(max_size = 4)]
+        title: Option<String>,
+    },
+    Empty,
+}
+
+#[actix_web::test]
+async fn extracts_the_variant_named_by_the_tag() {
+    let req = TestMultipartBuilder::new()
+        .text("kind", "image")
+        .file("file", "a.png", "image/png", "pixels")
+        .text("caption", "A long caption")
+        // Parts of other variants are skipped
+        .text("url", "https://example.com")
+        .set_on(TestRequest::post());
+    match extract::<Multipart<Post>>(req).await.unwrap().into_inner() {
+        Post::Image { file, caption } => {
+            assert_eq!(file.bytes, b"pixels");
+            assert_eq!(caption.as_deref(), Some("A long caption"));
+        }
+        post => panic!("{post:?}"),
+    }
+
+    let req = TestMultipartBuilder::new()
+        .text("kind", "link")
+        .text("url", "https://example.com")
+        // Not limited by the other variant
+        .file("file", "a.png", "image/png", "far too many pixels")
+        .set_on(TestRequest::post());
+    match extract::<Multipart<Post>>(req).await.unwrap().into_inner() {
+        Post::Link { url, title } => {
+            assert_eq!(url, "https://example.com");
+            assert_eq!(title, None);
+        }
+        post => panic!("{post:?}"),
+    }
+
+    let req = TestMultipartBuilder::new()
+        .text("kind", "empty")
+        .set_on(TestRequest::post());
+    let post = extract::<Multipart<Post>>(req).await.unwrap().into_inner();
+    assert!(matches!(post, Post::Empty), "{post:?}");
+}
+
+#[actix_web::test]
+async fn limits_the_fields_of_the_variant() {
+    let req = TestMultipartBuilder::new()
+        .text("kind", "link")
+        .text("url", "https://example.com")
+        .text("title", "A long title")
+        .set_on(TestRequest::post());
+    let err = extract::<Multipart<Post>>(req).await.unwrap_err();
+    assert_eq!(error_of(&err).1, "text_too_large");
+}
+
+#[actix_web::test]
+async fn rejects_invalid_tags() {
+    let cases = [
+        (
+            TestMultipartBuilder::new().text("kind", "video"),
+            "has no variant named \"video\"",
+        ),
+        (
+            TestMultipartBuilder::new()
+                .text("url", "https://example.com")
+                .text("kind", "link"),
+            "must be sent before the fields of its variant",
+        ),
+        (
+            TestMultipartBuilder::new()
+                .text("kind", "empty")
+                .text("kind", "link"),
+            "must only be sent once",
+        ),
+    ];
+
+    for (form, message) in cases {
+        let err = extract::<Multipart<Post>>(form.set_on(TestRequest::post()))
+            .await
+            .unwrap_err();
+        let (status, code, body) = common::error_body(&err).await;
+        assert_eq!((status.as_u16(), code.as_str()), (400, "invalid_field"));
+        assert!(body.contains(message), "{body}");
+    }
+}
+
+/// Marker wrapping a shared form.
+#[derive(Deserialize, MultipartForm, Debug)]
+struct AdminUpload(Upload<String>);
+
+#[actix_web::test]
+async fn extracts_newtypes_like_the_form_they_wrap() {
+    let req = TestMultipartBuilder::new()
+        .file("file", "a.png", "image/png", "pixels")
+        .text("meta", "reviewed")
+        .set_on(TestRequest::post());
+    let AdminUpload(upload) = extract::<Multipart<AdminUpload>>(req)
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(upload.file.bytes, b"pixels");
+    assert_eq!(upload.meta, "reviewed");
+
+    let req = TestMultipartBuilder::new()
+        .file("file", "a.png", "image/png", "far too many pixels")
+        .text("meta", "reviewed")
+        .set_on(TestRequest::post());
+    let err = extract::<Multipart<AdminUpload>>(req).await.unwrap_err();
+    assert_eq!(error_of(&err).1, "file_too_large");
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Listing {
+    title: String,
+    #[multipart(rename = "price-cents")]
+    price: u32,
+    #[multipart(one_of = "draft,published")]
+    status: String,
+    #[multipart(default = 3)]
+    quantity: u8,
+    tags: Vec<String>,
+    photo: Option<File>,
+}
+
+/// Request sending the body of an example.
+fn example_request(example: &FormExample) -> TestRequest {
+    example
+        .parts
+        .iter()
+        .fold(TestMultipartBuilder::new(), |form, part| match part.value {
+            ExampleValue::Text(text) => form.text(part.name, text),
+            ExampleValue::File {
+                filename,
+                content_type,
+            } => form.file(part.name, filename, content_type, "bytes"),
+        })
+        .set_on(TestRequest::post())
+}
+
+#[actix_web::test]
+async fn describes_an_example_body() {
+    let example = Listing::example();
+    assert_eq!(
+        example.to_json(),
+        json!({
+            "title": "text",
+            "price-cents": "1",
+            "status": "draft",
+            "quantity": "3",
+            "tags": "text",
+            "photo": "file.bin",
+        })
+    );
+
+    // Examples are valid bodies of their form
+    let listing = extract::<Multipart<Listing>>(example_request(&example))
+        .await
+        .unwrap();
+    assert_eq!((listing.title.as_str(), listing.price), ("text", 1));
+    assert_eq!((listing.status.as_str(), listing.quantity), ("draft", 3));
+    assert_eq!(listing.tags, ["text"]);
+    assert_eq!(listing.photo.as_ref().unwrap().name, "file.bin");
+
+    let example = Post::example();
+    assert_eq!(
+        example.to_json(),
+        json!({ "kind": "image", "file": "file.bin", "caption": "text" })
+    );
+    let post = extract::<Multipart<Post>>(example_request(&example))
+        .await
+        .unwrap();
+    assert!(matches!(post.into_inner(), Post::Image { .. }));
+
+    assert_eq!(AdminUpload::example(), Upload::<String>::example());
+}
@@ -0,0 +1,207 @@
+mod common;
+
+use actix_multipart_extract::{File, Multipart, MultipartForm};
+use actix_web::http::StatusCode;
+use common::{error_of, extract, RawBody};
+use serde::Deserialize;
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Transfer {
+    name: String,
+    #[multipart(max_size = 4)]
+    file: Option<File>,
+}
+
+#[actix_web::test]
+async fn decodes_base64_and_quoted_printable_parts() {
+    let req = RawBody::new()
+        .part(
+            &[
+                "Content-Disposition: form-data; name=\"name\"",
+                "Content-Transfer-Encoding: quoted-printable",
+            ],
+            "caf=C3=A9",
+        )
+        .part(
+            &[
+                "Content-Disposition: form-data; name=\"file\"; filename=\"a.bin\"",
+                "Content-Transfer-Encoding: base64",
+            ],
+            "AAEC\r\nAw==",
+        )
+        .request();
+
+    let form = extract::<Multipart<Transfer>>(req).await.unwrap();
+    assert_eq!(form.name, "café");
+    assert_eq!(form.file.as_ref().unwrap().bytes, [0, 1, 2, 3]);
+}
+
+#[actix_web::test]
+async fn limits_the_decoded_size() {
+    let req = RawBody::new()
+        .text("name", "a")
+        .part(
+            &[
+                "Content-Disposition: form-data; name=\"file\"; filename=\"a.bin\"",
+                "Content-Transfer-Encoding: base64",
+            ],
+            "AAECAwQ=",
+        )
+        .request();
+
+    let err = extract::<Multipart<Transfer>>(req).await.unwrap_err();
+    assert_eq!(error_of(&err).1, "file_too_large");
+}
+
+#[actix_web::test]
+async fn rejects_invalid_base64() {
+    let req = RawBody::new()
+        .part(
+            &[
+                "Content-Disposition: form-data; name=\"name\"",
+                "Content-Transfer-Encoding: base64",
+            ],
+            "not base64!",
+        )
+        .request();
+
+    let err = extract::<Multipart<Transfer>>(req).await.unwrap_err();
+    assert_eq!(
+        error_of(&err),
+        (StatusCode::BAD_REQUEST, "decode_error".to_string())
+    );
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Text {
+    name: String,
+    bio: Option<String>,
+}
+
+#[cfg(feature = "encoding")]
+#[actix_web::test]
+async fn transcodes_text_by_charset() {
+    let req = RawBody::new()
+        .part(
+            &[
+                "Content-Disposition: form-data; name=\"name\"",
+                "Content-Type: text/plain; charset=iso-8859-1",
+            ],
+            b"caf\xe9",
+        )
+        .part(
+            &[
+                "Content-Disposition: form-data; name=\"bio\"",
+                "Content-Type: text/plain; charset=shift_jis",
+            ],
+            b"\x93\xfa\x96\x7b",
+        )
+        .request();
+
+    let form = extract::<Multipart<Text>>(req).await.unwrap();
+    assert_eq!(form.name, "café");
+    assert_eq!(form.bio.as_deref(), Some("日本"));
+}
+
+#[cfg(feature = "encoding")]
+#[actix_web::test]
+async fn rejects_unknown_charsets() {
+    let req = RawBody::new()
+        .part(
+            &[
+                "Content-Disposition: form-data; name=\"name\"",
+                "Content-Type: text/plain; charset=klingon",
+            ],
+            "a",
+        )
+        .request();
+
+    let err = extract::<Multipart<Text>>(req).await.unwrap_err();
+    assert_eq!(error_of(&err).1, "decode_error");
+}
+
+#[actix_web::test]
+async fn skips_text_which_is_not_utf8_without_a_charset() {
+    let req = RawBody::new()
+        .text("name", "a")
+        .text("bio", b"caf\xe9")
+        .request();
+
+    let form = extract::<Multipart<Text>>(req).await.unwrap();
+    assert_eq!(form.name, "a");
+    assert_eq!(form.bio, None);
+}
+
+#[cfg(feature = "compression")]
+mod compression {
+    use actix_multipart_extract::MultipartConfig;
+    use std::io::Write;
+
+    use super::*;
+    use common::extract_with;
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Compressed {
+        #[multipart(max_size = 1KB)]
+        small: Option<File>,
+        large: Option<File>,
+    }
+
+    fn gzip_file(name: &str, bytes: &[u8]) -> RawBody {
+        RawBody::new().part(
+            &[
+                &format!("Content-Disposition: form-data; name=\"{name}\"; filename=\"a.txt\""),
+                "Content-Encoding: gzip",
+            ],
+            gzip(bytes),
+        )
+    }
+
+    #[actix_web::test]
+    async fn decompresses_parts() {
+        let req = gzip_file("large", b"hello world").request();
+
+        let form = extract::<Multipart<Compressed>>(req).await.unwrap();
+        assert_eq!(form.large.as_ref().unwrap().bytes, b"hello world");
+        assert!(form.small.is_none());
+    }
+
+    #[actix_web::test]
+    async fn limits_decompressed_parts_to_the_max_size_of_their_field() {
+        let req = gzip_file("small", &[0; 4096]).request();
+
+        let err = extract::<Multipart<Compressed>>(req).await.unwrap_err();
+        assert_eq!(error_of(&err).1, "decompressed_too_large");
+    }
+
+    #[actix_web::test]
+    async fn keeps_the_max_size_of_fields_below_the_max_decompressed_size() {
+        let config = MultipartConfig::default().set_max_decompressed_size(16 * 1024 * 1024);
+        let req = gzip_file("small", &[0; 4096]).request();
+
+        let err = extract_with::<Multipart<Compressed>>(req, config)
+            .await
+            .unwrap_err();
+        assert_eq!(error_of(&err).1, "decompressed_too_large");
+    }
+
+    #[actix_web::test]
+    async fn limits_decompressed_parts_of_unbounded_fields() {
+        let req = gzip_file("large", &[0; 11 * 1024 * 1024]).request();
+        let err = extract::<Multipart<Compressed>>(req).await.unwrap_err();
+        assert_eq!(error_of(&err).1, "decompressed_too_large");
+
+        let config = MultipartConfig::default().set_max_decompressed_size(16 * 1024 * 1024);
+        let req = gzip_file("large", &[0; 11 * 1024 * 1024]).request();
+        let form = extract_with::<Multipart<Compressed>>(req, config)
+            .await
+            .unwrap();
+        assert_eq!(form.large.as_ref().unwrap().bytes.len(), 11 * 1024 * 1024);
+    }
+}
@@ -0,0 +1,422 @@
+mod common;
+
+use actix_multipart_extract::{
+    test::TestMultipartBuilder, ErrorFormat, File, FileMeta, FileSink, FileStorage, Limits,
+    Multipart, MultipartConfig, MultipartError, MultipartForm,
+};
+use actix_web::{
+    http::{header, StatusCode},
+    test::TestRequest,
+    FromRequest, HttpResponse, ResponseError,
+};
+use common::{error_body, extract_with};
+use serde::Deserialize;
+use std::{
+    error::Error,
+    fmt::Debug,
+    io,
+    sync::{Arc, Mutex},
+};
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Upload {
+    #[multipart(max_size = 4)]
+    title: String,
+    file: File,
+}
+
+fn upload(title: &str) -> TestRequest {
+    TestMultipartBuilder::new()
+        .text("title", title)
+        .file("file", "a.txt", "text/plain", "A")
+        .set_on(TestRequest::post())
+}
+
+/// Config answering in French to requests accepting it, for text too long.
+fn localized() -> MultipartConfig {
+    MultipartConfig::default().set_error_message(|err, req| {
+        let french = req
+            .headers()
+            .get(header::ACCEPT_LANGUAGE)
+            .is_some_and(|language| language.as_bytes().starts_with(b"fr"));
+
+        match err {
+            MultipartError::TextFieldTooLarge { field, .. } if french => {
+                Some(format!("le champ {field} est trop long"))
+            }
+            _ => None,
+        }
+    })
+}
+
+#[actix_web::test]
+async fn localizes_the_messages_of_default_responses() {
+    let req = upload("Too long").insert_header((header::ACCEPT_LANGUAGE, "fr-FR"));
+    let err = extract_with::<Multipart<Upload>>(req, localized())
+        .await
+        .unwrap_err();
+    let (status, code, body) = error_body(&err).await;
+    assert_eq!((status.as_u16(), code.as_str()), (400, "text_too_large"));
+    assert_eq!(body, "le champ title est trop long");
+
+    let form = extract_with::<Multipart<Upload>>(upload("Ok"), localized())
+        .await
+        .unwrap();
+    assert_eq!((form.title.as_str(), form.file.size), ("Ok", 1));
+
+    // Other requests keep the default message
+    let err = extract_with::<Multipart<Upload>>(upload("Too long"), localized())
+        .await
+        .unwrap_err();
+    let (_, _, body) = error_body(&err).await;
+    assert!(body.contains("title"), "{body}");
+    assert_ne!(body, "le champ title est trop long");
+}
+
+/// Error a request was rejected with, kept by the error handler set on `config`.
+async fn captured<E: FromRequest + Debug>(
+    req: TestRequest,
+    config: MultipartConfig,
+) -> MultipartError {
+    let error = Arc::new(Mutex::new(None));
+    let kept = error.clone();
+    let config = config.set_error_handler(move |err| {
+        *kept.lock().unwrap() = Some(err);
+        HttpResponse::BadRequest().finish()
+    });
+
+    extract_with::<E>(req, config).await.unwrap_err();
+    let error = error.lock().unwrap().take().unwrap();
+    error
+}
+
+/// Builds a request to send it more than once, test requests can't be cloned.
+type MakeRequest = fn() -> TestRequest;
+
+#[actix_web::test]
+async fn has_the_code_of_the_error_in_default_responses() {
+    let strict = || MultipartConfig::default().set_deny_unknown_fields(true);
+    let cases: [(MakeRequest, &str); 4] = [
+        (
+            || {
+                TestRequest::post()
+                    .insert_header((header::CONTENT_TYPE, "application/json"))
+                    .set_payload("{}")
+            },
+            "not_multipart",
+        ),
+        (|| upload("Too long"), "text_too_large"),
+        (
+            || {
+                TestMultipartBuilder::new()
+                    .text("title", "Ok")
+                    .set_on(TestRequest::post())
+            },
+            "missing_field",
+        ),
+        (
+            || {
+                TestMultipartBuilder::new()
+                    .text("title", "Ok")
+                    .text("extra", "?")
+                    .set_on(TestRequest::post())
+            },
+            "unknown_field",
+        ),
+    ];
+
+    for (req, code) in cases {
+        let err = extract_with::<Multipart<Upload>>(req(), strict())
+            .await
+            .unwrap_err();
+        assert_eq!(common::error_of(&err).1, code);
+
+        let err = captured::<Multipart<Upload>>(req(), strict()).await;
+        assert_eq!(err.code(), code);
+    }
+}
+
+#[actix_web::test]
+async fn names_unknown_fields() {
+    let req = TestMultipartBuilder::new()
+        .text("title", "Ok")
+        .text("thumbnail", "?")
+        .set_on(TestRequest::post());
+    let config = MultipartConfig::default().set_deny_unknown_fields(true);
+
+    match captured::<Multipart<Upload>>(req, config).await {
+        MultipartError::UnknownField { field } => assert_eq!(field, "thumbnail"),
+        err => panic!("{err:?}"),
+    }
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Attachments {
+    files: Vec<File>,
+    notes: Vec<String>,
+}
+
+fn attachments(files: usize, notes: usize) -> TestRequest {
+    let form = (0..files).fold(TestMultipartBuilder::new(), |form, i| {
+        form.file("files[]", format!("{i}.txt"), "text/plain", "A")
+    });
+    (0..notes)
+        .fold(form, |form, _| form.text("notes[]", "note"))
+        .set_on(TestRequest::post())
+}
+
+fn counted() -> MultipartConfig {
+    MultipartConfig::default().set_limits_for(|_| Limits {
+        max_files: Some(2),
+        max_fields: Some(4),
+        ..Default::default()
+    })
+}
+
+#[actix_web::test]
+async fn reports_the_limit_and_count_of_parts() {
+    let form = extract_with::<Multipart<Attachments>>(attachments(2, 2), counted())
+        .await
+        .unwrap();
+    assert_eq!((form.files.len(), form.notes.len()), (2, 2));
+
+    match captured::<Multipart<Attachments>>(attachments(3, 0), counted()).await {
+        MultipartError::TooManyFiles { limit, count } => assert_eq!((limit, count), (2, 3)),
+        err => panic!("{err:?}"),
+    }
+
+    match captured::<Multipart<Attachments>>(attachments(1, 4), counted()).await {
+        MultipartError::TooManyFields { limit, count } => assert_eq!((limit, count), (4, 5)),
+        err => panic!("{err:?}"),
+    }
+}
+
+#[actix_web::test]
+async fn tells_text_too_long_from_files_too_large() {
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Profile {
+        #[multipart(max_size = 4)]
+        bio: String,
+        #[multipart(max_size = 4)]
+        avatar: File,
+    }
+
+    let profile = |bio: &str, avatar: &str| {
+        TestMultipartBuilder::new()
+            .text("bio", bio)
+            .file("avatar", "a.png", "image/png", avatar.to_string())
+            .set_on(TestRequest::post())
+    };
+    let config = MultipartConfig::default;
+
+    let form = extract_with::<Multipart<Profile>>(profile("Hey", "png"), config())
+        .await
+        .unwrap();
+    assert_eq!((form.bio.as_str(), form.avatar.size), ("Hey", 3));
+
+    match captured::<Multipart<Profile>>(profile("Hello", "png"), config()).await {
+        MultipartError::TextFieldTooLarge { field, limit } => {
+            assert_eq!((field.as_str(), limit), ("bio", 4))
+        }
+        err => panic!("{err:?}"),
+    }
+
+    match captured::<Multipart<Profile>>(profile("Hey", "large"), config()).await {
+        MultipartError::FileSizeError { field, limit } => {
+            assert_eq!((field.as_str(), limit), ("avatar", 4))
+        }
+        err => panic!("{err:?}"),
+    }
+}
+
+#[actix_web::test]
+async fn overrides_the_status_of_default_responses() {
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Post {
+        #[multipart(max_size = 8, one_of = "public,private")]
+        visibility: String,
+    }
+
+    let post = |visibility: &str| {
+        TestMultipartBuilder::new()
+            .text("visibility", visibility)
+            .set_on(TestRequest::post())
+    };
+    let config = || {
+        MultipartConfig::default().set_status_for(|err| match err {
+            MultipartError::FieldValidationError { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            err => err.status_code(),
+        })
+    };
+
+    let form = extract_with::<Multipart<Post>>(post("public"), config())
+        .await
+        .unwrap();
+    assert_eq!(form.visibility, "public");
+
+    let err = extract_with::<Multipart<Post>>(post("secret"), config())
+        .await
+        .unwrap_err();
+    let (status, code, body) = error_body(&err).await;
+    assert_eq!(
+        (status, code.as_str()),
+        (StatusCode::UNPROCESSABLE_ENTITY, "invalid_field")
+    );
+    assert!(body.contains("visibility"), "{body}");
+
+    let err = extract_with::<Multipart<Post>>(post("unlisted!"), config())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        common::error_of(&err),
+        (StatusCode::BAD_REQUEST, "text_too_large".to_string())
+    );
+}
+
+#[actix_web::test]
+async fn falls_through_partial_error_handlers() {
+    let partial = || {
+        MultipartConfig::default().set_partial_error_handler(|err| match err {
+            MultipartError::TextFieldTooLarge { field, .. } => {
+                Ok(HttpResponse::PayloadTooLarge().body(field))
+            }
+            err => Err(err),
+        })
+    };
+    let missing_file = || {
+        TestMultipartBuilder::new()
+            .text("title", "Ok")
+            .set_on(TestRequest::post())
+    };
+
+    let err = extract_with::<Multipart<Upload>>(upload("Too long"), partial())
+        .await
+        .unwrap_err();
+    let (status, _, body) = error_body(&err).await;
+    assert_eq!(
+        (status, body.as_str()),
+        (StatusCode::PAYLOAD_TOO_LARGE, "title")
+    );
+
+    // Other errors get the default response without an error handler
+    let err = extract_with::<Multipart<Upload>>(missing_file(), partial())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        common::error_of(&err),
+        (StatusCode::BAD_REQUEST, "missing_field".to_string())
+    );
+
+    // Or go to the error handler
+    let config = partial().set_error_handler(|err| HttpResponse::Conflict().body(err.code()));
+    let err = extract_with::<Multipart<Upload>>(missing_file(), config)
+        .await
+        .unwrap_err();
+    let (status, _, body) = error_body(&err).await;
+    assert_eq!(
+        (status, body.as_str()),
+        (StatusCode::CONFLICT, "missing_field")
+    );
+}
+
+/// Storage failing to store every file.
+struct FailingStorage;
+
+impl FileStorage for FailingStorage {
+    fn sink(
+        &self,
+        _: &str,
+        _: &FileMeta,
+        _: &MultipartConfig,
+    ) -> io::Result<Option<Box<dyn FileSink>>> {
+        Err(io::Error::new(io::ErrorKind::StorageFull, "disk full"))
+    }
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+#[allow(dead_code)]
+struct Counted {
+    count: u32,
+}
+
+#[actix_web::test]
+async fn chains_the_causes_of_errors() {
+    let req = TestMultipartBuilder::new()
+        .text("count", "many")
+        .set_on(TestRequest::post());
+    let err = captured::<Multipart<Counted>>(req, MultipartConfig::default()).await;
+    let source = err.source().unwrap();
+    assert!(source.is::<serde_json::Error>(), "{source:?}");
+    assert!(source.to_string().contains("many"), "{source}");
+
+    let config = MultipartConfig::default().set_file_storage(FailingStorage);
+    let err = captured::<Multipart<Upload>>(upload("Ok"), config).await;
+    let source = err.source().unwrap().downcast_ref::<io::Error>().unwrap();
+    assert_eq!(source.kind(), io::ErrorKind::StorageFull);
+
+    // Errors without a cause have no source
+    let err = captured::<Multipart<Upload>>(upload("Too long"), MultipartConfig::default()).await;
+    assert!(err.source().is_none());
+}
+
+#[actix_web::test]
+async fn names_missing_fields() {
+    let req = TestMultipartBuilder::new()
+        .text("title", "Ok")
+        .set_on(TestRequest::post());
+    let err = captured::<Multipart<Upload>>(req, MultipartConfig::default()).await;
+    assert!(
+        matches!(&err, MultipartError::MissingField { field } if field == "file"),
+        "{err:?}"
+    );
+    assert_eq!(err.field(), Some("file"));
+}
+
+#[actix_web::test]
+async fn formats_default_responses_as_json() {
+    let json = || MultipartConfig::default().set_error_format(ErrorFormat::Json);
+
+    let err = extract_with::<Multipart<Upload>>(upload("Too long"), json())
+        .await
+        .unwrap_err();
+    let res = err.as_response_error().error_response();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        res.headers().get(header::CONTENT_TYPE).unwrap(),
+        "application/json"
+    );
+    let body = actix_web::body::to_bytes(res.into_body()).await.unwrap();
+    assert_eq!(
+        serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+        serde_json::json!({
+            "code": "text_too_large",
+            "message": "Text of field (title) was too large (max size: 4 bytes)",
+            "field": "title",
+        })
+    );
+
+    // Errors about the whole request have no field
+    let req = TestRequest::post().insert_header((header::CONTENT_TYPE, "text/plain"));
+    let err = extract_with::<Multipart<Upload>>(req, json())
+        .await
+        .unwrap_err();
+    let (_, _, body) = error_body(&err).await;
+    let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(
+        (&body["code"], &body["field"]),
+        (
+            &serde_json::json!("not_multipart"),
+            &serde_json::Value::Null
+        )
+    );
+
+    // Plain text by default
+    let err = extract_with::<Multipart<Upload>>(upload("Too long"), MultipartConfig::default())
+        .await
+        .unwrap_err();
+    let (_, _, body) = error_body(&err).await;
+    assert_eq!(
+        body,
+        "Text of field (title) was too large (max size: 4 bytes)"
+    );
+}
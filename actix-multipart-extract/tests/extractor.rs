@@ -0,0 +1,891 @@
+mod common;
+
+use actix_multipart_extract::{
+    multipart_guard, test::TestMultipartBuilder, File, FormOrMultipart, Limits, Multipart,
+    MultipartConfig, MultipartForm, MultipartOrJson, MultipartWithRest, RawMultipart,
+    TextWhitespace, UnnamedParts,
+};
+use actix_web::{
+    http::StatusCode,
+    test::{call_service, init_service, read_body, TestRequest},
+    web::{self, Bytes},
+    App, HttpResponse,
+};
+use common::RawBody;
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+#[derive(Deserialize, MultipartForm, Debug, Clone, PartialEq)]
+struct Upload {
+    title: String,
+    file: File,
+}
+
+#[derive(Deserialize, MultipartForm)]
+struct Attachment {
+    file: File,
+}
+
+fn upload() -> TestRequest {
+    TestMultipartBuilder::new()
+        .text("title", "Notes")
+        .file("file", "notes.txt", "text/plain", "Hello")
+        .set_on(TestRequest::post())
+}
+
+#[actix_web::test]
+async fn moves_the_form_out() {
+    let mut form = common::extract::<Multipart<Upload>>(upload())
+        .await
+        .unwrap();
+
+    form.as_mut().title.push('!');
+    assert_eq!(form.as_ref().title, "Notes!");
+
+    let Upload { title, file } = form.into_inner();
+    assert_eq!(title, "Notes!");
+    assert_eq!(file.bytes, b"Hello");
+}
+
+#[actix_web::test]
+async fn passes_traits_through() {
+    let form = common::extract::<Multipart<Upload>>(upload())
+        .await
+        .unwrap();
+    let other = common::extract::<Multipart<Upload>>(upload())
+        .await
+        .unwrap();
+
+    assert_eq!(form, other);
+    assert_eq!(form.clone(), form);
+    assert!(format!("{form:?}").contains("Notes"));
+}
+
+#[actix_web::test]
+async fn serializes_as_the_form() {
+    #[derive(Deserialize, Serialize, MultipartForm, Debug)]
+    struct Note {
+        title: String,
+    }
+
+    let req = TestMultipartBuilder::new()
+        .text("title", "Notes")
+        .set_on(TestRequest::post());
+    let form = common::extract::<Multipart<Note>>(req).await.unwrap();
+
+    let json = serde_json::to_value(&form).unwrap();
+    assert_eq!(json, serde_json::json!({ "title": "Notes" }));
+}
+
+#[actix_web::test]
+async fn rejects_requests_which_arent_multipart() {
+    let req = TestRequest::post()
+        .insert_header(("Content-Type", "application/json"))
+        .set_payload(r#"{"title": "Notes"}"#);
+    let err = common::extract::<Multipart<Upload>>(req).await.unwrap_err();
+
+    let (status, code, body) = common::error_body(&err).await;
+    assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert_eq!(code, "not_multipart");
+    assert!(body.contains("multipart/form-data"), "{body}");
+    assert!(body.contains("application/json"), "{body}");
+
+    let err = common::extract::<Multipart<Upload>>(TestRequest::post())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        common::error_of(&err),
+        (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "not_multipart".to_string()
+        )
+    );
+}
+
+#[actix_web::test]
+async fn accepts_forms_sent_as_json() {
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Note {
+        title: String,
+        file: Option<File>,
+    }
+
+    let form = common::extract::<MultipartOrJson<Note>>(upload())
+        .await
+        .unwrap();
+    assert!(matches!(form, MultipartOrJson::Multipart(_)));
+    assert_eq!(form.title, "Notes");
+    assert_eq!(form.into_inner().file.unwrap().bytes, b"Hello");
+
+    let json = || {
+        TestRequest::post()
+            .insert_header(("Content-Type", "application/vnd.api+json"))
+            .set_payload(r#"{"title": "Notes"}"#)
+    };
+    let form = common::extract::<MultipartOrJson<Note>>(json())
+        .await
+        .unwrap();
+    assert!(matches!(form, MultipartOrJson::Json(_)));
+    assert_eq!((form.title.as_str(), form.file.is_none()), ("Notes", true));
+
+    let config = MultipartConfig::default()
+        .set_limits_for(|_| Limits {
+            total: Some(8),
+            ..Default::default()
+        })
+        .set_error_handler(|err| HttpResponse::Conflict().body(err.code()));
+    let err = common::extract_with::<MultipartOrJson<Note>>(json(), config)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        common::error_body(&err).await,
+        (
+            StatusCode::CONFLICT,
+            String::new(),
+            "request_too_large".to_string()
+        )
+    );
+
+    let req = TestRequest::post()
+        .insert_header(("Content-Type", "application/json"))
+        .set_payload(r#"{"name": "Notes"}"#);
+    let err = common::extract::<MultipartOrJson<Note>>(req)
+        .await
+        .unwrap_err();
+    assert_eq!(common::error_of(&err).1, "missing_field");
+}
+
+#[actix_web::test]
+async fn accepts_urlencoded_forms() {
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Comment {
+        #[serde(rename = "body")]
+        text: String,
+        #[multipart(default = 1)]
+        rating: u8,
+        tags: Vec<String>,
+        file: Option<File>,
+    }
+
+    let urlencoded = |body: &'static str| {
+        TestRequest::post()
+            .insert_header(("Content-Type", "application/x-www-form-urlencoded"))
+            .set_payload(body)
+    };
+    let form = common::extract::<FormOrMultipart<Comment>>(urlencoded(
+        "body=Nice+post%21&tags[]=a&tags=b&unknown=1",
+    ))
+    .await
+    .unwrap();
+    assert!(matches!(form, FormOrMultipart::Form(_)));
+    assert_eq!(form.text, "Nice post!");
+    assert_eq!(
+        (form.rating, form.tags.clone()),
+        (1, vec!["a".to_string(), "b".to_string()])
+    );
+    assert!(form.file.is_none());
+
+    let req = TestMultipartBuilder::new()
+        .text("body", "Nice post!")
+        .text("tags", "a")
+        .file("file", "notes.txt", "text/plain", "Hello")
+        .set_on(TestRequest::post());
+    let form = common::extract::<FormOrMultipart<Comment>>(req)
+        .await
+        .unwrap();
+    assert!(matches!(form, FormOrMultipart::Multipart(_)));
+    assert_eq!(form.into_inner().file.unwrap().bytes, b"Hello");
+
+    let config = MultipartConfig::default().set_limits_for(|_| Limits {
+        max_fields: Some(2),
+        ..Default::default()
+    });
+    let err = common::extract_with::<FormOrMultipart<Comment>>(
+        urlencoded("body=a&tags=b&tags=c"),
+        config,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(common::error_of(&err).1, "too_many_fields");
+}
+
+#[actix_web::test]
+async fn routes_multipart_requests_with_the_guard() {
+    let app = init_service(
+        App::new().service(
+            web::resource("/")
+                .route(
+                    web::post()
+                        .guard(multipart_guard())
+                        .to(|form: Multipart<Upload>| async move { form.into_inner().title }),
+                )
+                .route(web::post().to(|| async { "other" })),
+        ),
+    )
+    .await;
+
+    let res = call_service(&app, upload().to_request()).await;
+    assert_eq!(read_body(res).await, "Notes");
+
+    for content_type in [
+        "application/json",
+        "multipart/form-data",
+        "multipart/form-data; boundary=\"\"",
+        "multipart/mixed; boundary=b",
+    ] {
+        let req = TestRequest::post().insert_header(("Content-Type", content_type));
+        let res = call_service(&app, req.to_request()).await;
+        assert_eq!(read_body(res).await, "other", "{content_type}");
+    }
+}
+
+#[actix_web::test]
+async fn rejects_truncated_streams() {
+    let body = RawBody::new().text("title", "Notes").part(
+        &[
+            r#"Content-Disposition: form-data; name="file"; filename="notes.txt""#,
+            "Content-Type: text/plain",
+        ],
+        "Hello",
+    );
+
+    for bytes in [body.truncated(), body.to_bytes()[..60].to_vec()] {
+        let req = body.request().set_payload(bytes);
+        let err = common::extract::<Multipart<Upload>>(req).await.unwrap_err();
+        assert_eq!(
+            common::error_of(&err),
+            (StatusCode::BAD_REQUEST, "incomplete".to_string())
+        );
+    }
+
+    let form = common::extract::<Multipart<Upload>>(body.request())
+        .await
+        .unwrap();
+    assert_eq!(form.file.bytes, b"Hello");
+}
+
+#[actix_web::test]
+async fn rejects_malformed_streams() {
+    let body = RawBody::new()
+        .text("title", "Notes")
+        .part(&["not a header"], "Ignored")
+        .part(
+            &[r#"Content-Disposition: form-data; name="file"; filename="notes.txt""#],
+            "Hello",
+        );
+
+    let err = common::extract::<Multipart<Upload>>(body.request())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        common::error_of(&err),
+        (StatusCode::BAD_REQUEST, "malformed".to_string())
+    );
+
+    let err = common::extract::<RawMultipart>(body.request())
+        .await
+        .unwrap_err();
+    assert_eq!(common::error_of(&err).1, "malformed");
+}
+
+#[actix_web::test]
+async fn applies_the_policy_to_unnamed_parts() {
+    let body = RawBody::new()
+        .text("title", "Notes")
+        .part(&["Content-Disposition: form-data"], "anonymous")
+        .file("file", "notes.txt", "text/plain", "Hello");
+    let policy = |unnamed_parts| MultipartConfig::default().set_unnamed_parts(unnamed_parts);
+
+    // actix-multipart can't read parts without a name
+    if cfg!(not(feature = "multer")) {
+        let err = common::extract::<Multipart<Upload>>(body.request())
+            .await
+            .unwrap_err();
+        assert_eq!(common::error_of(&err).1, "malformed");
+        return;
+    }
+
+    let form = common::extract::<Multipart<Upload>>(body.request())
+        .await
+        .unwrap();
+    assert_eq!(form.file.bytes, b"Hello");
+
+    let err =
+        common::extract_with::<Multipart<Upload>>(body.request(), policy(UnnamedParts::Reject))
+            .await
+            .unwrap_err();
+    assert_eq!(common::error_of(&err).1, "unnamed_part");
+
+    let form = common::extract_with::<MultipartWithRest<Upload>>(
+        body.request(),
+        policy(UnnamedParts::Collect),
+    )
+    .await
+    .unwrap();
+    assert_eq!(form.title, "Notes");
+    assert_eq!(form.rest().len(), 1);
+    assert_eq!(
+        (form.rest()[0].name.as_deref(), &form.rest()[0].bytes[..]),
+        (None, &b"anonymous"[..])
+    );
+}
+
+#[actix_web::test]
+async fn throttles_reading_the_request() {
+    let body = RawBody::new().part(
+        &[r#"Content-Disposition: form-data; name="file"; filename="a.bin""#],
+        vec![0; 200],
+    );
+    let config = MultipartConfig::default().set_max_bytes_per_second(1000);
+
+    // Chunks are read right away until the rate goes over the limit
+    let chunks: Vec<_> = body
+        .to_bytes()
+        .chunks(50)
+        .map(Bytes::copy_from_slice)
+        .collect();
+    let throttled = body.to_bytes().len() - chunks.last().unwrap().len();
+    let chunks = futures::stream::iter(chunks.into_iter().map(Ok));
+
+    let started = Instant::now();
+    let req = body.request().app_data(config);
+    let form = common::extract_streamed::<Multipart<Attachment>>(req, chunks)
+        .await
+        .unwrap();
+
+    assert_eq!(form.file.size, 200);
+    assert!(started.elapsed() >= Duration::from_secs_f64(throttled as f64 / 1000.0));
+}
+
+#[actix_web::test]
+async fn keeps_the_parts_which_arent_fields() {
+    let req = || {
+        TestMultipartBuilder::new()
+            .text("note", "Forwarded")
+            .text("title", "Notes")
+            .file("file", "notes.txt", "text/plain", "Hello")
+            .file(
+                "attachment",
+                "../extra.bin",
+                "application/octet-stream",
+                "Extra",
+            )
+            .set_on(TestRequest::post())
+    };
+
+    for config in [
+        MultipartConfig::default(),
+        MultipartConfig::default().set_deny_unknown_fields(true),
+    ] {
+        let form = common::extract_with::<MultipartWithRest<Upload>>(req(), config)
+            .await
+            .unwrap();
+        assert_eq!(form.ordered_fields(), ["title", "file"]);
+
+        let (form, rest) = form.into_parts();
+        assert_eq!(
+            (form.title.as_str(), form.file.bytes.as_slice()),
+            ("Notes", &b"Hello"[..])
+        );
+
+        let parts: Vec<_> = rest
+            .iter()
+            .map(|part| {
+                (
+                    part.name.as_deref(),
+                    part.filename.as_deref(),
+                    &part.bytes[..],
+                )
+            })
+            .collect();
+        assert_eq!(
+            parts,
+            [
+                (Some("note"), None, &b"Forwarded"[..]),
+                (Some("attachment"), Some("extra.bin"), &b"Extra"[..]),
+            ]
+        );
+    }
+}
+
+#[actix_web::test]
+async fn reports_progress() {
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let recorded = calls.clone();
+    let config = MultipartConfig::default().set_progress_handler(move |field, read, total| {
+        recorded
+            .lock()
+            .unwrap()
+            .push((field.to_string(), read, total));
+    });
+
+    let body = RawBody::new().text("title", "Notes").part(
+        &[r#"Content-Disposition: form-data; name="file"; filename="notes.txt""#],
+        "Hello, world!",
+    );
+    let len = body.to_bytes().len();
+    common::extract_with::<Multipart<Upload>>(body.request(), config)
+        .await
+        .unwrap();
+
+    let calls = calls.lock().unwrap();
+    assert!(calls.iter().all(|(_, _, total)| *total == Some(len)));
+    assert!(calls.windows(2).all(|calls| calls[0].1 <= calls[1].1));
+    assert!(calls.last().unwrap().1 <= len);
+
+    let mut fields: Vec<_> = calls.iter().map(|(field, ..)| field.as_str()).collect();
+    fields.dedup();
+    assert_eq!(fields, ["title", "file"]);
+}
+
+#[actix_web::test]
+async fn bounds_the_bytes_read_ahead() {
+    let body = RawBody::new().part(
+        &[r#"Content-Disposition: form-data; name="file"; filename="a.bin""#],
+        vec![7; 200],
+    );
+
+    for (config, min_reads) in [
+        (MultipartConfig::default(), 1),
+        (MultipartConfig::default().set_read_chunk_size(16), 1),
+        (
+            MultipartConfig::default()
+                .set_read_chunk_size(16)
+                .set_max_read_ahead(32),
+            5,
+        ),
+    ] {
+        let reads = Arc::new(Mutex::new(0));
+        let counted = reads.clone();
+        let config = config.set_progress_handler(move |_, _, _| *counted.lock().unwrap() += 1);
+
+        // Received at once, the parser reads it in steps of the read ahead
+        let req = body.request().app_data(config);
+        let form = common::extract::<Multipart<Attachment>>(req).await.unwrap();
+
+        assert_eq!(form.file.bytes, vec![7; 200]);
+        assert!(*reads.lock().unwrap() >= min_reads, "{reads:?}");
+    }
+}
+
+#[actix_web::test]
+async fn reuses_buffers_between_parts() {
+    let long = "a".repeat(10_000);
+    for title in [long.as_str(), "Notes", &long[..20], "b"] {
+        let req = TestMultipartBuilder::new()
+            .text("title", title)
+            .file("file", "notes.txt", "text/plain", title.to_string())
+            .set_on(TestRequest::post());
+        let form = common::extract::<Multipart<Upload>>(req).await.unwrap();
+
+        assert_eq!(form.title, title);
+        assert_eq!(form.file.bytes, title.as_bytes());
+    }
+}
+
+#[actix_web::test]
+async fn doesnt_preallocate_the_claimed_length() {
+    let req = upload().insert_header(("Content-Length", "1000000000000"));
+    let form = common::extract::<Multipart<Upload>>(req).await.unwrap();
+
+    assert_eq!(form.title, "Notes");
+    assert_eq!(form.file.bytes, b"Hello");
+}
+
+#[actix_web::test]
+async fn collects_repeated_parts_of_lists() {
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Filters {
+        colors: Vec<String>,
+        sizes: Vec<u32>,
+        photos: Vec<File>,
+        sort: String,
+    }
+
+    // Like a `<select multiple>` and a group of checkboxes
+    let req = TestMultipartBuilder::new()
+        .text("colors", "red")
+        .text("sizes", "38")
+        .text("colors", "blue")
+        .text("sizes", "40")
+        .file("photos", "a.png", "image/png", "a")
+        .file("photos", "b.png", "image/png", "b")
+        .text("sort", "price")
+        .set_on(TestRequest::post());
+    let form = common::extract::<Multipart<Filters>>(req).await.unwrap();
+
+    assert_eq!(form.colors, ["red", "blue"]);
+    assert_eq!(form.sizes, [38, 40]);
+    let photos: Vec<_> = form
+        .photos
+        .iter()
+        .map(|photo| photo.name.as_str())
+        .collect();
+    assert_eq!(photos, ["a.png", "b.png"]);
+    assert_eq!(form.sort, "price");
+
+    // A single part is a list of one value
+    let req = TestMultipartBuilder::new()
+        .text("colors", "red")
+        .text("sizes", "38")
+        .file("photos", "a.png", "image/png", "a")
+        .text("sort", "price")
+        .set_on(TestRequest::post());
+    let form = common::extract::<Multipart<Filters>>(req).await.unwrap();
+
+    assert_eq!(form.colors, ["red"]);
+    assert_eq!(form.sizes, [38]);
+    assert_eq!(form.photos.len(), 1);
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Search {
+    page: Option<i64>,
+    archived: Option<bool>,
+    #[multipart(one_of = "public,private")]
+    visibility: Option<String>,
+}
+
+#[actix_web::test]
+async fn reads_null_text_as_null() {
+    let req = TestMultipartBuilder::new()
+        .text("page", "null")
+        .text("archived", "null")
+        .text("visibility", "null")
+        .set_on(TestRequest::post());
+    let config = MultipartConfig::default().set_null_literal(true);
+    let form = common::extract_with::<Multipart<Search>>(req, config)
+        .await
+        .unwrap();
+    assert_eq!(
+        (form.page, form.archived, form.visibility.as_deref()),
+        (None, None, None)
+    );
+
+    // Other text is still parsed
+    let config = MultipartConfig::default().set_null_literal(true);
+    let req = TestMultipartBuilder::new()
+        .text("page", "2")
+        .text("archived", "true")
+        .text("visibility", "public")
+        .set_on(TestRequest::post());
+    let form = common::extract_with::<Multipart<Search>>(req, config)
+        .await
+        .unwrap();
+    assert_eq!(
+        (form.page, form.archived, form.visibility.as_deref()),
+        (Some(2), Some(true), Some("public"))
+    );
+
+    // Read as text by default, which isn't a number
+    let req = TestMultipartBuilder::new()
+        .text("page", "null")
+        .set_on(TestRequest::post());
+    let err = common::extract::<Multipart<Search>>(req).await.unwrap_err();
+    assert_eq!(common::error_of(&err).1, "parse_error");
+}
+
+#[actix_web::test]
+async fn strips_byte_order_marks_and_whitespace() {
+    let search = || {
+        TestMultipartBuilder::new()
+            .text("page", "\u{feff}2")
+            .text("archived", " true\r\n")
+            .text("visibility", "\u{feff}public ")
+            .set_on(TestRequest::post())
+    };
+
+    let config = MultipartConfig::default()
+        .set_strip_bom(true)
+        .set_text_whitespace(TextWhitespace::Trim);
+    let form = common::extract_with::<Multipart<Search>>(search(), config)
+        .await
+        .unwrap();
+    assert_eq!(
+        (form.page, form.archived, form.visibility.as_deref()),
+        (Some(2), Some(true), Some("public"))
+    );
+
+    // Only trailing whitespace is removed, the leading space of archived is kept
+    let config = MultipartConfig::default()
+        .set_strip_bom(true)
+        .set_text_whitespace(TextWhitespace::TrimEnd);
+    let err = common::extract_with::<Multipart<Search>>(search(), config)
+        .await
+        .unwrap_err();
+    assert_eq!(common::error_of(&err).1, "parse_error");
+
+    // Kept by default, the visibility isn't one of the allowed values
+    let err = common::extract::<Multipart<Search>>(search())
+        .await
+        .unwrap_err();
+    assert_eq!(common::error_of(&err).1, "invalid_field");
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Album {
+    #[serde(rename = "albumTitle")]
+    title: String,
+    photos: Vec<File>,
+}
+
+#[actix_web::test]
+async fn lists_the_fields_in_the_order_they_were_sent() {
+    let req = TestMultipartBuilder::new()
+        .file("photos[]", "a.png", "image/png", "a")
+        .text("albumTitle", "Holidays")
+        .text("unknown", "skipped")
+        .file("photos[]", "b.png", "image/png", "b")
+        .set_on(TestRequest::post());
+    let form = common::extract::<Multipart<Album>>(req).await.unwrap();
+    assert_eq!((form.title.as_str(), form.photos.len()), ("Holidays", 2));
+
+    assert_eq!(form.ordered_fields(), ["photos", "albumTitle", "photos"]);
+}
+
+mod early_exit {
+    use actix_multipart_extract::EarlyExit;
+    use actix_web::{error::PayloadError, rt::time::timeout};
+    use futures::{stream, StreamExt};
+
+    use super::*;
+
+    /// The title and file of an upload followed by parts which never finish arriving.
+    fn stalled() -> (
+        TestRequest,
+        impl futures::Stream<Item = Result<Bytes, PayloadError>>,
+    ) {
+        let body = RawBody::new()
+            .text("title", "Notes")
+            .part(
+                &[r#"Content-Disposition: form-data; name="file"; filename="a.txt""#],
+                "Hello",
+            )
+            .text("junk", "more");
+        let mut sent = Bytes::from(body.truncated());
+        sent.truncate(sent.len() - 2);
+
+        let chunks = stream::iter([Ok(sent)]).chain(stream::pending());
+        (body.request(), chunks)
+    }
+
+    #[actix_web::test]
+    async fn stops_reading_complete_forms() {
+        let (req, chunks) = stalled();
+        let config = MultipartConfig::default().set_early_exit(EarlyExit::Close);
+        let form = common::extract_streamed::<Multipart<Upload>>(req.app_data(config), chunks)
+            .await
+            .unwrap();
+
+        assert_eq!(form.title, "Notes");
+        assert_eq!(form.file.bytes, b"Hello");
+    }
+
+    #[actix_web::test]
+    async fn reads_the_rest_of_the_request_by_default() {
+        for early_exit in [EarlyExit::Disabled, EarlyExit::Drain] {
+            let (req, chunks) = stalled();
+            let config = MultipartConfig::default().set_early_exit(early_exit);
+            let req = req.app_data(config);
+            let extracted = common::extract_streamed::<Multipart<Upload>>(req, chunks);
+
+            timeout(Duration::from_millis(50), extracted)
+                .await
+                .unwrap_err();
+        }
+    }
+
+    #[actix_web::test]
+    async fn drains_without_parsing_the_rest() {
+        let body = RawBody::new()
+            .text("title", "Notes")
+            .part(
+                &[r#"Content-Disposition: form-data; name="file"; filename="a.txt""#],
+                "Hello",
+            )
+            .text("junk", "more");
+
+        let config = MultipartConfig::default().set_deny_unknown_fields(true);
+        let err = common::extract_with::<Multipart<Upload>>(body.request(), config)
+            .await
+            .unwrap_err();
+        assert_eq!(common::error_of(&err).1, "unknown_field");
+
+        let config = MultipartConfig::default()
+            .set_deny_unknown_fields(true)
+            .set_early_exit(EarlyExit::Drain);
+        let form = common::extract_with::<Multipart<Upload>>(body.request(), config)
+            .await
+            .unwrap();
+        assert_eq!(form.title, "Notes");
+    }
+
+    #[actix_web::test]
+    async fn reads_every_part_of_lists() {
+        #[derive(Deserialize, MultipartForm, Debug)]
+        struct Tagged {
+            title: String,
+            tags: Vec<String>,
+        }
+
+        let body = RawBody::new()
+            .text("title", "Notes")
+            .text("tags", "a")
+            .text("tags", "b");
+        let config = MultipartConfig::default().set_early_exit(EarlyExit::Close);
+        let form = common::extract_with::<Multipart<Tagged>>(body.request(), config)
+            .await
+            .unwrap();
+
+        assert_eq!(form.title, "Notes");
+        assert_eq!(form.tags, ["a", "b"]);
+    }
+}
+
+mod timeout {
+    use actix_multipart_extract::{Field, MultipartStream};
+    use actix_web::{error::PayloadError, rt::time::sleep};
+    use futures::{stream, StreamExt};
+
+    use super::*;
+
+    fn body() -> RawBody {
+        RawBody::new().text("title", "Notes").part(
+            &[
+                r#"Content-Disposition: form-data; name="file"; filename="notes.txt""#,
+                "Content-Type: text/plain",
+            ],
+            "Hello",
+        )
+    }
+
+    fn config() -> MultipartConfig {
+        MultipartConfig::default().set_read_timeout(Duration::from_millis(50))
+    }
+
+    /// The body split in two chunks, with `delay` before the second one.
+    fn chunks(delay: Duration) -> impl futures::Stream<Item = Result<Bytes, PayloadError>> {
+        let mut bytes = Bytes::from(body().to_bytes());
+        let first = bytes.split_to(60);
+
+        stream::iter([first, bytes])
+            .enumerate()
+            .then(move |(i, chunk)| async move {
+                if i == 1 {
+                    sleep(delay).await;
+                }
+                Ok(chunk)
+            })
+    }
+
+    #[actix_web::test]
+    async fn aborts_stalled_uploads() {
+        let req = body().request().app_data(config());
+        let err =
+            common::extract_streamed::<Multipart<Upload>>(req, chunks(Duration::from_secs(5)))
+                .await
+                .unwrap_err();
+
+        assert_eq!(
+            common::error_of(&err),
+            (StatusCode::REQUEST_TIMEOUT, "timeout".to_string())
+        );
+    }
+
+    #[actix_web::test]
+    async fn waits_for_chunks_within_the_timeout() {
+        let req = body().request().app_data(config());
+        let form =
+            common::extract_streamed::<Multipart<Upload>>(req, chunks(Duration::from_millis(10)))
+                .await
+                .unwrap();
+
+        assert_eq!(form.title, "Notes");
+        assert_eq!(form.file.bytes, b"Hello");
+    }
+
+    #[actix_web::test]
+    async fn doesnt_count_time_spent_by_the_consumer() {
+        let req = body().request().app_data(config());
+        let mut fields =
+            common::extract_streamed::<MultipartStream<Upload>>(req, chunks(Duration::ZERO))
+                .await
+                .unwrap();
+
+        match fields.next().await.unwrap() {
+            Some(Field::Text { name, value }) => {
+                assert_eq!((name.as_str(), value), ("title", "Notes".into()))
+            }
+            field => panic!("unexpected {field:?}"),
+        }
+
+        // Longer than the timeout, the client isn't waited for meanwhile
+        sleep(Duration::from_millis(100)).await;
+
+        match fields.next().await.unwrap() {
+            Some(Field::File {
+                name, mut stream, ..
+            }) => {
+                assert_eq!(name, "file");
+                let mut bytes = Vec::new();
+                while let Some(chunk) = stream.next().await {
+                    bytes.extend_from_slice(&chunk.unwrap());
+                }
+                assert_eq!(bytes, b"Hello");
+            }
+            field => panic!("unexpected {field:?}"),
+        }
+
+        assert!(fields.next().await.unwrap().is_none());
+    }
+
+    #[actix_web::test]
+    async fn aborts_parts_received_past_their_deadline() {
+        #[derive(Deserialize, MultipartForm, Debug)]
+        struct Deadlined {
+            title: String,
+            #[multipart(deadline = "50ms")]
+            file: File,
+        }
+
+        // The file trickles in, each chunk well within the read timeout
+        let bytes = body().to_bytes();
+        let start = bytes.windows(5).position(|w| w == b"Hello").unwrap();
+        let chunks = |delay| {
+            let chunks = [
+                &bytes[..start],
+                &bytes[start..start + 2],
+                &bytes[start + 2..start + 4],
+                &bytes[start + 4..],
+            ]
+            .map(Bytes::copy_from_slice);
+
+            stream::iter(chunks).then(move |chunk| async move {
+                sleep(delay).await;
+                Ok::<_, PayloadError>(chunk)
+            })
+        };
+
+        let req = body().request().app_data(config());
+        let err = common::extract_streamed::<Multipart<Deadlined>>(
+            req,
+            chunks(Duration::from_millis(30)),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            common::error_of(&err),
+            (StatusCode::REQUEST_TIMEOUT, "deadline_exceeded".to_string())
+        );
+
+        let req = body().request().app_data(config());
+        let form = common::extract_streamed::<Multipart<Deadlined>>(req, chunks(Duration::ZERO))
+            .await
+            .unwrap();
+        assert_eq!(form.title, "Notes");
+        assert_eq!(form.file.bytes, b"Hello");
+    }
+}
@@ -0,0 +1,487 @@
+mod common;
+
+use actix_multipart_extract::{File, FilenamePolicy, Multipart, MultipartConfig, MultipartForm};
+use actix_web::test::TestRequest;
+use common::{error_of, extract, extract_with, RawBody};
+use serde::Deserialize;
+use serde_json::json;
+
+fn file(name: &str, content_type: &str, bytes: &[u8]) -> File {
+    File {
+        content_type: content_type.to_string(),
+        detected_type: None,
+        guessed_type: None,
+        name: name.to_string(),
+        bytes: bytes.to_vec(),
+        size: bytes.len(),
+        hash: None,
+        duplicate_of: None,
+    }
+}
+
+#[test]
+fn parses_the_content_type_and_extension() {
+    let png = file("photo.tar.gz", "image/png; charset=binary", b"");
+    assert_eq!(png.mime().essence_str(), "image/png");
+    assert_eq!(png.extension(), Some("gz"));
+
+    let invalid = file("README", "not a type", b"");
+    assert_eq!(invalid.mime(), mime::APPLICATION_OCTET_STREAM);
+    assert_eq!(invalid.extension(), None);
+}
+
+#[test]
+fn serializes_files_without_their_bytes() {
+    let photo = file("photo.png", "image/png", b"pixels");
+    assert_eq!(
+        serde_json::to_value(&photo).unwrap(),
+        json!({ "name": "photo.png", "content_type": "image/png", "size": 6 })
+    );
+}
+
+#[test]
+fn leaves_the_bytes_out_of_debug_output() {
+    let secret = file("secret.txt", "text/plain", b"hunter2");
+    let debug = format!("{secret:?}");
+
+    assert!(debug.contains(r#"name: "secret.txt""#), "{debug}");
+    assert!(debug.contains("size: 7"), "{debug}");
+    assert!(!debug.contains("bytes"), "{debug}");
+    // Neither as text nor as numbers
+    assert!(!debug.contains("hunter2"), "{debug}");
+    assert!(!debug.contains("104, 117"), "{debug}");
+}
+
+#[cfg(feature = "sniff")]
+mod sniff {
+    use actix_multipart_extract::{File, Multipart, MultipartForm};
+    use serde::Deserialize;
+
+    use crate::common::{error_body, error_of, extract, RawBody};
+
+    /// Start of a PNG file.
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Upload {
+        file: Option<File>,
+        #[multipart(verify_content_type)]
+        verified: Option<File>,
+        #[multipart(strict_content_type)]
+        strict: Option<File>,
+    }
+
+    #[actix_web::test]
+    async fn detects_the_content_type_of_files() {
+        let req = RawBody::new()
+            .file("file", "a.bin", "application/octet-stream", PNG)
+            .request();
+
+        let form = extract::<Multipart<Upload>>(req).await.unwrap();
+        let file = form.file.as_ref().unwrap();
+        assert_eq!(file.content_type, "application/octet-stream");
+        assert_eq!(file.detected_type.as_deref(), Some("image/png"));
+        assert!(form.verified.is_none());
+    }
+
+    #[actix_web::test]
+    async fn rejects_files_not_matching_their_declared_type() {
+        let req = RawBody::new()
+            .file("verified", "a.png", "image/png", b"%PDF-1.7\n")
+            .request();
+        let err = extract::<Multipart<Upload>>(req).await.unwrap_err();
+        assert_eq!(error_of(&err).1, "content_type_mismatch");
+
+        // Undetected files pass
+        let req = RawBody::new()
+            .file("verified", "a.png", "image/png", "text")
+            .request();
+        assert!(extract::<Multipart<Upload>>(req).await.is_ok());
+    }
+
+    #[actix_web::test]
+    async fn rejects_unknown_content_declared_as_a_known_type() {
+        let req = RawBody::new()
+            .file("strict", "a.png", "image/png", "text")
+            .request();
+        let err = extract::<Multipart<Upload>>(req).await.unwrap_err();
+        let (_, code, body) = error_body(&err).await;
+        assert_eq!(code, "content_type_mismatch");
+        assert!(
+            body.contains("declared as image/png but detected as unknown content"),
+            "{body}"
+        );
+
+        let req = RawBody::new()
+            .file("strict", "a.png", "image/png", b"%PDF-1.7\n")
+            .request();
+        let err = extract::<Multipart<Upload>>(req).await.unwrap_err();
+        let (_, _, body) = error_body(&err).await;
+        assert!(
+            body.contains("declared as image/png but detected as application/pdf"),
+            "{body}"
+        );
+
+        // Types which can't be detected pass
+        let req = RawBody::new()
+            .file("strict", "a.txt", "text/plain", "text")
+            .request();
+        let form = extract::<Multipart<Upload>>(req).await.unwrap();
+        assert_eq!(form.strict.as_ref().unwrap().bytes, b"text");
+    }
+}
+
+#[cfg(feature = "mime-guess")]
+mod mime_guess {
+    use actix_multipart_extract::{File, Multipart, MultipartForm};
+    use serde::Deserialize;
+
+    use crate::common::{extract, RawBody};
+
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Upload {
+        file: File,
+    }
+
+    #[actix_web::test]
+    async fn guesses_missing_content_types_from_the_extension() {
+        let req = RawBody::new()
+            .part(
+                &["Content-Disposition: form-data; name=\"file\"; filename=\"photo.PNG\""],
+                "bytes",
+            )
+            .request();
+
+        let form = extract::<Multipart<Upload>>(req).await.unwrap();
+        assert_eq!(form.file.guessed_type.as_deref(), Some("image/png"));
+        assert_eq!(form.file.content_type, "image/png");
+    }
+
+    #[actix_web::test]
+    async fn keeps_the_content_type_sent() {
+        let req = RawBody::new()
+            .part(
+                &[
+                    "Content-Disposition: form-data; name=\"file\"; filename=\"notes.txt\"",
+                    "Content-Type: application/octet-stream",
+                ],
+                "bytes",
+            )
+            .request();
+
+        let form = extract::<Multipart<Upload>>(req).await.unwrap();
+        assert_eq!(form.file.guessed_type.as_deref(), Some("text/plain"));
+        assert_eq!(form.file.content_type, "application/octet-stream");
+    }
+}
+
+#[cfg(feature = "hash")]
+mod hash {
+    use actix_multipart_extract::{File, Multipart, MultipartConfig, MultipartForm};
+    use serde::Deserialize;
+
+    use crate::common::{extract, extract_with, RawBody};
+
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Upload {
+        #[multipart(hash = "sha256")]
+        file: File,
+        #[multipart(hash = "sha384")]
+        other: Option<File>,
+        plain: Option<File>,
+    }
+
+    #[actix_web::test]
+    async fn hashes_files_of_fields_with_an_algorithm() {
+        let req = RawBody::new()
+            .file("file", "a.txt", "text/plain", "abc")
+            .file("other", "b.txt", "text/plain", "abc")
+            .file("plain", "c.txt", "text/plain", "abc")
+            .request();
+
+        let form = extract::<Multipart<Upload>>(req).await.unwrap();
+        assert_eq!(
+            form.file.hash.as_deref(),
+            Some("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+        assert_eq!(
+            form.other.as_ref().unwrap().hash.as_deref(),
+            Some(concat!(
+                "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed",
+                "8086072ba1e7cc2358baeca134c825a7"
+            ))
+        );
+        assert_eq!(form.plain.as_ref().unwrap().hash, None);
+    }
+
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Bulk {
+        #[multipart(hash = "sha256")]
+        cover: File,
+        #[multipart(hash = "sha256")]
+        photos: Vec<File>,
+    }
+
+    #[actix_web::test]
+    async fn marks_files_sent_twice() {
+        let body = RawBody::new()
+            .file("photos", "a.png", "image/png", "a")
+            .file("photos", "b.png", "image/png", "b")
+            .file("cover", "cover.png", "image/png", "b")
+            .file("photos", "c.png", "image/png", "a");
+
+        let config = MultipartConfig::default().set_detect_duplicate_files(true);
+        let form = extract_with::<Multipart<Bulk>>(body.request(), config)
+            .await
+            .unwrap();
+        let duplicates: Vec<_> = form
+            .photos
+            .iter()
+            .map(|photo| photo.duplicate_of.as_deref())
+            .collect();
+        assert_eq!(duplicates, [None, None, Some("photos[0]")]);
+        assert_eq!(form.cover.duplicate_of.as_deref(), Some("photos[1]"));
+        assert_eq!(form.cover.bytes, b"b");
+
+        // Not detected by default
+        let form = extract::<Multipart<Bulk>>(body.request()).await.unwrap();
+        assert!(form.photos.iter().all(|photo| photo.duplicate_of.is_none()));
+        assert_eq!(form.cover.duplicate_of, None);
+    }
+}
+
+#[actix_web::test]
+async fn counts_the_bytes_received() {
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Upload {
+        file: File,
+        empty: File,
+    }
+
+    let req = RawBody::new()
+        .file("file", "a.txt", "text/plain", "hello")
+        .file("empty", "b.txt", "text/plain", "")
+        .request();
+
+    let form = extract::<Multipart<Upload>>(req).await.unwrap();
+    assert_eq!((form.file.size(), form.file.is_empty()), (5, false));
+    assert_eq!((form.empty.size(), form.empty.is_empty()), (0, true));
+}
+
+#[actix_web::test]
+async fn reads_binary_parts_into_bytes() {
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Signed {
+        signature: Vec<u8>,
+        key: Option<Vec<u8>>,
+        chunks: Vec<Vec<u8>>,
+        #[multipart(max_size = 2)]
+        nonce: Option<Vec<u8>>,
+    }
+
+    let req = RawBody::new()
+        .text("signature", [0xff, 0x00, 0xfe])
+        .file("key", "key.bin", "application/octet-stream", [0x80])
+        .text("chunks", "a")
+        .text("chunks", [0xc3])
+        .request();
+
+    let form = extract::<Multipart<Signed>>(req).await.unwrap();
+    assert_eq!(form.signature, [0xff, 0x00, 0xfe]);
+    assert_eq!(form.key.as_deref(), Some(&[0x80][..]));
+    assert_eq!(form.chunks, [b"a".to_vec(), vec![0xc3]]);
+    assert_eq!(form.nonce, None);
+
+    let req = RawBody::new()
+        .text("signature", "")
+        .text("nonce", [1, 2, 3])
+        .request();
+    let err = extract::<Multipart<Signed>>(req).await.unwrap_err();
+    assert_eq!(error_of(&err).1, "text_too_large");
+}
+
+#[actix_web::test]
+async fn streams_files_in_bounded_chunks() {
+    use futures::{AsyncReadExt, TryStreamExt};
+
+    let bytes: Vec<u8> = (0..150 * 1024).map(|i| i as u8).collect();
+
+    let chunks: Vec<_> = file("a.bin", "application/octet-stream", &bytes)
+        .into_stream()
+        .try_collect()
+        .await
+        .unwrap();
+    let lens: Vec<_> = chunks.iter().map(|chunk| chunk.len()).collect();
+    assert_eq!(lens, [64 * 1024, 64 * 1024, 22 * 1024]);
+    assert_eq!(chunks.concat(), bytes);
+
+    let mut read = Vec::new();
+    file("a.bin", "application/octet-stream", &bytes)
+        .into_reader()
+        .read_to_end(&mut read)
+        .await
+        .unwrap();
+    assert_eq!(read, bytes);
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Named {
+    file: File,
+}
+
+fn named(filename: &str) -> TestRequest {
+    RawBody::new()
+        .file("file", filename, "text/plain", "a")
+        .request()
+}
+
+#[actix_web::test]
+async fn sanitizes_file_names() {
+    for (sent, name) in [
+        ("../../etc/passwd", "passwd"),
+        ("dir/sub/a.txt", "a.txt"),
+        ("a\tb.txt", "ab.txt"),
+        ("..", ""),
+        ("report.pdf", "report.pdf"),
+    ] {
+        let form = extract::<Multipart<Named>>(named(sent)).await.unwrap();
+        assert_eq!(form.file.name, name, "{sent:?}");
+    }
+}
+
+#[actix_web::test]
+async fn applies_the_file_name_policy() {
+    let keep = || MultipartConfig::default().set_filename_policy(FilenamePolicy::Keep);
+    let strict = || MultipartConfig::default().set_filename_policy(FilenamePolicy::Strict);
+
+    let form = extract_with::<Multipart<Named>>(named("../a.txt"), keep())
+        .await
+        .unwrap();
+    assert_eq!(form.file.name, "../a.txt");
+
+    let err = extract_with::<Multipart<Named>>(named("../a.txt"), strict())
+        .await
+        .unwrap_err();
+    assert_eq!(error_of(&err).1, "invalid_filename");
+
+    let form = extract_with::<Multipart<Named>>(named("a.txt"), strict())
+        .await
+        .unwrap();
+    assert_eq!(form.file.name, "a.txt");
+}
+
+#[cfg(feature = "image")]
+mod image {
+    use actix_multipart_extract::{
+        test::TestMultipartBuilder, File, FileMeta, FileSink, Multipart, MultipartConfig,
+        MultipartForm, StreamedFile,
+    };
+    use actix_web::test::TestRequest;
+    use futures::SinkExt;
+    use image::{ImageFormat, RgbImage};
+    use serde::Deserialize;
+    use std::io::{self, Cursor};
+
+    use crate::common::{error_body, error_of, extract, extract_with};
+
+    fn encode(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+        let mut bytes = Cursor::new(Vec::new());
+        RgbImage::new(width, height)
+            .write_to(&mut bytes, format)
+            .unwrap();
+        bytes.into_inner()
+    }
+
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Avatar {
+        #[multipart(image(max_width = 64, max_height = 32, formats = "png,gif"))]
+        image: File,
+    }
+
+    fn avatar(bytes: Vec<u8>) -> TestRequest {
+        TestMultipartBuilder::new()
+            .file("image", "avatar", "image/png", bytes)
+            .set_on(TestRequest::post())
+    }
+
+    #[actix_web::test]
+    async fn accepts_images_within_the_rules() {
+        for format in [ImageFormat::Png, ImageFormat::Gif] {
+            let bytes = encode(64, 32, format);
+            let form = extract::<Multipart<Avatar>>(avatar(bytes.clone()))
+                .await
+                .unwrap();
+            assert_eq!(form.image.bytes, bytes);
+        }
+    }
+
+    #[actix_web::test]
+    async fn rejects_images_breaking_the_rules() {
+        let cases = [
+            (
+                encode(65, 32, ImageFormat::Png),
+                "width of 65px is over 64px",
+            ),
+            (
+                encode(64, 33, ImageFormat::Png),
+                "height of 33px is over 32px",
+            ),
+            (
+                encode(8, 8, ImageFormat::Jpeg),
+                "format jpeg isn't one of png, gif",
+            ),
+            (b"not an image".to_vec(), "unknown image format"),
+        ];
+
+        for (bytes, reason) in cases {
+            let err = extract::<Multipart<Avatar>>(avatar(bytes))
+                .await
+                .unwrap_err();
+            let (status, code, body) = error_body(&err).await;
+            assert_eq!((status.as_u16(), code.as_str()), (400, "invalid_image"));
+            assert!(body.contains(reason), "{body}");
+        }
+
+        // A valid header followed by a truncated image is still corrupt
+        let mut bytes = encode(8, 8, ImageFormat::Png);
+        bytes.truncate(20);
+        let err = extract::<Multipart<Avatar>>(avatar(bytes))
+            .await
+            .unwrap_err();
+        assert_eq!(error_of(&err).1, "invalid_image");
+    }
+
+    #[actix_web::test]
+    async fn checks_images_streamed_into_sinks() {
+        #[derive(Deserialize, MultipartForm, Debug)]
+        struct Photo {
+            #[multipart(image(max_width = 64))]
+            photo: StreamedFile,
+        }
+
+        let config = || {
+            MultipartConfig::default().set_file_storage(|_: &str, _: &FileMeta| {
+                let sink =
+                    futures::sink::drain().sink_map_err(|never| -> io::Error { match never {} });
+                Some(Box::new(sink) as Box<dyn FileSink>)
+            })
+        };
+        let photo = |bytes| {
+            TestMultipartBuilder::new()
+                .file("photo", "photo.png", "image/png", bytes)
+                .set_on(TestRequest::post())
+        };
+
+        let bytes = encode(64, 64, ImageFormat::Png);
+        let form = extract_with::<Multipart<Photo>>(photo(bytes.clone()), config())
+            .await
+            .unwrap();
+        assert_eq!(form.photo.size, bytes.len());
+
+        let bytes = encode(128, 64, ImageFormat::Png);
+        let err = extract_with::<Multipart<Photo>>(photo(bytes), config())
+            .await
+            .unwrap_err();
+        assert_eq!(error_of(&err).1, "invalid_image");
+    }
+}
@@ -0,0 +1,312 @@
+mod common;
+
+use actix_multipart_extract::{
+    File, Limits, MemoryBudget, Multipart, MultipartConfig, MultipartForm, MultipartLimit,
+};
+use actix_web::{
+    error::PayloadError,
+    http::{header, StatusCode},
+    rt::time::timeout,
+    test::{call_service, init_service, TestRequest},
+    web::{self, Bytes},
+    App,
+};
+use common::RawBody;
+use futures::{stream, StreamExt};
+use serde::Deserialize;
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Attachment {
+    file: File,
+}
+
+fn file(bytes: &[u8]) -> RawBody {
+    RawBody::new().part(
+        &[r#"Content-Disposition: form-data; name="file"; filename="a.bin""#],
+        bytes,
+    )
+}
+
+fn memory_limited(budget: &MemoryBudget) -> MultipartConfig {
+    MultipartConfig::default().set_shared_memory_limit(budget.clone())
+}
+
+#[actix_web::test]
+async fn releases_memory_once_extracted() {
+    let budget = MemoryBudget::new(1000);
+    let form = common::extract_with::<Multipart<Attachment>>(
+        file(&[1; 600]).request(),
+        memory_limited(&budget),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(form.file.size, 600);
+    assert_eq!(budget.used(), 0);
+}
+
+#[actix_web::test]
+async fn shares_the_memory_budget_between_requests() {
+    let budget = MemoryBudget::new(1000);
+
+    // Stalls once most of the file was buffered
+    let body = file(&[1; 600]);
+    let mut sent = Bytes::from(body.to_bytes());
+    sent.truncate(sent.len() - 50);
+    let chunks = stream::iter([Ok::<_, PayloadError>(sent)]).chain(stream::pending());
+    let req = body.request().app_data(memory_limited(&budget));
+    let mut stalled = Box::pin(common::extract_streamed::<Multipart<Attachment>>(
+        req, chunks,
+    ));
+    timeout(Duration::from_millis(20), &mut stalled)
+        .await
+        .unwrap_err();
+    assert!(budget.used() >= 500, "{}", budget.used());
+
+    let err = common::extract_with::<Multipart<Attachment>>(
+        file(&[1; 600]).request(),
+        memory_limited(&budget),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(
+        common::error_of(&err),
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "memory_limit_reached".to_string()
+        )
+    );
+
+    drop(stalled);
+    assert_eq!(budget.used(), 0);
+    common::extract_with::<Multipart<Attachment>>(
+        file(&[1; 600]).request(),
+        memory_limited(&budget),
+    )
+    .await
+    .unwrap();
+}
+
+/// Limits of the requests of the free plan, other requests are unlimited.
+fn planned() -> MultipartConfig {
+    MultipartConfig::default().set_limits_for(|req| match req.headers().get("x-plan") {
+        Some(plan) if plan == "pro" => Limits::default(),
+        _ => Limits {
+            total: Some(1000),
+            per_file: Some(100),
+            max_files: Some(2),
+            max_fields: Some(3),
+        },
+    })
+}
+
+async fn planned_error(body: RawBody) -> (StatusCode, String) {
+    let err = common::extract_with::<Multipart<Files>>(body.request(), planned())
+        .await
+        .unwrap_err();
+    common::error_of(&err)
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Files {
+    files: Vec<File>,
+}
+
+fn files(count: usize, size: usize) -> RawBody {
+    (0..count).fold(RawBody::new(), |body, i| {
+        body.part(
+            &[&format!(
+                r#"Content-Disposition: form-data; name="files"; filename="{i}.bin""#
+            )],
+            vec![1; size],
+        )
+    })
+}
+
+#[actix_web::test]
+async fn limits_requests_with_the_limits_for_them() {
+    assert_eq!(
+        planned_error(files(1, 2000)).await,
+        (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "request_too_large".to_string()
+        )
+    );
+    assert_eq!(
+        planned_error(files(1, 101)).await,
+        (StatusCode::BAD_REQUEST, "file_too_large".to_string())
+    );
+    assert_eq!(
+        planned_error(files(3, 1)).await,
+        (StatusCode::BAD_REQUEST, "too_many_files".to_string())
+    );
+    assert_eq!(
+        planned_error(files(2, 1).text("a", "1").text("b", "2")).await,
+        (StatusCode::BAD_REQUEST, "too_many_fields".to_string())
+    );
+
+    let form = common::extract_with::<Multipart<Files>>(files(2, 100).request(), planned())
+        .await
+        .unwrap();
+    assert_eq!(form.files.len(), 2);
+
+    let req = files(5, 2000).request().insert_header(("x-plan", "pro"));
+    let form = common::extract_with::<Multipart<Files>>(req, planned())
+        .await
+        .unwrap();
+    assert_eq!(form.files.len(), 5);
+}
+
+#[actix_web::test]
+async fn rejects_requests_declared_too_large_before_reading_them() {
+    static CALLED: AtomicBool = AtomicBool::new(false);
+
+    async fn upload(form: Multipart<Attachment>) -> String {
+        CALLED.store(true, Ordering::SeqCst);
+        form.file.size.to_string()
+    }
+
+    let app = init_service(
+        App::new()
+            .wrap(MultipartLimit::new(1000))
+            .route("/", web::post().to(upload))
+            .route("/text", web::post().to(|body: String| async move { body })),
+    )
+    .await;
+
+    // Declared over the limit, the body sent is never read
+    let req = file(&[1; 10])
+        .request()
+        .insert_header((header::CONTENT_LENGTH, "5000"));
+    let res = call_service(&app, req.to_request()).await;
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(
+        res.headers().get("x-error-code").unwrap(),
+        "request_too_large"
+    );
+    assert!(!CALLED.load(Ordering::SeqCst));
+
+    let res = call_service(&app, file(&[1; 600]).request().to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(CALLED.load(Ordering::SeqCst));
+
+    // Other requests aren't limited
+    let req = TestRequest::post()
+        .uri("/text")
+        .set_payload(vec![b'a'; 2000]);
+    let res = call_service(&app, req.to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn limits_the_headers_of_parts() {
+    let limited = || MultipartConfig::default().set_max_part_header_size(200);
+
+    let form = common::extract_with::<Multipart<Attachment>>(file(b"a").request(), limited())
+        .await
+        .unwrap();
+    assert_eq!(form.file.size, 1);
+
+    let filename = "a".repeat(300);
+    let body = RawBody::new().part(
+        &[&format!(
+            r#"Content-Disposition: form-data; name="file"; filename="{filename}""#
+        )],
+        b"a",
+    );
+    let err = common::extract_with::<Multipart<Attachment>>(body.request(), limited())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        common::error_of(&err),
+        (
+            StatusCode::BAD_REQUEST,
+            "part_headers_too_large".to_string()
+        )
+    );
+
+    // Rejected before the body of the part is read
+    let sent = Bytes::from(body.truncated());
+    let chunks = stream::iter([Ok::<_, PayloadError>(sent)]).chain(stream::pending());
+    let req = body.request().app_data(limited());
+    let extract = common::extract_streamed::<Multipart<Attachment>>(req, chunks);
+    let err = timeout(Duration::from_secs(1), extract)
+        .await
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(common::error_of(&err).1, "part_headers_too_large");
+}
+
+#[cfg(feature = "compression")]
+mod compression {
+    use std::io::Write;
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn counts_decompressed_bytes() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        encoder.write_all(&[0; 100_000]).unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert!(compressed.len() < 1000);
+
+        let body = RawBody::new().part(
+            &[
+                r#"Content-Disposition: form-data; name="file"; filename="a.bin""#,
+                "Content-Encoding: gzip",
+            ],
+            compressed,
+        );
+        let budget = MemoryBudget::new(10_000);
+        let err =
+            common::extract_with::<Multipart<Attachment>>(body.request(), memory_limited(&budget))
+                .await
+                .unwrap_err();
+
+        assert_eq!(
+            common::error_of(&err),
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "memory_limit_reached".to_string()
+            )
+        );
+        assert_eq!(budget.used(), 0);
+    }
+
+    #[actix_web::test]
+    async fn reserves_no_buffer_for_compressed_parts() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        encoder.write_all(b"hello").unwrap();
+
+        let body = RawBody::new()
+            .part(
+                &[
+                    r#"Content-Disposition: form-data; name="file"; filename="a.bin""#,
+                    "Content-Encoding: gzip",
+                ],
+                encoder.finish().unwrap(),
+            )
+            .text("padding", vec![b'a'; 50_000]);
+
+        // The rest of the request is larger than the budget when the compressed part is read
+        let mut bytes = Bytes::from(body.to_bytes());
+        let start = bytes.len() - 50_100;
+        let head = stream::iter([Ok::<_, PayloadError>(bytes.split_to(start))]);
+        let rest = stream::once(async {
+            actix_web::rt::time::sleep(Duration::from_millis(10)).await;
+            Ok(bytes)
+        });
+        let chunks = head.chain(rest);
+        let budget = MemoryBudget::new(20_000);
+        let req = body.request().app_data(memory_limited(&budget));
+        let form = common::extract_streamed::<Multipart<Attachment>>(req, chunks)
+            .await
+            .unwrap();
+
+        assert_eq!(form.file.bytes, b"hello");
+    }
+}
@@ -0,0 +1,268 @@
+//! Tests of the parsing backend, `multer` with the `multer` feature and `actix-multipart`
+//! otherwise. Every other test also runs with both.
+
+mod common;
+
+use actix_multipart_extract::{test::TestMultipartBuilder, File, Multipart, MultipartForm};
+use actix_web::{test::TestRequest, web::Bytes};
+use common::RawBody;
+use futures::stream;
+use serde::Deserialize;
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Upload {
+    title: String,
+    file: File,
+}
+
+fn upload() -> TestMultipartBuilder {
+    TestMultipartBuilder::new().text("title", "Notes").file(
+        "file",
+        "notes.txt",
+        "text/plain",
+        "Hello\r\n--not-a-boundary\r\n",
+    )
+}
+
+#[actix_web::test]
+async fn parses_bodies_split_anywhere() {
+    let upload = upload();
+    let body = upload.body();
+
+    for size in [1, 2, 3, 7, 16] {
+        let chunks: Vec<_> = body
+            .chunks(size)
+            .map(|c| Ok(Bytes::copy_from_slice(c)))
+            .collect();
+        let req = upload.set_on(TestRequest::post());
+        let form = common::extract_streamed::<Multipart<Upload>>(req, stream::iter(chunks))
+            .await
+            .unwrap();
+
+        assert_eq!(form.title, "Notes");
+        assert_eq!(form.file.bytes, b"Hello\r\n--not-a-boundary\r\n");
+    }
+}
+
+#[actix_web::test]
+async fn parses_boundaries_with_special_characters() {
+    for boundary in [
+        "a",
+        "----WebKitFormBoundary7MA4YWxkTrZu0gW",
+        "with spaces",
+        "'()+_,-./:=?",
+    ] {
+        let req = upload().boundary(boundary).set_on(TestRequest::post());
+        let form = common::extract::<Multipart<Upload>>(req).await.unwrap();
+
+        assert_eq!(form.title, "Notes", "{boundary:?}");
+        assert_eq!(form.file.name, "notes.txt");
+    }
+}
+
+#[actix_web::test]
+async fn skips_the_preamble_and_epilogue() {
+    let body = RawBody::new().text("title", "Notes").part(
+        &[r#"Content-Disposition: form-data; name="file"; filename="notes.txt""#],
+        "Hello",
+    );
+    let mut bytes = b"preamble\r\n".to_vec();
+    bytes.extend(body.to_bytes());
+    bytes.extend_from_slice(b"epilogue");
+
+    let form = common::extract::<Multipart<Upload>>(body.request().set_payload(bytes))
+        .await
+        .unwrap();
+    assert_eq!(form.title, "Notes");
+    assert_eq!(form.file.bytes, b"Hello");
+}
+
+#[actix_web::test]
+async fn decodes_encoded_field_names() {
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Intl {
+        #[serde(rename = "título")]
+        title: String,
+        #[serde(rename = "größe")]
+        size: u32,
+        #[serde(rename = "名前")]
+        name: Option<String>,
+        #[serde(rename = "100%")]
+        complete: bool,
+    }
+
+    let req = RawBody::new()
+        .part(
+            &[r#"Content-Disposition: form-data; name="t%C3%ADtulo""#],
+            "Notes",
+        )
+        .part(
+            &["Content-Disposition: form-data; name=\"size\"; name*=iso-8859-1''gr%F6%DFe"],
+            "3",
+        )
+        .part(
+            &["Content-Disposition: form-data; name=\"name\"; name*=UTF-8''%E5%90%8D%E5%89%8D"],
+            "Ana",
+        )
+        .text("100%", "true")
+        .request();
+    let form = common::extract::<Multipart<Intl>>(req).await.unwrap();
+
+    assert_eq!((form.title.as_str(), form.size), ("Notes", 3));
+    assert_eq!((form.name.as_deref(), form.complete), (Some("Ana"), true));
+}
+
+mod mixed {
+    use super::*;
+
+    // Only rejected without multer
+    #[cfg_attr(not(feature = "multer"), allow(dead_code))]
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Files {
+        #[multipart(max_size = 8)]
+        files: Vec<File>,
+    }
+
+    fn mixed(parts: &str) -> RawBody {
+        RawBody::new()
+            .part(
+                &[
+                    r#"Content-Disposition: form-data; name="files""#,
+                    "Content-Type: multipart/mixed; boundary=inner",
+                ],
+                format!("{parts}--inner--"),
+            )
+            .part(
+                &[r#"Content-Disposition: form-data; name="files"; filename="c.txt""#],
+                "C",
+            )
+    }
+
+    const FILES: &str = concat!(
+        "--inner\r\n",
+        "Content-Disposition: file; filename=\"a.txt\"\r\n",
+        "Content-Type: text/plain\r\n\r\n",
+        "A\r\n",
+        "--inner\r\n",
+        "Content-Disposition: file\r\n\r\n",
+        "not a file\r\n",
+        "--inner\r\n",
+        "Content-Disposition: file; filename=\"b.txt\"\r\n\r\n",
+        "B\r\n",
+    );
+
+    #[cfg(feature = "multer")]
+    #[actix_web::test]
+    async fn collects_nested_files_in_the_field() {
+        let form = common::extract::<Multipart<Files>>(mixed(FILES).request())
+            .await
+            .unwrap();
+
+        let files: Vec<_> = form
+            .files
+            .iter()
+            .map(|file| (file.name.as_str(), file.bytes.as_slice()))
+            .collect();
+        assert_eq!(
+            files,
+            [("a.txt", &b"A"[..]), ("b.txt", b"B"), ("c.txt", b"C")]
+        );
+        assert_eq!(form.files[0].content_type, "text/plain");
+    }
+
+    #[cfg(feature = "multer")]
+    #[actix_web::test]
+    async fn rejects_nested_parts_without_a_boundary() {
+        let body = RawBody::new().part(
+            &[
+                r#"Content-Disposition: form-data; name="files""#,
+                "Content-Type: multipart/mixed",
+            ],
+            FILES,
+        );
+        let err = common::extract::<Multipart<Files>>(body.request())
+            .await
+            .unwrap_err();
+        assert_eq!(common::error_of(&err).1, "malformed");
+    }
+
+    #[cfg(not(feature = "multer"))]
+    #[actix_web::test]
+    async fn rejects_nested_parts_without_multer() {
+        let err = common::extract::<Multipart<Files>>(mixed(FILES).request())
+            .await
+            .unwrap_err();
+        assert_eq!(common::error_of(&err).1, "nested_unsupported");
+    }
+
+    #[cfg(feature = "multer")]
+    #[actix_web::test]
+    async fn limits_nested_files() {
+        let parts = concat!(
+            "--inner\r\n",
+            "Content-Disposition: file; filename=\"a.txt\"\r\n\r\n",
+            "more than eight bytes\r\n",
+        );
+        let err = common::extract::<Multipart<Files>>(mixed(parts).request())
+            .await
+            .unwrap_err();
+
+        assert_eq!(common::error_of(&err).1, "file_too_large");
+    }
+}
+
+mod related {
+    use super::*;
+
+    // Only rejected without multer
+    #[cfg_attr(not(feature = "multer"), allow(dead_code))]
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Message {
+        #[multipart(content_id = "<root@example.com>")]
+        body: String,
+        #[multipart(content_id = "image@example.com")]
+        image: File,
+        caption: Option<String>,
+    }
+
+    #[cfg(not(feature = "multer"))]
+    #[actix_web::test]
+    async fn rejects_related_requests_without_multer() {
+        let body = RawBody::new().text("caption", "Holidays");
+        let req = body.request_as(r#"multipart/related; type="text/plain""#);
+        let err = common::extract::<Multipart<Message>>(req)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            common::error_of(&err),
+            (
+                actix_web::http::StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "not_multipart".to_string()
+            )
+        );
+    }
+
+    #[cfg(feature = "multer")]
+    #[actix_web::test]
+    async fn maps_parts_by_their_content_id() {
+        let body = RawBody::new()
+            .part(
+                &["Content-ID: <root@example.com>", "Content-Type: text/plain"],
+                "Hello",
+            )
+            .part(
+                &["Content-ID: <image@example.com>", "Content-Type: image/png"],
+                "pixels",
+            )
+            .part(&["Content-ID: <unknown@example.com>"], "skipped")
+            .text("caption", "Holidays");
+        let req = body.request_as(r#"multipart/related; type="text/plain""#);
+
+        let form = common::extract::<Multipart<Message>>(req).await.unwrap();
+        assert_eq!(form.body, "Hello");
+        assert_eq!(form.image.bytes, b"pixels");
+        assert_eq!(form.image.content_type, "image/png");
+        assert_eq!(form.image.name, "");
+        assert_eq!(form.caption.as_deref(), Some("Holidays"));
+    }
+}
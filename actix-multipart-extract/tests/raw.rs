@@ -0,0 +1,55 @@
+mod common;
+
+use actix_multipart_extract::{Limits, MultipartConfig, RawMultipart};
+use actix_web::{http::StatusCode, HttpResponse};
+use common::RawBody;
+
+fn body() -> RawBody {
+    RawBody::new()
+        .text("title", "Notes")
+        .part(
+            &[
+                r#"Content-Disposition: form-data; name="file"; filename="../notes.txt""#,
+                "Content-Type: text/plain",
+                "Content-Transfer-Encoding: base64",
+            ],
+            "SGVsbG8=",
+        )
+        .text("title", "Again")
+}
+
+#[actix_web::test]
+async fn extracts_every_part_in_order() {
+    let parts = common::extract::<RawMultipart>(body().request())
+        .await
+        .unwrap()
+        .into_inner();
+
+    let names: Vec<_> = parts.iter().map(|part| part.name.as_deref()).collect();
+    assert_eq!(names, [Some("title"), Some("file"), Some("title")]);
+
+    assert_eq!(parts[0].filename, None);
+    assert_eq!(parts[0].bytes, "Notes");
+    assert_eq!(parts[2].bytes, "Again");
+
+    assert_eq!(parts[1].filename.as_deref(), Some("notes.txt"));
+    assert_eq!(parts[1].headers.get("Content-Type").unwrap(), "text/plain");
+    assert_eq!(parts[1].bytes, "Hello");
+}
+
+#[actix_web::test]
+async fn shares_limits_and_error_handling() {
+    let config = MultipartConfig::default()
+        .set_limits_for(|_| Limits {
+            per_file: Some(4),
+            ..Limits::default()
+        })
+        .set_error_handler(|err| HttpResponse::ImATeapot().body(err.to_string()));
+    let err = common::extract_with::<RawMultipart>(body().request(), config)
+        .await
+        .unwrap_err();
+
+    let (status, _, body) = common::error_body(&err).await;
+    assert_eq!(status, StatusCode::IM_A_TEAPOT);
+    assert!(body.contains("too large"), "{body}");
+}
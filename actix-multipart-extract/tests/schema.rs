@@ -0,0 +1,122 @@
+#[cfg(feature = "schemars")]
+mod schemars {
+    use actix_multipart_extract::{schemars::schema_for, File, MultipartForm};
+    use serde::Deserialize;
+    use serde_json::json;
+
+    // Only described, never extracted
+    #[allow(dead_code)]
+    #[derive(Deserialize, MultipartForm)]
+    struct Upload {
+        #[multipart(max_size = 1KB)]
+        file: File,
+        tags: Vec<String>,
+        #[multipart(rename = "displayName")]
+        name: Option<String>,
+    }
+
+    #[test]
+    fn describes_files_as_binary_strings_with_their_max_size() {
+        let schema = serde_json::to_value(schema_for!(Upload)).unwrap();
+
+        assert_eq!(
+            schema["properties"]["file"],
+            json!({ "type": "string", "format": "binary", "x-max-size": 1000 })
+        );
+        assert_eq!(
+            schema["properties"]["tags"],
+            json!({ "type": "array", "items": { "type": "string" } })
+        );
+        assert_eq!(
+            schema["properties"]["displayName"]["type"],
+            json!(["string", "null"])
+        );
+        assert_eq!(schema["required"], json!(["file", "tags"]));
+    }
+}
+
+#[cfg(feature = "paperclip")]
+mod paperclip {
+    use actix_multipart_extract::{File, Multipart, MultipartForm};
+    use paperclip::{actix::OperationModifier, v2::models::DefaultOperationRaw};
+    use serde::Deserialize;
+    use serde_json::json;
+
+    // Only described, never extracted
+    #[allow(dead_code)]
+    #[derive(Deserialize, MultipartForm, paperclip::actix::Apiv2Schema)]
+    struct Upload {
+        file: File,
+        tags: Vec<String>,
+        note: Option<String>,
+    }
+
+    #[test]
+    fn documents_fields_as_form_data_parameters() {
+        let mut op = DefaultOperationRaw::default();
+        <Multipart<Upload> as OperationModifier>::update_parameter(&mut op);
+        let op = serde_json::to_value(op).unwrap();
+
+        assert_eq!(op["consumes"], json!(["multipart/form-data"]));
+        assert_eq!(
+            op["parameters"],
+            json!([
+                {
+                    "in": "formData",
+                    "name": "file",
+                    "required": true,
+                    "type": "file",
+                    "format": "binary"
+                },
+                { "in": "formData", "name": "note", "type": "string" },
+                {
+                    "in": "formData",
+                    "name": "tags",
+                    "required": true,
+                    "type": "array",
+                    "items": { "type": "string" }
+                }
+            ])
+        );
+    }
+}
+
+#[cfg(feature = "apistos")]
+mod apistos {
+    use actix_multipart_extract::{File, Multipart, MultipartForm};
+    use apistos::ApiComponent;
+    // The derive of `ApiComponent` refers to the schemars of apistos as `schemars`
+    use apistos_schemars as schemars;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    // Only described, never extracted
+    #[allow(dead_code)]
+    #[derive(Deserialize, MultipartForm, ApiComponent, schemars::JsonSchema)]
+    struct Upload {
+        #[multipart(max_size = 1KB)]
+        file: File,
+        name: String,
+    }
+
+    #[test]
+    fn documents_the_body_as_multipart() {
+        assert_eq!(
+            <Multipart<Upload> as ApiComponent>::content_type(),
+            "multipart/form-data"
+        );
+
+        let (name, schema) = <Multipart<Upload> as ApiComponent>::schema().unwrap();
+        let schema = serde_json::to_value(schema).unwrap();
+
+        assert_eq!(name, "Upload");
+        assert_eq!(
+            schema["properties"]["file"],
+            json!({ "type": "string", "format": "binary", "x-max-size": 1000 })
+        );
+        assert_eq!(
+            schema["examples"],
+            json!([{ "file": "file.bin", "name": "text" }])
+        );
+    }
+}
@@ -0,0 +1,217 @@
+mod common;
+
+use actix_multipart_extract::{
+    test::TestMultipartBuilder, EncodeError, File, Multipart, MultipartBody, MultipartForm,
+    MultipartSerialize,
+};
+use actix_web::test::TestRequest;
+use serde::Deserialize;
+
+#[derive(Deserialize, MultipartForm, MultipartSerialize, Debug)]
+struct Post {
+    title: String,
+    #[serde(rename = "tag")]
+    tags: Vec<String>,
+    draft: bool,
+    views: Option<u32>,
+    cover: File,
+    attachments: Vec<File>,
+}
+
+fn file(name: &str, bytes: &[u8]) -> File {
+    File {
+        content_type: "text/plain".to_string(),
+        detected_type: None,
+        guessed_type: None,
+        name: name.to_string(),
+        bytes: bytes.to_vec(),
+        size: bytes.len(),
+        hash: None,
+        duplicate_of: None,
+    }
+}
+
+fn post() -> Post {
+    Post {
+        title: "Hello \"world\"".to_string(),
+        tags: vec!["a".to_string(), "b".to_string()],
+        draft: true,
+        views: None,
+        cover: file("cover.txt", b"cover"),
+        attachments: vec![file("1.txt", b"one"), file("2.txt", b"two")],
+    }
+}
+
+#[actix_web::test]
+async fn encodes_forms_which_extract_back() {
+    let body = post().to_multipart().unwrap();
+    let req = TestMultipartBuilder::from(body).set_on(TestRequest::post());
+
+    let form = common::extract::<Multipart<Post>>(req).await.unwrap();
+    assert_eq!(form.title, "Hello \"world\"");
+    assert_eq!(form.tags, ["a", "b"]);
+    assert!(form.draft);
+    assert_eq!(form.views, None);
+
+    let files = std::iter::once(&form.cover).chain(&form.attachments);
+    let sent: Vec<_> = files
+        .map(|f| (f.name.as_str(), f.content_type.as_str(), f.bytes.as_slice()))
+        .collect();
+    assert_eq!(
+        sent,
+        [
+            ("cover.txt", "text/plain", &b"cover"[..]),
+            ("1.txt", "text/plain", b"one"),
+            ("2.txt", "text/plain", b"two"),
+        ]
+    );
+}
+
+#[derive(Deserialize, MultipartForm, MultipartSerialize, Debug, PartialEq)]
+struct Signed {
+    signature: Vec<u8>,
+    chunks: Vec<Vec<u8>>,
+    nonce: Option<Vec<u8>>,
+}
+
+#[actix_web::test]
+async fn encodes_byte_fields_as_raw_parts() {
+    let signed = Signed {
+        signature: vec![0, 0xff, 0xfe, b'\r', b'\n'],
+        chunks: vec![vec![0x80], vec![]],
+        nonce: None,
+    };
+
+    let mut body = signed.to_multipart().unwrap();
+    body.set_boundary("b").unwrap();
+    let encoded = body.to_bytes();
+    let head = "--b\r\nContent-Disposition: form-data; name=\"signature\"\r\n\
+        Content-Type: application/octet-stream\r\n\r\n";
+    assert!(encoded.starts_with(&[head.as_bytes(), &signed.signature].concat()));
+
+    let req = TestMultipartBuilder::from(body).set_on(TestRequest::post());
+    let form = common::extract::<Multipart<Signed>>(req).await.unwrap();
+    assert_eq!(form.into_inner(), signed);
+}
+
+#[test]
+fn writes_text_and_file_parts() {
+    let mut body = post().to_multipart().unwrap();
+    body.set_boundary("b").unwrap();
+    let encoded = String::from_utf8(body.to_bytes().to_vec()).unwrap();
+
+    for part in [
+        "--b\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nHello \"world\"\r\n",
+        "--b\r\nContent-Disposition: form-data; name=\"tag[]\"\r\n\r\na\r\n",
+        "--b\r\nContent-Disposition: form-data; name=\"draft\"\r\n\r\ntrue\r\n",
+        concat!(
+            "--b\r\nContent-Disposition: form-data; name=\"cover\"; filename=\"cover.txt\"\r\n",
+            "Content-Type: text/plain\r\n\r\ncover\r\n"
+        ),
+        "name=\"attachments[]\"; filename=\"2.txt\"",
+    ] {
+        assert!(encoded.contains(part), "{part:?} in {encoded:?}");
+    }
+    assert!(!encoded.contains("views"));
+    assert!(encoded.ends_with("--b--\r\n"));
+}
+#[test]
+fn quotes_boundaries_which_arent_tokens() {
+    let mut body = post().to_multipart().unwrap();
+
+    body.set_boundary("simple-boundary").unwrap();
+    assert_eq!(
+        body.content_type(),
+        "multipart/form-data; boundary=simple-boundary"
+    );
+
+    body.set_boundary("with spaces:=").unwrap();
+    assert_eq!(
+        body.content_type(),
+        r#"multipart/form-data; boundary="with spaces:=""#
+    );
+}
+
+#[test]
+fn rejects_headers_injected_in_parts() {
+    let injected = "text/plain\r\nX-Injected: true";
+    let mut body = MultipartBody::new();
+    assert_eq!(
+        body.file("file", "a.txt", injected, "A"),
+        Err(EncodeError::InvalidContentType(injected.to_string()))
+    );
+    assert!(body
+        .file("file", "a.txt", "text/plain; charset=utf-8", "A")
+        .is_ok());
+
+    let mut post = post();
+    post.cover.content_type = injected.to_string();
+    assert!(post.to_multipart().is_err());
+
+    // Names and filenames are escaped instead
+    body.text("a\"\r\nb", "A");
+    body.file("file", "a\"\r\n.txt", "text/plain", "A").unwrap();
+    let encoded = String::from_utf8(body.to_bytes().to_vec()).unwrap();
+    assert!(encoded.contains(r#"name="a%22%0D%0Ab""#), "{encoded:?}");
+    assert!(
+        encoded.contains(r#"filename="a%22%0D%0A.txt""#),
+        "{encoded:?}"
+    );
+    assert!(!encoded.contains("X-Injected"));
+}
+
+#[test]
+fn rejects_invalid_boundaries() {
+    let mut body = post().to_multipart().unwrap();
+    let boundary = body.boundary().to_string();
+
+    for invalid in ["", "a\r\nb", "quoted\"", "space ", "é", &"a".repeat(71)] {
+        assert_eq!(
+            body.set_boundary(invalid),
+            Err(EncodeError::InvalidBoundary(invalid.to_string()))
+        );
+    }
+    assert_eq!(body.boundary(), boundary);
+}
+
+#[cfg(feature = "awc")]
+mod awc_client {
+    use actix_multipart_extract::ClientRequestExt;
+    use actix_web::{web, App, HttpServer};
+
+    use super::*;
+
+    async fn echo(form: Multipart<Post>) -> String {
+        let names: Vec<_> = form.attachments.iter().map(|f| f.name.as_str()).collect();
+        format!(
+            "{} {:?} {} {:?}",
+            form.title, form.tags, form.cover.name, names
+        )
+    }
+
+    #[actix_web::test]
+    async fn sends_forms() {
+        let server = HttpServer::new(|| App::new().route("/", web::post().to(echo)))
+            .workers(1)
+            .bind(("127.0.0.1", 0))
+            .unwrap();
+        let addr = server.addrs()[0];
+        let server = server.run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+
+        let mut res = awc::Client::new()
+            .post(format!("http://{addr}/"))
+            .send_multipart_form(&post())
+            .await
+            .unwrap();
+
+        assert!(res.status().is_success());
+        assert_eq!(
+            res.body().await.unwrap(),
+            r#"Hello "world" ["a", "b"] cover.txt ["1.txt", "2.txt"]"#
+        );
+
+        handle.stop(true).await;
+    }
+}
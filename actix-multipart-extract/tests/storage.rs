@@ -0,0 +1,887 @@
+mod common;
+
+use actix_multipart_extract::{
+    test::TestMultipartBuilder, ContentScanner, File, FileMeta, FileSink, FileStorage,
+    MemoryStorage, Multipart, MultipartConfig, MultipartForm, StreamedFile,
+};
+use actix_web::{http::StatusCode, test::TestRequest, web::Bytes};
+use futures::{
+    future::LocalBoxFuture,
+    stream::{LocalBoxStream, StreamExt},
+    Sink,
+};
+use serde::Deserialize;
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Sink collecting the bytes of a file.
+#[derive(Default)]
+struct Collect(Vec<u8>);
+
+impl Sink<Bytes> for Collect {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Bytes) -> io::Result<()> {
+        self.get_mut().0.extend_from_slice(&item);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Upload {
+    video: StreamedFile,
+    thumbnail: File,
+}
+
+fn upload() -> TestRequest {
+    TestMultipartBuilder::new()
+        .file("video", "video.mp4", "video/mp4", "frames")
+        .file("thumbnail", "thumbnail.png", "image/png", "pixels")
+        .set_on(TestRequest::post())
+}
+
+#[actix_web::test]
+async fn streams_files_into_sinks() {
+    let config = MultipartConfig::default().set_file_storage(|field: &str, meta: &FileMeta| {
+        assert_eq!(meta.content_type.starts_with("video/"), field == "video");
+        (field == "video").then(|| Box::new(Collect::default()) as Box<dyn FileSink>)
+    });
+
+    let form = common::extract_with::<Multipart<Upload>>(upload(), config)
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert_eq!(form.thumbnail.bytes, b"pixels");
+    assert_eq!(form.video.size, 6);
+    assert_eq!(
+        form.video.meta,
+        FileMeta {
+            name: "video.mp4".to_string(),
+            content_type: "video/mp4".to_string(),
+        }
+    );
+    assert_eq!(form.video.downcast::<Collect>().ok().unwrap().0, b"frames");
+}
+
+#[actix_web::test]
+async fn rejects_files_buffered_for_streamed_fields() {
+    let err = common::extract::<Multipart<Upload>>(upload())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        common::error_of(&err),
+        (StatusCode::BAD_REQUEST, "parse_error".to_string())
+    );
+}
+
+/// Streams videos into sinks and buffers other files, failing on files without a name.
+struct VideoStorage;
+
+impl FileStorage for VideoStorage {
+    fn sink(
+        &self,
+        _: &str,
+        meta: &FileMeta,
+        _: &MultipartConfig,
+    ) -> io::Result<Option<Box<dyn FileSink>>> {
+        if meta.name.is_empty() {
+            return Err(io::Error::other("no name"));
+        }
+
+        Ok(meta
+            .content_type
+            .starts_with("video/")
+            .then(|| Box::new(Collect::default()) as Box<dyn FileSink>))
+    }
+}
+
+#[actix_web::test]
+async fn routes_files_through_the_storage() {
+    let config = MultipartConfig::default().set_file_storage(VideoStorage);
+    let form = common::extract_with::<Multipart<Upload>>(upload(), config)
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert_eq!(form.thumbnail.bytes, b"pixels");
+    assert_eq!(form.video.downcast::<Collect>().ok().unwrap().0, b"frames");
+}
+
+#[actix_web::test]
+async fn buffers_files_with_the_memory_storage() {
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Thumbnail {
+        thumbnail: File,
+    }
+
+    let config = MultipartConfig::default().set_file_storage(MemoryStorage);
+    let form = common::extract_with::<Multipart<Thumbnail>>(upload(), config)
+        .await
+        .unwrap();
+
+    assert_eq!(form.thumbnail.bytes, b"pixels");
+}
+
+#[actix_web::test]
+async fn rejects_files_which_cant_be_stored() {
+    let req = TestMultipartBuilder::new()
+        .file("video", "", "video/mp4", "frames")
+        .set_on(TestRequest::post());
+    let config = MultipartConfig::default().set_file_storage(VideoStorage);
+    let err = common::extract_with::<Multipart<Upload>>(req, config)
+        .await
+        .unwrap_err();
+
+    let (status, code, body) = common::error_body(&err).await;
+    assert_eq!(
+        (status, code.as_str()),
+        (StatusCode::BAD_REQUEST, "storage_error")
+    );
+    assert!(body.contains("no name"), "{body}");
+}
+
+/// Rejects files containing `virus`.
+struct Scanner;
+
+impl ContentScanner for Scanner {
+    fn scan<'a>(
+        &'a self,
+        _: &'a str,
+        meta: &'a FileMeta,
+        bytes: LocalBoxStream<'static, Bytes>,
+    ) -> LocalBoxFuture<'a, Result<(), String>> {
+        Box::pin(async move {
+            let bytes: Vec<u8> = bytes
+                .fold(Vec::new(), |mut all, chunk| async move {
+                    all.extend_from_slice(&chunk);
+                    all
+                })
+                .await;
+            match bytes.windows(5).any(|window| window == b"virus") {
+                true => Err(format!("{} is infected", meta.name)),
+                false => Ok(()),
+            }
+        })
+    }
+}
+
+fn scanned(video: &[u8], thumbnail: &[u8]) -> TestRequest {
+    TestMultipartBuilder::new()
+        .file("video", "video.mp4", "video/mp4", video.to_vec())
+        .file(
+            "thumbnail",
+            "thumbnail.png",
+            "image/png",
+            thumbnail.to_vec(),
+        )
+        .set_on(TestRequest::post())
+}
+
+fn scanned_config() -> MultipartConfig {
+    MultipartConfig::default()
+        .set_file_storage(VideoStorage)
+        .set_content_scanner(Scanner)
+}
+
+#[actix_web::test]
+async fn passes_clean_files_scanned() {
+    // Larger than the chunks buffered files are scanned in
+    let thumbnail = vec![b'x'; 200 * 1024];
+    let form =
+        common::extract_with::<Multipart<Upload>>(scanned(b"frames", &thumbnail), scanned_config())
+            .await
+            .unwrap()
+            .into_inner();
+
+    assert_eq!(form.thumbnail.bytes, thumbnail);
+    assert_eq!(form.video.downcast::<Collect>().ok().unwrap().0, b"frames");
+}
+
+#[actix_web::test]
+async fn rejects_files_flagged_by_the_scanner() {
+    let mut thumbnail = vec![b'x'; 200 * 1024];
+    thumbnail.extend_from_slice(b"virus");
+
+    let cases = [
+        (scanned(b"frames", &thumbnail), "thumbnail.png is infected"),
+        (scanned(b"a virus", b"pixels"), "video.mp4 is infected"),
+    ];
+
+    for (req, reason) in cases {
+        let err = common::extract_with::<Multipart<Upload>>(req, scanned_config())
+            .await
+            .unwrap_err();
+        let (status, code, body) = common::error_body(&err).await;
+        assert_eq!(
+            (status, code.as_str()),
+            (StatusCode::BAD_REQUEST, "rejected")
+        );
+        assert!(body.contains(reason), "{body}");
+    }
+}
+
+#[actix_web::test]
+async fn doesnt_scan_text() {
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Comment {
+        text: String,
+    }
+
+    let req = TestMultipartBuilder::new()
+        .text("text", "no virus here")
+        .set_on(TestRequest::post());
+    let config = MultipartConfig::default().set_content_scanner(Scanner);
+    let form = common::extract_with::<Multipart<Comment>>(req, config)
+        .await
+        .unwrap();
+
+    assert_eq!(form.text, "no virus here");
+}
+
+#[cfg(feature = "sniff")]
+#[actix_web::test]
+async fn checks_the_content_type_of_streamed_files() {
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Verified {
+        #[multipart(strict_content_type)]
+        video: StreamedFile,
+    }
+
+    let video = |content: &'static [u8]| {
+        TestMultipartBuilder::new()
+            .file("video", "video.mp4", "video/mp4", content)
+            .set_on(TestRequest::post())
+    };
+    let config = || MultipartConfig::default().set_file_storage(VideoStorage);
+
+    let form =
+        common::extract_with::<Multipart<Verified>>(video(b"\0\0\0\x18ftypisom\0\0\0\0"), config())
+            .await
+            .unwrap();
+    assert_eq!(form.video.size, 16);
+
+    let err = common::extract_with::<Multipart<Verified>>(video(b"%PDF-1.7\n"), config())
+        .await
+        .unwrap_err();
+    assert_eq!(common::error_of(&err).1, "content_type_mismatch");
+}
+
+#[cfg(feature = "object-store")]
+mod object_storage {
+    use actix_multipart_extract::UploadedObject;
+    use actix_web::rt::time::{sleep, timeout};
+    use object_store::{memory::InMemory, path::Path, ObjectStore};
+    use std::{sync::Arc, time::Duration};
+
+    use super::*;
+
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Video {
+        video: UploadedObject,
+        title: String,
+    }
+
+    fn config(store: &Arc<InMemory>) -> MultipartConfig {
+        MultipartConfig::default()
+            .set_object_store(store.clone(), |field: &str, meta: &FileMeta| {
+                Some(Path::from(format!("{field}/{}", meta.name)))
+            })
+    }
+
+    #[actix_web::test]
+    async fn uploads_files_to_the_store() {
+        let store = Arc::new(InMemory::new());
+        let req = TestMultipartBuilder::new()
+            .file("video", "video.mp4", "video/mp4", "frames")
+            .text("title", "Holidays")
+            .set_on(TestRequest::post());
+
+        let form = common::extract_with::<Multipart<Video>>(req, config(&store))
+            .await
+            .unwrap();
+
+        assert_eq!(form.title, "Holidays");
+        assert_eq!(form.video.location, Path::from("video/video.mp4"));
+        assert_eq!(form.video.size, 6);
+        assert_eq!(form.video.meta.content_type, "video/mp4");
+
+        let stored = store.get(&form.video.location).await.unwrap();
+        assert_eq!(stored.bytes().await.unwrap(), "frames");
+    }
+
+    #[actix_web::test]
+    async fn deletes_uploads_of_rejected_forms() {
+        let store = Arc::new(InMemory::new());
+        let req = TestMultipartBuilder::new()
+            .file("video", "video.mp4", "video/mp4", "frames")
+            .set_on(TestRequest::post());
+
+        // The title is missing
+        common::extract_with::<Multipart<Video>>(req, config(&store))
+            .await
+            .unwrap_err();
+
+        // Cleaned up in the background
+        sleep(Duration::from_millis(50)).await;
+        assert!(store.head(&Path::from("video/video.mp4")).await.is_err());
+    }
+
+    #[actix_web::test]
+    async fn deletes_uploads_of_cancelled_requests() {
+        let store = Arc::new(InMemory::new());
+        let (req, chunks) = stalled_after_video();
+        let mut extracted = Box::pin(common::extract_streamed::<Multipart<Video>>(
+            req.app_data(config(&store)),
+            chunks,
+        ));
+        timeout(Duration::from_millis(50), &mut extracted)
+            .await
+            .unwrap_err();
+        assert!(store.head(&Path::from("video/video.mp4")).await.is_ok());
+
+        // The client disconnected
+        drop(extracted);
+        sleep(Duration::from_millis(50)).await;
+        assert!(store.head(&Path::from("video/video.mp4")).await.is_err());
+    }
+}
+
+/// Request stalling once its video was sent, before the rest of the form.
+#[cfg(any(feature = "object-store", feature = "tempfile"))]
+fn stalled_after_video() -> (
+    TestRequest,
+    impl futures::Stream<Item = Result<Bytes, actix_web::error::PayloadError>>,
+) {
+    use common::RawBody;
+    use futures::stream;
+
+    let body = RawBody::new()
+        .file("video", "video.mp4", "video/mp4", "frames")
+        .text("title", "Holidays");
+    let mut sent = Bytes::from(body.truncated());
+    sent.truncate(sent.len() - 4);
+
+    (
+        body.request(),
+        stream::iter([Ok(sent)]).chain(stream::pending()),
+    )
+}
+
+#[cfg(feature = "tempfile")]
+mod temp_dir {
+    use actix_multipart_extract::{TempDirStorage, TempFile, TEMP_DIR_ENV};
+    use actix_web::rt::time::timeout;
+    use std::{fs, path::Path, time::Duration};
+
+    use super::*;
+
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Video {
+        video: TempFile,
+        title: String,
+    }
+
+    fn video() -> TestRequest {
+        TestMultipartBuilder::new()
+            .file("video", "video.mp4", "video/mp4", "frames")
+            .text("title", "Holidays")
+            .set_on(TestRequest::post())
+    }
+
+    async fn stored_in(config: MultipartConfig) -> TempFile {
+        let form = common::extract_with::<Multipart<Video>>(video(), config)
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(form.title, "Holidays");
+        form.video
+    }
+
+    fn is_in(file: &TempFile, dir: &Path) -> bool {
+        file.file.path().parent() == Some(dir)
+    }
+
+    #[actix_web::test]
+    async fn writes_files_to_the_temp_dir() {
+        let (config_dir, env_dir, storage_dir) = (
+            tempfile::tempdir().unwrap(),
+            tempfile::tempdir().unwrap(),
+            tempfile::tempdir().unwrap(),
+        );
+
+        // The only test of this binary reading the variable
+        std::env::set_var(TEMP_DIR_ENV, env_dir.path());
+
+        let file =
+            stored_in(MultipartConfig::default().set_file_storage(TempDirStorage::new())).await;
+        assert!(is_in(&file, env_dir.path()));
+        assert_eq!(fs::read(file.file.path()).unwrap(), b"frames");
+        assert_eq!((file.size, file.meta.name.as_str()), (6, "video.mp4"));
+
+        let config = MultipartConfig::default()
+            .set_temp_dir(config_dir.path())
+            .set_file_storage(TempDirStorage::new());
+        assert!(is_in(&stored_in(config).await, config_dir.path()));
+
+        let config = MultipartConfig::default()
+            .set_temp_dir(config_dir.path())
+            .set_file_storage(TempDirStorage::in_dir(storage_dir.path()));
+        assert!(is_in(&stored_in(config).await, storage_dir.path()));
+
+        std::env::remove_var(TEMP_DIR_ENV);
+    }
+
+    #[actix_web::test]
+    async fn deletes_files_of_rejected_requests() {
+        let dir = tempfile::tempdir().unwrap();
+        let req = TestMultipartBuilder::new()
+            .file("video", "video.mp4", "video/mp4", "frames")
+            .set_on(TestRequest::post());
+        let config =
+            MultipartConfig::default().set_file_storage(TempDirStorage::in_dir(dir.path()));
+
+        // The title is missing
+        common::extract_with::<Multipart<Video>>(req, config)
+            .await
+            .unwrap_err();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        let file = stored_in(
+            MultipartConfig::default().set_file_storage(TempDirStorage::in_dir(dir.path())),
+        )
+        .await;
+        let path = file.file.path().to_owned();
+        drop(file);
+        assert!(!path.exists());
+    }
+
+    #[actix_web::test]
+    async fn deletes_files_of_cancelled_requests() {
+        let dir = tempfile::tempdir().unwrap();
+        let (req, chunks) = stalled_after_video();
+        let config =
+            MultipartConfig::default().set_file_storage(TempDirStorage::in_dir(dir.path()));
+        let mut extracted = Box::pin(common::extract_streamed::<Multipart<Video>>(
+            req.app_data(config),
+            chunks,
+        ));
+        timeout(Duration::from_millis(50), &mut extracted)
+            .await
+            .unwrap_err();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // The client disconnected
+        drop(extracted);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
+
+#[cfg(feature = "tempfile")]
+mod spooled {
+    use actix_multipart_extract::{MultipartSerialize, SpooledFile};
+    use std::{fs, io::Read};
+
+    use super::*;
+
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Attachments {
+        small: SpooledFile,
+        large: SpooledFile,
+        buffered: File,
+    }
+
+    #[actix_web::test]
+    async fn spills_large_files_to_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let req = TestMultipartBuilder::new()
+            .file("small", "small.txt", "text/plain", "tiny")
+            .file("large", "large.txt", "text/plain", "larger than eight")
+            .file(
+                "buffered",
+                "buffered.txt",
+                "text/plain",
+                "also larger than eight",
+            )
+            .set_on(TestRequest::post());
+        let config = MultipartConfig::default()
+            .set_memory_threshold(8)
+            .set_temp_dir(dir.path());
+
+        let form = common::extract_with::<Multipart<Attachments>>(req, config)
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert!(form.small.is_in_memory());
+        assert_eq!(form.small.path(), None);
+        assert_eq!(
+            (form.small.size, &form.small.bytes().unwrap()[..]),
+            (4, &b"tiny"[..])
+        );
+
+        assert!(!form.large.is_in_memory());
+        assert_eq!(form.large.path().unwrap().parent(), Some(dir.path()));
+        assert_eq!(form.large.meta.name, "large.txt");
+        let mut contents = String::new();
+        form.large
+            .reader()
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "larger than eight");
+
+        // Only `SpooledFile` fields are spooled
+        assert_eq!(form.buffered.bytes, b"also larger than eight");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        let kept = dir.path().join("kept.txt");
+        form.large.persist(&kept).unwrap();
+        form.small.persist(dir.path().join("small.txt")).unwrap();
+        assert_eq!(fs::read(&kept).unwrap(), b"larger than eight");
+        assert_eq!(fs::read(dir.path().join("small.txt")).unwrap(), b"tiny");
+    }
+
+    #[derive(Deserialize, MultipartForm, MultipartSerialize, Debug)]
+    struct Large {
+        large: SpooledFile,
+    }
+
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Wrapped(Large);
+
+    #[actix_web::test]
+    async fn spools_fields_of_newtype_forms() {
+        let dir = tempfile::tempdir().unwrap();
+
+        for contents in ["tiny", "larger than eight"] {
+            let req = TestMultipartBuilder::new()
+                .file("large", "large.txt", "text/plain", contents)
+                .set_on(TestRequest::post());
+            let config = MultipartConfig::default()
+                .set_memory_threshold(8)
+                .set_temp_dir(dir.path());
+            let Wrapped(form) = common::extract_with::<Multipart<Wrapped>>(req, config)
+                .await
+                .unwrap()
+                .into_inner();
+
+            assert_eq!(form.large.is_in_memory(), contents.len() <= 8);
+            let mut read = String::new();
+            form.large
+                .reader()
+                .unwrap()
+                .read_to_string(&mut read)
+                .unwrap();
+            assert_eq!(read, contents);
+        }
+    }
+
+    #[actix_web::test]
+    async fn encodes_spooled_files() {
+        let dir = tempfile::tempdir().unwrap();
+
+        for contents in ["tiny", "larger than eight"] {
+            let config = || {
+                MultipartConfig::default()
+                    .set_memory_threshold(8)
+                    .set_temp_dir(dir.path())
+            };
+            let req = TestMultipartBuilder::new()
+                .file("large", "large.txt", "text/plain", contents)
+                .set_on(TestRequest::post());
+            let form = common::extract_with::<Multipart<Large>>(req, config())
+                .await
+                .unwrap();
+
+            let req = TestMultipartBuilder::from(form.to_multipart().unwrap())
+                .set_on(TestRequest::post());
+            let form = common::extract_with::<Multipart<Large>>(req, config())
+                .await
+                .unwrap();
+            assert_eq!(form.large.meta.name, "large.txt");
+            assert_eq!(form.large.meta.content_type, "text/plain");
+            assert_eq!(form.large.bytes().unwrap(), contents.as_bytes());
+        }
+    }
+}
+
+#[cfg(feature = "resumable")]
+mod resumable {
+    use actix_multipart_extract::{
+        MemoryBudget, MultipartError, MultipartMetrics, ResumableUploads, UPLOAD_ID_HEADER,
+        UPLOAD_OFFSET_HEADER,
+    };
+    use actix_web::{
+        http::{header, StatusCode},
+        HttpResponse,
+    };
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::*;
+
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Upload {
+        title: String,
+        file: File,
+    }
+
+    /// A request sending `chunk` of the file at `range`.
+    fn chunk(id: Option<&str>, range: &str, chunk: &'static str) -> TestRequest {
+        let req = TestMultipartBuilder::new()
+            .text("title", "Notes")
+            .file("file", "notes.txt", "text/plain", chunk)
+            .set_on(TestRequest::post())
+            .insert_header((header::CONTENT_RANGE, range));
+
+        match id {
+            Some(id) => req.insert_header((UPLOAD_ID_HEADER, id)),
+            None => req,
+        }
+    }
+
+    async fn send(
+        uploads: &ResumableUploads,
+        req: TestRequest,
+    ) -> Result<Upload, actix_web::Error> {
+        let config = MultipartConfig::default().set_resumable_uploads(uploads.clone());
+        common::extract_with::<Multipart<Upload>>(req, config)
+            .await
+            .map(Multipart::into_inner)
+    }
+
+    /// Status, code, `Upload-Offset` and `Range` headers of the response to an error.
+    fn response(err: actix_web::Error) -> (StatusCode, String, Option<String>, Option<String>) {
+        let res = err.as_response_error().error_response();
+        let header = |name| {
+            res.headers()
+                .get(name)
+                .map(|value| value.to_str().unwrap().to_string())
+        };
+        let code = header("X-Error-Code").unwrap();
+        (
+            res.status(),
+            code,
+            header(UPLOAD_OFFSET_HEADER),
+            header(header::RANGE.as_str()),
+        )
+    }
+
+    fn incomplete(
+        offset: &str,
+        range: Option<&str>,
+    ) -> (StatusCode, String, Option<String>, Option<String>) {
+        (
+            StatusCode::ACCEPTED,
+            "upload_incomplete".to_string(),
+            Some(offset.to_string()),
+            range.map(str::to_string),
+        )
+    }
+
+    #[actix_web::test]
+    async fn assembles_chunks() {
+        let uploads = ResumableUploads::new(Duration::from_secs(60));
+
+        let err = send(&uploads, chunk(Some("a"), "bytes 0-2/11", "Hel"))
+            .await
+            .unwrap_err();
+        assert_eq!(response(err), incomplete("3", Some("bytes=0-2")));
+
+        // Asking how much was received
+        let err = send(&uploads, chunk(Some("a"), "bytes */11", ""))
+            .await
+            .unwrap_err();
+        assert_eq!(response(err), incomplete("3", Some("bytes=0-2")));
+
+        let err = send(&uploads, chunk(Some("a"), "bytes 3-5/11", "lo "))
+            .await
+            .unwrap_err();
+        assert_eq!(response(err), incomplete("6", Some("bytes=0-5")));
+        assert_eq!(uploads.len(), 1);
+
+        // Nothing was received for other uploads
+        let err = send(&uploads, chunk(Some("b"), "bytes */11", ""))
+            .await
+            .unwrap_err();
+        assert_eq!(response(err), incomplete("0", None));
+
+        let form = send(&uploads, chunk(Some("a"), "bytes 6-10/11", "world"))
+            .await
+            .unwrap();
+        assert_eq!(form.title, "Notes");
+        assert_eq!(
+            (form.file.name.as_str(), &form.file.bytes[..]),
+            ("notes.txt", &b"Hello world"[..])
+        );
+        assert!(uploads.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn rejects_invalid_chunks() {
+        let uploads = ResumableUploads::new(Duration::from_secs(60));
+        send(&uploads, chunk(Some("a"), "bytes 0-2/11", "Hel"))
+            .await
+            .unwrap_err();
+
+        let err = send(&uploads, chunk(Some("a"), "bytes 6-10/11", "world"))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            response(err),
+            (
+                StatusCode::CONFLICT,
+                "upload_offset_mismatch".to_string(),
+                Some("3".to_string()),
+                None
+            )
+        );
+
+        // Uploads only start with their first bytes
+        let err = send(&uploads, chunk(Some("b"), "bytes 3-5/11", "lo "))
+            .await
+            .unwrap_err();
+        assert_eq!(response(err).2.as_deref(), Some("0"));
+        assert_eq!(uploads.len(), 1);
+
+        for req in [
+            chunk(None, "bytes 0-2/11", "Hel"),
+            chunk(Some("b"), "bytes 0-3/11", "Hel"),
+            chunk(Some("b"), "bytes 2-1/11", "Hel"),
+        ] {
+            let err = send(&uploads, req).await.unwrap_err();
+            assert_eq!(
+                response(err),
+                (
+                    StatusCode::BAD_REQUEST,
+                    "invalid_upload".to_string(),
+                    None,
+                    None
+                )
+            );
+        }
+        assert_eq!(uploads.len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn limits_incomplete_uploads() {
+        let rejected = |code: &str| {
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                code.to_string(),
+                None,
+                None,
+            )
+        };
+
+        let uploads = ResumableUploads::new(Duration::from_secs(60)).set_max_uploads(1);
+        send(&uploads, chunk(Some("a"), "bytes 0-2/11", "Hel"))
+            .await
+            .unwrap_err();
+        let err = send(&uploads, chunk(Some("b"), "bytes 0-2/11", "Hel"))
+            .await
+            .unwrap_err();
+        assert_eq!(response(err), rejected("too_many_uploads"));
+        send(&uploads, chunk(Some("a"), "bytes 3-10/11", "lo world"))
+            .await
+            .unwrap();
+
+        let uploads = ResumableUploads::new(Duration::from_secs(60)).set_max_bytes(8);
+        let err = send(&uploads, chunk(Some("a"), "bytes 0-2/11", "Hel"))
+            .await
+            .unwrap_err();
+        assert_eq!(response(err).1, "file_too_large");
+
+        send(&uploads, chunk(Some("a"), "bytes 0-4/8", "Hello"))
+            .await
+            .unwrap_err();
+        let err = send(&uploads, chunk(Some("b"), "bytes 0-4/8", "world"))
+            .await
+            .unwrap_err();
+        assert_eq!(response(err), rejected("memory_limit_reached"));
+        assert_eq!(uploads.len(), 1);
+    }
+
+    /// Metrics recording the codes of rejected requests.
+    #[derive(Clone, Default)]
+    struct Rejected(Arc<Mutex<Vec<String>>>);
+
+    impl MultipartMetrics for Rejected {
+        fn on_rejected(&self, error: &MultipartError) {
+            self.0.lock().unwrap().push(error.code().to_string());
+        }
+    }
+
+    #[actix_web::test]
+    async fn answers_incomplete_uploads_without_the_error_handlers() {
+        let uploads = ResumableUploads::new(Duration::from_secs(60));
+        let rejected = Rejected::default();
+        let config = || {
+            MultipartConfig::default()
+                .set_resumable_uploads(uploads.clone())
+                .set_metrics(rejected.clone())
+                .set_status_for(|_| StatusCode::IM_A_TEAPOT)
+                .set_error_handler(|err| HttpResponse::BadRequest().body(err.code()))
+        };
+
+        let req = chunk(Some("a"), "bytes 0-2/11", "Hel");
+        let err = common::extract_with::<Multipart<Upload>>(req, config())
+            .await
+            .unwrap_err();
+        assert_eq!(response(err), incomplete("3", Some("bytes=0-2")));
+        assert!(rejected.0.lock().unwrap().is_empty());
+
+        // Other errors of uploads still go through them
+        let req = chunk(Some("a"), "bytes 6-10/11", "world");
+        let err = common::extract_with::<Multipart<Upload>>(req, config())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.as_response_error().status_code(),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(*rejected.0.lock().unwrap(), ["upload_offset_mismatch"]);
+    }
+
+    #[actix_web::test]
+    async fn counts_incomplete_uploads_in_the_memory_limit() {
+        let uploads = ResumableUploads::new(Duration::from_secs(60));
+        let budget = MemoryBudget::new(1000);
+        let config = || {
+            MultipartConfig::default()
+                .set_resumable_uploads(uploads.clone())
+                .set_shared_memory_limit(budget.clone())
+        };
+
+        let req = chunk(Some("a"), "bytes 0-2/11", "Hel");
+        common::extract_with::<Multipart<Upload>>(req, config())
+            .await
+            .unwrap_err();
+        assert_eq!(budget.used(), 3);
+
+        let req = chunk(Some("a"), "bytes 3-10/11", "lo world");
+        common::extract_with::<Multipart<Upload>>(req, config())
+            .await
+            .unwrap();
+        assert_eq!(budget.used(), 0);
+    }
+}
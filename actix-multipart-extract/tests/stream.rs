@@ -0,0 +1,207 @@
+mod common;
+
+use actix_multipart_extract::{
+    Field, File, MemoryBudget, MultipartConfig, MultipartError, MultipartForm, MultipartStream,
+    PartMeta,
+};
+use actix_web::{error::PayloadError, web::Bytes};
+use common::RawBody;
+use futures::StreamExt;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+fn parse_csv(bytes: &[u8], meta: &PartMeta) -> Result<Value, String> {
+    assert_eq!(meta.field, "labels");
+    let text = std::str::from_utf8(bytes).map_err(|err| err.to_string())?;
+    Ok(text.split(',').collect::<Vec<_>>().into())
+}
+
+// Only streamed, never extracted
+#[allow(dead_code)]
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Upload {
+    #[multipart(trim)]
+    title: String,
+    count: u32,
+    tags: Vec<String>,
+    #[multipart(with = "parse_csv")]
+    labels: Vec<String>,
+    #[multipart(default = 3)]
+    retries: u32,
+    #[multipart(default = "public")]
+    visibility: String,
+    #[multipart(max_size = 8)]
+    file: File,
+}
+
+/// Every field streamed from `body`, with the bytes of files.
+async fn stream_all(
+    body: RawBody,
+    config: MultipartConfig,
+) -> Result<Vec<(String, Value)>, MultipartError> {
+    let req = body.request().app_data(config);
+    let mut fields = common::extract::<MultipartStream<Upload>>(req)
+        .await
+        .unwrap();
+    let mut streamed = Vec::new();
+
+    while let Some(field) = fields.next().await? {
+        streamed.push(match field {
+            Field::Text { name, value } => (name, value),
+            Field::File {
+                name,
+                meta,
+                mut stream,
+            } => {
+                let mut bytes = Vec::new();
+                while let Some(chunk) = stream.next().await {
+                    bytes.extend_from_slice(&chunk?);
+                }
+                let contents = String::from_utf8(bytes).unwrap();
+                (name, json!({ "name": meta.name, "contents": contents }))
+            }
+        });
+    }
+
+    Ok(streamed)
+}
+
+const FILE: &[&str] = &[
+    r#"Content-Disposition: form-data; name="file"; filename="a.txt""#,
+    "Content-Type: text/plain",
+];
+
+#[actix_web::test]
+async fn streams_fields_as_multipart_parses_them() {
+    let body = RawBody::new()
+        .text("title", "  Notes ")
+        .text("count", "42")
+        .text("tags[]", "a")
+        .text("unknown", "skipped")
+        .text("tags", "b")
+        .text("labels", "x,y")
+        .text("visibility", "private")
+        .part(FILE, "Hello");
+
+    let fields = stream_all(body, MultipartConfig::default()).await.unwrap();
+    assert_eq!(
+        fields,
+        [
+            ("title".to_string(), json!("Notes")),
+            ("count".to_string(), json!(42)),
+            ("tags".to_string(), json!("a")),
+            ("tags".to_string(), json!("b")),
+            ("labels".to_string(), json!(["x", "y"])),
+            ("visibility".to_string(), json!("private")),
+            (
+                "file".to_string(),
+                json!({ "name": "a.txt", "contents": "Hello" })
+            ),
+            // Defaults of the fields which weren't sent come last
+            ("retries".to_string(), json!(3)),
+        ]
+    );
+}
+
+#[actix_web::test]
+async fn limits_streamed_files() {
+    let body = RawBody::new().part(FILE, "more than eight bytes");
+    let err = stream_all(body, MultipartConfig::default())
+        .await
+        .unwrap_err();
+
+    assert!(
+        matches!(&err, MultipartError::FileSizeError { field, limit: 8 } if field == "file"),
+        "{err:?}"
+    );
+}
+
+#[actix_web::test]
+async fn checks_text_fields() {
+    let body = RawBody::new().text("labels", [0xff, 0xfe]);
+    let err = stream_all(body, MultipartConfig::default())
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, MultipartError::FieldValidationError { field, .. } if field == "labels"),
+        "{err:?}"
+    );
+
+    let body = RawBody::new().text("unknown", "rejected");
+    let config = MultipartConfig::default().set_deny_unknown_fields(true);
+    let err = stream_all(body, config).await.unwrap_err();
+    assert!(
+        matches!(&err, MultipartError::UnknownField { field } if field == "unknown"),
+        "{err:?}"
+    );
+}
+
+#[actix_web::test]
+async fn holds_text_fields_to_the_shared_memory_limit() {
+    let budget = MemoryBudget::new(1000);
+    let tag = "a".repeat(600);
+    let body = RawBody::new().text("tags", &tag).text("tags", &tag);
+    let config = MultipartConfig::default().set_shared_memory_limit(budget.clone());
+    let req = body.request().app_data(config);
+    let mut fields = common::extract::<MultipartStream<Upload>>(req)
+        .await
+        .unwrap();
+
+    // The first tag is still held by the handler while the second one is read
+    fields.next().await.unwrap().unwrap();
+    assert!(budget.used() >= 600, "{}", budget.used());
+    let err = fields.next().await.unwrap_err();
+    assert!(
+        matches!(err, MultipartError::MemoryLimitError { limit: 1000 }),
+        "{err:?}"
+    );
+
+    drop(fields);
+    assert_eq!(budget.used(), 0);
+}
+
+#[actix_web::test]
+async fn fails_on_malformed_streams() {
+    let body = RawBody::new()
+        .text("title", "Notes")
+        .part(&["not a header"], "Ignored")
+        .part(FILE, "Hello");
+    let err = stream_all(body, MultipartConfig::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, MultipartError::Malformed), "{err:?}");
+}
+
+#[actix_web::test]
+async fn fails_files_with_chunks_which_cant_be_read() {
+    let body = RawBody::new().part(FILE, "Hello");
+
+    // The stream fails in the middle of the file
+    let bytes = body.to_bytes();
+    let middle = bytes.windows(5).position(|w| w == b"Hello").unwrap() + 3;
+    let (head, tail) = bytes.split_at(middle);
+    let chunks = futures::stream::iter([
+        Ok(Bytes::copy_from_slice(head)),
+        Err(PayloadError::Overflow),
+        Ok(Bytes::copy_from_slice(tail)),
+    ])
+    .then(|chunk| async {
+        // Each chunk arrives on its own
+        actix_web::rt::task::yield_now().await;
+        chunk
+    });
+
+    let mut fields = common::extract_streamed::<MultipartStream<Upload>>(body.request(), chunks)
+        .await
+        .unwrap();
+    let Some(Field::File { stream, .. }) = fields.next().await.unwrap() else {
+        panic!("expected the file");
+    };
+
+    // The file ends with the error instead of looking complete
+    let read: Vec<_> = stream.collect().await;
+    assert!(
+        matches!(read.last(), Some(Err(MultipartError::Malformed))),
+        "{read:?}"
+    );
+}
@@ -0,0 +1,409 @@
+mod common;
+
+use actix_multipart_extract::{
+    test::TestMultipartBuilder, File, Multipart, MultipartConfig, MultipartError, MultipartForm,
+    MultipartMetrics, MultipartStats, RawMultipart,
+};
+use actix_web::{test::TestRequest, FromRequest, HttpMessage};
+use common::extract_with;
+use serde::Deserialize;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Metrics recording the requests they were called for.
+#[derive(Clone, Default)]
+struct Recorded {
+    completed: Arc<Mutex<Vec<(usize, usize)>>>,
+    rejected: Arc<Mutex<Vec<String>>>,
+}
+
+impl MultipartMetrics for Recorded {
+    fn on_request_complete(&self, bytes: usize, files: usize, _duration: Duration) {
+        self.completed.lock().unwrap().push((bytes, files));
+    }
+
+    fn on_rejected(&self, error: &MultipartError) {
+        self.rejected.lock().unwrap().push(error.code().to_string());
+    }
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Upload {
+    #[multipart(max_size = 8)]
+    title: String,
+    files: Vec<File>,
+}
+
+fn upload(title: &str) -> TestRequest {
+    TestMultipartBuilder::new()
+        .text("title", title)
+        .file("files[]", "a.txt", "text/plain", "a")
+        .file("files[]", "b.txt", "text/plain", "b")
+        .set_on(TestRequest::post())
+}
+
+#[actix_web::test]
+async fn reports_completed_requests() {
+    let metrics = Recorded::default();
+    let config = MultipartConfig::default().set_metrics(metrics.clone());
+    let form = extract_with::<Multipart<Upload>>(upload("Hello"), config)
+        .await
+        .unwrap();
+    assert_eq!((form.title.as_str(), form.files.len()), ("Hello", 2));
+
+    let completed = metrics.completed.lock().unwrap().clone();
+    assert_eq!(completed.len(), 1);
+    let (bytes, files) = completed[0];
+    assert_eq!(files, 2);
+    assert!(bytes > 7, "{bytes}");
+    assert!(metrics.rejected.lock().unwrap().is_empty());
+
+    // Also called for raw parts
+    let config = MultipartConfig::default().set_metrics(metrics.clone());
+    let raw = extract_with::<RawMultipart>(upload("Hello"), config)
+        .await
+        .unwrap();
+    assert_eq!(raw.len(), 3);
+    assert_eq!(metrics.completed.lock().unwrap()[1], (bytes, 2));
+}
+
+#[actix_web::test]
+async fn reports_rejected_requests() {
+    let metrics = Recorded::default();
+    let config = MultipartConfig::default().set_metrics(metrics.clone());
+    extract_with::<Multipart<Upload>>(upload("Far too long"), config)
+        .await
+        .unwrap_err();
+
+    assert_eq!(*metrics.rejected.lock().unwrap(), ["text_too_large"]);
+    assert!(metrics.completed.lock().unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn inserts_stats_into_extensions() {
+    let (req, mut payload) = upload("Hello").to_http_parts();
+    let form = Multipart::<Upload>::from_request(&req, &mut payload)
+        .await
+        .unwrap();
+    assert_eq!(form.files.len(), 2);
+
+    let stats = *req.extensions().get::<MultipartStats>().unwrap();
+    assert_eq!((stats.file_count, stats.field_count), (2, 3));
+    assert!(stats.total_bytes > 7, "{stats:?}");
+
+    // Also inserted for raw parts
+    let (req, mut payload) = upload("Hello").to_http_parts();
+    RawMultipart::from_request(&req, &mut payload)
+        .await
+        .unwrap();
+    let raw_stats = *req.extensions().get::<MultipartStats>().unwrap();
+    assert_eq!(raw_stats.total_bytes, stats.total_bytes);
+
+    // Rejected requests have none
+    let (req, mut payload) = upload("Far too long").to_http_parts();
+    Multipart::<Upload>::from_request(&req, &mut payload)
+        .await
+        .unwrap_err();
+    assert!(req.extensions().get::<MultipartStats>().is_none());
+}
+
+#[cfg(feature = "tracing")]
+mod tracing_spans {
+    use actix_multipart_extract::{test::TestMultipartBuilder, File, Multipart, MultipartForm};
+    use actix_web::test::TestRequest;
+    use serde::Deserialize;
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        fmt::Debug,
+        sync::{Arc, Mutex, OnceLock},
+    };
+    use tracing::{
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+        Event, Metadata, Subscriber,
+    };
+    use tracing_core::span::Current;
+
+    use crate::common::extract;
+
+    type Fields = HashMap<&'static str, String>;
+
+    struct RecordedSpan {
+        metadata: &'static Metadata<'static>,
+        fields: Fields,
+    }
+
+    struct RecordedEvent {
+        /// Index of the span the event was recorded in.
+        span: Option<usize>,
+        fields: Fields,
+    }
+
+    #[derive(Default)]
+    struct Recorded {
+        spans: Vec<RecordedSpan>,
+        events: Vec<RecordedEvent>,
+    }
+
+    thread_local! {
+        static ENTERED: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// Subscriber recording every span and event of every test, set once as the global
+    /// default so other threads can't cache callsites as disabled.
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Recorded>>);
+
+    struct Visitor<'a>(&'a mut Fields);
+
+    impl Visit for Visitor<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name(), value.to_string());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0.insert(field.name(), format!("{value:?}"));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut fields = Fields::new();
+            span.record(&mut Visitor(&mut fields));
+
+            let mut recorded = self.0.lock().unwrap();
+            recorded.spans.push(RecordedSpan {
+                metadata: span.metadata(),
+                fields,
+            });
+            Id::from_u64(recorded.spans.len() as u64)
+        }
+
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            let mut recorded = self.0.lock().unwrap();
+            let span = &mut recorded.spans[span.into_u64() as usize - 1];
+            values.record(&mut Visitor(&mut span.fields));
+        }
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields::new();
+            event.record(&mut Visitor(&mut fields));
+
+            let span = ENTERED.with(|entered| entered.borrow().last().copied());
+            let mut recorded = self.0.lock().unwrap();
+            recorded.events.push(RecordedEvent { span, fields });
+        }
+
+        fn enter(&self, span: &Id) {
+            ENTERED.with(|entered| entered.borrow_mut().push(span.into_u64() as usize - 1));
+        }
+
+        fn exit(&self, _: &Id) {
+            ENTERED.with(|entered| entered.borrow_mut().pop());
+        }
+
+        fn current_span(&self) -> Current {
+            match ENTERED.with(|entered| entered.borrow().last().copied()) {
+                Some(span) => {
+                    let metadata = self.0.lock().unwrap().spans[span].metadata;
+                    Current::new(Id::from_u64(span as u64 + 1), metadata)
+                }
+                None => Current::none(),
+            }
+        }
+    }
+
+    fn recorder() -> &'static Recorder {
+        static RECORDER: OnceLock<Recorder> = OnceLock::new();
+
+        RECORDER.get_or_init(|| {
+            let recorder = Recorder::default();
+            tracing::subscriber::set_global_default(recorder.clone()).unwrap();
+            recorder
+        })
+    }
+
+    /// Index of the only extraction span of a form.
+    fn extract_span(recorded: &Recorded, form: &str) -> usize {
+        let spans: Vec<_> = (0..recorded.spans.len())
+            .filter(|&i| {
+                let span = &recorded.spans[i];
+                span.metadata.name() == "multipart.extract" && span.fields["form"].ends_with(form)
+            })
+            .collect();
+
+        assert_eq!(spans.len(), 1);
+        spans[0]
+    }
+
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Traced {
+        title: String,
+        file: File,
+    }
+
+    #[actix_web::test]
+    async fn records_extractions_and_their_fields() {
+        let recorder = recorder();
+        let req = TestMultipartBuilder::new()
+            .text("title", "Hello")
+            .file("file", "a.png", "image/png", "pixels")
+            .set_on(TestRequest::post());
+        let form = extract::<Multipart<Traced>>(req).await.unwrap();
+        assert_eq!((form.title.as_str(), form.file.size), ("Hello", 6));
+
+        let recorded = recorder.0.lock().unwrap();
+        let span = extract_span(&recorded, "::Traced");
+        let bytes_read: usize = recorded.spans[span].fields["bytes_read"].parse().unwrap();
+        assert!(bytes_read > 11, "{bytes_read}");
+
+        let fields: Vec<_> = recorded
+            .events
+            .iter()
+            .filter(|event| event.span == Some(span))
+            .map(|event| &event.fields)
+            .collect();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[0]["message"], "multipart field read");
+        assert_eq!(
+            (fields[0]["field"].as_str(), fields[0]["size"].as_str()),
+            ("title", "5")
+        );
+        assert_eq!(
+            (
+                fields[1]["field"].as_str(),
+                fields[1]["size"].as_str(),
+                fields[1]["content_type"].as_str()
+            ),
+            ("file", "6", "image/png")
+        );
+        assert!(fields[1].contains_key("duration_ms"));
+    }
+
+    // Only rejected, never extracted
+    #[allow(dead_code)]
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct TracedFailure {
+        #[multipart(max_size = 2)]
+        title: String,
+    }
+
+    #[actix_web::test]
+    async fn records_failed_extractions() {
+        let recorder = recorder();
+        let req = TestMultipartBuilder::new()
+            .text("title", "Hello")
+            .set_on(TestRequest::post());
+        extract::<Multipart<TracedFailure>>(req).await.unwrap_err();
+
+        let recorded = recorder.0.lock().unwrap();
+        let span = extract_span(&recorded, "::TracedFailure");
+        let failure = recorded
+            .events
+            .iter()
+            .find(|event| {
+                event.span == Some(span) && event.fields["message"] == "multipart extraction failed"
+            })
+            .unwrap();
+        assert!(
+            failure.fields["error"].contains("title"),
+            "{:?}",
+            failure.fields
+        );
+    }
+}
+
+#[cfg(feature = "logging")]
+mod logging {
+    use actix_multipart_extract::{test::TestMultipartBuilder, Multipart, MultipartForm};
+    use actix_web::test::TestRequest;
+    use log::{Level, Log, Metadata, Record};
+    use serde::Deserialize;
+    use std::sync::{Mutex, Once};
+
+    use crate::common::extract;
+
+    /// Logger recording every message of every test.
+    struct Logger(Mutex<Vec<(Level, String)>>);
+
+    impl Log for Logger {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record<'_>) {
+            let message = record.args().to_string();
+            self.0.lock().unwrap().push((record.level(), message));
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: Logger = Logger(Mutex::new(Vec::new()));
+
+    fn init_logger() {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| {
+            log::set_logger(&LOGGER).unwrap();
+            log::set_max_level(log::LevelFilter::Trace);
+        });
+    }
+
+    /// Messages logged so far which contain `text`.
+    fn logged(text: &str) -> Vec<(Level, String)> {
+        let logged = LOGGER.0.lock().unwrap();
+        logged
+            .iter()
+            .filter(|(_, message)| message.contains(text))
+            .cloned()
+            .collect()
+    }
+
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Logged {
+        #[multipart(max_size = 8)]
+        title: String,
+    }
+
+    #[actix_web::test]
+    async fn logs_skipped_parts() {
+        init_logger();
+        let req = TestMultipartBuilder::new()
+            .text("title", "Hello")
+            .text("logged_extra", "skipped")
+            .set_on(TestRequest::post());
+        let form = extract::<Multipart<Logged>>(req).await.unwrap();
+        assert_eq!(form.title, "Hello");
+
+        let logged = logged("\"logged_extra\"");
+        assert_eq!(logged.len(), 1);
+        assert_eq!(logged[0].0, Level::Debug);
+        assert!(logged[0].1.contains("has no such field"), "{}", logged[0].1);
+    }
+
+    #[actix_web::test]
+    async fn logs_rejections_by_limits_as_warnings() {
+        init_logger();
+        let req = TestMultipartBuilder::new()
+            .text("title", "Far too long for the title")
+            .set_on(TestRequest::post());
+        extract::<Multipart<Logged>>(req).await.unwrap_err();
+
+        let logged = logged("multipart request rejected");
+        assert!(
+            logged
+                .iter()
+                .any(|(level, message)| { *level == Level::Warn && message.contains("title") }),
+            "{logged:?}"
+        );
+    }
+}
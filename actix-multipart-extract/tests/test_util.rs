@@ -0,0 +1,69 @@
+use actix_multipart_extract::{
+    test::TestMultipartBuilder, File, Multipart, MultipartBody, MultipartForm,
+};
+use actix_web::{test, web, App, FromRequest, HttpResponse};
+use serde::Deserialize;
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Upload {
+    name: String,
+    avatar: File,
+}
+
+async fn upload(form: Multipart<Upload>) -> HttpResponse {
+    HttpResponse::Ok().body(format!(
+        "{} {} {} {:?}",
+        form.name, form.avatar.name, form.avatar.content_type, form.avatar.bytes
+    ))
+}
+
+fn builder() -> TestMultipartBuilder {
+    TestMultipartBuilder::new().text("name", "avatar").file(
+        "avatar",
+        "a.png",
+        "image/png",
+        vec![1, 2, 3],
+    )
+}
+
+#[actix_web::test]
+async fn sends_forms_to_services() {
+    let app = test::init_service(App::new().route("/", web::post().to(upload))).await;
+
+    let body = test::call_and_read_body(&app, builder().to_request()).await;
+    assert_eq!(body, "avatar a.png image/png [1, 2, 3]");
+}
+
+#[actix_web::test]
+async fn builds_requests_for_extractors() {
+    let (req, mut payload) = builder().boundary("my-boundary").to_http_parts();
+    assert_eq!(
+        req.headers().get("content-type").unwrap(),
+        "multipart/form-data; boundary=my-boundary"
+    );
+
+    let form = Multipart::<Upload>::from_request(&req, &mut payload)
+        .await
+        .unwrap();
+    assert_eq!((form.name.as_str(), form.avatar.size), ("avatar", 3));
+}
+
+#[actix_web::test]
+async fn builds_requests_from_bodies() {
+    let mut body = MultipartBody::new();
+    body.text("name", "from body");
+    body.file("avatar", "b.txt", "text/plain", "hi").unwrap();
+
+    let builder = TestMultipartBuilder::from(body.clone());
+    assert_eq!(builder.content_type(), body.content_type());
+    assert_eq!(builder.body(), body.to_bytes());
+
+    let req = builder.set_on(test::TestRequest::post().uri("/upload"));
+    let (req, mut payload) = req.to_http_parts();
+    assert_eq!(req.path(), "/upload");
+
+    let form = Multipart::<Upload>::from_request(&req, &mut payload)
+        .await
+        .unwrap();
+    assert_eq!(form.avatar.bytes, b"hi");
+}
@@ -0,0 +1,33 @@
+#[test]
+fn reports_invalid_forms() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
+
+#[cfg(not(feature = "sniff"))]
+#[test]
+fn reports_sniff_options_without_the_feature() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/features/sniff.rs");
+}
+
+#[cfg(not(feature = "hash"))]
+#[test]
+fn reports_hash_options_without_the_feature() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/features/hash.rs");
+}
+
+#[cfg(not(feature = "image"))]
+#[test]
+fn reports_image_options_without_the_feature() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/features/image.rs");
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn reports_invalid_image_options() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/image/*.rs");
+}
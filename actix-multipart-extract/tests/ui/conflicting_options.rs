@@ -0,0 +1,35 @@
+use actix_multipart_extract::{MultipartForm, PartMeta};
+
+fn default_tags() -> Vec<String> {
+    Vec::new()
+}
+
+fn parse(_: &[u8], _: &PartMeta) -> Result<serde_json::Value, String> {
+    Ok(serde_json::Value::Null)
+}
+
+#[derive(MultipartForm)]
+struct TwoDefaults {
+    #[multipart(default = "new", default_fn = "default_tags")]
+    tags: Vec<String>,
+}
+
+#[derive(MultipartForm)]
+struct FileAndText {
+    #[multipart(as_file, as_text)]
+    notes: String,
+}
+
+#[derive(MultipartForm)]
+struct ConvertedText {
+    #[multipart(with = "parse", trim)]
+    tags: Vec<String>,
+}
+
+#[derive(MultipartForm)]
+struct TextWithFileOptions {
+    #[multipart(as_text, max_filename_length = 10)]
+    notes: String,
+}
+
+fn main() {}
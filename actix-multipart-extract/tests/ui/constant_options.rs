@@ -0,0 +1,17 @@
+use actix_multipart_extract::MultipartForm;
+
+const VISIBILITIES: &str = "public,private";
+
+#[derive(MultipartForm)]
+struct Post {
+    #[multipart(one_of = VISIBILITIES)]
+    visibility: String,
+}
+
+#[derive(MultipartForm)]
+struct Note {
+    #[multipart(default = 2 * 5)]
+    priority: u32,
+}
+
+fn main() {}
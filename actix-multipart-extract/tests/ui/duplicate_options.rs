@@ -0,0 +1,25 @@
+use actix_multipart_extract::MultipartForm;
+
+#[derive(MultipartForm)]
+struct Repeated {
+    #[multipart(max_size = 1MB)]
+    #[multipart(max_size = 2MB)]
+    file: Vec<u8>,
+}
+
+#[derive(MultipartForm)]
+#[multipart(rename_all = "camelCase", rename_all = "kebab-case")]
+struct RepeatedOnStruct {
+    display_name: String,
+}
+
+// Aliases can be repeated, but not shared by two fields
+#[derive(MultipartForm)]
+struct SharedAlias {
+    #[multipart(alias = "title", alias = "heading")]
+    name: String,
+    #[multipart(alias = "title")]
+    label: String,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use actix_multipart_extract::{File, MultipartForm};
+
+#[derive(MultipartForm)]
+struct Hashed {
+    #[multipart(hash = "sha256")]
+    file: File,
+}
+
+fn main() {}
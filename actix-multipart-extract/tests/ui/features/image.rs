@@ -0,0 +1,9 @@
+use actix_multipart_extract::{File, MultipartForm};
+
+#[derive(MultipartForm)]
+struct Avatar {
+    #[multipart(image(max_width = 512, formats = "png"))]
+    file: File,
+}
+
+fn main() {}
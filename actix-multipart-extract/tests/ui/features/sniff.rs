@@ -0,0 +1,15 @@
+use actix_multipart_extract::{File, MultipartForm};
+
+#[derive(MultipartForm)]
+struct Verified {
+    #[multipart(verify_content_type)]
+    file: File,
+}
+
+#[derive(MultipartForm)]
+struct Strict {
+    #[multipart(strict_content_type)]
+    file: File,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use actix_multipart_extract::MultipartForm;
+
+#[derive(MultipartForm)]
+struct Photo {
+    #[multipart(image(max_width = "wide"))]
+    photo: Vec<u8>,
+}
+
+fn main() {}
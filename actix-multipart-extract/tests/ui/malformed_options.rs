@@ -0,0 +1,21 @@
+use actix_multipart_extract::MultipartForm;
+
+#[derive(MultipartForm)]
+struct Avatar {
+    #[multipart(max_size = "5 parsecs")]
+    image: Vec<u8>,
+}
+
+#[derive(MultipartForm)]
+struct Profile {
+    #[multipart("trim")]
+    name: String,
+}
+
+#[derive(MultipartForm)]
+struct Upload {
+    #[multipart(deadline = "soon")]
+    file: Vec<u8>,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use actix_multipart_extract::{File, MultipartForm};
+use serde::Deserialize;
+
+#[derive(Deserialize, MultipartForm)]
+struct Upload {
+    file: File,
+}
+
+#[derive(Deserialize, MultipartForm)]
+struct AdminUpload(#[multipart(max_size = 5MB)] Upload);
+
+fn main() {}
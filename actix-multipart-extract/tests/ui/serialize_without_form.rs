@@ -0,0 +1,9 @@
+use actix_multipart_extract::MultipartSerialize;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, MultipartSerialize)]
+struct Upload {
+    title: String,
+}
+
+fn main() {}
@@ -0,0 +1,36 @@
+use actix_multipart_extract::MultipartForm;
+use serde::Deserialize;
+
+#[derive(Deserialize, MultipartForm)]
+#[multipart(tag = "kind")]
+struct Upload {
+    url: String,
+}
+
+#[derive(Deserialize, MultipartForm)]
+#[serde(tag = "kind")]
+#[multipart(tag = "kind")]
+enum Post {
+    Image {
+        #[multipart(max_size = 8)]
+        caption: String,
+    },
+    Link {
+        caption: String,
+    },
+}
+
+#[derive(Deserialize, MultipartForm)]
+#[serde(tag = "kind")]
+#[multipart(tag = "kind")]
+enum Wrapped {
+    Text(String),
+}
+
+#[derive(Deserialize, MultipartForm)]
+#[multipart(tag = "kind")]
+enum Untagged {
+    Link { url: String },
+}
+
+fn main() {}
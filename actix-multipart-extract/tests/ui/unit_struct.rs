@@ -0,0 +1,6 @@
+use actix_multipart_extract::MultipartForm;
+
+#[derive(MultipartForm)]
+struct Empty;
+
+fn main() {}
@@ -0,0 +1,33 @@
+use actix_multipart_extract::MultipartForm;
+
+#[derive(MultipartForm)]
+struct Misspelled {
+    #[multipart(maxsize = 1MB)]
+    file: Vec<u8>,
+}
+
+#[derive(MultipartForm)]
+struct Unknown {
+    #[multipart(colour = "red")]
+    name: String,
+}
+
+#[derive(MultipartForm)]
+struct Misplaced {
+    #[multipart(rename_all = "camelCase")]
+    display_name: String,
+}
+
+#[derive(MultipartForm)]
+struct WrongKind {
+    #[multipart(trim = true)]
+    name: String,
+}
+
+#[derive(MultipartForm)]
+#[multipart(rename = "upload")]
+struct UnknownOnStruct {
+    name: String,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use actix_multipart_extract::MultipartForm;
+
+#[derive(MultipartForm)]
+enum Upload {
+    Text { text: String },
+    Link { url: String },
+}
+
+fn main() {}
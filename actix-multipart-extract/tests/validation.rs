@@ -0,0 +1,352 @@
+mod common;
+
+use actix_multipart_extract::{test::TestMultipartBuilder, File, Multipart, MultipartForm};
+use actix_web::test::TestRequest;
+use common::{error_body, error_of, extract};
+use serde::Deserialize;
+
+fn texts(fields: &[(&str, &str)]) -> TestRequest {
+    fields
+        .iter()
+        .fold(TestMultipartBuilder::new(), |form, (name, value)| {
+            form.text(*name, *value)
+        })
+        .set_on(TestRequest::post())
+}
+
+fn even(count: &u32) -> Result<(), String> {
+    match count % 2 {
+        0 => Ok(()),
+        _ => Err("must be even".to_string()),
+    }
+}
+
+fn not_empty(file: &Option<File>) -> Result<(), String> {
+    match file {
+        Some(file) if file.is_empty() => Err("is empty".to_string()),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Checked {
+    #[multipart(validate = "even")]
+    count: u32,
+    #[multipart(validate = "not_empty")]
+    file: Option<File>,
+}
+
+#[actix_web::test]
+async fn validates_fields_with_functions() {
+    let ok = extract::<Multipart<Checked>>(texts(&[("count", "4")])).await;
+    assert_eq!(ok.unwrap().count, 4);
+
+    let err = extract::<Multipart<Checked>>(texts(&[("count", "3")]))
+        .await
+        .unwrap_err();
+    let (_, code, body) = error_body(&err).await;
+    assert_eq!(code, "invalid_field");
+    assert!(
+        body.contains("count") && body.contains("must be even"),
+        "{body}"
+    );
+
+    let req = TestMultipartBuilder::new()
+        .text("count", "2")
+        .file("file", "a.txt", "text/plain", "")
+        .set_on(TestRequest::post());
+    let err = extract::<Multipart<Checked>>(req).await.unwrap_err();
+    assert_eq!(error_of(&err).1, "invalid_field");
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Visibility {
+    #[multipart(one_of = "public,private")]
+    visibility: String,
+    #[multipart(one_of = "1,2,3")]
+    level: Option<u8>,
+}
+
+#[actix_web::test]
+async fn limits_text_to_a_set_of_values() {
+    let form =
+        extract::<Multipart<Visibility>>(texts(&[("visibility", "private"), ("level", "2")]))
+            .await
+            .unwrap();
+    assert_eq!((form.visibility.as_str(), form.level), ("private", Some(2)));
+
+    for fields in [
+        &[("visibility", "secret")][..],
+        &[("visibility", "Public")][..],
+        &[("visibility", "public"), ("level", "4")][..],
+    ] {
+        let err = extract::<Multipart<Visibility>>(texts(fields))
+            .await
+            .unwrap_err();
+        assert_eq!(error_of(&err).1, "invalid_field", "{fields:?}");
+    }
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Ids {
+    id: uuid::Uuid,
+    #[multipart(uuid)]
+    parent: Option<String>,
+}
+
+#[actix_web::test]
+async fn reads_uuids() {
+    let form = extract::<Multipart<Ids>>(texts(&[
+        ("id", "12345678123456781234567812345678"),
+        ("parent", "{67e55044-10b1-426f-9247-bb680e5fe0c8}"),
+    ]))
+    .await
+    .unwrap();
+
+    assert_eq!(form.id.to_string(), "12345678-1234-5678-1234-567812345678");
+    assert_eq!(
+        form.parent.as_deref(),
+        Some("{67e55044-10b1-426f-9247-bb680e5fe0c8}")
+    );
+}
+
+#[derive(Deserialize, MultipartForm, Debug)]
+struct Labels {
+    code: std::borrow::Cow<'static, str>,
+    flag: Box<str>,
+    notes: Vec<std::borrow::Cow<'static, str>>,
+}
+
+#[actix_web::test]
+async fn reads_text_into_str_pointers() {
+    let form = extract::<Multipart<Labels>>(texts(&[
+        ("code", "0042"),
+        ("flag", "true"),
+        ("notes", "1"),
+        ("notes", "two"),
+    ]))
+    .await
+    .unwrap();
+
+    assert!(matches!(form.code, std::borrow::Cow::Owned(_)));
+    assert_eq!((form.code.as_ref(), form.flag.as_ref()), ("0042", "true"));
+    assert_eq!(form.notes, ["1", "two"]);
+}
+
+#[actix_web::test]
+async fn rejects_text_which_is_not_a_uuid() {
+    let err = extract::<Multipart<Ids>>(texts(&[
+        ("id", "67e55044-10b1-426f-9247-bb680e5fe0c8"),
+        ("parent", "67e55044-10b1-426f-9247"),
+    ]))
+    .await
+    .unwrap_err();
+
+    let (_, code, body) = error_body(&err).await;
+    assert_eq!(code, "invalid_field");
+    assert!(body.contains("must be a UUID"), "{body}");
+}
+
+#[cfg(feature = "validate")]
+mod validator_form {
+    use actix_multipart_extract::{test::TestMultipartBuilder, Multipart, MultipartForm};
+    use actix_web::{http::StatusCode, test::TestRequest};
+    use serde::Deserialize;
+    use validator::Validate;
+
+    use crate::common::{error_body, extract};
+
+    #[derive(Deserialize, MultipartForm, Validate, Debug)]
+    struct Signup {
+        #[validate(length(min = 3))]
+        name: String,
+        #[validate(range(max = 150))]
+        age: u32,
+    }
+
+    fn signup(name: &str, age: &str) -> TestRequest {
+        TestMultipartBuilder::new()
+            .text("name", name)
+            .text("age", age)
+            .set_on(TestRequest::post())
+    }
+
+    #[actix_web::test]
+    async fn validates_forms_after_extracting_them() {
+        let form = extract::<Multipart<Signup>>(signup("alice", "30"))
+            .await
+            .unwrap();
+        assert_eq!((form.name.as_str(), form.age), ("alice", 30));
+
+        let err = extract::<Multipart<Signup>>(signup("al", "200"))
+            .await
+            .unwrap_err();
+        let (status, code, body) = error_body(&err).await;
+        assert_eq!(
+            (status, code.as_str()),
+            (StatusCode::BAD_REQUEST, "validation_failed")
+        );
+        assert!(body.contains("name") && body.contains("age"), "{body}");
+    }
+}
+
+#[cfg(feature = "garde")]
+mod garde_form {
+    use actix_multipart_extract::{test::TestMultipartBuilder, Multipart, MultipartForm};
+    use actix_web::test::TestRequest;
+    use garde::Validate;
+    use serde::Deserialize;
+
+    use crate::common::{error_body, extract};
+
+    #[derive(Deserialize, MultipartForm, Validate, Debug)]
+    struct Signup {
+        #[garde(length(min = 3))]
+        name: String,
+        #[garde(range(max = 150))]
+        age: u32,
+    }
+
+    fn signup(name: &str, age: &str) -> TestRequest {
+        TestMultipartBuilder::new()
+            .text("name", name)
+            .text("age", age)
+            .set_on(TestRequest::post())
+    }
+
+    #[actix_web::test]
+    async fn validates_forms_after_extracting_them() {
+        let form = extract::<Multipart<Signup>>(signup("alice", "30"))
+            .await
+            .unwrap();
+        assert_eq!((form.name.as_str(), form.age), ("alice", 30));
+
+        let err = extract::<Multipart<Signup>>(signup("al", "200"))
+            .await
+            .unwrap_err();
+        let (_, code, body) = error_body(&err).await;
+        assert_eq!(code, "validation_failed");
+        assert!(body.contains("name") && body.contains("age"), "{body}");
+    }
+}
+
+#[cfg(feature = "pattern")]
+mod pattern {
+    use super::*;
+
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Username {
+        #[multipart(pattern = "^[a-z0-9_-]{3,16}$")]
+        name: String,
+        #[multipart(pattern = "^#[0-9a-f]{6}$")]
+        color: Option<String>,
+    }
+
+    #[actix_web::test]
+    async fn checks_text_against_patterns() {
+        let form = extract::<Multipart<Username>>(texts(&[("name", "alice_1")]))
+            .await
+            .unwrap();
+        assert_eq!(
+            (form.name.as_str(), form.color.as_deref()),
+            ("alice_1", None)
+        );
+
+        for fields in [
+            &[("name", "Alice!")][..],
+            &[("name", "alice"), ("color", "red")][..],
+        ] {
+            let err = extract::<Multipart<Username>>(texts(fields))
+                .await
+                .unwrap_err();
+            assert_eq!(error_of(&err).1, "invalid_field", "{fields:?}");
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+mod chrono_fields {
+    use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+
+    use super::*;
+
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Event {
+        #[multipart(format = "%d/%m/%Y")]
+        day: NaiveDate,
+        #[multipart(format = "%H:%M")]
+        starts: Option<NaiveTime>,
+        created: DateTime<Utc>,
+    }
+
+    #[actix_web::test]
+    async fn parses_dates_with_their_format() {
+        let form = extract::<Multipart<Event>>(texts(&[
+            ("day", "24/12/2024"),
+            ("starts", "18:30"),
+            ("created", "2024-12-01T10:00:00Z"),
+        ]))
+        .await
+        .unwrap();
+
+        assert_eq!(form.day, NaiveDate::from_ymd_opt(2024, 12, 24).unwrap());
+        assert_eq!(form.starts, NaiveTime::from_hms_opt(18, 30, 0));
+        assert_eq!(form.created.to_rfc3339(), "2024-12-01T10:00:00+00:00");
+    }
+
+    #[actix_web::test]
+    async fn rejects_dates_not_matching_their_format() {
+        let err = extract::<Multipart<Event>>(texts(&[
+            ("day", "2024-12-24"),
+            ("created", "2024-12-01T10:00:00Z"),
+        ]))
+        .await
+        .unwrap_err();
+
+        let (_, code, body) = error_body(&err).await;
+        assert_eq!(code, "invalid_field");
+        assert!(body.contains("day"), "{body}");
+    }
+}
+
+#[cfg(feature = "decimal")]
+mod decimal {
+    use rust_decimal::Decimal;
+
+    use super::*;
+
+    #[derive(Deserialize, MultipartForm, Debug)]
+    struct Price {
+        #[multipart(max_scale = 2)]
+        amount: Decimal,
+        #[multipart(max_scale = 0)]
+        quantity: Option<String>,
+    }
+
+    #[actix_web::test]
+    async fn reads_decimals_with_their_scale() {
+        let form = extract::<Multipart<Price>>(texts(&[
+            ("amount", "12345678901234567890.05"),
+            ("quantity", "3"),
+        ]))
+        .await
+        .unwrap();
+
+        assert_eq!(form.amount.to_string(), "12345678901234567890.05");
+        assert_eq!(form.quantity.as_deref(), Some("3"));
+    }
+
+    #[actix_web::test]
+    async fn rejects_more_decimal_places_than_the_max_scale() {
+        for fields in [
+            &[("amount", "1.005")][..],
+            &[("amount", "twelve")][..],
+            &[("amount", "1"), ("quantity", "1.5")][..],
+        ] {
+            let err = extract::<Multipart<Price>>(texts(fields))
+                .await
+                .unwrap_err();
+            assert_eq!(error_of(&err).1, "invalid_field", "{fields:?}");
+        }
+    }
+}